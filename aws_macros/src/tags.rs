@@ -12,6 +12,9 @@ struct Input {
 enum ElementKind {
     Required,
     Optional,
+    /// Required field marked with `#[tag(default)]`, falls back to `Default::default()`
+    /// if the tag is missing
+    Defaulted,
 }
 
 #[derive(Debug)]
@@ -20,7 +23,8 @@ struct Element {
     vis: syn::Visibility,
     ty: syn::Path,
     kind: ElementKind,
-    name: String,
+    /// Evaluates to the tag key as `&'static str`
+    name: proc_macro2::TokenStream,
     attrs: Vec<syn::Attribute>,
 }
 
@@ -80,7 +84,13 @@ fn parse_type(input: syn::Type) -> (syn::Path, ElementKind) {
     }
 }
 
-fn parse_field_attrs(attrs: &mut Vec<syn::Attribute>) -> Option<String> {
+#[derive(Debug, Default)]
+struct FieldAttrs {
+    key: Option<proc_macro2::TokenStream>,
+    default: bool,
+}
+
+fn parse_field_attrs(attrs: &mut Vec<syn::Attribute>) -> FieldAttrs {
     let index_of_tag_attribute = attrs
         .iter()
         .enumerate()
@@ -96,40 +106,49 @@ fn parse_field_attrs(attrs: &mut Vec<syn::Attribute>) -> Option<String> {
             _ => None,
         });
 
-    match index_of_tag_attribute {
-        Some((i, meta_list)) => {
-            let removed_attribute = attrs.remove(i);
-            drop(removed_attribute);
+    let mut field_attrs = FieldAttrs::default();
 
-            let expr: syn::Expr = match meta_list.parse_args() {
-                Ok(expr) => expr,
-                Err(e) => panic!("failed parsing tag field attribute: {e}"),
-            };
+    if let Some((i, meta_list)) = index_of_tag_attribute {
+        let removed_attribute = attrs.remove(i);
+        drop(removed_attribute);
 
-            let syn::Expr::Assign(assign) = expr else {
-                panic!("invalid expression in tag field attribute")
-            };
+        let metas = match meta_list.parse_args_with(
+            syn::punctuated::Punctuated::<syn::Meta, syn::Token![,]>::parse_terminated,
+        ) {
+            Ok(metas) => metas,
+            Err(e) => panic!("failed parsing tag field attribute: {e}"),
+        };
 
-            match *assign.left {
-                syn::Expr::Path(ref exprpath) => {
+        for meta in metas {
+            match meta {
+                syn::Meta::NameValue(name_value) => {
                     assert!(
-                        exprpath.path.is_ident("key"),
+                        name_value.path.is_ident("key"),
                         "invalid tag field attribute key"
                     );
-                }
-                _ => panic!("invalid expression in tag field attribute, left side"),
-            }
 
-            match *assign.right {
-                syn::Expr::Lit(ref expr_lit) => match expr_lit.lit {
-                    syn::Lit::Str(ref lit_str) => Some(lit_str.value()),
-                    _ => panic!("right side of tag field not a string literal"),
-                },
-                _ => panic!("right side of tag field attribute not a literal"),
+                    match name_value.value {
+                        syn::Expr::Lit(ref expr_lit) => match expr_lit.lit {
+                            syn::Lit::Str(ref lit_str) => field_attrs.key = Some(quote!(#lit_str)),
+                            _ => panic!("right side of tag field not a string literal"),
+                        },
+                        // A `StaticTagKey` constant
+                        syn::Expr::Path(ref expr_path) => {
+                            field_attrs.key = Some(quote!(#expr_path.as_str()));
+                        }
+                        _ => panic!("right side of tag field attribute not a literal or a path"),
+                    }
+                }
+                syn::Meta::Path(path) => {
+                    assert!(path.is_ident("default"), "invalid tag field attribute");
+                    field_attrs.default = true;
+                }
+                syn::Meta::List(_) => panic!("invalid expression in tag field attribute"),
             }
         }
-        None => None,
     }
+
+    field_attrs
 }
 
 fn parse_fields(input: impl IntoIterator<Item = syn::Field>) -> Vec<Element> {
@@ -139,14 +158,23 @@ fn parse_fields(input: impl IntoIterator<Item = syn::Field>) -> Vec<Element> {
         let vis = field.vis;
         let (ty, kind) = parse_type(field.ty);
 
-        let name = parse_field_attrs(&mut field.attrs);
+        let field_attrs = parse_field_attrs(&mut field.attrs);
+
+        let kind = match (kind, field_attrs.default) {
+            (ElementKind::Required | ElementKind::Defaulted, true) => ElementKind::Defaulted,
+            (ElementKind::Optional, true) => panic!("optional fields cannot have a default"),
+            (kind, false) => kind,
+        };
 
         elements.push(Element {
             ident: ident.clone(),
             vis,
             ty,
             kind,
-            name: name.unwrap_or_else(|| ident.to_string()),
+            name: field_attrs.key.unwrap_or_else(|| {
+                let name = ident.to_string();
+                quote!(#name)
+            }),
             attrs: field.attrs,
         });
     }
@@ -164,7 +192,7 @@ fn parse_struct(input: syn::ItemStruct) -> Input {
     }
 }
 
-fn build_output(input: Input) -> TokenStream {
+fn build_output(input: Input, with_default: bool) -> TokenStream {
     let root = quote! { ::aws_lib };
 
     let ident = input.ident;
@@ -180,7 +208,7 @@ fn build_output(input: Input) -> TokenStream {
                 let ty = &element.ty;
                 let attrs = &element.attrs;
                 match element.kind {
-                    ElementKind::Required => {
+                    ElementKind::Required | ElementKind::Defaulted => {
                         quote!(
                             #(#attrs)
                             *
@@ -211,7 +239,7 @@ fn build_output(input: Input) -> TokenStream {
             let ty = &element.ty;
             let attrs = cfg_attrs(&element.attrs);
             match element.kind {
-                ElementKind::Required => quote! {
+                ElementKind::Required | ElementKind::Defaulted => quote! {
                     #(#attrs)
                     *
                     #ident: #ty
@@ -280,6 +308,17 @@ fn build_output(input: Input) -> TokenStream {
 
                     }
                 }
+                ElementKind::Defaulted => {
+                    quote! {
+                        let value: #ty = match value {
+                            ::std::option::Option::Some(value) => {
+                                 #try_convert
+                            }
+                            ::std::option::Option::None => <#ty as ::std::default::Default>::default(),
+                        };
+                        value
+                    }
+                }
                 ElementKind::Optional => {
                     quote! {
                         let value: ::std::option::Option<#ty> = value.map(|value: #root::tags::RawTagValue| {
@@ -323,7 +362,7 @@ fn build_output(input: Input) -> TokenStream {
                 let tag_name = &element.name;
                 let attrs= &element.attrs;
                 match element.kind {
-                    ElementKind::Required => {
+                    ElementKind::Required | ElementKind::Defaulted => {
                         quote! {
                             #(#attrs)
                             *
@@ -356,8 +395,74 @@ fn build_output(input: Input) -> TokenStream {
             })
             .collect();
 
+        let with_fields: Vec<proc_macro2::TokenStream> = input
+            .elements
+            .iter()
+            .map(|element| {
+                let ident = &element.ident;
+                let ty = &element.ty;
+                let attrs = cfg_attrs(&element.attrs);
+                let with_ident = quote::format_ident!("with_{}", ident);
+                let value = match element.kind {
+                    ElementKind::Required | ElementKind::Defaulted => quote! { value },
+                    ElementKind::Optional => quote! { ::std::option::Option::Some(value) },
+                };
+                quote! {
+                    #(#attrs)
+                    *
+                    #[must_use]
+                    #vis fn #with_ident(mut self, value: #ty) -> Self {
+                        self.#ident = #value;
+                        self
+                    }
+                }
+            })
+            .collect();
+
+        // Only possible if no field requires a value
+        assert!(
+            !with_default
+                || input
+                    .elements
+                    .iter()
+                    .all(|element| !matches!(element.kind, ElementKind::Required)),
+            "#[Tags(default)] requires all fields to be optional or marked with #[tag(default)]"
+        );
+        let default_impl = with_default.then(|| {
+            let default_fields = input.elements.iter().map(|element| {
+                let ident = &element.ident;
+                let ty = &element.ty;
+                let attrs = cfg_attrs(&element.attrs);
+                let value = match element.kind {
+                    ElementKind::Required | ElementKind::Defaulted => {
+                        quote! { <#ty as ::std::default::Default>::default() }
+                    }
+                    ElementKind::Optional => quote! { ::std::option::Option::None },
+                };
+                quote! {
+                    #(#attrs)
+                    *
+                    #ident: #value
+                }
+            });
+
+            quote! {
+                impl ::std::default::Default for #ident {
+                    fn default() -> Self {
+                        Self {
+                            #(#default_fields),*
+                        }
+                    }
+                }
+            }
+        });
+
         quote! {
+            #default_impl
+
             impl #ident {
+                #(#with_fields)*
+
                 #vis fn from_values(#(#params),*) -> Self {
                     Self {
                         #(#from_fields),*
@@ -393,10 +498,14 @@ fn build_output(input: Input) -> TokenStream {
     reason = "this is the usual signature for proc macros, and the inner function should have the same signature"
 )]
 pub(crate) fn transform(attr: TokenStream, item: TokenStream) -> TokenStream {
-    assert!(
-        attr.is_empty(),
-        "cannot take any attribute macro attributes"
-    );
+    // `#[Tags(default)]` opts into a `Default` implementation
+    let with_default = if attr.is_empty() {
+        false
+    } else {
+        let attr = syn::parse_macro_input!(attr as syn::Ident);
+        assert!(attr == "default", "invalid attribute macro attribute");
+        true
+    };
 
     let input = syn::parse_macro_input!(item as syn::Item);
 
@@ -405,5 +514,5 @@ pub(crate) fn transform(attr: TokenStream, item: TokenStream) -> TokenStream {
         _ => panic!("only applicable to structs"),
     };
 
-    build_output(input)
+    build_output(input, with_default)
 }
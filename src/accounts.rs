@@ -0,0 +1,97 @@
+//! Running the same query against several accounts and regions at once
+//!
+//! ```rust,no_run
+//! # use aws_lib::{accounts::{AccountSet, AccountTarget}, Account, ProfileConfig, Region, RoleArn};
+//! # async fn f(profile_config: ProfileConfig) -> Result<(), aws_lib::Error> {
+//! let accounts = AccountSet::load(
+//!     &profile_config,
+//!     vec![
+//!         AccountTarget::new(Account::new("111111111111".to_owned()), None, Region::EuCentral1),
+//!         AccountTarget::new(
+//!             Account::new("222222222222".to_owned()),
+//!             Some(RoleArn::new("arn:aws:iam::222222222222:role/inventory".to_owned())),
+//!             Region::EuCentral1,
+//!         ),
+//!     ],
+//! )
+//! .await;
+//!
+//! for result in accounts.run(aws_lib::keys::key_pair_usage).await {
+//!     println!("{}: {} instances", result.account.id(), result.result?.len());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::future::Future;
+
+use super::{load_region_client, Account, Error, ProfileConfig, Region, RegionClient, RoleArn};
+
+/// An account and region to load clients for
+#[derive(Debug, Clone)]
+pub struct AccountTarget {
+    account: Account,
+    role: Option<RoleArn>,
+    region: Region,
+}
+
+impl AccountTarget {
+    /// If `role` is `None`, the credentials of the profiles are used directly.
+    pub const fn new(account: Account, role: Option<RoleArn>, region: Region) -> Self {
+        Self {
+            account,
+            role,
+            region,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct AccountResult<T> {
+    pub account: Account,
+    pub region: Region,
+    pub result: Result<T, Error>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AccountSet {
+    clients: Vec<(Account, RegionClient)>,
+}
+
+impl AccountSet {
+    pub async fn load(profile_config: &ProfileConfig, targets: Vec<AccountTarget>) -> Self {
+        let clients =
+            futures_util::future::join_all(targets.into_iter().map(|target| async move {
+                let client =
+                    load_region_client(target.region, profile_config, target.role.as_ref()).await;
+                (target.account, client)
+            }))
+            .await;
+
+        Self { clients }
+    }
+
+    pub fn clients(&self) -> &[(Account, RegionClient)] {
+        &self.clients
+    }
+
+    /// Runs `query` against all accounts and regions concurrently. A failure in one
+    /// account does not affect the others.
+    pub async fn run<'a, F, Fut, T>(&'a self, query: F) -> Vec<AccountResult<T>>
+    where
+        F: Fn(&'a RegionClient) -> Fut,
+        Fut: Future<Output = Result<T, Error>>,
+    {
+        futures_util::future::join_all(self.clients.iter().map(|&(ref account, ref client)| {
+            let query = query(client);
+            async move {
+                AccountResult {
+                    account: account.clone(),
+                    region: client.region,
+                    result: query.await,
+                }
+            }
+        }))
+        .await
+    }
+}
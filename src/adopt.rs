@@ -0,0 +1,229 @@
+//! Adopting existing resources that were not created through this crate
+//!
+//! Adoption first checks the resource against a [`TagQuery`], so only the expected
+//! resources are taken over. It then adds the tags the resource would have carried if
+//! it had been created through the client: the given tags and the default tags of the
+//! client, e.g. ownership tags. Tags that the resource already carries with a
+//! different value are never overwritten, adoption fails instead, as the resource is
+//! most likely owned by someone else.
+
+use super::{
+    hosted_zone_tags, query::TagQuery, tags::TagList, Eip, EipAllocationId, Error, HostedZoneId,
+    Instance, InstanceId, RegionClient, Route53Zone,
+};
+
+#[derive(Debug, Clone, Copy)]
+pub struct AdoptionSchema<'a> {
+    /// Has to match the current tags of the resource
+    pub required: &'a TagQuery,
+    /// Added to the resource, together with the default tags of the client
+    pub tags: &'a TagList,
+}
+
+fn rejected(resource: &str, reason: String) -> Error {
+    Error::AdoptionRejected {
+        resource: resource.to_owned(),
+        reason,
+    }
+}
+
+/// The tags of `wanted` that `current` does not carry yet. Fails if `current` carries
+/// one of them with a different value.
+fn missing_tags(resource: &str, current: &TagList, wanted: &TagList) -> Result<TagList, Error> {
+    let mut missing = TagList::new();
+
+    for tag in wanted.as_slice() {
+        match current.get(tag.key().clone()) {
+            None => missing.push(tag.clone()),
+            Some(existing) if existing.value() == tag.value() => (),
+            Some(existing) => {
+                return Err(rejected(
+                    resource,
+                    format!(
+                        "tag {} is \"{}\" instead of \"{}\"",
+                        tag.key(),
+                        existing.value(),
+                        tag.value()
+                    ),
+                ))
+            }
+        }
+    }
+
+    Ok(missing)
+}
+
+/// Validates `current` against `schema` and returns the tags to add
+fn tags_to_add(
+    client: &RegionClient,
+    schema: &AdoptionSchema<'_>,
+    resource: &str,
+    current: &TagList,
+) -> Result<TagList, Error> {
+    if !schema.required.matches(current) {
+        return Err(rejected(
+            resource,
+            "tags do not match the required tags".to_owned(),
+        ));
+    }
+
+    missing_tags(resource, current, &client.tags_with_defaults(schema.tags))
+}
+
+async fn add_ec2_tags(client: &RegionClient, resource: &str, tags: &TagList) -> Result<(), Error> {
+    if tags.as_slice().is_empty() {
+        return Ok(());
+    }
+
+    let _output = client
+        .main
+        .ec2
+        .create_tags()
+        .resources(resource)
+        .set_tags(Some(tags.clone().into()))
+        .send()
+        .await?;
+
+    Ok(())
+}
+
+fn not_found(entity: &str, id: &str) -> Error {
+    Error::InvalidArgument {
+        message: format!("{entity} {id} not found"),
+    }
+}
+
+pub async fn adopt_instance(
+    client: &RegionClient,
+    id: &InstanceId,
+    schema: &AdoptionSchema<'_>,
+) -> Result<Instance, Error> {
+    let mut instance = client
+        .main
+        .ec2
+        .describe_instances()
+        .instance_ids(id.as_str())
+        .send()
+        .await?
+        .reservations
+        .unwrap_or_default()
+        .into_iter()
+        .flat_map(|reservation| reservation.instances.unwrap_or_default())
+        .next()
+        .map(Instance::try_from_aws)
+        .transpose()?
+        .ok_or_else(|| not_found("instance", id.as_str()))?;
+
+    let missing = tags_to_add(client, schema, id.as_str(), &instance.tags)?;
+    add_ec2_tags(client, id.as_str(), &missing).await?;
+    instance.tags = instance.tags.with_defaults(&missing);
+
+    Ok(instance)
+}
+
+pub async fn adopt_eip(
+    client: &RegionClient,
+    id: &EipAllocationId,
+    schema: &AdoptionSchema<'_>,
+) -> Result<Eip, Error> {
+    let mut eip: Eip = client
+        .main
+        .ec2
+        .describe_addresses()
+        .allocation_ids(id.as_str())
+        .send()
+        .await?
+        .addresses
+        .unwrap_or_default()
+        .pop()
+        .ok_or_else(|| not_found("eip", id.as_str()))?
+        .try_into()?;
+
+    let missing = tags_to_add(client, schema, id.as_str(), &eip.tags)?;
+    add_ec2_tags(client, id.as_str(), &missing).await?;
+    eip.tags = eip.tags.with_defaults(&missing);
+
+    Ok(eip)
+}
+
+pub async fn adopt_zone(
+    client: &RegionClient,
+    id: &HostedZoneId,
+    schema: &AdoptionSchema<'_>,
+) -> Result<Route53Zone, Error> {
+    let zone: Route53Zone = client
+        .main
+        .route53
+        .get_hosted_zone()
+        .id(id.as_str())
+        .send()
+        .await?
+        .hosted_zone
+        .ok_or_else(|| not_found("hosted zone", id.as_str()))?
+        .into();
+
+    let current = hosted_zone_tags(client, id).await?;
+
+    let missing = tags_to_add(client, schema, id.as_str(), &current)?;
+    if !missing.as_slice().is_empty() {
+        let _output = client
+            .main
+            .route53
+            .change_tags_for_resource()
+            .resource_type(aws_sdk_route53::types::TagResourceType::Hostedzone)
+            .resource_id(id.as_str())
+            .set_add_tags(Some(
+                missing
+                    .as_slice()
+                    .iter()
+                    .map(|tag| {
+                        aws_sdk_route53::types::Tag::builder()
+                            .key(tag.key().as_str())
+                            .value(tag.value().as_str())
+                            .build()
+                    })
+                    .collect(),
+            ))
+            .send()
+            .await?;
+    }
+
+    Ok(zone)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tags::RawTag;
+
+    #[test]
+    fn only_missing_tags_are_added() {
+        let current = TagList::from_vec(vec![
+            RawTag::new("Name".to_owned(), "legacy-web".to_owned()),
+            RawTag::new("owner".to_owned(), "infra".to_owned()),
+        ]);
+
+        let missing = missing_tags(
+            "i-0123456789abcdef0",
+            &current,
+            &TagList::from_vec(vec![
+                RawTag::new("owner".to_owned(), "infra".to_owned()),
+                RawTag::new("cost-center".to_owned(), "42".to_owned()),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(
+            missing,
+            TagList::from_vec(vec![RawTag::new("cost-center".to_owned(), "42".to_owned())])
+        );
+
+        assert!(matches!(
+            missing_tags(
+                "i-0123456789abcdef0",
+                &current,
+                &TagList::from_vec(vec![RawTag::new("owner".to_owned(), "web".to_owned())]),
+            ),
+            Err(Error::AdoptionRejected { .. })
+        ));
+    }
+}
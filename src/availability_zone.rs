@@ -0,0 +1,165 @@
+//! Availability zones and their zone IDs
+//!
+//! Zone names like `eu-central-1a` are mapped to the physical zones independently for
+//! each account, so the same name can refer to different zones in two accounts. Zone
+//! IDs like `euc1-az2` refer to the same physical zone in all accounts. Anything that
+//! has to line up across accounts, e.g. subnets of a shared VPC, should be based on
+//! zone IDs and translated to names with a [`ZoneMapping`] of each account.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::{AvailabilityZone, Error, RegionClient};
+
+crate::string_newtype!(ZoneId);
+
+impl ZoneId {
+    pub const fn new(value: String) -> Self {
+        Self(value)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+crate::wrap_aws_enum!(AvailabilityZoneState);
+crate::wrap_aws_enum!(AvailabilityZoneOptInStatus);
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct Az {
+    name: AvailabilityZone,
+    id: ZoneId,
+    state: AvailabilityZoneState,
+    opt_in_status: AvailabilityZoneOptInStatus,
+}
+
+impl TryFrom<aws_sdk_ec2::types::AvailabilityZone> for Az {
+    type Error = Error;
+
+    fn try_from(zone: aws_sdk_ec2::types::AvailabilityZone) -> Result<Self, Self::Error> {
+        macro_rules! extract {
+            ($field:ident) => {
+                zone.$field.ok_or_else(|| Error::UnexpectedNoneValue {
+                    entity: stringify!($field).to_owned(),
+                })
+            };
+        }
+
+        Ok(Self {
+            name: AvailabilityZone(extract!(zone_name)?),
+            id: ZoneId(extract!(zone_id)?),
+            state: AvailabilityZoneState(extract!(state)?),
+            opt_in_status: AvailabilityZoneOptInStatus(extract!(opt_in_status)?),
+        })
+    }
+}
+
+impl Az {
+    pub const fn name(&self) -> &AvailabilityZone {
+        &self.name
+    }
+
+    pub const fn id(&self) -> &ZoneId {
+        &self.id
+    }
+
+    pub const fn state(&self) -> &AvailabilityZoneState {
+        &self.state
+    }
+
+    pub const fn opt_in_status(&self) -> &AvailabilityZoneOptInStatus {
+        &self.opt_in_status
+    }
+
+    /// Whether instances can be launched in the zone
+    pub fn is_usable(&self) -> bool {
+        *self.state.inner() == aws_sdk_ec2::types::AvailabilityZoneState::Available
+            && *self.opt_in_status.inner()
+                != aws_sdk_ec2::types::AvailabilityZoneOptInStatus::NotOptedIn
+    }
+}
+
+/// All availability zones of the region of `client`, including local and wavelength
+/// zones that the account has not opted in to
+pub async fn list_availability_zones(client: &RegionClient) -> Result<Vec<Az>, Error> {
+    client
+        .main
+        .ec2
+        .describe_availability_zones()
+        .all_availability_zones(true)
+        .send()
+        .await?
+        .availability_zones
+        .unwrap_or_default()
+        .into_iter()
+        .map(TryInto::try_into)
+        .collect()
+}
+
+/// Translates between zone names and zone IDs of one account
+#[derive(Debug, Clone)]
+pub struct ZoneMapping {
+    zones: Vec<(AvailabilityZone, ZoneId)>,
+}
+
+impl ZoneMapping {
+    pub async fn load(client: &RegionClient) -> Result<Self, Error> {
+        Ok(Self::from_zones(list_availability_zones(client).await?))
+    }
+
+    pub fn from_zones(zones: impl IntoIterator<Item = Az>) -> Self {
+        Self {
+            zones: zones.into_iter().map(|zone| (zone.name, zone.id)).collect(),
+        }
+    }
+
+    pub fn zone_id(&self, name: &AvailabilityZone) -> Option<&ZoneId> {
+        self.zones
+            .iter()
+            .find(|&&(ref zone_name, _)| zone_name == name)
+            .map(|&(_, ref id)| id)
+    }
+
+    pub fn zone_name(&self, id: &ZoneId) -> Option<&AvailabilityZone> {
+        self.zones
+            .iter()
+            .find(|&&(_, ref zone_id)| zone_id == id)
+            .map(|&(ref name, _)| name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn translate_names_and_ids() {
+        let zone = |name: &str, id: &str| Az {
+            name: AvailabilityZone::new(name.to_owned()),
+            id: ZoneId::new(id.to_owned()),
+            state: AvailabilityZoneState::new(aws_sdk_ec2::types::AvailabilityZoneState::Available),
+            opt_in_status: AvailabilityZoneOptInStatus::new(
+                aws_sdk_ec2::types::AvailabilityZoneOptInStatus::OptInNotRequired,
+            ),
+        };
+
+        let mapping = ZoneMapping::from_zones([
+            zone("eu-central-1a", "euc1-az2"),
+            zone("eu-central-1b", "euc1-az3"),
+        ]);
+
+        assert_eq!(
+            mapping.zone_id(&AvailabilityZone::new("eu-central-1b".to_owned())),
+            Some(&ZoneId::new("euc1-az3".to_owned()))
+        );
+        assert_eq!(
+            mapping.zone_name(&ZoneId::new("euc1-az2".to_owned())),
+            Some(&AvailabilityZone::new("eu-central-1a".to_owned()))
+        );
+        assert!(mapping
+            .zone_id(&AvailabilityZone::new("eu-central-1c".to_owned()))
+            .is_none());
+    }
+}
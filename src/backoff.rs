@@ -0,0 +1,54 @@
+use std::time::Duration;
+
+/// Exponential backoff with full jitter
+pub(crate) struct Backoff {
+    base: Duration,
+    max: Duration,
+    attempt: u32,
+}
+
+impl Backoff {
+    pub(crate) const fn new(base: Duration, max: Duration) -> Self {
+        Self {
+            base,
+            max,
+            attempt: 0,
+        }
+    }
+
+    /// Returns a random delay between zero and the exponentially growing upper bound
+    pub(crate) fn next_delay(&mut self) -> Duration {
+        let bound = self
+            .base
+            .saturating_mul(2_u32.saturating_pow(self.attempt))
+            .min(self.max);
+        self.attempt = self.attempt.saturating_add(1);
+
+        Duration::from_millis(fastrand::u64(
+            0..=u64::try_from(bound.as_millis()).unwrap_or(u64::MAX),
+        ))
+    }
+
+    pub(crate) async fn sleep(&mut self) {
+        tokio::time::sleep(self.next_delay()).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delays_are_bounded() {
+        let mut backoff = Backoff::new(Duration::from_millis(100), Duration::from_secs(1));
+
+        for bound in [100, 200, 400, 800, 1000, 1000] {
+            let delay = backoff.next_delay();
+            assert!(
+                delay <= Duration::from_millis(bound),
+                "delay {}ms exceeds {bound}ms",
+                delay.as_millis()
+            );
+        }
+    }
+}
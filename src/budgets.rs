@@ -0,0 +1,225 @@
+//! Cost guardrails for everything carrying a tag: a monthly budget and a cost anomaly
+//! monitor, both alerting to an SNS topic
+//!
+//! The tag has to be activated as a cost allocation tag in the billing console,
+//! otherwise neither the budget nor the monitor see any costs.
+
+use std::collections::HashMap;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::{tags::RawTag, Error, RegionClient};
+
+const CURRENCY: &str = "USD";
+/// Budgets filter on tags as `user:<key>$<value>`
+const BUDGET_TAG_FILTER: &str = "TagKeyValue";
+/// Alert when actual spend passes this percentage of the limit
+const ACTUAL_THRESHOLD_PERCENT: f64 = 80.0;
+/// Alert when the forecast for the month passes this percentage of the limit
+const FORECASTED_THRESHOLD_PERCENT: f64 = 100.0;
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CostGuardrails {
+    pub budget_name: String,
+    pub anomaly_monitor_arn: String,
+    pub anomaly_subscription_arn: String,
+}
+
+/// Budget names must not contain `:` and `\`
+fn budget_name(tag: &RawTag) -> String {
+    format!("tag-{}-{}", tag.key(), tag.value().as_str())
+        .chars()
+        .map(|c| if matches!(c, ':' | '\\') { '-' } else { c })
+        .collect()
+}
+
+fn budget_notification(
+    notification_type: aws_sdk_budgets::types::NotificationType,
+    threshold_percent: f64,
+    sns_topic_arn: &str,
+) -> aws_sdk_budgets::types::NotificationWithSubscribers {
+    aws_sdk_budgets::types::NotificationWithSubscribers::builder()
+        .notification(
+            aws_sdk_budgets::types::Notification::builder()
+                .notification_type(notification_type)
+                .comparison_operator(aws_sdk_budgets::types::ComparisonOperator::GreaterThan)
+                .threshold(threshold_percent)
+                .threshold_type(aws_sdk_budgets::types::ThresholdType::Percentage)
+                .build()
+                .expect("builder has all required fields set"),
+        )
+        .subscribers(
+            aws_sdk_budgets::types::Subscriber::builder()
+                .subscription_type(aws_sdk_budgets::types::SubscriptionType::Sns)
+                .address(sns_topic_arn)
+                .build()
+                .expect("builder has all required fields set"),
+        )
+        .build()
+        .expect("builder has all required fields set")
+}
+
+async fn create_budget(
+    client: &RegionClient,
+    name: &str,
+    tag: &RawTag,
+    monthly_limit_usd: u64,
+    sns_topic_arn: &str,
+) -> Result<(), Error> {
+    let account = client.caller_identity().await?;
+
+    let budget = aws_sdk_budgets::types::Budget::builder()
+        .budget_name(name)
+        .budget_type(aws_sdk_budgets::types::BudgetType::Cost)
+        .time_unit(aws_sdk_budgets::types::TimeUnit::Monthly)
+        .budget_limit(
+            aws_sdk_budgets::types::Spend::builder()
+                .amount(monthly_limit_usd.to_string())
+                .unit(CURRENCY)
+                .build()
+                .expect("builder has all required fields set"),
+        )
+        .set_cost_filters(Some(HashMap::from([(
+            BUDGET_TAG_FILTER.to_owned(),
+            vec![format!("user:{}${}", tag.key(), tag.value().as_str())],
+        )])))
+        .build()
+        .expect("builder has all required fields set");
+
+    let _output = client
+        .main
+        .budgets
+        .create_budget()
+        .account_id(account.account().id())
+        .budget(budget)
+        .notifications_with_subscribers(budget_notification(
+            aws_sdk_budgets::types::NotificationType::Actual,
+            ACTUAL_THRESHOLD_PERCENT,
+            sns_topic_arn,
+        ))
+        .notifications_with_subscribers(budget_notification(
+            aws_sdk_budgets::types::NotificationType::Forecasted,
+            FORECASTED_THRESHOLD_PERCENT,
+            sns_topic_arn,
+        ))
+        .send()
+        .await?;
+
+    Ok(())
+}
+
+async fn create_anomaly_monitor(
+    client: &RegionClient,
+    name: &str,
+    tag: &RawTag,
+    anomaly_threshold_usd: u64,
+    sns_topic_arn: &str,
+) -> Result<(String, String), Error> {
+    let costexplorer = &client.main.costexplorer;
+
+    let monitor_arn = costexplorer
+        .create_anomaly_monitor()
+        .anomaly_monitor(
+            aws_sdk_costexplorer::types::AnomalyMonitor::builder()
+                .monitor_name(name)
+                .monitor_type(aws_sdk_costexplorer::types::MonitorType::Custom)
+                .monitor_specification(
+                    aws_sdk_costexplorer::types::Expression::builder()
+                        .tags(
+                            aws_sdk_costexplorer::types::TagValues::builder()
+                                .key(tag.key().as_str())
+                                .values(tag.value().as_str())
+                                .match_options(aws_sdk_costexplorer::types::MatchOption::Equals)
+                                .build(),
+                        )
+                        .build(),
+                )
+                .build()
+                .expect("builder has all required fields set"),
+        )
+        .send()
+        .await?
+        .monitor_arn;
+
+    let subscription_arn = costexplorer
+        .create_anomaly_subscription()
+        .anomaly_subscription(
+            aws_sdk_costexplorer::types::AnomalySubscription::builder()
+                .subscription_name(name)
+                .monitor_arn_list(&monitor_arn)
+                .subscribers(
+                    aws_sdk_costexplorer::types::Subscriber::builder()
+                        .r#type(aws_sdk_costexplorer::types::SubscriberType::Sns)
+                        .address(sns_topic_arn)
+                        .build(),
+                )
+                // SNS subscribers only support immediate alerts
+                .frequency(aws_sdk_costexplorer::types::AnomalySubscriptionFrequency::Immediate)
+                .threshold_expression(
+                    aws_sdk_costexplorer::types::Expression::builder()
+                        .dimensions(
+                            aws_sdk_costexplorer::types::DimensionValues::builder()
+                                .key(
+                                    aws_sdk_costexplorer::types::Dimension::AnomalyTotalImpactAbsolute,
+                                )
+                                .values(anomaly_threshold_usd.to_string())
+                                .match_options(
+                                    aws_sdk_costexplorer::types::MatchOption::GreaterThanOrEqual,
+                                )
+                                .build(),
+                        )
+                        .build(),
+                )
+                .build()
+                .expect("builder has all required fields set"),
+        )
+        .send()
+        .await?
+        .subscription_arn;
+
+    Ok((monitor_arn, subscription_arn))
+}
+
+/// Creates a monthly cost budget of `monthly_limit_usd` for all resources tagged with
+/// `tag`, and a cost anomaly monitor for the same resources.
+///
+/// The budget alerts when actual spend exceeds 80% of the limit and when the forecast
+/// exceeds the limit. The anomaly monitor alerts on anomalies with a total impact of
+/// at least `anomaly_threshold_usd`. All alerts go to `sns_topic_arn`, whose policy
+/// has to allow `budgets.amazonaws.com` and `costalerts.amazonaws.com` to publish.
+#[expect(
+    clippy::missing_panics_doc,
+    reason = "only expect() on builder instances"
+)]
+pub async fn create_budget_for_tag(
+    client: &RegionClient,
+    tag: &RawTag,
+    monthly_limit_usd: u64,
+    anomaly_threshold_usd: u64,
+    sns_topic_arn: &str,
+) -> Result<CostGuardrails, Error> {
+    let name = budget_name(tag);
+
+    create_budget(client, &name, tag, monthly_limit_usd, sns_topic_arn).await?;
+    let (anomaly_monitor_arn, anomaly_subscription_arn) =
+        create_anomaly_monitor(client, &name, tag, anomaly_threshold_usd, sns_topic_arn).await?;
+
+    Ok(CostGuardrails {
+        budget_name: name,
+        anomaly_monitor_arn,
+        anomaly_subscription_arn,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn budget_name_from_tag() {
+        let tag = RawTag::new("environment".to_owned(), "prod:eu".to_owned());
+        assert_eq!(budget_name(&tag), "tag-environment-prod-eu");
+    }
+}
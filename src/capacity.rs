@@ -0,0 +1,244 @@
+//! On-demand capacity reservations for EC2 instances
+
+use super::{
+    pagination, tags::TagList, Ami, AvailabilityZone, Error, InstanceType, Protection,
+    RegionClient, Timestamp,
+};
+
+crate::wrap_aws_enum!(CapacityReservationState);
+
+crate::string_newtype!(CapacityReservationId);
+
+impl CapacityReservationId {
+    pub const fn new(value: String) -> Self {
+        Self(value)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub(crate) fn to_specification(&self) -> aws_sdk_ec2::types::CapacityReservationSpecification {
+        aws_sdk_ec2::types::CapacityReservationSpecification::builder()
+            .capacity_reservation_target(
+                aws_sdk_ec2::types::CapacityReservationTarget::builder()
+                    .capacity_reservation_id(self.as_str())
+                    .build(),
+            )
+            .build()
+    }
+}
+
+impl crate::Ec2ResourceId for CapacityReservationId {
+    fn resource_id(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Parameters for [`CapacityReservation::create()`]
+///
+/// Without an `end_date`, the reservation stays active until it is cancelled.
+pub struct NewCapacityReservation<'a> {
+    /// The AMI the reserved instances are launched from. Instances only use the
+    /// reservation if their platform matches, so it is taken from the AMI.
+    pub ami: &'a Ami,
+    pub instance_type: &'a InstanceType,
+    pub availability_zone: &'a AvailabilityZone,
+    pub instance_count: i32,
+    pub end_date: Option<Timestamp>,
+    pub tags: &'a TagList,
+}
+
+#[derive(Debug, Clone)]
+pub struct CapacityReservation {
+    id: CapacityReservationId,
+    instance_type: InstanceType,
+    availability_zone: AvailabilityZone,
+    total_instance_count: i32,
+    available_instance_count: i32,
+    state: CapacityReservationState,
+    end_date: Option<Timestamp>,
+    tags: TagList,
+}
+
+impl TryFrom<aws_sdk_ec2::types::CapacityReservation> for CapacityReservation {
+    type Error = Error;
+
+    fn try_from(reservation: aws_sdk_ec2::types::CapacityReservation) -> Result<Self, Self::Error> {
+        macro_rules! extract {
+            ($field:ident) => {
+                reservation
+                    .$field
+                    .ok_or_else(|| Error::UnexpectedNoneValue {
+                        entity: stringify!($field).to_owned(),
+                    })
+            };
+        }
+
+        Ok(Self {
+            id: CapacityReservationId(extract!(capacity_reservation_id)?),
+            instance_type: InstanceType(extract!(instance_type)?.as_str().into()),
+            availability_zone: AvailabilityZone(extract!(availability_zone)?),
+            total_instance_count: extract!(total_instance_count)?,
+            available_instance_count: extract!(available_instance_count)?,
+            state: CapacityReservationState(extract!(state)?),
+            end_date: reservation.end_date.map(TryInto::try_into).transpose()?,
+            tags: reservation.tags.unwrap_or_default().try_into()?,
+        })
+    }
+}
+
+/// AMIs without platform details are taken to be Linux, like EC2 does for billing
+fn instance_platform(ami: &Ami) -> aws_sdk_ec2::types::CapacityReservationInstancePlatform {
+    ami.platform_details.as_deref().map_or(
+        aws_sdk_ec2::types::CapacityReservationInstancePlatform::LinuxUnix,
+        aws_sdk_ec2::types::CapacityReservationInstancePlatform::from,
+    )
+}
+
+impl CapacityReservation {
+    pub async fn create(
+        client: &RegionClient,
+        config: &NewCapacityReservation<'_>,
+    ) -> Result<Self, Error> {
+        let end_date_type = if config.end_date.is_some() {
+            aws_sdk_ec2::types::EndDateType::Limited
+        } else {
+            aws_sdk_ec2::types::EndDateType::Unlimited
+        };
+
+        client
+            .main
+            .ec2
+            .create_capacity_reservation()
+            .instance_type(config.instance_type.inner().as_str())
+            .instance_platform(instance_platform(config.ami))
+            .availability_zone(config.availability_zone.as_str())
+            .instance_count(config.instance_count)
+            .end_date_type(end_date_type)
+            .set_end_date(config.end_date.map(Into::into))
+            .tag_specifications(
+                aws_sdk_ec2::types::TagSpecification::builder()
+                    .resource_type(aws_sdk_ec2::types::ResourceType::CapacityReservation)
+                    .set_tags(Some(client.tags_with_defaults(config.tags).into()))
+                    .build(),
+            )
+            .send()
+            .await?
+            .capacity_reservation
+            .ok_or(Error::UnexpectedNoneValue {
+                entity: "CreateCapacityReservationOutput.capacity_reservation".to_owned(),
+            })?
+            .try_into()
+    }
+
+    pub async fn find_by_tags(client: &RegionClient, tags: &TagList) -> Result<Vec<Self>, Error> {
+        pagination::list_capacity_reservations(client, tags.clone().into())
+            .await?
+            .into_iter()
+            .map(TryInto::try_into)
+            .collect()
+    }
+
+    pub async fn modify_count(&self, client: &RegionClient, count: i32) -> Result<(), Error> {
+        let _output = client
+            .main
+            .ec2
+            .modify_capacity_reservation()
+            .capacity_reservation_id(self.id.as_str())
+            .instance_count(count)
+            .send()
+            .await?;
+
+        Ok(())
+    }
+
+    /// Cancels the reservation. Fails with [`Error::ResourceProtected`] if the reservation
+    /// carries the protection tag of `client`, unless `protection` is
+    /// [`Protection::Override`].
+    pub async fn cancel(&self, client: &RegionClient, protection: Protection) -> Result<(), Error> {
+        client.check_protection(&self.id, &self.tags, protection)?;
+
+        let _output = client
+            .main
+            .ec2
+            .cancel_capacity_reservation()
+            .capacity_reservation_id(self.id.as_str())
+            .send()
+            .await?;
+
+        Ok(())
+    }
+
+    pub const fn id(&self) -> &CapacityReservationId {
+        &self.id
+    }
+
+    pub const fn instance_type(&self) -> &InstanceType {
+        &self.instance_type
+    }
+
+    pub const fn availability_zone(&self) -> &AvailabilityZone {
+        &self.availability_zone
+    }
+
+    pub const fn total_instance_count(&self) -> i32 {
+        self.total_instance_count
+    }
+
+    pub const fn available_instance_count(&self) -> i32 {
+        self.available_instance_count
+    }
+
+    pub const fn state(&self) -> &CapacityReservationState {
+        &self.state
+    }
+
+    pub const fn end_date(&self) -> Option<&Timestamp> {
+        self.end_date.as_ref()
+    }
+
+    pub const fn tags(&self) -> &TagList {
+        &self.tags
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ami(platform_details: Option<&str>) -> Ami {
+        Ami {
+            id: crate::AmiId("ami-0123".to_owned()),
+            tags: TagList::new(),
+            creation_date: Timestamp::now(),
+            boot_mode: None,
+            tpm_support: None,
+            ena_support: true,
+            sriov_net_support: false,
+            root_device_name: None,
+            architecture: None,
+            platform_details: platform_details.map(ToOwned::to_owned),
+        }
+    }
+
+    #[test]
+    fn platform_from_ami() {
+        assert_eq!(
+            instance_platform(&ami(Some("Windows"))),
+            aws_sdk_ec2::types::CapacityReservationInstancePlatform::Windows
+        );
+        assert_eq!(
+            instance_platform(&ami(Some("Red Hat Enterprise Linux"))),
+            aws_sdk_ec2::types::CapacityReservationInstancePlatform::RedHatEnterpriseLinux
+        );
+        assert_eq!(
+            instance_platform(&ami(Some("Linux/UNIX"))),
+            aws_sdk_ec2::types::CapacityReservationInstancePlatform::LinuxUnix
+        );
+        assert_eq!(
+            instance_platform(&ami(None)),
+            aws_sdk_ec2::types::CapacityReservationInstancePlatform::LinuxUnix
+        );
+    }
+}
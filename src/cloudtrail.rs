@@ -0,0 +1,176 @@
+//! Looking up who changed a resource, via CloudTrail
+//!
+//! Only management events of the last 90 days are available, as recorded by the
+//! event history of CloudTrail. No trail needs to be set up for this.
+
+use std::net::IpAddr;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::{pagination, Error, RegionClient, Timestamp};
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangeEvent {
+    pub event_id: String,
+    /// The API call, e.g. `StopInstances`
+    pub event_name: String,
+    /// The ARN of the calling identity, or its user name if the ARN is not recorded
+    pub principal: Option<String>,
+    /// `None` if the call was made by an AWS service on behalf of the principal, as
+    /// CloudTrail then records the hostname of the service instead
+    pub source_ip: Option<IpAddr>,
+    pub time: Timestamp,
+}
+
+struct EventDetails {
+    principal_arn: Option<String>,
+    source_ip: Option<IpAddr>,
+}
+
+fn parse_event_details(event: &str) -> Result<EventDetails, Error> {
+    let event: serde_json::Value =
+        serde_json::from_str(event).map_err(|e| Error::InvalidResponseError {
+            message: format!("invalid cloudtrail event: {e}"),
+        })?;
+
+    Ok(EventDetails {
+        principal_arn: event
+            .get("userIdentity")
+            .and_then(|identity| identity.get("arn"))
+            .and_then(serde_json::Value::as_str)
+            .map(ToOwned::to_owned),
+        source_ip: event
+            .get("sourceIPAddress")
+            .and_then(serde_json::Value::as_str)
+            .and_then(|address| address.parse().ok()),
+    })
+}
+
+impl TryFrom<aws_sdk_cloudtrail::types::Event> for ChangeEvent {
+    type Error = Error;
+
+    fn try_from(event: aws_sdk_cloudtrail::types::Event) -> Result<Self, Self::Error> {
+        let details = event
+            .cloud_trail_event
+            .as_deref()
+            .map(parse_event_details)
+            .transpose()?;
+
+        let (principal_arn, source_ip) = match details {
+            Some(details) => (details.principal_arn, details.source_ip),
+            None => (None, None),
+        };
+
+        Ok(Self {
+            event_id: event.event_id.ok_or_else(|| Error::UnexpectedNoneValue {
+                entity: "Event.event_id".to_owned(),
+            })?,
+            event_name: event.event_name.ok_or_else(|| Error::UnexpectedNoneValue {
+                entity: "Event.event_name".to_owned(),
+            })?,
+            principal: principal_arn.or(event.username),
+            source_ip,
+            time: event
+                .event_time
+                .ok_or_else(|| Error::UnexpectedNoneValue {
+                    entity: "Event.event_time".to_owned(),
+                })?
+                .try_into()?,
+        })
+    }
+}
+
+/// Lists the changes to the resource `resource_id` since `since`, newest first.
+///
+/// `resource_id` is whatever identifies the resource in CloudTrail, e.g. an instance
+/// ID, the allocation ID of an EIP or the ID of a hosted zone. Read-only calls like
+/// `Describe*` are not included.
+#[expect(
+    clippy::missing_panics_doc,
+    reason = "only expect() on builder instances"
+)]
+pub async fn who_changed(
+    client: &RegionClient,
+    resource_id: &str,
+    since: Timestamp,
+) -> Result<Vec<ChangeEvent>, Error> {
+    let cloudtrail = &client.main.cloudtrail;
+    let attribute = aws_sdk_cloudtrail::types::LookupAttribute::builder()
+        .attribute_key(aws_sdk_cloudtrail::types::LookupAttributeKey::ResourceName)
+        .attribute_value(resource_id)
+        .build()
+        .expect("builder has all required fields set");
+    let attribute = &attribute;
+
+    // Lookups are limited to two requests per second per account, so throttling is
+    // expected for resources with a long history
+    let events = pagination::paginate(|token| async move {
+        let response = cloudtrail
+            .lookup_events()
+            .lookup_attributes(attribute.clone())
+            .start_time(since.into())
+            .set_next_token(token)
+            .send()
+            .await?;
+
+        Ok(pagination::Page {
+            items: response.events.unwrap_or_default(),
+            next_token: response.next_token,
+        })
+    })
+    .await?;
+
+    events
+        .into_iter()
+        .filter(|event| event.read_only.as_deref() != Some("true"))
+        .map(ChangeEvent::try_from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_details() {
+        let details = parse_event_details(
+            r#"{
+                "eventName": "StopInstances",
+                "userIdentity": {
+                    "type": "AssumedRole",
+                    "arn": "arn:aws:sts::111111111111:assumed-role/admin/alice"
+                },
+                "sourceIPAddress": "192.0.2.1"
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            details.principal_arn.as_deref(),
+            Some("arn:aws:sts::111111111111:assumed-role/admin/alice")
+        );
+        assert_eq!(details.source_ip, Some("192.0.2.1".parse().unwrap()));
+
+        let details = parse_event_details(
+            r#"{
+                "eventName": "TerminateInstances",
+                "userIdentity": {
+                    "type": "AWSService",
+                    "invokedBy": "autoscaling.amazonaws.com"
+                },
+                "sourceIPAddress": "autoscaling.amazonaws.com"
+            }"#,
+        )
+        .unwrap();
+
+        assert!(details.principal_arn.is_none());
+        assert!(details.source_ip.is_none());
+
+        assert!(matches!(
+            parse_event_details("not json"),
+            Err(Error::InvalidResponseError { ref message }) if message.starts_with("invalid cloudtrail event")
+        ));
+    }
+}
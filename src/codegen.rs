@@ -0,0 +1,198 @@
+//! CloudFormation template fragments from the typed configs of this crate
+//!
+//! The same config that [`launch_ec2_instance()`](crate::launch_ec2_instance()) or
+//! [`create_route53_record()`](crate::create_route53_record()) applies can be rendered
+//! as a template resource instead, so resources can move between imperative and
+//! declarative management without maintaining two definitions.
+//!
+//! The rendered resources match what the imperative path creates, including the
+//! default tags of the client, with one exception: `AWS::EC2::Instance` has no metadata
+//! options, so these keep the AWS defaults.
+
+use serde_json::{json, Map, Value};
+
+use super::{
+    tags::TagList, BlockDeviceConfig, Error, NewEc2Config, RecordTarget, RegionClient, Route53Zone,
+    RECORD_TTL,
+};
+
+/// The `Resources` section of a template
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Template {
+    resources: Map<String, Value>,
+}
+
+impl Template {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fails if a resource with `logical_id` already exists
+    pub fn add_resource(&mut self, logical_id: &str, resource: Value) -> Result<(), Error> {
+        if self.resources.contains_key(logical_id) {
+            return Err(Error::InvalidArgument {
+                message: format!("duplicate logical id \"{logical_id}\" in template"),
+            });
+        }
+        let _previous = self.resources.insert(logical_id.to_owned(), resource);
+        Ok(())
+    }
+
+    pub fn to_value(&self) -> Value {
+        json!({
+            "AWSTemplateFormatVersion": "2010-09-09",
+            "Resources": self.resources,
+        })
+    }
+
+    pub fn to_json(&self) -> Result<String, Error> {
+        serde_json::to_string_pretty(&self.to_value()).map_err(|e| Error::InvalidArgument {
+            message: format!("cannot render template: {e}"),
+        })
+    }
+
+    #[cfg(feature = "codegen-yaml")]
+    pub fn to_yaml(&self) -> Result<String, Error> {
+        serde_yaml::to_string(&self.to_value()).map_err(|e| Error::InvalidArgument {
+            message: format!("cannot render template: {e}"),
+        })
+    }
+}
+
+fn tags(tags: &TagList) -> Value {
+    tags.as_slice()
+        .iter()
+        .map(|tag| json!({ "Key": tag.key().as_str(), "Value": tag.value().as_str() }))
+        .collect()
+}
+
+/// Inserts `value` under `key` unless it is `None`
+fn insert_some(properties: &mut Map<String, Value>, key: &str, value: Option<Value>) {
+    if let Some(value) = value {
+        let _previous = properties.insert(key.to_owned(), value);
+    }
+}
+
+fn block_device_mapping(config: &BlockDeviceConfig, device_name: &str) -> Value {
+    let mut ebs = Map::new();
+    insert_some(&mut ebs, "VolumeSize", config.size_gib.map(Value::from));
+    insert_some(
+        &mut ebs,
+        "VolumeType",
+        config
+            .volume_type
+            .as_ref()
+            .map(|volume_type| Value::from(volume_type.inner().as_str())),
+    );
+    insert_some(&mut ebs, "Iops", config.iops.map(Value::from));
+    insert_some(&mut ebs, "Throughput", config.throughput.map(Value::from));
+    insert_some(&mut ebs, "Encrypted", config.encrypted.map(Value::from));
+    insert_some(
+        &mut ebs,
+        "DeleteOnTermination",
+        config.delete_on_termination.map(Value::from),
+    );
+
+    json!({ "DeviceName": device_name, "Ebs": ebs })
+}
+
+/// An `AWS::EC2::Instance` resource, as [`launch_ec2_instance()`](crate::launch_ec2_instance())
+/// would launch it
+pub fn instance(client: &RegionClient, config: &NewEc2Config<'_>) -> Value {
+    let mut properties = Map::new();
+    let _previous = properties.insert("ImageId".to_owned(), config.ami.id.as_str().into());
+    let _previous = properties.insert(
+        "InstanceType".to_owned(),
+        config.instance_type.inner().as_str().into(),
+    );
+    let _previous = properties.insert(
+        "KeyName".to_owned(),
+        config.instance_keypair_name.as_str().into(),
+    );
+    let _previous = properties.insert(
+        "SecurityGroupIds".to_owned(),
+        json!([config.security_group.id.as_str()]),
+    );
+    let _previous = properties.insert("SubnetId".to_owned(), config.subnet_id.as_str().into());
+    let _previous = properties.insert("UserData".to_owned(), config.user_data.into());
+    let _previous = properties.insert(
+        "IamInstanceProfile".to_owned(),
+        config.instance_profile_name.as_str().into(),
+    );
+    let _previous = properties.insert("Monitoring".to_owned(), config.detailed_monitoring.into());
+    let _previous = properties.insert("DisableApiTermination".to_owned(), true.into());
+    insert_some(
+        &mut properties,
+        "InstanceInitiatedShutdownBehavior",
+        config
+            .shutdown_behavior
+            .map(|behavior| behavior.inner().as_str().into()),
+    );
+    insert_some(
+        &mut properties,
+        "CapacityReservationSpecification",
+        config.capacity_reservation.map(|reservation| {
+            json!({
+                "CapacityReservationTarget": {
+                    "CapacityReservationId": reservation.as_str(),
+                },
+            })
+        }),
+    );
+    insert_some(
+        &mut properties,
+        "BlockDeviceMappings",
+        config.root_volume.map(|root_volume| {
+            json!([block_device_mapping(root_volume, config.ami.root_device())])
+        }),
+    );
+    let _previous = properties.insert(
+        "Tags".to_owned(),
+        tags(&client.tags_with_defaults(config.tags)),
+    );
+
+    json!({ "Type": "AWS::EC2::Instance", "Properties": properties })
+}
+
+/// An `AWS::Route53::RecordSet` resource, as
+/// [`create_route53_record()`](crate::create_route53_record()) would create it
+pub fn record_set(
+    target: RecordTarget<'_>,
+    zone: &Route53Zone,
+    fqdn: &str,
+) -> Result<Value, Error> {
+    target.check_zone(zone)?;
+
+    Ok(json!({
+        "Type": "AWS::Route53::RecordSet",
+        "Properties": {
+            "HostedZoneId": zone.hosted_zone_id.as_str(),
+            "Name": fqdn,
+            "Type": "A",
+            "TTL": RECORD_TTL.to_string(),
+            "ResourceRecords": [target.ip()?],
+        },
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duplicate_logical_ids() {
+        let mut template = Template::new();
+        template
+            .add_resource("Record", json!({ "Type": "AWS::Route53::RecordSet" }))
+            .unwrap();
+
+        assert!(matches!(
+            template.add_resource("Record", json!({ "Type": "AWS::Route53::RecordSet" })),
+            Err(Error::InvalidArgument { ref message }) if message.contains("\"Record\"")
+        ));
+        assert_eq!(
+            template.to_value()["Resources"]["Record"]["Type"],
+            "AWS::Route53::RecordSet"
+        );
+    }
+}
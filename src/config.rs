@@ -0,0 +1,290 @@
+//! Loading everything needed to build clients from a config file
+//!
+//! ```toml
+//! regions = ["eu-central-1", "us-east-1"]
+//!
+//! [profiles]
+//! main = "production"
+//! # defaults to the main profile
+//! cdn = "production-cdn"
+//! # optional, assumed by the region clients, using the main profile
+//! role = "arn:aws:iam::111111111111:role/deploy"
+//! # optional, assumed by the CDN client, using the CDN profile
+//! cdn_role = "arn:aws:iam::222222222222:role/deploy-cdn"
+//! # optional, e.g. for LocalStack
+//! endpoint_url = "http://localhost:4566"
+//! # optional, identify the application in the user agent
+//! app_name = "deployer"
+//! user_agent_suffix = "deployer/1.2.0"
+//!
+//! # optional, in seconds, see `WaitConfig`
+//! [wait]
+//! instance_start = 900
+//!
+//! [default_tags]
+//! team = "infrastructure"
+//! ```
+//!
+//! YAML files with the same structure are supported with the `config-yaml` feature.
+
+use std::{collections::BTreeMap, fs, path::Path};
+
+use serde::{Deserialize, Deserializer};
+
+use super::{
+    load_cdn_client, load_region_client, tags::TagList, waiter::WaitConfig, CdnClient, Error,
+    ProfileConfig, ProfileName, Region, RegionClient, RoleArn,
+};
+
+fn deserialize_app_name<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<aws_config::AppName>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|app_name| aws_config::AppName::new(app_name).map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProfilesConfig {
+    main: String,
+    cdn: Option<String>,
+    role: Option<RoleArn>,
+    cdn_role: Option<RoleArn>,
+    endpoint_url: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_app_name")]
+    app_name: Option<aws_config::AppName>,
+    user_agent_suffix: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ClientConfig {
+    regions: Vec<Region>,
+    profiles: ProfilesConfig,
+    /// Missing fields keep their default, see [`WaitConfig`]
+    #[serde(default)]
+    wait: WaitConfig,
+    #[serde(default)]
+    default_tags: BTreeMap<String, String>,
+}
+
+fn config_error(message: impl Into<String>) -> Error {
+    Error::ConfigError {
+        message: message.into(),
+    }
+}
+
+impl ClientConfig {
+    pub fn from_toml_str(input: &str) -> Result<Self, Error> {
+        toml::from_str(input).map_err(|e| config_error(e.to_string()))
+    }
+
+    #[cfg(feature = "config-yaml")]
+    pub fn from_yaml_str(input: &str) -> Result<Self, Error> {
+        serde_yaml::from_str(input).map_err(|e| config_error(e.to_string()))
+    }
+
+    /// Reads the file at `path`, as YAML if its extension is `yaml` or `yml`, and as TOML
+    /// otherwise.
+    pub fn from_file(path: &Path) -> Result<Self, Error> {
+        let input = fs::read_to_string(path)
+            .map_err(|e| config_error(format!("cannot read {}: {e}", path.display())))?;
+
+        match path.extension().and_then(|extension| extension.to_str()) {
+            #[cfg(feature = "config-yaml")]
+            Some("yaml" | "yml") => Self::from_yaml_str(&input),
+            #[cfg(not(feature = "config-yaml"))]
+            Some("yaml" | "yml") => Err(config_error(
+                "yaml config files require the config-yaml feature",
+            )),
+            _ => Self::from_toml_str(&input),
+        }
+    }
+
+    pub fn regions(&self) -> &[Region] {
+        &self.regions
+    }
+
+    pub fn profile_config(&self) -> ProfileConfig {
+        let main = ProfileName::new(self.profiles.main.clone());
+        ProfileConfig {
+            profile_name_cdn: self
+                .profiles
+                .cdn
+                .clone()
+                .map_or_else(|| main.clone(), ProfileName::new),
+            profile_name_main: main,
+            endpoint_url: self.profiles.endpoint_url.clone(),
+            app_name: self.profiles.app_name.clone(),
+            user_agent_suffix: self.profiles.user_agent_suffix.clone(),
+            operation_log: None,
+            notifier: None,
+        }
+    }
+
+    /// The role assumed by the clients of the main profile
+    pub const fn role(&self) -> Option<&RoleArn> {
+        self.profiles.role.as_ref()
+    }
+
+    /// The role assumed by the clients of the CDN profile
+    pub const fn cdn_role(&self) -> Option<&RoleArn> {
+        self.profiles.cdn_role.as_ref()
+    }
+
+    /// Tags that should be applied to all created resources
+    pub fn default_tags(&self) -> TagList {
+        self.default_tags.clone().into()
+    }
+
+    /// The default [`WaitConfig`] with the overrides of the config file
+    pub const fn wait_config(&self) -> WaitConfig {
+        self.wait
+    }
+
+    /// Loads a client for each region, with the [`wait_config()`](Self::wait_config())
+    /// and the [`default_tags()`](Self::default_tags()) applied.
+    pub async fn load_clients(&self) -> Vec<RegionClient> {
+        let profile_config = self.profile_config();
+
+        let mut clients = vec![];
+        for region in &self.regions {
+            clients.push(
+                load_region_client(*region, &profile_config, self.role())
+                    .await
+                    .with_wait_config(self.wait_config())
+                    .with_default_tags(self.default_tags()),
+            );
+        }
+        clients
+    }
+
+    /// Loads the [`CdnClient`], with the [`wait_config()`](Self::wait_config()) and the
+    /// [`default_tags()`](Self::default_tags()) applied.
+    pub async fn load_cdn_client(&self) -> CdnClient {
+        load_cdn_client(&self.profile_config(), self.cdn_role())
+            .await
+            .with_wait_config(self.wait_config())
+            .with_default_tags(self.default_tags())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn parse_toml() {
+        let config = ClientConfig::from_toml_str(
+            r#"
+            regions = ["eu-central-1"]
+
+            [profiles]
+            main = "production"
+            app_name = "deployer"
+
+            [wait]
+            instance_start = 900
+
+            [default_tags]
+            team = "infrastructure"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.regions().len(), 1);
+        assert_eq!(
+            config.profile_config().profile_name_cdn.as_str(),
+            "production"
+        );
+        assert!(config.role().is_none());
+        assert!(config.cdn_role().is_none());
+        assert!(config.profile_config().app_name.is_some());
+        assert_eq!(
+            config.wait_config().instance_start,
+            Duration::from_secs(900)
+        );
+        assert_eq!(
+            config.wait_config().instance_stop,
+            WaitConfig::default().instance_stop
+        );
+        assert!(config.default_tags().get("team".to_owned()).is_some());
+    }
+
+    #[test]
+    fn roles_per_profile() {
+        let config = ClientConfig::from_toml_str(
+            r#"
+            regions = []
+
+            [profiles]
+            main = "production"
+            role = "arn:aws:iam::111111111111:role/deploy"
+            cdn_role = "arn:aws:iam::222222222222:role/deploy-cdn"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.role().map(RoleArn::as_str),
+            Some("arn:aws:iam::111111111111:role/deploy")
+        );
+        assert_eq!(
+            config.cdn_role().map(RoleArn::as_str),
+            Some("arn:aws:iam::222222222222:role/deploy-cdn")
+        );
+    }
+
+    #[test]
+    fn reject_unknown_wait_fields() {
+        assert!(matches!(
+            ClientConfig::from_toml_str(
+                r#"
+                regions = []
+
+                [profiles]
+                main = "production"
+
+                [wait]
+                instance_sart = 900
+                "#,
+            ),
+            Err(Error::ConfigError { ref message }) if message.contains("instance_sart")
+        ));
+    }
+
+    #[test]
+    fn reject_invalid_app_name() {
+        assert!(matches!(
+            ClientConfig::from_toml_str(
+                r#"
+                regions = []
+
+                [profiles]
+                main = "production"
+                app_name = "no spaces allowed"
+                "#,
+            ),
+            Err(Error::ConfigError { .. })
+        ));
+    }
+
+    #[test]
+    fn reject_unknown_fields() {
+        assert!(matches!(
+            ClientConfig::from_toml_str(
+                r#"
+                regions = []
+                unknown = true
+
+                [profiles]
+                main = "production"
+                "#,
+            ),
+            Err(Error::ConfigError { ref message }) if message.contains("unknown")
+        ));
+    }
+}
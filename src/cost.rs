@@ -0,0 +1,407 @@
+//! Rough cost estimates for resources managed by this crate
+//!
+//! Estimates are meant for hints like "this will cost about $70/month" and ignore
+//! discounts, data transfer and usage-based charges. With the `pricing` feature,
+//! instance prices are looked up via the AWS Pricing API. Everything else uses fixed
+//! list prices of us-east-1.
+//!
+//! The creation calls in this module return the created resource together with its
+//! estimate as [`Costed`], e.g. [`allocate_eip()`] and, with the `pricing` feature,
+//! [`launch_ec2_instance()`]. An orchestration collects them in a [`CostReport`]:
+//!
+//! ```rust,no_run
+//! # use aws_lib::{cost::{self, CostReport}, tags::TagList, Error, RegionClient};
+//! # async fn f(client: &RegionClient, tags: &TagList) -> Result<(), Error> {
+//! let mut report = CostReport::new();
+//!
+//! let eip = report.record("eip", cost::allocate_eip(client, tags).await?);
+//!
+//! println!("{report}");
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! File systems are not created by this crate, [`efs_cost()`] estimates those created
+//! elsewhere. The other functions like [`volume_cost()`] estimate resources before
+//! they are created, e.g. for a prompt.
+
+use std::{fmt, iter::Sum};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::{tags::TagList, Eip, Error, RegionClient};
+#[cfg(feature = "pricing")]
+use super::{Instance, InstanceType, NewEc2Config};
+
+pub const HOURS_PER_MONTH: u64 = 730;
+
+const MICRODOLLARS_PER_DOLLAR: u64 = 1_000_000;
+const MICRODOLLARS_PER_CENT: u64 = 10_000;
+const CENTS_PER_DOLLAR: u64 = 100;
+
+/// Monthly list price of a public IPv4 address, in cents
+const EIP_MONTHLY_CENTS: u64 = 365;
+/// Monthly list price of one GiB of EFS standard storage, in cents
+const EFS_MONTHLY_CENTS_PER_GIB: u64 = 30;
+/// Monthly list price of one GiB of EBS snapshot storage (standard tier), in cents
+const SNAPSHOT_MONTHLY_CENTS_PER_GIB: u64 = 5;
+
+/// Monthly list price of one GiB of provisioned EBS storage, in cents. Provisioned
+/// IOPS and throughput are not included.
+const fn volume_monthly_cents_per_gib(volume_type: &aws_sdk_ec2::types::VolumeType) -> u64 {
+    match *volume_type {
+        aws_sdk_ec2::types::VolumeType::Gp3 => 8,
+        aws_sdk_ec2::types::VolumeType::Io1 | aws_sdk_ec2::types::VolumeType::Io2 => 13,
+        aws_sdk_ec2::types::VolumeType::St1 => 5,
+        aws_sdk_ec2::types::VolumeType::Sc1 => 2,
+        // gp2, standard and types introduced after this crate was written
+        _ => 10,
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct EstimatedCost {
+    microdollars_per_hour: u64,
+}
+
+impl EstimatedCost {
+    pub const fn from_hourly_microdollars(microdollars: u64) -> Self {
+        Self {
+            microdollars_per_hour: microdollars,
+        }
+    }
+
+    pub const fn from_monthly_cents(cents: u64) -> Self {
+        Self {
+            microdollars_per_hour: match cents
+                .saturating_mul(MICRODOLLARS_PER_CENT)
+                .checked_div(HOURS_PER_MONTH)
+            {
+                Some(value) => value,
+                None => 0,
+            },
+        }
+    }
+
+    /// Parses a decimal dollar amount as returned by the pricing API, e.g. `0.0960000000`.
+    ///
+    /// Digits beyond one microdollar are truncated.
+    pub fn parse_hourly_usd(value: &str) -> Option<Self> {
+        // `u64::from_str()` accepts a leading `+`, even after the decimal point
+        if !value
+            .bytes()
+            .all(|byte| byte.is_ascii_digit() || byte == b'.')
+        {
+            return None;
+        }
+
+        let (dollars, fraction) = value.split_once('.').unwrap_or((value, ""));
+
+        let dollars: u64 = dollars.parse().ok()?;
+
+        let fraction = fraction.get(..6).unwrap_or(fraction);
+        let microdollars = if fraction.is_empty() {
+            0
+        } else {
+            let digits = u32::try_from(fraction.len()).ok()?;
+            let fraction: u64 = fraction.parse().ok()?;
+            fraction.checked_mul(10_u64.checked_pow(6_u32.checked_sub(digits)?)?)?
+        };
+
+        Some(Self {
+            microdollars_per_hour: dollars
+                .checked_mul(MICRODOLLARS_PER_DOLLAR)?
+                .checked_add(microdollars)?,
+        })
+    }
+
+    pub const fn hourly_microdollars(self) -> u64 {
+        self.microdollars_per_hour
+    }
+
+    pub const fn monthly_microdollars(self) -> u64 {
+        self.microdollars_per_hour.saturating_mul(HOURS_PER_MONTH)
+    }
+
+    pub const fn monthly_cents(self) -> u64 {
+        match self
+            .monthly_microdollars()
+            .checked_div(MICRODOLLARS_PER_CENT)
+        {
+            Some(value) => value,
+            None => 0,
+        }
+    }
+
+    #[must_use]
+    pub const fn saturating_add(self, other: Self) -> Self {
+        Self {
+            microdollars_per_hour: self
+                .microdollars_per_hour
+                .saturating_add(other.microdollars_per_hour),
+        }
+    }
+}
+
+impl Sum for EstimatedCost {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), Self::saturating_add)
+    }
+}
+
+impl fmt::Display for EstimatedCost {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let cents = self.monthly_cents();
+        write!(
+            f,
+            "${}.{:02}/month",
+            cents.checked_div(CENTS_PER_DOLLAR).unwrap_or_default(),
+            cents.checked_rem(CENTS_PER_DOLLAR).unwrap_or_default()
+        )
+    }
+}
+
+/// A newly created resource together with its estimated cost
+#[derive(Debug, Clone)]
+pub struct Costed<T> {
+    pub resource: T,
+    pub cost: EstimatedCost,
+}
+
+/// The estimated costs of the resources created by an orchestration
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CostReport {
+    entries: Vec<(String, EstimatedCost)>,
+}
+
+impl CostReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the cost of `costed` under `label` and returns the resource
+    pub fn record<T>(&mut self, label: impl Into<String>, costed: Costed<T>) -> T {
+        self.entries.push((label.into(), costed.cost));
+        costed.resource
+    }
+
+    /// The recorded costs, in the order they were recorded
+    pub fn entries(&self) -> &[(String, EstimatedCost)] {
+        &self.entries
+    }
+
+    pub fn total(&self) -> EstimatedCost {
+        self.entries.iter().map(|&(_, cost)| cost).sum()
+    }
+}
+
+/// One line per entry, followed by the total
+impl fmt::Display for CostReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for &(ref label, cost) in &self.entries {
+            writeln!(f, "{label}: {cost}")?;
+        }
+        write!(f, "total: {}", self.total())
+    }
+}
+
+/// Estimated cost of an elastic IP, which is charged like any public IPv4 address
+pub const fn eip_cost() -> EstimatedCost {
+    EstimatedCost::from_monthly_cents(EIP_MONTHLY_CENTS)
+}
+
+/// Allocates an EIP like [`Eip::allocate()`], together with its estimated cost
+pub async fn allocate_eip(client: &RegionClient, tags: &TagList) -> Result<Costed<Eip>, Error> {
+    Ok(Costed {
+        resource: Eip::allocate(client, tags).await?,
+        cost: eip_cost(),
+    })
+}
+
+/// Estimated cost of an EFS filesystem storing `size_gib` in the standard storage class
+pub const fn efs_cost(size_gib: u64) -> EstimatedCost {
+    EstimatedCost::from_monthly_cents(size_gib.saturating_mul(EFS_MONTHLY_CENTS_PER_GIB))
+}
+
+/// Estimated cost of an EBS volume of `volume_type` with `size_gib`
+pub fn volume_cost(volume_type: &aws_sdk_ec2::types::VolumeType, size_gib: i32) -> EstimatedCost {
+    EstimatedCost::from_monthly_cents(
+        u64::try_from(size_gib)
+            .unwrap_or_default()
+            .saturating_mul(volume_monthly_cents_per_gib(volume_type)),
+    )
+}
+
+/// Estimated cost of EBS snapshots of volumes with `size_gib` in total. Snapshots are
+/// incremental, so this is an upper bound.
+pub fn snapshot_cost(size_gib: i32) -> EstimatedCost {
+    EstimatedCost::from_monthly_cents(
+        u64::try_from(size_gib)
+            .unwrap_or_default()
+            .saturating_mul(SNAPSHOT_MONTHLY_CENTS_PER_GIB),
+    )
+}
+
+#[cfg(feature = "pricing")]
+fn parse_on_demand_price(product: &str) -> Result<EstimatedCost, Error> {
+    let product: serde_json::Value =
+        serde_json::from_str(product).map_err(|e| Error::InvalidResponseError {
+            message: format!("invalid pricing product: {e}"),
+        })?;
+
+    product
+        .get("terms")
+        .and_then(|terms| terms.get("OnDemand"))
+        .and_then(serde_json::Value::as_object)
+        .and_then(|terms| terms.values().next())
+        .and_then(|term| term.get("priceDimensions"))
+        .and_then(serde_json::Value::as_object)
+        .and_then(|dimensions| dimensions.values().next())
+        .and_then(|dimension| dimension.get("pricePerUnit"))
+        .and_then(|price| price.get("USD"))
+        .and_then(serde_json::Value::as_str)
+        .and_then(EstimatedCost::parse_hourly_usd)
+        .ok_or_else(|| Error::InvalidResponseError {
+            message: "pricing product does not contain an on-demand USD price".to_owned(),
+        })
+}
+
+/// Looks up the on-demand price of a Linux instance of type `instance_type` in the
+/// region of `client`.
+#[cfg(feature = "pricing")]
+#[expect(
+    clippy::missing_panics_doc,
+    reason = "only expect() on builder instances"
+)]
+pub async fn instance_cost(
+    client: &RegionClient,
+    instance_type: &InstanceType,
+) -> Result<EstimatedCost, Error> {
+    let filter = |field: &str, value: &str| {
+        aws_sdk_pricing::types::Filter::builder()
+            .r#type(aws_sdk_pricing::types::FilterType::TermMatch)
+            .field(field)
+            .value(value)
+            .build()
+            .expect("builder has missing fields")
+    };
+
+    let output = client
+        .main
+        .pricing
+        .get_products()
+        .service_code("AmazonEC2")
+        .filters(filter("regionCode", client.region.as_str()))
+        .filters(filter("instanceType", instance_type.inner().as_str()))
+        .filters(filter("operatingSystem", "Linux"))
+        .filters(filter("tenancy", "Shared"))
+        .filters(filter("preInstalledSw", "NA"))
+        .filters(filter("capacitystatus", "Used"))
+        .max_results(1)
+        .send()
+        .await?;
+
+    parse_on_demand_price(output.price_list().first().ok_or_else(|| {
+        Error::UnexpectedNoneValue {
+            entity: "GetProductsOutput.price_list".to_owned(),
+        }
+    })?)
+}
+
+/// Estimated cost of launching an instance with `config` via
+/// [`launch_ec2_instance()`](crate::launch_ec2_instance()).
+#[cfg(feature = "pricing")]
+pub async fn launch_cost(
+    client: &RegionClient,
+    config: &NewEc2Config<'_>,
+) -> Result<EstimatedCost, Error> {
+    instance_cost(client, config.instance_type).await
+}
+
+/// Launches an instance like [`launch_ec2_instance()`](crate::launch_ec2_instance()),
+/// together with its estimated cost. The price is looked up first, so a failing
+/// lookup does not leave an instance behind.
+#[cfg(feature = "pricing")]
+pub async fn launch_ec2_instance(
+    client: &RegionClient,
+    config: &NewEc2Config<'_>,
+) -> Result<Costed<Instance>, Error> {
+    let cost = launch_cost(client, config).await?;
+
+    Ok(Costed {
+        resource: super::launch_ec2_instance(client, config).await?,
+        cost,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_hourly_usd() {
+        assert_eq!(
+            EstimatedCost::parse_hourly_usd("0.0960000000"),
+            Some(EstimatedCost::from_hourly_microdollars(96_000))
+        );
+        assert_eq!(
+            EstimatedCost::parse_hourly_usd("1.5"),
+            Some(EstimatedCost::from_hourly_microdollars(1_500_000))
+        );
+        assert_eq!(
+            EstimatedCost::parse_hourly_usd("2"),
+            Some(EstimatedCost::from_hourly_microdollars(2_000_000))
+        );
+        assert_eq!(EstimatedCost::parse_hourly_usd("abc"), None);
+        assert_eq!(EstimatedCost::parse_hourly_usd("0.x"), None);
+        assert_eq!(EstimatedCost::parse_hourly_usd("+1.5"), None);
+        assert_eq!(EstimatedCost::parse_hourly_usd("1.+5"), None);
+        assert_eq!(EstimatedCost::parse_hourly_usd("-1"), None);
+    }
+
+    #[test]
+    fn monthly_cost() {
+        let cost = EstimatedCost::from_hourly_microdollars(96_000);
+        assert_eq!(cost.monthly_cents(), 7008);
+        assert_eq!(cost.to_string(), "$70.08/month");
+
+        assert_eq!(eip_cost().monthly_cents(), 365);
+        assert_eq!(
+            volume_cost(&aws_sdk_ec2::types::VolumeType::Gp3, 100).monthly_cents(),
+            800
+        );
+        assert_eq!(snapshot_cost(-1), EstimatedCost::default());
+        assert_eq!(
+            [cost, eip_cost()].into_iter().sum::<EstimatedCost>(),
+            cost.saturating_add(eip_cost())
+        );
+    }
+
+    #[test]
+    fn cost_report() {
+        let mut report = CostReport::new();
+        let instance = report.record(
+            "instance",
+            Costed {
+                resource: "i-0123",
+                cost: EstimatedCost::from_hourly_microdollars(96_000),
+            },
+        );
+        let _eip = report.record(
+            "eip",
+            Costed {
+                resource: "eipalloc-0123",
+                cost: eip_cost(),
+            },
+        );
+
+        assert_eq!(instance, "i-0123");
+        assert_eq!(report.total().monthly_cents(), 7373);
+        assert_eq!(
+            report.to_string(),
+            "instance: $70.08/month\neip: $3.65/month\ntotal: $73.73/month"
+        );
+    }
+}
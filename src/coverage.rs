@@ -0,0 +1,332 @@
+//! Reserved instance and savings plan coverage of running instances
+
+use std::collections::BTreeMap;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::{pagination, tags::TagList, Error, InstanceType, RegionClient, Timestamp};
+
+const EC2_COMPUTE_SERVICE: &str = "Amazon Elastic Compute Cloud - Compute";
+const FULL_COVERAGE_PERCENTAGE: f64 = 100.0;
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct InstanceTypeCoverage {
+    instance_type: InstanceType,
+    running_instances: usize,
+    reservation_coverage_percentage: Option<f64>,
+    savings_plans_coverage_percentage: Option<f64>,
+}
+
+impl InstanceTypeCoverage {
+    pub const fn instance_type(&self) -> &InstanceType {
+        &self.instance_type
+    }
+
+    pub const fn running_instances(&self) -> usize {
+        self.running_instances
+    }
+
+    pub const fn reservation_coverage_percentage(&self) -> Option<f64> {
+        self.reservation_coverage_percentage
+    }
+
+    pub const fn savings_plans_coverage_percentage(&self) -> Option<f64> {
+        self.savings_plans_coverage_percentage
+    }
+
+    /// Whether either reservations or savings plans cover all running hours of this
+    /// instance type.
+    pub fn is_fully_covered(&self) -> bool {
+        self.reservation_coverage_percentage
+            .unwrap_or_default()
+            .max(self.savings_plans_coverage_percentage.unwrap_or_default())
+            >= FULL_COVERAGE_PERCENTAGE
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct CoverageReport {
+    instance_types: Vec<InstanceTypeCoverage>,
+}
+
+impl CoverageReport {
+    pub fn instance_types(&self) -> &[InstanceTypeCoverage] {
+        &self.instance_types
+    }
+
+    pub fn gaps(&self) -> impl Iterator<Item = &InstanceTypeCoverage> {
+        self.instance_types
+            .iter()
+            .filter(|coverage| !coverage.is_fully_covered())
+    }
+}
+
+fn parse_percentage(value: &str) -> Result<f64, Error> {
+    let percentage: f64 = value.parse().map_err(|e| Error::InvalidResponseError {
+        message: format!("invalid coverage percentage \"{value}\": {e}"),
+    })?;
+
+    if percentage.is_finite() {
+        Ok(percentage)
+    } else {
+        Err(Error::InvalidResponseError {
+            message: format!("invalid coverage percentage \"{value}\""),
+        })
+    }
+}
+
+fn instance_family(instance_type: &str) -> &str {
+    instance_type
+        .split_once('.')
+        .map_or(instance_type, |(family, _size)| family)
+}
+
+fn time_period(
+    start: Timestamp,
+    end: Timestamp,
+) -> Result<aws_sdk_costexplorer::types::DateInterval, Error> {
+    aws_sdk_costexplorer::types::DateInterval::builder()
+        .start(start.inner().format("%Y-%m-%d").to_string())
+        .end(end.inner().format("%Y-%m-%d").to_string())
+        .build()
+        .map_err(|e| Error::InvalidArgument {
+            message: format!("invalid coverage period from {start} to {end}: {e}"),
+        })
+}
+
+/// Cost Explorer reports coverage for the whole account, while the running instances
+/// are those of the region of `client`
+fn region_filter(client: &RegionClient) -> aws_sdk_costexplorer::types::Expression {
+    aws_sdk_costexplorer::types::Expression::builder()
+        .dimensions(
+            aws_sdk_costexplorer::types::DimensionValues::builder()
+                .key(aws_sdk_costexplorer::types::Dimension::Region)
+                .values(client.region.as_str())
+                .build(),
+        )
+        .build()
+}
+
+async fn running_instance_types(
+    client: &RegionClient,
+    tags: &TagList,
+) -> Result<BTreeMap<String, usize>, Error> {
+    let mut filters: Vec<aws_sdk_ec2::types::Filter> = tags.clone().into();
+    filters.push(
+        aws_sdk_ec2::types::Filter::builder()
+            .name("instance-state-name")
+            .values("running")
+            .build(),
+    );
+
+    let mut counts = BTreeMap::new();
+
+    for instance in pagination::list_instances(client, filters).await? {
+        let instance_type = instance
+            .instance_type
+            .ok_or_else(|| Error::UnexpectedNoneValue {
+                entity: "instance_type".to_owned(),
+            })?;
+
+        let count: &mut usize = counts.entry(instance_type.as_str().to_owned()).or_default();
+        *count = count.saturating_add(1);
+    }
+
+    Ok(counts)
+}
+
+/// Latest reservation coverage percentage per instance type
+async fn reservation_coverage(
+    client: &RegionClient,
+    start: Timestamp,
+    end: Timestamp,
+) -> Result<BTreeMap<String, f64>, Error> {
+    let mut coverage = BTreeMap::new();
+    let mut next_page_token = None;
+
+    loop {
+        let output = client
+            .main
+            .costexplorer
+            .get_reservation_coverage()
+            .time_period(time_period(start, end)?)
+            .granularity(aws_sdk_costexplorer::types::Granularity::Monthly)
+            .group_by(
+                aws_sdk_costexplorer::types::GroupDefinition::builder()
+                    .r#type(aws_sdk_costexplorer::types::GroupDefinitionType::Dimension)
+                    .key("INSTANCE_TYPE")
+                    .build(),
+            )
+            .filter(
+                aws_sdk_costexplorer::types::Expression::builder()
+                    .and(
+                        aws_sdk_costexplorer::types::Expression::builder()
+                            .dimensions(
+                                aws_sdk_costexplorer::types::DimensionValues::builder()
+                                    .key(aws_sdk_costexplorer::types::Dimension::Service)
+                                    .values(EC2_COMPUTE_SERVICE)
+                                    .build(),
+                            )
+                            .build(),
+                    )
+                    .and(region_filter(client))
+                    .build(),
+            )
+            .set_next_page_token(next_page_token.take())
+            .send()
+            .await?;
+
+        for group in output
+            .coverages_by_time()
+            .iter()
+            .flat_map(|coverage_by_time| coverage_by_time.groups())
+        {
+            let instance_type = group
+                .attributes()
+                .and_then(|attributes| attributes.get("instanceType"))
+                .ok_or_else(|| Error::UnexpectedNoneValue {
+                    entity: "ReservationCoverageGroup.attributes.instanceType".to_owned(),
+                })?;
+
+            if let Some(percentage) = group
+                .coverage()
+                .and_then(|coverage| coverage.coverage_hours())
+                .and_then(|hours| hours.coverage_hours_percentage())
+            {
+                let _previous =
+                    coverage.insert(instance_type.clone(), parse_percentage(percentage)?);
+            }
+        }
+
+        next_page_token = output.next_page_token;
+        if next_page_token.is_none() {
+            break;
+        }
+    }
+
+    Ok(coverage)
+}
+
+/// Latest savings plan coverage percentage per instance family
+async fn savings_plans_coverage(
+    client: &RegionClient,
+    start: Timestamp,
+    end: Timestamp,
+) -> Result<BTreeMap<String, f64>, Error> {
+    let mut coverage = BTreeMap::new();
+    let mut next_token = None;
+
+    loop {
+        let output = client
+            .main
+            .costexplorer
+            .get_savings_plans_coverage()
+            .time_period(time_period(start, end)?)
+            .granularity(aws_sdk_costexplorer::types::Granularity::Monthly)
+            .group_by(
+                aws_sdk_costexplorer::types::GroupDefinition::builder()
+                    .r#type(aws_sdk_costexplorer::types::GroupDefinitionType::Dimension)
+                    .key("INSTANCE_FAMILY")
+                    .build(),
+            )
+            .filter(region_filter(client))
+            .set_next_token(next_token.take())
+            .send()
+            .await?;
+
+        for savings_plans_coverage in output.savings_plans_coverages() {
+            let family = savings_plans_coverage
+                .attributes()
+                .and_then(|attributes| attributes.get("INSTANCE_FAMILY"))
+                .ok_or_else(|| Error::UnexpectedNoneValue {
+                    entity: "SavingsPlansCoverage.attributes.INSTANCE_FAMILY".to_owned(),
+                })?;
+
+            if let Some(percentage) = savings_plans_coverage
+                .coverage()
+                .and_then(|coverage| coverage.coverage_percentage())
+            {
+                let _previous = coverage.insert(family.clone(), parse_percentage(percentage)?);
+            }
+        }
+
+        next_token = output.next_token;
+        if next_token.is_none() {
+            break;
+        }
+    }
+
+    Ok(coverage)
+}
+
+/// Cross-references all running instances matching `tags` with the reservation and
+/// savings plan coverage reported by Cost Explorer between `start` and `end`. Both only
+/// consider the region of `client`.
+///
+/// Coverage is reported per instance type. As savings plans are only reported per
+/// instance family, all types of a family share the same savings plan coverage. If the
+/// period spans multiple months, the coverage of the latest month is used.
+pub async fn coverage_report(
+    client: &RegionClient,
+    tags: &TagList,
+    start: Timestamp,
+    end: Timestamp,
+) -> Result<CoverageReport, Error> {
+    let running = running_instance_types(client, tags).await?;
+    let reservations = reservation_coverage(client, start, end).await?;
+    let savings_plans = savings_plans_coverage(client, start, end).await?;
+
+    Ok(CoverageReport {
+        instance_types: running
+            .into_iter()
+            .map(|(instance_type, running_instances)| InstanceTypeCoverage {
+                reservation_coverage_percentage: reservations.get(&instance_type).copied(),
+                savings_plans_coverage_percentage: savings_plans
+                    .get(instance_family(&instance_type))
+                    .copied(),
+                instance_type: InstanceType(instance_type.as_str().into()),
+                running_instances,
+            })
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn instance_families() {
+        assert_eq!(instance_family("m5.large"), "m5");
+        assert_eq!(instance_family("u-6tb1.metal"), "u-6tb1");
+        assert_eq!(instance_family("m5"), "m5");
+        assert_eq!(instance_family("m5."), "m5");
+        assert_eq!(instance_family(""), "");
+    }
+
+    #[test]
+    fn percentages() {
+        assert_eq!(
+            parse_percentage("87.5").unwrap().to_bits(),
+            87.5_f64.to_bits()
+        );
+        assert_eq!(
+            parse_percentage("100").unwrap().to_bits(),
+            100.0_f64.to_bits()
+        );
+        assert_eq!(parse_percentage("0").unwrap().to_bits(), 0.0_f64.to_bits());
+
+        for value in ["", "abc", "87,5", "87.5%", "NaN", "inf"] {
+            assert!(
+                matches!(
+                    parse_percentage(value),
+                    Err(Error::InvalidResponseError { ref message }) if message.contains(value)
+                ),
+                "{value} accepted"
+            );
+        }
+    }
+}
@@ -0,0 +1,388 @@
+//! Copying an EFS file system to S3 with DataSync, e.g. for disaster recovery
+//!
+//! A backup is a DataSync task between an EFS location and an S3 location. The task
+//! runs on its schedule, and can also be started explicitly with [`start_task()`].
+
+use std::time::Duration;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::{tags::TagList, waiter, Efs, Error, RegionClient, RoleArn, SecurityGroupId, SubnetId};
+
+crate::string_newtype!(TaskArn);
+
+impl TaskArn {
+    pub const fn new(value: String) -> Self {
+        Self(value)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+crate::string_newtype!(TaskExecutionArn);
+
+impl TaskExecutionArn {
+    pub const fn new(value: String) -> Self {
+        Self(value)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Where DataSync mounts the file system from
+#[derive(Debug, Clone, Copy)]
+pub struct EfsSource<'a> {
+    pub efs: &'a Efs,
+    /// A subnet that has a mount target of `efs`
+    pub subnet: &'a SubnetId,
+    /// Have to allow NFS traffic to the mount target
+    pub security_groups: &'a [SecurityGroupId],
+    /// The directory to copy, `/` for the whole file system
+    pub directory: &'a str,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct S3Destination<'a> {
+    /// E.g. `arn:aws:s3:::efs-backups`
+    pub bucket_arn: &'a str,
+    /// The prefix to copy to, e.g. `/shared-home`
+    pub prefix: &'a str,
+    /// Has to allow DataSync to write to the bucket
+    pub role: &'a RoleArn,
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskExecutionStatus {
+    /// Queued, launching or preparing
+    Pending,
+    Transferring,
+    Verifying,
+    Success,
+    Error,
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskExecution {
+    pub arn: TaskExecutionArn,
+    pub status: TaskExecutionStatus,
+    pub bytes_transferred: i64,
+    pub files_transferred: i64,
+    /// Why the execution failed, if it did
+    pub error: Option<String>,
+}
+
+impl TaskExecution {
+    pub const fn is_finished(&self) -> bool {
+        matches!(
+            self.status,
+            TaskExecutionStatus::Success | TaskExecutionStatus::Error
+        )
+    }
+}
+
+impl TryFrom<aws_sdk_datasync::operation::describe_task_execution::DescribeTaskExecutionOutput>
+    for TaskExecution
+{
+    type Error = Error;
+
+    fn try_from(
+        execution: aws_sdk_datasync::operation::describe_task_execution::DescribeTaskExecutionOutput,
+    ) -> Result<Self, Self::Error> {
+        let status = match execution.status {
+            Some(aws_sdk_datasync::types::TaskExecutionStatus::Transferring) => {
+                TaskExecutionStatus::Transferring
+            }
+            Some(aws_sdk_datasync::types::TaskExecutionStatus::Verifying) => {
+                TaskExecutionStatus::Verifying
+            }
+            Some(aws_sdk_datasync::types::TaskExecutionStatus::Success) => {
+                TaskExecutionStatus::Success
+            }
+            Some(aws_sdk_datasync::types::TaskExecutionStatus::Error) => TaskExecutionStatus::Error,
+            Some(
+                aws_sdk_datasync::types::TaskExecutionStatus::Queued
+                | aws_sdk_datasync::types::TaskExecutionStatus::Launching
+                | aws_sdk_datasync::types::TaskExecutionStatus::Preparing,
+            ) => TaskExecutionStatus::Pending,
+            Some(ref status) => {
+                return Err(Error::InvalidResponseError {
+                    message: format!("unknown task execution status {}", status.as_str()),
+                })
+            }
+            None => {
+                return Err(Error::UnexpectedNoneValue {
+                    entity: "DescribeTaskExecutionOutput.status".to_owned(),
+                })
+            }
+        };
+
+        Ok(Self {
+            arn: TaskExecutionArn(execution.task_execution_arn.ok_or_else(|| {
+                Error::UnexpectedNoneValue {
+                    entity: "DescribeTaskExecutionOutput.task_execution_arn".to_owned(),
+                }
+            })?),
+            status,
+            bytes_transferred: execution.bytes_transferred,
+            files_transferred: execution.files_transferred,
+            error: execution
+                .result
+                .and_then(|result| result.error_detail.or(result.error_code)),
+        })
+    }
+}
+
+fn datasync_tags(tags: &TagList) -> Vec<aws_sdk_datasync::types::TagListEntry> {
+    tags.as_slice()
+        .iter()
+        .map(|tag| {
+            aws_sdk_datasync::types::TagListEntry::builder()
+                .key(tag.key().as_str())
+                .value(tag.value().as_str())
+                .build()
+                .expect("builder has all required fields set")
+        })
+        .collect()
+}
+
+/// The ARN of an EC2 resource in the same partition, region and account as `efs_arn`
+fn ec2_arn(efs_arn: &str, resource: &str) -> Result<String, Error> {
+    let mut parts = efs_arn.split(':');
+    match (
+        parts.next(),
+        parts.next(),
+        parts.next(),
+        parts.next(),
+        parts.next(),
+    ) {
+        (Some("arn"), Some(partition), Some(_service), Some(region), Some(account)) => {
+            Ok(format!("arn:{partition}:ec2:{region}:{account}:{resource}"))
+        }
+        _ => Err(Error::InvalidResponseError {
+            message: format!("invalid file system ARN \"{efs_arn}\""),
+        }),
+    }
+}
+
+async fn create_efs_location(
+    client: &RegionClient,
+    source: &EfsSource<'_>,
+    tags: &[aws_sdk_datasync::types::TagListEntry],
+) -> Result<String, Error> {
+    let efs_arn = client
+        .main
+        .efs
+        .describe_file_systems()
+        .file_system_id(source.efs.id().as_str())
+        .send()
+        .await?
+        .file_systems
+        .and_then(|file_systems| file_systems.into_iter().next())
+        .and_then(|file_system| file_system.file_system_arn)
+        .ok_or_else(|| Error::UnexpectedNoneValue {
+            entity: "FileSystemDescription.file_system_arn".to_owned(),
+        })?;
+
+    let ec2_config = aws_sdk_datasync::types::Ec2Config::builder()
+        .subnet_arn(ec2_arn(
+            &efs_arn,
+            &format!("subnet/{}", source.subnet.as_str()),
+        )?)
+        .set_security_group_arns(Some(
+            source
+                .security_groups
+                .iter()
+                .map(|group| ec2_arn(&efs_arn, &format!("security-group/{}", group.as_str())))
+                .collect::<Result<_, _>>()?,
+        ))
+        .build()
+        .expect("builder has all required fields set");
+
+    client
+        .main
+        .datasync
+        .create_location_efs()
+        .efs_filesystem_arn(efs_arn)
+        .ec2_config(ec2_config)
+        .subdirectory(source.directory)
+        .set_tags(Some(tags.to_vec()))
+        .send()
+        .await?
+        .location_arn
+        .ok_or_else(|| Error::UnexpectedNoneValue {
+            entity: "CreateLocationEfsOutput.location_arn".to_owned(),
+        })
+}
+
+async fn create_s3_location(
+    client: &RegionClient,
+    destination: &S3Destination<'_>,
+    tags: &[aws_sdk_datasync::types::TagListEntry],
+) -> Result<String, Error> {
+    client
+        .main
+        .datasync
+        .create_location_s3()
+        .s3_bucket_arn(destination.bucket_arn)
+        .s3_config(
+            aws_sdk_datasync::types::S3Config::builder()
+                .bucket_access_role_arn(destination.role.as_str())
+                .build()
+                .expect("builder has all required fields set"),
+        )
+        .subdirectory(destination.prefix)
+        .set_tags(Some(tags.to_vec()))
+        .send()
+        .await?
+        .location_arn
+        .ok_or_else(|| Error::UnexpectedNoneValue {
+            entity: "CreateLocationS3Output.location_arn".to_owned(),
+        })
+}
+
+/// Creates a task that copies `source` to `destination`.
+///
+/// `schedule` is a cron or rate expression like `cron(0 3 * * ? *)`. Without a
+/// schedule, the task only runs when started with [`start_task()`].
+#[expect(
+    clippy::missing_panics_doc,
+    reason = "only expect() on builder instances"
+)]
+pub async fn create_efs_backup_task(
+    client: &RegionClient,
+    name: &str,
+    source: &EfsSource<'_>,
+    destination: &S3Destination<'_>,
+    schedule: Option<&str>,
+    tags: &TagList,
+) -> Result<TaskArn, Error> {
+    let tags = datasync_tags(&client.tags_with_defaults(tags));
+
+    let source_location = create_efs_location(client, source, &tags).await?;
+    let destination_location = create_s3_location(client, destination, &tags).await?;
+
+    client
+        .main
+        .datasync
+        .create_task()
+        .name(name)
+        .source_location_arn(source_location)
+        .destination_location_arn(destination_location)
+        .set_schedule(schedule.map(|expression| {
+            aws_sdk_datasync::types::TaskSchedule::builder()
+                .schedule_expression(expression)
+                .build()
+                .expect("builder has all required fields set")
+        }))
+        .set_tags(Some(tags))
+        .send()
+        .await?
+        .task_arn
+        .map(TaskArn)
+        .ok_or_else(|| Error::UnexpectedNoneValue {
+            entity: "CreateTaskOutput.task_arn".to_owned(),
+        })
+}
+
+/// Starts a run of `task` outside of its schedule
+pub async fn start_task(client: &RegionClient, task: &TaskArn) -> Result<TaskExecutionArn, Error> {
+    client
+        .main
+        .datasync
+        .start_task_execution()
+        .task_arn(task.as_str())
+        .send()
+        .await?
+        .task_execution_arn
+        .map(TaskExecutionArn)
+        .ok_or_else(|| Error::UnexpectedNoneValue {
+            entity: "StartTaskExecutionOutput.task_execution_arn".to_owned(),
+        })
+}
+
+pub async fn task_execution(
+    client: &RegionClient,
+    execution: &TaskExecutionArn,
+) -> Result<TaskExecution, Error> {
+    client
+        .main
+        .datasync
+        .describe_task_execution()
+        .task_execution_arn(execution.as_str())
+        .send()
+        .await?
+        .try_into()
+}
+
+/// Waits until `execution` succeeded or failed. A failed execution is not an error,
+/// check [`TaskExecution::status`].
+///
+/// If `max_wait` is `None`, the [`WaitConfig`](crate::waiter::WaitConfig) of `client`
+/// applies.
+pub async fn wait_for_task_execution(
+    client: &RegionClient,
+    execution: &TaskExecutionArn,
+    max_wait: Option<Duration>,
+) -> Result<TaskExecution, Error> {
+    let max_wait = max_wait.unwrap_or(client.wait_config.datasync_execution);
+
+    waiter::poll(client.waiter_profile, max_wait, || async move {
+        let result = task_execution(client, execution).await?;
+        Ok(result.is_finished().then_some(result))
+    })
+    .await?
+    .ok_or_else(|| Error::DataSyncExceededMaxWait {
+        max_wait,
+        execution: execution.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ec2_arn_from_efs_arn() {
+        assert_eq!(
+            ec2_arn(
+                "arn:aws:elasticfilesystem:eu-central-1:111111111111:file-system/fs-0123",
+                "subnet/subnet-0123"
+            )
+            .unwrap(),
+            "arn:aws:ec2:eu-central-1:111111111111:subnet/subnet-0123"
+        );
+        assert!(matches!(
+            ec2_arn("fs-0123", "subnet/subnet-0123"),
+            Err(Error::InvalidResponseError { ref message }) if message.contains("fs-0123")
+        ));
+    }
+
+    #[test]
+    fn execution_status() {
+        let execution = |status: &str| {
+            TaskExecution::try_from(
+                aws_sdk_datasync::operation::describe_task_execution::DescribeTaskExecutionOutput::builder()
+                    .task_execution_arn("arn:aws:datasync:eu-central-1:111111111111:task/task-1/execution/exec-1")
+                    .status(status.into())
+                    .build(),
+            )
+        };
+
+        assert_eq!(
+            execution("LAUNCHING").unwrap().status,
+            TaskExecutionStatus::Pending
+        );
+        assert!(execution("SUCCESS").unwrap().is_finished());
+        assert!(matches!(
+            execution("SOMETHING_NEW"),
+            Err(Error::InvalidResponseError { ref message }) if message.contains("SOMETHING_NEW")
+        ));
+    }
+}
@@ -0,0 +1,333 @@
+//! Deploying application artifacts onto instances
+//!
+//! The artifact is uploaded to S3, and the instances are told about it with three
+//! tags: `deploy:artifact` holds the S3 URI of the artifact that should run,
+//! `deploy:revision` the ETag of the uploaded object, and `deploy:status` the state of
+//! the rollout on that instance. The ETag tells apart two uploads to the same key.
+//! There are two ways to get the instance to act on it, see [`Signal`].
+//!
+//! With [`Signal::Tag`], an agent on the instance (usually set up in its user data)
+//! watches its own tags via the instance metadata, pulls the artifact when
+//! `deploy:artifact` changes, and updates `deploy:status` to `in-progress` and then
+//! to `succeeded` or `failed`.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::{
+    ssm::{self, CommandId, CommandStatus},
+    tags::{RawTag, StaticTagKey, TagList},
+    Error, InstanceId, RegionClient,
+};
+
+pub const ARTIFACT_TAG_KEY: StaticTagKey = StaticTagKey::new("deploy:artifact");
+pub const REVISION_TAG_KEY: StaticTagKey = StaticTagKey::new("deploy:revision");
+pub const STATUS_TAG_KEY: StaticTagKey = StaticTagKey::new("deploy:status");
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Artifact {
+    pub bucket: String,
+    pub key: String,
+    /// The ETag of this upload, as returned by S3
+    pub etag: String,
+}
+
+impl Artifact {
+    pub fn s3_uri(&self) -> String {
+        format!("s3://{}/{}", self.bucket, self.key)
+    }
+}
+
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeployStatus {
+    /// The instance has not picked up the artifact yet
+    Pending,
+    InProgress,
+    Succeeded,
+    Failed,
+    /// A later deployment replaced this one on the instance
+    Superseded,
+}
+
+impl DeployStatus {
+    const fn tag_value(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::InProgress => "in-progress",
+            Self::Succeeded => "succeeded",
+            Self::Failed => "failed",
+            Self::Superseded => "superseded",
+        }
+    }
+
+    fn from_tag_value(value: &str) -> Option<Self> {
+        match value {
+            "pending" => Some(Self::Pending),
+            "in-progress" => Some(Self::InProgress),
+            "succeeded" => Some(Self::Succeeded),
+            "failed" => Some(Self::Failed),
+            "superseded" => Some(Self::Superseded),
+            _ => None,
+        }
+    }
+
+    pub const fn is_finished(self) -> bool {
+        !matches!(self, Self::Pending | Self::InProgress)
+    }
+}
+
+/// How instances learn about a new artifact
+#[derive(Debug, Clone, Copy)]
+pub enum Signal<'a> {
+    /// Only set the tags, and leave the rest to an agent on the instance
+    Tag,
+    /// Additionally copy the artifact to `path` and run `restart` via SSM Run
+    /// Command. `restart` does not run if the copy fails, and the copy fails if the
+    /// object was overwritten in the meantime. The instance profile needs read access
+    /// to the artifact, and the AWS CLI has to be installed.
+    Ssm { path: &'a str, restart: &'a str },
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct Deployment {
+    pub artifact: Artifact,
+    pub instances: Vec<InstanceId>,
+    /// Only set for [`Signal::Ssm`]
+    pub command: Option<CommandId>,
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstanceDeployStatus {
+    pub instance: InstanceId,
+    pub status: DeployStatus,
+}
+
+/// Quotes `value` for a POSIX shell
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+/// Fetches exactly the uploaded revision of `artifact`, and stops before `restart` if
+/// that fails
+fn ssm_script(artifact: &Artifact, path: &str, restart: &str) -> Vec<String> {
+    vec![
+        "set -e".to_owned(),
+        format!(
+            "aws s3api get-object --bucket {} --key {} --if-match {} {}",
+            shell_quote(&artifact.bucket),
+            shell_quote(&artifact.key),
+            shell_quote(&artifact.etag),
+            shell_quote(path)
+        ),
+        restart.to_owned(),
+    ]
+}
+
+/// Uploads `body` as the artifact `key` in `bucket`
+pub async fn upload_artifact(
+    client: &RegionClient,
+    bucket: &str,
+    key: &str,
+    body: Vec<u8>,
+) -> Result<Artifact, Error> {
+    let output = client
+        .main
+        .s3
+        .put_object()
+        .bucket(bucket)
+        .key(key)
+        .body(body.into())
+        .send()
+        .await?;
+
+    Ok(Artifact {
+        bucket: bucket.to_owned(),
+        key: key.to_owned(),
+        etag: output.e_tag.ok_or_else(|| Error::UnexpectedNoneValue {
+            entity: "PutObjectOutput.e_tag".to_owned(),
+        })?,
+    })
+}
+
+async fn set_status_tags(
+    client: &RegionClient,
+    instances: &[InstanceId],
+    artifact: &Artifact,
+    status: DeployStatus,
+) -> Result<(), Error> {
+    let mut tags = TagList::new();
+    tags.push(RawTag::new(ARTIFACT_TAG_KEY, artifact.s3_uri()));
+    tags.push(RawTag::new(REVISION_TAG_KEY, artifact.etag.clone()));
+    tags.push(RawTag::new(STATUS_TAG_KEY, status.tag_value().to_owned()));
+
+    let _output = client
+        .main
+        .ec2
+        .create_tags()
+        .set_resources(Some(
+            instances.iter().map(|id| id.as_str().to_owned()).collect(),
+        ))
+        .set_tags(Some(tags.into()))
+        .send()
+        .await?;
+
+    Ok(())
+}
+
+/// Rolls `artifact` out to all `instances` at once. Use [`deployment_status()`] to
+/// follow the rollout.
+pub async fn deploy(
+    client: &RegionClient,
+    artifact: &Artifact,
+    instances: &[InstanceId],
+    signal: Signal<'_>,
+) -> Result<Deployment, Error> {
+    set_status_tags(client, instances, artifact, DeployStatus::Pending).await?;
+
+    let command = match signal {
+        Signal::Tag => None,
+        Signal::Ssm { path, restart } => Some(
+            ssm::run_shell_script(client, instances, ssm_script(artifact, path, restart)).await?,
+        ),
+    };
+
+    Ok(Deployment {
+        artifact: artifact.clone(),
+        instances: instances.to_vec(),
+        command,
+    })
+}
+
+/// The status of `deployment` on each of its instances.
+///
+/// For [`Signal::Ssm`], the status is taken from the command, and written to the
+/// `deploy:status` tag once the command has finished.
+pub async fn deployment_status(
+    client: &RegionClient,
+    deployment: &Deployment,
+) -> Result<Vec<InstanceDeployStatus>, Error> {
+    let uri = deployment.artifact.s3_uri();
+    let etag = &deployment.artifact.etag;
+    let mut statuses = Vec::with_capacity(deployment.instances.len());
+
+    for instance in &deployment.instances {
+        let tags = TagList::from_vec(
+            client
+                .main
+                .ec2
+                .describe_tags()
+                .filters(
+                    aws_sdk_ec2::types::Filter::builder()
+                        .name("resource-id")
+                        .values(instance.as_str())
+                        .build(),
+                )
+                .send()
+                .await?
+                .tags
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|tag| Some(RawTag::new(tag.key?, tag.value.unwrap_or_default())))
+                .collect(),
+        );
+
+        // Comparing the revision as well catches redeployments of the same key
+        let is_current = tags
+            .get(ARTIFACT_TAG_KEY)
+            .is_some_and(|tag| tag.value().as_str() == uri)
+            && tags
+                .get(REVISION_TAG_KEY)
+                .is_some_and(|tag| tag.value().as_str() == etag.as_str());
+
+        let tagged_status = if is_current {
+            tags.get(STATUS_TAG_KEY)
+                .and_then(|tag| DeployStatus::from_tag_value(tag.value().as_str()))
+                .unwrap_or(DeployStatus::Pending)
+        } else {
+            DeployStatus::Superseded
+        };
+
+        let status = match deployment.command {
+            Some(ref command) if !tagged_status.is_finished() => {
+                let status = match ssm::command_result(client, command, instance).await? {
+                    None => DeployStatus::InProgress,
+                    Some(result) if result.status == CommandStatus::Success => {
+                        DeployStatus::Succeeded
+                    }
+                    Some(_) => DeployStatus::Failed,
+                };
+                if status.is_finished() {
+                    set_status_tags(
+                        client,
+                        std::slice::from_ref(instance),
+                        &deployment.artifact,
+                        status,
+                    )
+                    .await?;
+                }
+                status
+            }
+            _ => tagged_status,
+        };
+
+        statuses.push(InstanceDeployStatus {
+            instance: instance.clone(),
+            status,
+        });
+    }
+
+    Ok(statuses)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quoting_for_shell() {
+        assert_eq!(shell_quote("/opt/app"), "'/opt/app'");
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+    }
+
+    #[test]
+    fn ssm_script_stops_on_failure() {
+        let artifact = Artifact {
+            bucket: "artifacts".to_owned(),
+            key: "app.tar.gz".to_owned(),
+            etag: "\"abc\"".to_owned(),
+        };
+
+        assert_eq!(
+            ssm_script(&artifact, "/opt/app.tar.gz", "systemctl restart app"),
+            [
+                "set -e",
+                r#"aws s3api get-object --bucket 'artifacts' --key 'app.tar.gz' --if-match '"abc"' '/opt/app.tar.gz'"#,
+                "systemctl restart app",
+            ]
+        );
+    }
+
+    #[test]
+    fn status_tag_values() {
+        for status in [
+            DeployStatus::Pending,
+            DeployStatus::InProgress,
+            DeployStatus::Succeeded,
+            DeployStatus::Failed,
+            DeployStatus::Superseded,
+        ] {
+            assert_eq!(
+                DeployStatus::from_tag_value(status.tag_value()),
+                Some(status)
+            );
+        }
+    }
+}
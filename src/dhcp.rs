@@ -0,0 +1,258 @@
+//! DHCP option sets, to point instances of a VPC to custom name servers
+
+use std::net::IpAddr;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::{
+    pagination,
+    query::{self, TagQuery},
+    tags::TagList,
+    Error, Protection, RegionClient, VpcId,
+};
+
+const DOMAIN_NAME_KEY: &str = "domain-name";
+const DOMAIN_NAME_SERVERS_KEY: &str = "domain-name-servers";
+const AMAZON_PROVIDED_DNS: &str = "AmazonProvidedDNS";
+
+crate::string_newtype!(DhcpOptionsId);
+
+impl DhcpOptionsId {
+    pub const fn new(value: String) -> Self {
+        Self(value)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NameServer {
+    /// The Route 53 resolver of the VPC
+    AmazonProvided,
+    Ip(IpAddr),
+}
+
+impl NameServer {
+    fn to_value(self) -> String {
+        match self {
+            Self::AmazonProvided => AMAZON_PROVIDED_DNS.to_owned(),
+            Self::Ip(ip) => ip.to_string(),
+        }
+    }
+
+    fn from_value(value: &str) -> Result<Self, Error> {
+        if value == AMAZON_PROVIDED_DNS {
+            return Ok(Self::AmazonProvided);
+        }
+
+        value
+            .parse()
+            .map(Self::Ip)
+            .map_err(|e| Error::InvalidResponseError {
+                message: format!("invalid name server \"{value}\" in DHCP options: {e}"),
+            })
+    }
+}
+
+/// The settings of a DHCP option set
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DhcpConfig {
+    /// The search domain of the instances
+    pub domain_name: Option<String>,
+    /// At most four name servers. If empty, AWS defaults to
+    /// [`NameServer::AmazonProvided`].
+    pub name_servers: Vec<NameServer>,
+}
+
+impl DhcpConfig {
+    fn to_aws(&self) -> Vec<aws_sdk_ec2::types::NewDhcpConfiguration> {
+        let mut configurations = vec![];
+
+        if let Some(ref domain_name) = self.domain_name {
+            configurations.push(
+                aws_sdk_ec2::types::NewDhcpConfiguration::builder()
+                    .key(DOMAIN_NAME_KEY)
+                    .values(domain_name)
+                    .build(),
+            );
+        }
+
+        if !self.name_servers.is_empty() {
+            configurations.push(
+                aws_sdk_ec2::types::NewDhcpConfiguration::builder()
+                    .key(DOMAIN_NAME_SERVERS_KEY)
+                    .set_values(Some(
+                        self.name_servers
+                            .iter()
+                            .copied()
+                            .map(NameServer::to_value)
+                            .collect(),
+                    ))
+                    .build(),
+            );
+        }
+
+        configurations
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct DhcpOptions {
+    pub id: DhcpOptionsId,
+    pub config: DhcpConfig,
+    pub tags: TagList,
+}
+
+impl TryFrom<aws_sdk_ec2::types::DhcpOptions> for DhcpOptions {
+    type Error = Error;
+
+    fn try_from(options: aws_sdk_ec2::types::DhcpOptions) -> Result<Self, Self::Error> {
+        let mut config = DhcpConfig {
+            domain_name: None,
+            name_servers: vec![],
+        };
+
+        for configuration in options.dhcp_configurations.unwrap_or_default() {
+            let mut values = configuration
+                .values
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|value| value.value);
+
+            match configuration.key.as_deref() {
+                Some(DOMAIN_NAME_KEY) => config.domain_name = values.next(),
+                Some(DOMAIN_NAME_SERVERS_KEY) => {
+                    config.name_servers = values
+                        .map(|value| NameServer::from_value(&value))
+                        .collect::<Result<_, _>>()?;
+                }
+                // NTP and NetBIOS settings are not managed here
+                _ => (),
+            }
+        }
+
+        Ok(Self {
+            id: DhcpOptionsId(options.dhcp_options_id.ok_or_else(|| {
+                Error::UnexpectedNoneValue {
+                    entity: "DhcpOptions.dhcp_options_id".to_owned(),
+                }
+            })?),
+            config,
+            tags: options.tags.unwrap_or_default().try_into()?,
+        })
+    }
+}
+
+impl DhcpOptions {
+    pub async fn create(
+        client: &RegionClient,
+        config: DhcpConfig,
+        tags: &TagList,
+    ) -> Result<Self, Error> {
+        let tags = client.tags_with_defaults(tags);
+
+        let id = client
+            .main
+            .ec2
+            .create_dhcp_options()
+            .set_dhcp_configurations(Some(config.to_aws()))
+            .tag_specifications(
+                aws_sdk_ec2::types::TagSpecification::builder()
+                    .resource_type(aws_sdk_ec2::types::ResourceType::DhcpOptions)
+                    .set_tags(Some(tags.clone().into()))
+                    .build(),
+            )
+            .send()
+            .await?
+            .dhcp_options
+            .and_then(|options| options.dhcp_options_id)
+            .ok_or_else(|| Error::UnexpectedNoneValue {
+                entity: "CreateDhcpOptionsOutput.dhcp_options.dhcp_options_id".to_owned(),
+            })?;
+
+        Ok(Self {
+            id: DhcpOptionsId(id),
+            config,
+            tags,
+        })
+    }
+
+    /// Replaces the option set of `vpc`. Running instances pick up the new settings
+    /// when they renew their DHCP lease.
+    pub async fn associate(&self, client: &RegionClient, vpc: &VpcId) -> Result<(), Error> {
+        let _output = client
+            .main
+            .ec2
+            .associate_dhcp_options()
+            .dhcp_options_id(self.id.as_str())
+            .vpc_id(vpc.as_str())
+            .send()
+            .await?;
+
+        Ok(())
+    }
+
+    /// Deletes the option set. Fails with [`Error::ResourceProtected`] if the option set
+    /// carries the protection tag of `client`, unless `protection` is
+    /// [`Protection::Override`].
+    ///
+    /// Also fails while the option set is still associated with a VPC.
+    pub async fn delete(self, client: &RegionClient, protection: Protection) -> Result<(), Error> {
+        client.check_protection_of(self.id.as_str(), &self.tags, protection)?;
+
+        let _output = client
+            .main
+            .ec2
+            .delete_dhcp_options()
+            .dhcp_options_id(self.id.as_str())
+            .send()
+            .await?;
+
+        Ok(())
+    }
+}
+
+pub async fn find_dhcp_options_sets(
+    client: &RegionClient,
+    query: &TagQuery,
+) -> Result<Vec<DhcpOptions>, Error> {
+    pagination::list_dhcp_options(client, query.ec2_filters())
+        .await?
+        .into_iter()
+        .map(TryInto::try_into)
+        .collect()
+}
+
+pub async fn find_dhcp_options(
+    client: &RegionClient,
+    query: &TagQuery,
+) -> Result<Option<DhcpOptions>, Error> {
+    query::find_unique("dhcp options", find_dhcp_options_sets(client, query).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn name_server_values() {
+        let servers = [
+            NameServer::AmazonProvided,
+            NameServer::Ip("10.0.0.2".parse().unwrap()),
+        ];
+
+        for server in servers {
+            assert_eq!(NameServer::from_value(&server.to_value()).unwrap(), server);
+        }
+        assert!(matches!(
+            NameServer::from_value("resolver.internal"),
+            Err(Error::InvalidResponseError { ref message }) if message.contains("resolver.internal")
+        ));
+    }
+}
@@ -0,0 +1,120 @@
+//! Waiting for DNS records to propagate, using real DNS queries
+//!
+//! Route53 reports changes as done as soon as its own nameservers serve them, but
+//! public resolvers may still cache old answers for a while.
+
+use std::{
+    net,
+    time::{Duration, Instant},
+};
+
+use hickory_resolver::{
+    config::{NameServerConfigGroup, ResolverConfig, ResolverOpts},
+    TokioAsyncResolver,
+};
+
+use super::{Error, Ip};
+
+const DNS_PORT: u16 = 53;
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+fn resolver(address: net::IpAddr) -> TokioAsyncResolver {
+    let mut opts = ResolverOpts::default();
+    // Every poll has to hit the resolver, otherwise we would only see our own cache
+    opts.cache_size = 0;
+
+    TokioAsyncResolver::tokio(
+        ResolverConfig::from_parts(
+            None,
+            vec![],
+            NameServerConfigGroup::from_ips_clear(&[address], DNS_PORT, true),
+        ),
+        opts,
+    )
+}
+
+async fn resolves_to(resolver: &TokioAsyncResolver, fqdn: &str, expected: net::IpAddr) -> bool {
+    // Lookup errors (NXDOMAIN, timeouts) just mean that the record is not there yet
+    resolver
+        .lookup_ip(fqdn)
+        .await
+        .is_ok_and(|lookup| lookup.iter().any(|ip| ip == expected))
+}
+
+/// How long to sleep before the next poll, `None` if `max_wait` is used up. The last
+/// sleep is cut short, so the final poll happens right at `max_wait`.
+fn next_sleep(elapsed: Duration, max_wait: Duration) -> Option<Duration> {
+    max_wait
+        .checked_sub(elapsed)
+        .filter(|remaining| !remaining.is_zero())
+        .map(|remaining| remaining.min(POLL_INTERVAL))
+}
+
+/// Queries all `resolvers` until each of them resolves `fqdn` to `expected_ip`, for at
+/// most `max_wait`.
+pub async fn wait_until_resolvable(
+    fqdn: &str,
+    expected_ip: &Ip,
+    resolvers: &[net::IpAddr],
+    max_wait: Duration,
+) -> Result<(), Error> {
+    if resolvers.is_empty() {
+        return Err(Error::InvalidArgument {
+            message: "no resolvers given".to_owned(),
+        });
+    }
+
+    let start = Instant::now();
+    let mut pending: Vec<TokioAsyncResolver> = resolvers.iter().copied().map(resolver).collect();
+
+    loop {
+        let mut still_pending = vec![];
+        for resolver in pending {
+            if !resolves_to(&resolver, fqdn, expected_ip.0).await {
+                still_pending.push(resolver);
+            }
+        }
+        pending = still_pending;
+
+        if pending.is_empty() {
+            return Ok(());
+        }
+
+        let Some(sleep) = next_sleep(start.elapsed(), max_wait) else {
+            return Err(Error::DnsExceededMaxWait {
+                fqdn: fqdn.to_owned(),
+                max_wait,
+            });
+        };
+
+        tokio::time::sleep(sleep).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sleeps_stay_within_max_wait() {
+        let max_wait = Duration::from_secs(12);
+
+        assert_eq!(next_sleep(Duration::ZERO, max_wait), Some(POLL_INTERVAL));
+        assert_eq!(
+            next_sleep(Duration::from_secs(10), max_wait),
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(next_sleep(max_wait, max_wait), None);
+        assert_eq!(next_sleep(Duration::from_secs(13), max_wait), None);
+    }
+
+    #[tokio::test]
+    async fn resolvers_are_required() {
+        let ip = Ip::new("192.0.2.1".parse().unwrap());
+
+        assert!(matches!(
+            wait_until_resolvable("web.example.com", &ip, &[], Duration::from_secs(1)).await,
+            Err(Error::InvalidArgument { .. })
+        ));
+    }
+}
@@ -0,0 +1,372 @@
+//! One-off Fargate tasks, e.g. for batch jobs
+//!
+//! A task definition has a single container, which logs to CloudWatch Logs with the
+//! `awslogs` driver. After [`run_task()`], [`wait_for_task()`] waits for the task to
+//! stop, and [`task_logs()`] fetches what the container logged.
+
+use std::time::Duration;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::{
+    pagination, tags::TagList, waiter, Error, RegionClient, RoleArn, SecurityGroupId, SubnetId,
+};
+
+/// The name of the single container of each task definition
+const CONTAINER_NAME: &str = "main";
+const STOPPED_STATUS: &str = "STOPPED";
+
+crate::string_newtype!(TaskDefinitionArn);
+
+impl TaskDefinitionArn {
+    pub const fn new(value: String) -> Self {
+        Self(value)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+crate::string_newtype!(TaskArn);
+
+impl TaskArn {
+    pub const fn new(value: String) -> Self {
+        Self(value)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The last part of the ARN, which is also used in the log stream name
+    fn task_id(&self) -> &str {
+        self.0.rsplit('/').next().unwrap_or(&self.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TaskDefinition<'a> {
+    pub family: &'a str,
+    /// E.g. `public.ecr.aws/docker/library/alpine:3`
+    pub image: &'a str,
+    /// In CPU units, 1024 is one vCPU. Only certain combinations with `memory_mib` are
+    /// valid on Fargate.
+    pub cpu: u32,
+    pub memory_mib: u32,
+    /// Used by ECS to pull the image and to write logs
+    pub execution_role: &'a RoleArn,
+    /// Used by the container itself
+    pub task_role: Option<&'a RoleArn>,
+    /// Has to exist already
+    pub log_group: &'a str,
+    pub tags: &'a TagList,
+}
+
+/// Changes to the task definition for a single run
+#[derive(Debug, Clone, Default)]
+pub struct Overrides {
+    pub environment: Vec<(String, String)>,
+    pub command: Option<Vec<String>>,
+    pub cpu: Option<u32>,
+    pub memory_mib: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RunConfig<'a> {
+    /// Name or ARN of the cluster
+    pub cluster: &'a str,
+    pub task_definition: &'a TaskDefinitionArn,
+    pub subnets: &'a [SubnetId],
+    pub security_groups: &'a [SecurityGroupId],
+    /// Required to pull images from public registries in subnets without a NAT gateway
+    pub assign_public_ip: bool,
+    pub overrides: &'a Overrides,
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoppedTask {
+    pub arn: TaskArn,
+    /// `None` if the container never ran, e.g. because the image could not be pulled
+    pub exit_code: Option<i32>,
+    pub stopped_reason: Option<String>,
+}
+
+impl StoppedTask {
+    pub fn succeeded(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+}
+
+fn ecs_tags(tags: &TagList) -> Vec<aws_sdk_ecs::types::Tag> {
+    tags.as_slice()
+        .iter()
+        .map(|tag| {
+            aws_sdk_ecs::types::Tag::builder()
+                .key(tag.key().as_str())
+                .value(tag.value().as_str())
+                .build()
+        })
+        .collect()
+}
+
+fn environment(variables: &[(String, String)]) -> Vec<aws_sdk_ecs::types::KeyValuePair> {
+    variables
+        .iter()
+        .map(|&(ref name, ref value)| {
+            aws_sdk_ecs::types::KeyValuePair::builder()
+                .name(name)
+                .value(value)
+                .build()
+        })
+        .collect()
+}
+
+/// Registers a new revision of the task definition family
+#[expect(
+    clippy::missing_panics_doc,
+    reason = "only expect() on builder instances"
+)]
+pub async fn register_task_definition(
+    client: &RegionClient,
+    definition: &TaskDefinition<'_>,
+) -> Result<TaskDefinitionArn, Error> {
+    let container = aws_sdk_ecs::types::ContainerDefinition::builder()
+        .name(CONTAINER_NAME)
+        .image(definition.image)
+        .essential(true)
+        .log_configuration(
+            aws_sdk_ecs::types::LogConfiguration::builder()
+                .log_driver(aws_sdk_ecs::types::LogDriver::Awslogs)
+                .options("awslogs-group", definition.log_group)
+                .options("awslogs-region", client.region.as_str())
+                .options("awslogs-stream-prefix", definition.family)
+                .build()
+                .expect("builder has all required fields set"),
+        )
+        .build();
+
+    let task_definition = client
+        .main
+        .ecs
+        .register_task_definition()
+        .family(definition.family)
+        .requires_compatibilities(aws_sdk_ecs::types::Compatibility::Fargate)
+        .network_mode(aws_sdk_ecs::types::NetworkMode::Awsvpc)
+        .cpu(definition.cpu.to_string())
+        .memory(definition.memory_mib.to_string())
+        .execution_role_arn(definition.execution_role.as_str())
+        .set_task_role_arn(definition.task_role.map(|role| role.as_str().to_owned()))
+        .container_definitions(container)
+        .set_tags(Some(ecs_tags(&client.tags_with_defaults(definition.tags))))
+        .send()
+        .await?
+        .task_definition
+        .ok_or_else(|| Error::UnexpectedNoneValue {
+            entity: "RegisterTaskDefinitionOutput.task_definition".to_owned(),
+        })?;
+
+    Ok(TaskDefinitionArn(
+        task_definition
+            .task_definition_arn
+            .ok_or_else(|| Error::UnexpectedNoneValue {
+                entity: "TaskDefinition.task_definition_arn".to_owned(),
+            })?,
+    ))
+}
+
+/// Starts a single Fargate task. Fails with [`Error::EcsRunTaskFailed`] if ECS cannot
+/// place the task, e.g. due to missing capacity.
+#[expect(
+    clippy::missing_panics_doc,
+    reason = "only expect() on builder instances"
+)]
+pub async fn run_task(client: &RegionClient, config: &RunConfig<'_>) -> Result<TaskArn, Error> {
+    let overrides = config.overrides;
+
+    let output = client
+        .main
+        .ecs
+        .run_task()
+        .cluster(config.cluster)
+        .task_definition(config.task_definition.as_str())
+        .launch_type(aws_sdk_ecs::types::LaunchType::Fargate)
+        .count(1)
+        .network_configuration(
+            aws_sdk_ecs::types::NetworkConfiguration::builder()
+                .awsvpc_configuration(
+                    aws_sdk_ecs::types::AwsVpcConfiguration::builder()
+                        .set_subnets(Some(
+                            config.subnets.iter().map(ToString::to_string).collect(),
+                        ))
+                        .set_security_groups(Some(
+                            config
+                                .security_groups
+                                .iter()
+                                .map(ToString::to_string)
+                                .collect(),
+                        ))
+                        .assign_public_ip(if config.assign_public_ip {
+                            aws_sdk_ecs::types::AssignPublicIp::Enabled
+                        } else {
+                            aws_sdk_ecs::types::AssignPublicIp::Disabled
+                        })
+                        .build()
+                        .expect("builder has all required fields set"),
+                )
+                .build(),
+        )
+        .overrides(
+            aws_sdk_ecs::types::TaskOverride::builder()
+                .container_overrides(
+                    aws_sdk_ecs::types::ContainerOverride::builder()
+                        .name(CONTAINER_NAME)
+                        .set_environment(Some(environment(&overrides.environment)))
+                        .set_command(overrides.command.clone())
+                        .build(),
+                )
+                .set_cpu(overrides.cpu.map(|cpu| cpu.to_string()))
+                .set_memory(overrides.memory_mib.map(|memory| memory.to_string()))
+                .build(),
+        )
+        .send()
+        .await?;
+
+    if let Some(failure) = output.failures.unwrap_or_default().into_iter().next() {
+        return Err(Error::EcsRunTaskFailed {
+            reason: failure
+                .reason
+                .or(failure.detail)
+                .unwrap_or_else(|| "unknown".to_owned()),
+        });
+    }
+
+    output
+        .tasks
+        .unwrap_or_default()
+        .into_iter()
+        .next()
+        .and_then(|task| task.task_arn)
+        .map(TaskArn)
+        .ok_or_else(|| Error::UnexpectedNoneValue {
+            entity: "RunTaskOutput.tasks".to_owned(),
+        })
+}
+
+async fn stopped_task(
+    client: &RegionClient,
+    cluster: &str,
+    task: &TaskArn,
+) -> Result<Option<StoppedTask>, Error> {
+    let Some(described) = client
+        .main
+        .ecs
+        .describe_tasks()
+        .cluster(cluster)
+        .tasks(task.as_str())
+        .send()
+        .await?
+        .tasks
+        .unwrap_or_default()
+        .into_iter()
+        .next()
+    else {
+        return Err(Error::UnexpectedNoneValue {
+            entity: format!("task {task}"),
+        });
+    };
+
+    if described.last_status.as_deref() != Some(STOPPED_STATUS) {
+        return Ok(None);
+    }
+
+    Ok(Some(StoppedTask {
+        arn: task.clone(),
+        exit_code: described
+            .containers
+            .unwrap_or_default()
+            .into_iter()
+            .find(|container| container.name.as_deref() == Some(CONTAINER_NAME))
+            .and_then(|container| container.exit_code),
+        stopped_reason: described.stopped_reason,
+    }))
+}
+
+/// Waits until the task has stopped. A failed container is not an error, check
+/// [`StoppedTask::succeeded()`].
+///
+/// If `max_wait` is `None`, the [`WaitConfig`](crate::waiter::WaitConfig) of `client`
+/// applies.
+pub async fn wait_for_task(
+    client: &RegionClient,
+    cluster: &str,
+    task: &TaskArn,
+    max_wait: Option<Duration>,
+) -> Result<StoppedTask, Error> {
+    let max_wait = max_wait.unwrap_or(client.wait_config.ecs_task);
+
+    waiter::poll(client.waiter_profile, max_wait, || async move {
+        stopped_task(client, cluster, task).await
+    })
+    .await?
+    .ok_or_else(|| Error::EcsTaskExceededMaxWait {
+        max_wait,
+        task: task.to_string(),
+    })
+}
+
+/// The log messages of the container of `task`, oldest first. `definition` has to be
+/// the task definition the task was started from.
+pub async fn task_logs(
+    client: &RegionClient,
+    definition: &TaskDefinition<'_>,
+    task: &TaskArn,
+) -> Result<Vec<String>, Error> {
+    let logs = &client.main.cloudwatchlogs;
+    let log_group = definition.log_group;
+    let log_stream = format!("{}/{CONTAINER_NAME}/{}", definition.family, task.task_id());
+    let log_stream = log_stream.as_str();
+
+    let events = pagination::paginate(|token: Option<String>| async move {
+        let output = logs
+            .get_log_events()
+            .log_group_name(log_group)
+            .log_stream_name(log_stream)
+            .start_from_head(true)
+            .set_next_token(token.clone())
+            .send()
+            .await?;
+
+        // The last page returns the token it was called with
+        Ok(pagination::Page {
+            items: output.events.unwrap_or_default(),
+            next_token: output
+                .next_forward_token
+                .filter(|next| Some(next) != token.as_ref()),
+        })
+    })
+    .await?;
+
+    Ok(events
+        .into_iter()
+        .filter_map(|event| event.message)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn task_id_from_arn() {
+        assert_eq!(
+            TaskArn::new(
+                "arn:aws:ecs:eu-central-1:111111111111:task/batch/0123456789abcdef".to_owned()
+            )
+            .task_id(),
+            "0123456789abcdef"
+        );
+    }
+}
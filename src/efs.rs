@@ -0,0 +1,227 @@
+//! Mounting EFS file systems with efs-utils
+//!
+//! [`Efs::mount_options()`](crate::Efs::mount_options()) returns the options that
+//! AWS recommends for mounting with the `efs` mount helper: traffic is encrypted with
+//! TLS, and the client reconnects on a new port after a network interruption. The
+//! result can be rendered as a line for `/etc/fstab` or as a systemd mount unit, e.g.
+//! for user data.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::{EfsId, Error};
+
+crate::string_newtype!(AccessPointId);
+
+impl AccessPointId {
+    pub const fn new(value: String) -> Self {
+        Self(value)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MountOptions {
+    pub file_system: EfsId,
+    /// Mount through this access point, which enforces its root directory and POSIX
+    /// identity
+    pub access_point: Option<AccessPointId>,
+    /// Authenticate with the IAM role of the instance, for file system policies that
+    /// grant access to roles. The role needs the `elasticfilesystem:ClientMount`
+    /// permission.
+    pub iam: bool,
+}
+
+impl MountOptions {
+    /// Without IAM authentication
+    pub const fn new(file_system: EfsId, access_point: Option<AccessPointId>) -> Self {
+        Self {
+            file_system,
+            access_point,
+            iam: false,
+        }
+    }
+
+    /// The source of the mount, the root of the file system or of the access point
+    pub fn what(&self) -> String {
+        format!("{}:/", self.file_system)
+    }
+
+    /// The options for the `efs` mount type. `_netdev` delays the mount until the
+    /// network is up, and `noresvport` makes the client use a new port when it
+    /// reconnects. Traffic is always encrypted with `tls`, which IAM authentication and
+    /// access points require anyway.
+    pub fn options(&self) -> Vec<String> {
+        let mut options = vec![
+            "_netdev".to_owned(),
+            "noresvport".to_owned(),
+            "tls".to_owned(),
+        ];
+        if self.iam {
+            options.push("iam".to_owned());
+        }
+        if let Some(ref access_point) = self.access_point {
+            options.push(format!("accesspoint={access_point}"));
+        }
+        options
+    }
+
+    /// A line for `/etc/fstab` that mounts the file system at `mount_point`
+    pub fn render_fstab_line(&self, mount_point: &str) -> Result<String, Error> {
+        check_mount_point(mount_point)?;
+
+        Ok(format!(
+            "{} {mount_point} efs {} 0 0",
+            self.what(),
+            self.options().join(",")
+        ))
+    }
+
+    /// A systemd mount unit that mounts the file system at `mount_point`. It has to be
+    /// installed as [`mount_unit_name()`] of `mount_point`, e.g.
+    /// `/etc/systemd/system/mnt-data.mount`, and enabled.
+    pub fn render_mount_unit(&self, mount_point: &str) -> Result<String, Error> {
+        check_mount_point(mount_point)?;
+
+        Ok(format!(
+            "[Unit]
+Description=EFS {file_system} at {mount_point}
+Wants=network-online.target
+After=network-online.target
+
+[Mount]
+What={what}
+Where={mount_point}
+Type=efs
+Options={options}
+
+[Install]
+WantedBy=remote-fs.target
+",
+            file_system = self.file_system,
+            what = self.what(),
+            options = self.options().join(","),
+        ))
+    }
+}
+
+/// Mount points have to be absolute, and cannot contain whitespace as fstab fields
+/// are separated by it
+fn check_mount_point(mount_point: &str) -> Result<(), Error> {
+    if !mount_point.starts_with('/') || mount_point.contains(char::is_whitespace) {
+        return Err(Error::InvalidArgument {
+            message: format!(
+                "mount point \"{mount_point}\" has to be an absolute path without whitespace"
+            ),
+        });
+    }
+    Ok(())
+}
+
+/// Escapes a single path segment like systemd does, e.g. `efs-data` as `efs\x2ddata`
+fn escape_segment(segment: &str) -> String {
+    segment
+        .bytes()
+        .enumerate()
+        .map(|(i, byte)| match byte {
+            b'.' if i == 0 => "\\x2e".to_owned(),
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'_' | b'.' | b':' => {
+                char::from(byte).to_string()
+            }
+            _ => format!("\\x{byte:02x}"),
+        })
+        .collect()
+}
+
+/// The name that systemd requires for the mount unit of `mount_point`, e.g.
+/// `mnt-data.mount` for `/mnt/data`. Follows `systemd-escape --path`: repeated slashes
+/// and `.` segments are dropped, `..` segments are rejected.
+pub fn mount_unit_name(mount_point: &str) -> Result<String, Error> {
+    check_mount_point(mount_point)?;
+
+    let segments: Vec<&str> = mount_point
+        .split('/')
+        .filter(|segment| !segment.is_empty() && *segment != ".")
+        .collect();
+
+    if segments.contains(&"..") {
+        return Err(Error::InvalidArgument {
+            message: format!("mount point \"{mount_point}\" must not contain \"..\""),
+        });
+    }
+
+    if segments.is_empty() {
+        return Ok("-.mount".to_owned());
+    }
+
+    Ok(format!(
+        "{}.mount",
+        segments
+            .into_iter()
+            .map(escape_segment)
+            .collect::<Vec<_>>()
+            .join("-")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_with_access_point() {
+        let options = MountOptions::new(
+            EfsId::new("fs-1234".to_owned()),
+            Some(AccessPointId::new("fsap-5678".to_owned())),
+        );
+
+        assert_eq!(
+            options.render_fstab_line("/mnt/data").unwrap(),
+            "fs-1234:/ /mnt/data efs _netdev,noresvport,tls,accesspoint=fsap-5678 0 0"
+        );
+        assert!(options
+            .render_mount_unit("/mnt/data")
+            .unwrap()
+            .contains("\nOptions=_netdev,noresvport,tls,accesspoint=fsap-5678\n"));
+        for mount_point in ["mnt/data", "/mnt/my data"] {
+            assert!(matches!(
+                options.render_fstab_line(mount_point),
+                Err(Error::InvalidArgument { ref message }) if message.contains(mount_point)
+            ));
+        }
+    }
+
+    #[test]
+    fn iam() {
+        let options = MountOptions {
+            iam: true,
+            ..MountOptions::new(EfsId::new("fs-1234".to_owned()), None)
+        };
+
+        assert_eq!(options.options(), ["_netdev", "noresvport", "tls", "iam"]);
+    }
+
+    #[test]
+    fn unit_names() {
+        assert_eq!(mount_unit_name("/mnt/data").unwrap(), "mnt-data.mount");
+        assert_eq!(
+            mount_unit_name("/mnt/efs-data/").unwrap(),
+            "mnt-efs\\x2ddata.mount"
+        );
+        assert_eq!(mount_unit_name("/").unwrap(), "-.mount");
+        assert_eq!(mount_unit_name("/mnt//data").unwrap(), "mnt-data.mount");
+        assert_eq!(mount_unit_name("/mnt/./data").unwrap(), "mnt-data.mount");
+        assert_eq!(
+            mount_unit_name("/srv/.cache").unwrap(),
+            "srv-\\x2ecache.mount"
+        );
+        assert!(matches!(
+            mount_unit_name("/mnt/../data"),
+            Err(Error::InvalidArgument { ref message }) if message.contains("\"..\"")
+        ));
+    }
+}
@@ -0,0 +1,242 @@
+//! Read-only view of EKS clusters and their managed nodegroups
+//!
+//! The nodes of a nodegroup are plain EC2 instances in the autoscaling groups of the
+//! nodegroup, so they are returned as [`Instance`]s, like all other instances.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::{pagination, Error, Instance, InstanceType, RegionClient};
+
+/// Set by EC2 autoscaling on all instances it launches
+const AUTOSCALING_GROUP_TAG: &str = "aws:autoscaling:groupName";
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cluster {
+    pub name: String,
+    pub arn: Option<String>,
+    /// The URL of the Kubernetes API server
+    pub endpoint: Option<String>,
+    /// For IAM roles for service accounts
+    pub oidc_issuer: Option<String>,
+    /// The Kubernetes version, e.g. `1.31`
+    pub version: Option<String>,
+    pub status: Option<String>,
+}
+
+impl TryFrom<aws_sdk_eks::types::Cluster> for Cluster {
+    type Error = Error;
+
+    fn try_from(cluster: aws_sdk_eks::types::Cluster) -> Result<Self, Self::Error> {
+        Ok(Self {
+            name: cluster.name.ok_or_else(|| Error::UnexpectedNoneValue {
+                entity: "Cluster.name".to_owned(),
+            })?,
+            arn: cluster.arn,
+            endpoint: cluster.endpoint,
+            oidc_issuer: cluster
+                .identity
+                .and_then(|identity| identity.oidc)
+                .and_then(|oidc| oidc.issuer),
+            version: cluster.version,
+            status: cluster.status.map(|status| status.as_str().to_owned()),
+        })
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct Nodegroup {
+    pub name: String,
+    pub cluster: String,
+    pub instance_types: Vec<InstanceType>,
+    pub autoscaling_groups: Vec<String>,
+    pub desired_size: Option<i32>,
+}
+
+impl TryFrom<aws_sdk_eks::types::Nodegroup> for Nodegroup {
+    type Error = Error;
+
+    fn try_from(nodegroup: aws_sdk_eks::types::Nodegroup) -> Result<Self, Self::Error> {
+        Ok(Self {
+            name: nodegroup
+                .nodegroup_name
+                .ok_or_else(|| Error::UnexpectedNoneValue {
+                    entity: "Nodegroup.nodegroup_name".to_owned(),
+                })?,
+            cluster: nodegroup
+                .cluster_name
+                .ok_or_else(|| Error::UnexpectedNoneValue {
+                    entity: "Nodegroup.cluster_name".to_owned(),
+                })?,
+            instance_types: nodegroup
+                .instance_types
+                .unwrap_or_default()
+                .into_iter()
+                .map(|instance_type| {
+                    InstanceType::new(aws_sdk_ec2::types::InstanceType::from(
+                        instance_type.as_str(),
+                    ))
+                })
+                .collect(),
+            autoscaling_groups: nodegroup
+                .resources
+                .and_then(|resources| resources.auto_scaling_groups)
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|group| group.name)
+                .collect(),
+            desired_size: nodegroup
+                .scaling_config
+                .and_then(|scaling_config| scaling_config.desired_size),
+        })
+    }
+}
+
+/// Terminated instances stay visible for a while after autoscaling replaced them, but
+/// are no longer nodes of the nodegroup
+const NODE_INSTANCE_STATES: [&str; 4] = ["pending", "running", "stopping", "stopped"];
+
+impl Nodegroup {
+    fn instance_filters(&self) -> Vec<aws_sdk_ec2::types::Filter> {
+        vec![
+            aws_sdk_ec2::types::Filter::builder()
+                .name(format!("tag:{AUTOSCALING_GROUP_TAG}"))
+                .set_values(Some(self.autoscaling_groups.clone()))
+                .build(),
+            aws_sdk_ec2::types::Filter::builder()
+                .name("instance-state-name")
+                .set_values(Some(
+                    NODE_INSTANCE_STATES
+                        .into_iter()
+                        .map(ToOwned::to_owned)
+                        .collect(),
+                ))
+                .build(),
+        ]
+    }
+
+    /// The instances currently in the autoscaling groups of the nodegroup. Instances
+    /// that are shutting down or terminated are left out.
+    pub async fn instances(&self, client: &RegionClient) -> Result<Vec<Instance>, Error> {
+        if self.autoscaling_groups.is_empty() {
+            return Ok(vec![]);
+        }
+
+        pagination::list_instances(client, self.instance_filters())
+            .await?
+            .into_iter()
+            .map(Instance::try_from_aws)
+            .collect()
+    }
+}
+
+/// The names of all clusters in the region
+pub async fn list_clusters(client: &RegionClient) -> Result<Vec<String>, Error> {
+    let eks = &client.main.eks;
+
+    pagination::paginate(|token| async move {
+        let output = eks.list_clusters().set_next_token(token).send().await?;
+        Ok(pagination::Page {
+            items: output.clusters.unwrap_or_default(),
+            next_token: output.next_token,
+        })
+    })
+    .await
+}
+
+pub async fn describe_cluster(client: &RegionClient, name: &str) -> Result<Cluster, Error> {
+    client
+        .main
+        .eks
+        .describe_cluster()
+        .name(name)
+        .send()
+        .await?
+        .cluster
+        .ok_or_else(|| Error::UnexpectedNoneValue {
+            entity: "DescribeClusterOutput.cluster".to_owned(),
+        })?
+        .try_into()
+}
+
+/// All managed nodegroups of `cluster`
+pub async fn list_nodegroups(
+    client: &RegionClient,
+    cluster: &str,
+) -> Result<Vec<Nodegroup>, Error> {
+    let eks = &client.main.eks;
+
+    let names = pagination::paginate(|token| async move {
+        let output = eks
+            .list_nodegroups()
+            .cluster_name(cluster)
+            .set_next_token(token)
+            .send()
+            .await?;
+        Ok(pagination::Page {
+            items: output.nodegroups.unwrap_or_default(),
+            next_token: output.next_token,
+        })
+    })
+    .await?;
+
+    let mut nodegroups = Vec::with_capacity(names.len());
+    for name in names {
+        nodegroups.push(
+            eks.describe_nodegroup()
+                .cluster_name(cluster)
+                .nodegroup_name(name)
+                .send()
+                .await?
+                .nodegroup
+                .ok_or_else(|| Error::UnexpectedNoneValue {
+                    entity: "DescribeNodegroupOutput.nodegroup".to_owned(),
+                })?
+                .try_into()?,
+        );
+    }
+
+    Ok(nodegroups)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn instance_filters_skip_gone_instances() {
+        let nodegroup = Nodegroup {
+            name: "workers".to_owned(),
+            cluster: "main".to_owned(),
+            instance_types: vec![],
+            autoscaling_groups: vec!["eks-workers-0123".to_owned()],
+            desired_size: Some(3),
+        };
+
+        let filters = nodegroup.instance_filters();
+        let states = filters
+            .iter()
+            .find(|filter| filter.name() == Some("instance-state-name"))
+            .unwrap()
+            .values();
+
+        assert!(states.contains(&"running".to_owned()), "running nodes");
+        assert!(
+            !states.contains(&"terminated".to_owned()),
+            "terminated nodes"
+        );
+        assert!(
+            !states.contains(&"shutting-down".to_owned()),
+            "shutting-down nodes"
+        );
+        assert!(
+            filters.iter().any(
+                |filter| filter.name() == Some("tag:aws:autoscaling:groupName")
+                    && filter.values() == ["eks-workers-0123"]
+            ),
+            "autoscaling group filter"
+        );
+    }
+}
@@ -1,13 +1,59 @@
 use std::{fmt, net, time::Duration};
 
-use crate::tags::{ParseTagError, ParseTagsError};
+use aws_sdk_ec2::{error::ProvideErrorMetadata, operation::RequestId as _};
+#[cfg(feature = "serde")]
+use serde::{ser::SerializeStruct as _, Serialize, Serializer};
+
+use crate::tags::{ParseTagError, ParseTagsError, RawTag};
+
+const CAPACITY_ERROR_CODES: [&str; 4] = [
+    "InsufficientInstanceCapacity",
+    "InsufficientHostCapacity",
+    "InsufficientReservedInstanceCapacity",
+    "InsufficientCapacity",
+];
+
+const QUOTA_ERROR_CODES: [&str; 5] = [
+    "InstanceLimitExceeded",
+    "VcpuLimitExceeded",
+    "AddressLimitExceeded",
+    "VolumeLimitExceeded",
+    "MaxSpotInstanceCountExceeded",
+];
+
+const THROTTLING_ERROR_CODES: [&str; 5] = [
+    "RequestLimitExceeded",
+    "Throttling",
+    "ThrottlingException",
+    "TooManyRequestsException",
+    "PriorRequestNotComplete",
+];
+
+/// Coarse classification of errors, to decide how to react to them
+#[cfg_attr(feature = "serde", derive(Serialize), serde(rename_all = "snake_case"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// AWS has no capacity for the request, e.g. for a certain instance type in an AZ
+    Capacity,
+    /// An account quota was hit
+    Quota,
+    /// The request was throttled
+    Throttling,
+    Other,
+}
 
 #[derive(Debug)]
 pub enum Error {
     UnexpectedNoneValue {
         entity: String,
     },
-    SdkError(Box<dyn std::error::Error + Send>),
+    SdkError {
+        code: Option<String>,
+        /// Required by AWS support to look into a failed request
+        request_id: Option<String>,
+        http_status: Option<u16>,
+        source: Box<dyn std::error::Error + Send>,
+    },
     InvalidResponseError {
         message: String,
     },
@@ -21,12 +67,185 @@ pub enum Error {
         max_wait: Duration,
         instance: super::InstanceId,
     },
+    InstanceWaitExceededMaxWait {
+        max_wait: Duration,
+        instance: super::InstanceId,
+        state: super::InstanceStateName,
+    },
     WaitError(Box<dyn std::error::Error + Send>),
     RunInstanceNoCapacity,
     InvalidTimestampError {
         value: String,
         message: String,
     },
+    LeaseHeld {
+        resource: String,
+        holder: String,
+        expires: super::Timestamp,
+    },
+    LeaseLost {
+        resource: String,
+    },
+    ResourceProtected {
+        resource: String,
+        tag: RawTag,
+    },
+    LaunchFallbackExhausted {
+        failures: Vec<(usize, Error)>,
+    },
+    CommandExceededMaxWait {
+        max_wait: Duration,
+        command: String,
+        instance: super::InstanceId,
+    },
+    InvalidArgument {
+        message: String,
+    },
+    DnsExceededMaxWait {
+        fqdn: String,
+        max_wait: Duration,
+    },
+    CredentialsError {
+        message: String,
+    },
+    CredentialsExpireTooSoon {
+        valid_for: Duration,
+        required: Duration,
+    },
+    EipQuotaExceededMaxWait {
+        max_wait: Duration,
+    },
+    AmiCopyFailed {
+        ami: super::AmiId,
+        message: String,
+    },
+    AmiCopyExceededMaxWait {
+        max_wait: Duration,
+        ami: super::AmiId,
+    },
+    OutsideMaintenanceWindow {
+        instance: super::InstanceId,
+        window: String,
+    },
+    ConfigError {
+        message: String,
+    },
+    VolumeModificationFailed {
+        volume: super::VolumeId,
+        message: String,
+    },
+    VolumeModificationExceededMaxWait {
+        max_wait: Duration,
+        volume: super::VolumeId,
+    },
+    TeardownStepFailed {
+        step: String,
+        message: String,
+    },
+    TeardownStepExceededMaxWait {
+        max_wait: Duration,
+        step: String,
+    },
+    WorkflowError {
+        run_id: String,
+        message: String,
+    },
+    ReachabilityAnalysisFailed {
+        message: String,
+    },
+    ReachabilityAnalysisExceededMaxWait {
+        max_wait: Duration,
+    },
+    FlowLogError {
+        resource: String,
+        message: String,
+    },
+    DataSyncExceededMaxWait {
+        max_wait: Duration,
+        execution: String,
+    },
+    RollbackFailed {
+        /// The error that triggered the rollback
+        error: Box<Error>,
+        rollback: Box<Error>,
+    },
+    /// The instance is in a state that does not allow the operation, e.g. starting an
+    /// instance that is still stopping. Retrying after a state change may succeed.
+    IncorrectInstanceState {
+        instance: super::InstanceId,
+        message: String,
+    },
+    ImdsError {
+        path: String,
+        message: String,
+    },
+    InstanceNotStopped {
+        instance: super::InstanceId,
+        state: super::InstanceStateName,
+    },
+    EcsRunTaskFailed {
+        reason: String,
+    },
+    EcsTaskExceededMaxWait {
+        max_wait: Duration,
+        task: String,
+    },
+    MinHealthyViolated {
+        healthy: usize,
+        batch: usize,
+        min_healthy: usize,
+    },
+    TargetHealthExceededMaxWait {
+        max_wait: Duration,
+        target_group: String,
+    },
+    CommandFailed {
+        instance: super::InstanceId,
+        stderr: String,
+    },
+    ReportError {
+        message: String,
+    },
+    SnapshotFailed {
+        snapshot: super::SnapshotId,
+        message: String,
+    },
+    SnapshotExceededMaxWait {
+        max_wait: Duration,
+        snapshot: super::SnapshotId,
+    },
+    AdoptionRejected {
+        resource: String,
+        reason: String,
+    },
+    StillReferenced {
+        ip: super::Ip,
+        records: Vec<String>,
+    },
+    WindowsPasswordDecryptionFailed {
+        instance: super::InstanceId,
+        message: String,
+    },
+    WindowsReadyExceededMaxWait {
+        max_wait: Duration,
+        instance: super::InstanceId,
+    },
+    ArchitectureMismatch {
+        ami: super::AmiId,
+        architecture: super::ArchitectureValues,
+        instance_type: super::InstanceType,
+        supported: Vec<String>,
+    },
+    NotOwned {
+        resource: String,
+        tag: RawTag,
+    },
+    /// The AMI was deregistered, but deleting some of its snapshots failed
+    AmiSnapshotsNotDeleted {
+        ami: super::AmiId,
+        deleted: Vec<super::SnapshotId>,
+        failed: Vec<(super::SnapshotId, Box<Self>)>,
+    },
 }
 
 impl fmt::Display for Error {
@@ -41,7 +260,24 @@ impl fmt::Display for Error {
             Self::UnexpectedNoneValue { ref entity } => {
                 write!(f, "entity \"{entity}\" was empty")
             }
-            Self::SdkError(ref e) => write!(f, "sdk error: {e}"),
+            Self::SdkError {
+                ref code,
+                ref request_id,
+                ref http_status,
+                ref source,
+            } => {
+                write!(f, "sdk error: {source}")?;
+                if let Some(ref code) = *code {
+                    write!(f, ", code {code}")?;
+                }
+                if let Some(ref http_status) = *http_status {
+                    write!(f, ", http status {http_status}")?;
+                }
+                if let Some(ref request_id) = *request_id {
+                    write!(f, ", request id {request_id}")?;
+                }
+                Ok(())
+            }
             Self::InvalidResponseError { ref message } => {
                 write!(f, "invalid api response: {message}")
             }
@@ -61,6 +297,17 @@ impl fmt::Display for Error {
                     max_wait.as_secs()
                 )
             }
+            Self::InstanceWaitExceededMaxWait {
+                ref max_wait,
+                ref instance,
+                ref state,
+            } => {
+                write!(
+                    f,
+                    "instance {instance} did not reach state {state} in {} seconds",
+                    max_wait.as_secs()
+                )
+            }
             Self::WaitError(ref e) => write!(f, "waiter error: {e}"),
             Self::RunInstanceNoCapacity => {
                 write!(f, "no capacity for rnu instance operation")
@@ -71,15 +318,436 @@ impl fmt::Display for Error {
             } => {
                 write!(f, "failed parsing \"{value}\" as timestamp: {message}")
             }
+            Self::LeaseHeld {
+                ref resource,
+                ref holder,
+                ref expires,
+            } => {
+                write!(
+                    f,
+                    "resource {resource} is leased by {holder} until {expires}"
+                )
+            }
+            Self::LeaseLost { ref resource } => {
+                write!(f, "lease on resource {resource} is not held anymore")
+            }
+            Self::ResourceProtected {
+                ref resource,
+                ref tag,
+            } => {
+                write!(
+                    f,
+                    "resource {resource} is protected by tag \"{}={}\"",
+                    tag.key(),
+                    tag.value()
+                )
+            }
+            Self::LaunchFallbackExhausted { ref failures } => {
+                write!(f, "all launch configurations failed")?;
+                for &(index, ref error) in failures {
+                    write!(f, "; configuration {index}: {error}")?;
+                }
+                Ok(())
+            }
+            Self::CommandExceededMaxWait {
+                ref max_wait,
+                ref command,
+                ref instance,
+            } => {
+                write!(
+                    f,
+                    "command {command} did not finish on instance {instance} in {} seconds",
+                    max_wait.as_secs()
+                )
+            }
+            Self::InvalidArgument { ref message } => {
+                write!(f, "invalid argument: {message}")
+            }
+            Self::DnsExceededMaxWait {
+                ref fqdn,
+                ref max_wait,
+            } => {
+                write!(
+                    f,
+                    "{fqdn} did not become resolvable in {} seconds",
+                    max_wait.as_secs()
+                )
+            }
+            Self::CredentialsError { ref message } => {
+                write!(f, "failed loading credentials: {message}")
+            }
+            Self::CredentialsExpireTooSoon {
+                ref valid_for,
+                ref required,
+            } => {
+                write!(
+                    f,
+                    "credentials are valid for {} more seconds, but {} seconds are required",
+                    valid_for.as_secs(),
+                    required.as_secs()
+                )
+            }
+            Self::EipQuotaExceededMaxWait { ref max_wait } => {
+                write!(
+                    f,
+                    "eip address quota did not free up in {} seconds",
+                    max_wait.as_secs()
+                )
+            }
+            Self::AmiCopyFailed {
+                ref ami,
+                ref message,
+            } => write!(f, "copy {ami} of ami failed: {message}"),
+            Self::AmiCopyExceededMaxWait {
+                ref max_wait,
+                ref ami,
+            } => {
+                write!(
+                    f,
+                    "copy {ami} of ami did not become available in {} seconds",
+                    max_wait.as_secs()
+                )
+            }
+            Self::OutsideMaintenanceWindow {
+                ref instance,
+                ref window,
+            } => write!(
+                f,
+                "instance {instance} is outside of its maintenance window \"{window}\""
+            ),
+            Self::ConfigError { ref message } => write!(f, "invalid config: {message}"),
+            Self::VolumeModificationFailed {
+                ref volume,
+                ref message,
+            } => write!(f, "modification of volume {volume} failed: {message}"),
+            Self::VolumeModificationExceededMaxWait {
+                ref max_wait,
+                ref volume,
+            } => {
+                write!(
+                    f,
+                    "modification of volume {volume} did not complete in {} seconds",
+                    max_wait.as_secs()
+                )
+            }
+            Self::TeardownStepFailed {
+                ref step,
+                ref message,
+            } => write!(f, "teardown step \"{step}\" failed: {message}"),
+            Self::TeardownStepExceededMaxWait {
+                ref max_wait,
+                ref step,
+            } => {
+                write!(
+                    f,
+                    "teardown step \"{step}\" did not finish in {} seconds",
+                    max_wait.as_secs()
+                )
+            }
+            Self::WorkflowError {
+                ref run_id,
+                ref message,
+            } => write!(f, "workflow run {run_id}: {message}"),
+            Self::ReachabilityAnalysisFailed { ref message } => {
+                write!(f, "reachability analysis failed: {message}")
+            }
+            Self::ReachabilityAnalysisExceededMaxWait { ref max_wait } => {
+                write!(
+                    f,
+                    "reachability analysis did not finish in {} seconds",
+                    max_wait.as_secs()
+                )
+            }
+            Self::FlowLogError {
+                ref resource,
+                ref message,
+            } => write!(f, "flow logs of {resource}: {message}"),
+            Self::DataSyncExceededMaxWait {
+                ref max_wait,
+                ref execution,
+            } => {
+                write!(
+                    f,
+                    "datasync task execution {execution} did not finish in {} seconds",
+                    max_wait.as_secs()
+                )
+            }
+            Self::RollbackFailed {
+                ref error,
+                ref rollback,
+            } => write!(f, "{error}, rollback failed as well: {rollback}"),
+            Self::IncorrectInstanceState {
+                ref instance,
+                ref message,
+            } => write!(f, "instance {instance} is in an incorrect state: {message}"),
+            Self::ImdsError {
+                ref path,
+                ref message,
+            } => write!(f, "instance metadata request for {path} failed: {message}"),
+            Self::InstanceNotStopped {
+                ref instance,
+                ref state,
+            } => write!(f, "instance {instance} is {state}, but has to be stopped"),
+            Self::EcsRunTaskFailed { ref reason } => {
+                write!(f, "ecs task was not started: {reason}")
+            }
+            Self::EcsTaskExceededMaxWait {
+                ref max_wait,
+                ref task,
+            } => {
+                write!(
+                    f,
+                    "ecs task {task} did not stop in {} seconds",
+                    max_wait.as_secs()
+                )
+            }
+            Self::MinHealthyViolated {
+                ref healthy,
+                ref batch,
+                ref min_healthy,
+            } => write!(
+                f,
+                "a batch of {batch} instances would leave fewer than {min_healthy} of {healthy} healthy"
+            ),
+            Self::TargetHealthExceededMaxWait {
+                ref max_wait,
+                ref target_group,
+            } => {
+                write!(
+                    f,
+                    "targets in {target_group} did not become healthy in {} seconds",
+                    max_wait.as_secs()
+                )
+            }
+            Self::CommandFailed {
+                ref instance,
+                ref stderr,
+            } => write!(f, "command failed on instance {instance}: {stderr}"),
+            Self::ReportError { ref message } => write!(f, "cannot write report: {message}"),
+            Self::SnapshotFailed {
+                ref snapshot,
+                ref message,
+            } => write!(f, "snapshot {snapshot} failed: {message}"),
+            Self::SnapshotExceededMaxWait {
+                ref max_wait,
+                ref snapshot,
+            } => {
+                write!(
+                    f,
+                    "snapshot {snapshot} did not complete in {} seconds",
+                    max_wait.as_secs()
+                )
+            }
+            Self::AdoptionRejected {
+                ref resource,
+                ref reason,
+            } => write!(f, "refusing to adopt {resource}: {reason}"),
+            Self::StillReferenced {
+                ref ip,
+                ref records,
+            } => write!(
+                f,
+                "ip {ip} is still referenced by records {}",
+                records.join(", ")
+            ),
+            Self::WindowsPasswordDecryptionFailed {
+                ref instance,
+                ref message,
+            } => write!(
+                f,
+                "failed to decrypt windows password of instance {instance}: {message}"
+            ),
+            Self::WindowsReadyExceededMaxWait {
+                ref max_wait,
+                ref instance,
+            } => {
+                write!(
+                    f,
+                    "windows instance {instance} was not ready after {} seconds",
+                    max_wait.as_secs()
+                )
+            }
+            Self::ArchitectureMismatch {
+                ref ami,
+                ref architecture,
+                ref instance_type,
+                ref supported,
+            } => write!(
+                f,
+                "ami {ami} is built for {architecture}, but instance type {instance_type} only supports {}",
+                supported.join(", ")
+            ),
+            Self::NotOwned {
+                ref resource,
+                ref tag,
+            } => {
+                write!(
+                    f,
+                    "resource {resource} does not carry ownership tag \"{}={}\"",
+                    tag.key(),
+                    tag.value()
+                )
+            }
+            Self::AmiSnapshotsNotDeleted {
+                ref ami,
+                ref failed,
+                ..
+            } => write!(
+                f,
+                "ami {ami} was deregistered, but deleting its snapshots failed: {}",
+                failed
+                    .iter()
+                    .map(|&(ref snapshot, ref error)| format!("{snapshot} ({error})"))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
         }
     }
 }
 
 impl std::error::Error for Error {}
 
-impl<T: std::error::Error + Send + 'static> From<aws_sdk_ec2::error::SdkError<T>> for Error {
+impl Error {
+    /// A stable identifier of the error variant, e.g. for the `type` of an API problem
+    /// document
+    pub const fn error_type(&self) -> &'static str {
+        match *self {
+            Self::UnexpectedNoneValue { .. } => "unexpected_none_value",
+            Self::SdkError { .. } => "sdk_error",
+            Self::InvalidResponseError { .. } => "invalid_response_error",
+            Self::MultipleMatches { .. } => "multiple_matches",
+            Self::InvalidTag(_) => "invalid_tag",
+            Self::InvalidTags(_) => "invalid_tags",
+            Self::RunInstancesEmptyResponse => "run_instances_empty_response",
+            Self::InstanceStopExceededMaxWait { .. } => "instance_stop_exceeded_max_wait",
+            Self::InstanceWaitExceededMaxWait { .. } => "instance_wait_exceeded_max_wait",
+            Self::WaitError(_) => "wait_error",
+            Self::RunInstanceNoCapacity => "run_instance_no_capacity",
+            Self::InvalidTimestampError { .. } => "invalid_timestamp_error",
+            Self::LeaseHeld { .. } => "lease_held",
+            Self::LeaseLost { .. } => "lease_lost",
+            Self::ResourceProtected { .. } => "resource_protected",
+            Self::LaunchFallbackExhausted { .. } => "launch_fallback_exhausted",
+            Self::CommandExceededMaxWait { .. } => "command_exceeded_max_wait",
+            Self::InvalidArgument { .. } => "invalid_argument",
+            Self::DnsExceededMaxWait { .. } => "dns_exceeded_max_wait",
+            Self::CredentialsError { .. } => "credentials_error",
+            Self::CredentialsExpireTooSoon { .. } => "credentials_expire_too_soon",
+            Self::EipQuotaExceededMaxWait { .. } => "eip_quota_exceeded_max_wait",
+            Self::AmiCopyFailed { .. } => "ami_copy_failed",
+            Self::AmiCopyExceededMaxWait { .. } => "ami_copy_exceeded_max_wait",
+            Self::OutsideMaintenanceWindow { .. } => "outside_maintenance_window",
+            Self::ConfigError { .. } => "config_error",
+            Self::VolumeModificationFailed { .. } => "volume_modification_failed",
+            Self::VolumeModificationExceededMaxWait { .. } => {
+                "volume_modification_exceeded_max_wait"
+            }
+            Self::TeardownStepFailed { .. } => "teardown_step_failed",
+            Self::TeardownStepExceededMaxWait { .. } => "teardown_step_exceeded_max_wait",
+            Self::WorkflowError { .. } => "workflow_error",
+            Self::ReachabilityAnalysisFailed { .. } => "reachability_analysis_failed",
+            Self::ReachabilityAnalysisExceededMaxWait { .. } => {
+                "reachability_analysis_exceeded_max_wait"
+            }
+            Self::FlowLogError { .. } => "flow_log_error",
+            Self::DataSyncExceededMaxWait { .. } => "data_sync_exceeded_max_wait",
+            Self::RollbackFailed { .. } => "rollback_failed",
+            Self::IncorrectInstanceState { .. } => "incorrect_instance_state",
+            Self::ImdsError { .. } => "imds_error",
+            Self::InstanceNotStopped { .. } => "instance_not_stopped",
+            Self::EcsRunTaskFailed { .. } => "ecs_run_task_failed",
+            Self::EcsTaskExceededMaxWait { .. } => "ecs_task_exceeded_max_wait",
+            Self::MinHealthyViolated { .. } => "min_healthy_violated",
+            Self::TargetHealthExceededMaxWait { .. } => "target_health_exceeded_max_wait",
+            Self::CommandFailed { .. } => "command_failed",
+            Self::ReportError { .. } => "report_error",
+            Self::SnapshotFailed { .. } => "snapshot_failed",
+            Self::SnapshotExceededMaxWait { .. } => "snapshot_exceeded_max_wait",
+            Self::AdoptionRejected { .. } => "adoption_rejected",
+            Self::StillReferenced { .. } => "still_referenced",
+            Self::WindowsPasswordDecryptionFailed { .. } => "windows_password_decryption_failed",
+            Self::WindowsReadyExceededMaxWait { .. } => "windows_ready_exceeded_max_wait",
+            Self::ArchitectureMismatch { .. } => "architecture_mismatch",
+            Self::NotOwned { .. } => "not_owned",
+            Self::AmiSnapshotsNotDeleted { .. } => "ami_snapshots_not_deleted",
+        }
+    }
+
+    /// The error code returned by the AWS API, if any
+    pub fn code(&self) -> Option<&str> {
+        match *self {
+            Self::SdkError { ref code, .. } => code.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// The ID of the failed request, if the AWS API returned one
+    pub fn request_id(&self) -> Option<&str> {
+        match *self {
+            Self::SdkError { ref request_id, .. } => request_id.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// The HTTP status of the AWS API response, if a response was received at all
+    pub const fn http_status(&self) -> Option<u16> {
+        match *self {
+            Self::SdkError { http_status, .. } => http_status,
+            _ => None,
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        match *self {
+            Self::RunInstanceNoCapacity => ErrorKind::Capacity,
+            Self::SdkError {
+                code: Some(ref code),
+                ..
+            } => {
+                if CAPACITY_ERROR_CODES.contains(&code.as_str()) {
+                    ErrorKind::Capacity
+                } else if QUOTA_ERROR_CODES.contains(&code.as_str()) {
+                    ErrorKind::Quota
+                } else if THROTTLING_ERROR_CODES.contains(&code.as_str()) {
+                    ErrorKind::Throttling
+                } else {
+                    ErrorKind::Other
+                }
+            }
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
+/// Serializes into a flat structure of the stable [`error_type()`](Error::error_type()),
+/// the [`kind()`](Error::kind()), the display message, and the AWS error details, if any.
+#[cfg(feature = "serde")]
+impl Serialize for Error {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Error", 6)?;
+        state.serialize_field("type", self.error_type())?;
+        state.serialize_field("kind", &self.kind())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.serialize_field("code", &self.code())?;
+        state.serialize_field("request_id", &self.request_id())?;
+        state.serialize_field("http_status", &self.http_status())?;
+        state.end()
+    }
+}
+
+impl<T> From<aws_sdk_ec2::error::SdkError<T>> for Error
+where
+    T: std::error::Error + ProvideErrorMetadata + Send + 'static,
+{
     fn from(value: aws_sdk_ec2::error::SdkError<T>) -> Self {
-        Self::SdkError(Box::new(value))
+        Self::SdkError {
+            code: value.code().map(ToOwned::to_owned),
+            request_id: value.request_id().map(ToOwned::to_owned),
+            http_status: value
+                .raw_response()
+                .map(|response| response.status().as_u16()),
+            source: Box::new(value),
+        }
     }
 }
 
@@ -0,0 +1,303 @@
+//! Security findings for instances, from GuardDuty and Inspector
+//!
+//! Both services have to be enabled in the region of the client. If GuardDuty has no
+//! detector, its findings are skipped.
+
+use std::cmp::Reverse;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::{pagination, query, Error, InstanceId, RegionClient};
+
+/// GuardDuty accepts at most 50 values per criterion and 50 IDs per `GetFindings`
+const GUARDDUTY_BATCH_SIZE: usize = 50;
+/// Inspector accepts at most 10 values per filter
+const INSPECTOR_BATCH_SIZE: usize = 10;
+
+const GUARDDUTY_INSTANCE_ID_CRITERION: &str = "resource.instanceDetails.instanceId";
+/// Archived findings were dismissed and are no longer active
+const GUARDDUTY_ARCHIVED_CRITERION: &str = "service.archived";
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FindingSource {
+    GuardDuty,
+    Inspector,
+}
+
+/// Ordered from least to most severe
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Informational,
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl Severity {
+    /// Maps the numeric GuardDuty severity to the ranges documented by GuardDuty
+    fn from_guardduty(score: f64) -> Self {
+        if score >= 9.0_f64 {
+            Self::Critical
+        } else if score >= 7.0_f64 {
+            Self::High
+        } else if score >= 4.0_f64 {
+            Self::Medium
+        } else if score >= 1.0_f64 {
+            Self::Low
+        } else {
+            Self::Informational
+        }
+    }
+
+    /// `UNTRIAGED` findings are ranked as [`Informational`](Self::Informational)
+    fn from_inspector(severity: &aws_sdk_inspector2::types::Severity) -> Self {
+        match *severity {
+            aws_sdk_inspector2::types::Severity::Critical => Self::Critical,
+            aws_sdk_inspector2::types::Severity::High => Self::High,
+            aws_sdk_inspector2::types::Severity::Medium => Self::Medium,
+            aws_sdk_inspector2::types::Severity::Low => Self::Low,
+            _ => Self::Informational,
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub source: FindingSource,
+    /// The finding ID for GuardDuty, the finding ARN for Inspector
+    pub id: String,
+    pub instance: InstanceId,
+    pub severity: Severity,
+    pub title: String,
+    /// E.g. `Recon:EC2/PortProbeUnprotectedPort` or `PACKAGE_VULNERABILITY`
+    pub finding_type: String,
+}
+
+impl TryFrom<aws_sdk_guardduty::types::Finding> for Finding {
+    type Error = Error;
+
+    fn try_from(finding: aws_sdk_guardduty::types::Finding) -> Result<Self, Self::Error> {
+        Ok(Self {
+            source: FindingSource::GuardDuty,
+            instance: finding
+                .resource
+                .and_then(|resource| resource.instance_details)
+                .and_then(|details| details.instance_id)
+                .map(InstanceId)
+                .ok_or_else(|| Error::UnexpectedNoneValue {
+                    entity: "Finding.resource.instance_details.instance_id".to_owned(),
+                })?,
+            severity: Severity::from_guardduty(finding.severity.ok_or_else(|| {
+                Error::UnexpectedNoneValue {
+                    entity: "Finding.severity".to_owned(),
+                }
+            })?),
+            title: finding.title.unwrap_or_default(),
+            finding_type: finding.r#type.ok_or_else(|| Error::UnexpectedNoneValue {
+                entity: "Finding.type".to_owned(),
+            })?,
+            id: finding.id.ok_or_else(|| Error::UnexpectedNoneValue {
+                entity: "Finding.id".to_owned(),
+            })?,
+        })
+    }
+}
+
+/// An Inspector finding concerns exactly one resource, which is `instance` for all
+/// findings returned by the instance filter
+fn inspector_finding(finding: aws_sdk_inspector2::types::Finding) -> Result<Finding, Error> {
+    let instance = finding
+        .resources
+        .into_iter()
+        .find(|resource| resource.r#type == aws_sdk_inspector2::types::ResourceType::AwsEc2Instance)
+        .map(|resource| InstanceId(resource.id))
+        .ok_or_else(|| Error::UnexpectedNoneValue {
+            entity: "Finding.resources[AWS_EC2_INSTANCE]".to_owned(),
+        })?;
+
+    Ok(Finding {
+        source: FindingSource::Inspector,
+        id: finding.finding_arn,
+        instance,
+        severity: Severity::from_inspector(&finding.severity),
+        title: finding.title.unwrap_or(finding.description),
+        finding_type: finding.r#type.as_str().to_owned(),
+    })
+}
+
+async fn guardduty_findings(
+    client: &RegionClient,
+    instances: &[InstanceId],
+) -> Result<Vec<Finding>, Error> {
+    let guardduty = &client.main.guardduty;
+
+    let Some(detector_id) = guardduty
+        .list_detectors()
+        .send()
+        .await?
+        .detector_ids
+        .and_then(|ids| ids.into_iter().next())
+    else {
+        return Ok(vec![]);
+    };
+    let detector_id = &detector_id;
+
+    let mut findings = vec![];
+    for batch in instances.chunks(GUARDDUTY_BATCH_SIZE) {
+        let criteria = aws_sdk_guardduty::types::FindingCriteria::builder()
+            .criterion(
+                GUARDDUTY_INSTANCE_ID_CRITERION,
+                aws_sdk_guardduty::types::Condition::builder()
+                    .set_equals(Some(
+                        batch.iter().map(|id| id.as_str().to_owned()).collect(),
+                    ))
+                    .build(),
+            )
+            .criterion(
+                GUARDDUTY_ARCHIVED_CRITERION,
+                aws_sdk_guardduty::types::Condition::builder()
+                    .equals("false")
+                    .build(),
+            )
+            .build();
+        let criteria = &criteria;
+
+        let finding_ids = pagination::paginate(|token| async move {
+            let response = guardduty
+                .list_findings()
+                .detector_id(detector_id)
+                .finding_criteria(criteria.clone())
+                .set_next_token(token)
+                .send()
+                .await?;
+
+            Ok(pagination::Page {
+                items: response.finding_ids.unwrap_or_default(),
+                // An empty token marks the last page
+                next_token: response.next_token.filter(|token| !token.is_empty()),
+            })
+        })
+        .await?;
+
+        for ids in finding_ids.chunks(GUARDDUTY_BATCH_SIZE) {
+            for finding in guardduty
+                .get_findings()
+                .detector_id(detector_id)
+                .set_finding_ids(Some(ids.to_vec()))
+                .send()
+                .await?
+                .findings
+                .unwrap_or_default()
+            {
+                findings.push(finding.try_into()?);
+            }
+        }
+    }
+
+    Ok(findings)
+}
+
+async fn inspector_findings(
+    client: &RegionClient,
+    instances: &[InstanceId],
+) -> Result<Vec<Finding>, Error> {
+    let inspector = &client.main.inspector2;
+
+    let mut findings = vec![];
+    for batch in instances.chunks(INSPECTOR_BATCH_SIZE) {
+        let criteria = aws_sdk_inspector2::types::FilterCriteria::builder()
+            .set_resource_id(Some(
+                batch
+                    .iter()
+                    .map(|id| {
+                        aws_sdk_inspector2::types::StringFilter::builder()
+                            .comparison(aws_sdk_inspector2::types::StringComparison::Equals)
+                            .value(id.as_str())
+                            .build()
+                            .expect("builder has all required fields set")
+                    })
+                    .collect(),
+            ))
+            .finding_status(
+                aws_sdk_inspector2::types::StringFilter::builder()
+                    .comparison(aws_sdk_inspector2::types::StringComparison::Equals)
+                    .value("ACTIVE")
+                    .build()
+                    .expect("builder has all required fields set"),
+            )
+            .build();
+        let criteria = &criteria;
+
+        let batch_findings = pagination::paginate(|token| async move {
+            let response = inspector
+                .list_findings()
+                .filter_criteria(criteria.clone())
+                .set_next_token(token)
+                .send()
+                .await?;
+
+            Ok(pagination::Page {
+                items: response.findings.unwrap_or_default(),
+                next_token: response.next_token,
+            })
+        })
+        .await?;
+
+        for finding in batch_findings {
+            findings.push(inspector_finding(finding)?);
+        }
+    }
+
+    Ok(findings)
+}
+
+/// Lists the active GuardDuty and Inspector findings of `instances`, most severe
+/// first.
+pub async fn findings_for_instances(
+    client: &RegionClient,
+    instances: &[InstanceId],
+) -> Result<Vec<Finding>, Error> {
+    if instances.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut findings = guardduty_findings(client, instances).await?;
+    findings.extend(inspector_findings(client, instances).await?);
+
+    findings.sort_by_key(|finding| Reverse(finding.severity));
+    Ok(findings)
+}
+
+/// Like [`findings_for_instances()`], for all instances matching `query`
+pub async fn findings_for_query(
+    client: &RegionClient,
+    query: &query::TagQuery,
+) -> Result<Vec<Finding>, Error> {
+    let instances = query::find_instances(client, query)
+        .await?
+        .iter()
+        .map(|instance| instance.instance_id().clone())
+        .collect::<Vec<InstanceId>>();
+
+    findings_for_instances(client, &instances).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn guardduty_severity_ranges() {
+        assert_eq!(Severity::from_guardduty(0.5_f64), Severity::Informational);
+        assert_eq!(Severity::from_guardduty(2.0_f64), Severity::Low);
+        assert_eq!(Severity::from_guardduty(5.0_f64), Severity::Medium);
+        assert_eq!(Severity::from_guardduty(8.0_f64), Severity::High);
+        assert_eq!(Severity::from_guardduty(9.5_f64), Severity::Critical);
+        assert!(Severity::Critical > Severity::Low);
+    }
+}
@@ -0,0 +1,273 @@
+//! VPC flow logs of VPCs and network interfaces
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::{pagination, tags::TagList, Error, RegionClient, RoleArn, VpcId};
+
+crate::string_newtype!(FlowLogId);
+
+impl FlowLogId {
+    pub const fn new(value: String) -> Self {
+        Self(value)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// What traffic is logged
+#[derive(Debug, Clone, Copy)]
+pub enum FlowLogResource<'a> {
+    Vpc(&'a VpcId),
+    /// A network interface ID like `eni-0123456789abcdef0`
+    NetworkInterface(&'a str),
+}
+
+impl FlowLogResource<'_> {
+    fn id(&self) -> &str {
+        match *self {
+            Self::Vpc(vpc) => vpc.as_str(),
+            Self::NetworkInterface(eni) => eni,
+        }
+    }
+
+    const fn resource_type(&self) -> aws_sdk_ec2::types::FlowLogsResourceType {
+        match *self {
+            Self::Vpc(_) => aws_sdk_ec2::types::FlowLogsResourceType::Vpc,
+            Self::NetworkInterface(_) => aws_sdk_ec2::types::FlowLogsResourceType::NetworkInterface,
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FlowLogDestination {
+    /// `role` has to allow the flow logs service to write to `log_group`
+    CloudWatchLogs { log_group: String, role: RoleArn },
+    /// An S3 bucket ARN, optionally with a prefix, e.g. `arn:aws:s3:::logs/flow/`
+    S3 { arn: String },
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct FlowLog {
+    pub id: FlowLogId,
+    /// The ID of the VPC or network interface
+    pub resource_id: String,
+    pub destination: Option<FlowLogDestination>,
+    /// Whether delivery works, e.g. `SUCCESS` or `FAILED`
+    pub delivery_status: Option<String>,
+    pub tags: TagList,
+}
+
+impl TryFrom<aws_sdk_ec2::types::FlowLog> for FlowLog {
+    type Error = Error;
+
+    fn try_from(flow_log: aws_sdk_ec2::types::FlowLog) -> Result<Self, Self::Error> {
+        let destination = match flow_log.log_destination_type {
+            Some(aws_sdk_ec2::types::LogDestinationType::S3) => flow_log
+                .log_destination
+                .map(|arn| FlowLogDestination::S3 { arn }),
+            Some(aws_sdk_ec2::types::LogDestinationType::CloudWatchLogs) => flow_log
+                .log_group_name
+                .zip(flow_log.deliver_logs_permission_arn)
+                .map(|(log_group, role)| FlowLogDestination::CloudWatchLogs {
+                    log_group,
+                    role: RoleArn(role),
+                }),
+            _ => None,
+        };
+
+        Ok(Self {
+            id: FlowLogId(
+                flow_log
+                    .flow_log_id
+                    .ok_or_else(|| Error::UnexpectedNoneValue {
+                        entity: "FlowLog.flow_log_id".to_owned(),
+                    })?,
+            ),
+            resource_id: flow_log
+                .resource_id
+                .ok_or_else(|| Error::UnexpectedNoneValue {
+                    entity: "FlowLog.resource_id".to_owned(),
+                })?,
+            destination,
+            delivery_status: flow_log.deliver_logs_status,
+            tags: flow_log.tags.unwrap_or_default().try_into()?,
+        })
+    }
+}
+
+/// Logs all accepted and rejected traffic of `resource` to `destination`.
+pub async fn enable_flow_logs(
+    client: &RegionClient,
+    resource: FlowLogResource<'_>,
+    destination: &FlowLogDestination,
+    tags: &TagList,
+) -> Result<FlowLogId, Error> {
+    let request = client
+        .main
+        .ec2
+        .create_flow_logs()
+        .resource_type(resource.resource_type())
+        .resource_ids(resource.id())
+        .traffic_type(aws_sdk_ec2::types::TrafficType::All)
+        .tag_specifications(
+            aws_sdk_ec2::types::TagSpecification::builder()
+                .resource_type(aws_sdk_ec2::types::ResourceType::VpcFlowLog)
+                .set_tags(Some(client.tags_with_defaults(tags).into()))
+                .build(),
+        );
+
+    let request = match *destination {
+        FlowLogDestination::CloudWatchLogs {
+            ref log_group,
+            ref role,
+        } => request
+            .log_destination_type(aws_sdk_ec2::types::LogDestinationType::CloudWatchLogs)
+            .log_group_name(log_group)
+            .deliver_logs_permission_arn(role.as_str()),
+        FlowLogDestination::S3 { ref arn } => request
+            .log_destination_type(aws_sdk_ec2::types::LogDestinationType::S3)
+            .log_destination(arn),
+    };
+
+    let output = request.send().await?;
+
+    // Errors for single resources are reported in the output instead of failing the call
+    if let Some(unsuccessful) = output
+        .unsuccessful
+        .and_then(|unsuccessful| unsuccessful.into_iter().next())
+    {
+        return Err(Error::FlowLogError {
+            resource: resource.id().to_owned(),
+            message: unsuccessful
+                .error
+                .and_then(|error| error.message)
+                .unwrap_or_default(),
+        });
+    }
+
+    output
+        .flow_log_ids
+        .and_then(|ids| ids.into_iter().next())
+        .map(FlowLogId)
+        .ok_or_else(|| Error::UnexpectedNoneValue {
+            entity: "CreateFlowLogsOutput.flow_log_ids".to_owned(),
+        })
+}
+
+/// The flow logs of `resource`, an empty list if there are none
+pub async fn flow_logs(
+    client: &RegionClient,
+    resource: FlowLogResource<'_>,
+) -> Result<Vec<FlowLog>, Error> {
+    pagination::list_flow_logs(
+        client,
+        vec![aws_sdk_ec2::types::Filter::builder()
+            .name("resource-id")
+            .values(resource.id())
+            .build()],
+    )
+    .await?
+    .into_iter()
+    .map(TryInto::try_into)
+    .collect()
+}
+
+/// Deletes all flow logs of `resource`. Returns the IDs of the deleted flow logs.
+pub async fn disable_flow_logs(
+    client: &RegionClient,
+    resource: FlowLogResource<'_>,
+) -> Result<Vec<FlowLogId>, Error> {
+    let ids: Vec<FlowLogId> = flow_logs(client, resource)
+        .await?
+        .into_iter()
+        .map(|flow_log| flow_log.id)
+        .collect();
+
+    if ids.is_empty() {
+        return Ok(ids);
+    }
+
+    let output = client
+        .main
+        .ec2
+        .delete_flow_logs()
+        .set_flow_log_ids(Some(ids.iter().map(|id| id.as_str().to_owned()).collect()))
+        .send()
+        .await?;
+
+    if let Some(unsuccessful) = output
+        .unsuccessful
+        .and_then(|unsuccessful| unsuccessful.into_iter().next())
+    {
+        return Err(Error::FlowLogError {
+            resource: unsuccessful
+                .resource_id
+                .unwrap_or_else(|| resource.id().to_owned()),
+            message: unsuccessful
+                .error
+                .and_then(|error| error.message)
+                .unwrap_or_default(),
+        });
+    }
+
+    Ok(ids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn destinations() {
+        let cloudwatch = FlowLog::try_from(
+            aws_sdk_ec2::types::FlowLog::builder()
+                .flow_log_id("fl-1")
+                .resource_id("vpc-1")
+                .log_destination_type(aws_sdk_ec2::types::LogDestinationType::CloudWatchLogs)
+                .log_group_name("flow-logs")
+                .deliver_logs_permission_arn("arn:aws:iam::123456789012:role/flow-logs")
+                .build(),
+        )
+        .unwrap();
+        assert_eq!(
+            cloudwatch.destination,
+            Some(FlowLogDestination::CloudWatchLogs {
+                log_group: "flow-logs".to_owned(),
+                role: RoleArn("arn:aws:iam::123456789012:role/flow-logs".to_owned()),
+            })
+        );
+
+        let s3 = FlowLog::try_from(
+            aws_sdk_ec2::types::FlowLog::builder()
+                .flow_log_id("fl-2")
+                .resource_id("eni-1")
+                .log_destination_type(aws_sdk_ec2::types::LogDestinationType::S3)
+                .log_destination("arn:aws:s3:::logs/flow/")
+                .build(),
+        )
+        .unwrap();
+        assert_eq!(
+            s3.destination,
+            Some(FlowLogDestination::S3 {
+                arn: "arn:aws:s3:::logs/flow/".to_owned(),
+            })
+        );
+    }
+
+    #[test]
+    fn missing_id() {
+        assert!(matches!(
+            FlowLog::try_from(
+                aws_sdk_ec2::types::FlowLog::builder()
+                    .resource_id("vpc-1")
+                    .build()
+            ),
+            Err(Error::UnexpectedNoneValue { ref entity }) if entity == "FlowLog.flow_log_id"
+        ));
+    }
+}
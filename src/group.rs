@@ -0,0 +1,415 @@
+//! Groups of instances that share a tag selector, with rolling operations
+//!
+//! Rolling operations process the instances of a group in batches. Before each batch,
+//! the group has to have enough healthy instances left to take the batch out of
+//! service, otherwise the operation stops with [`Error::MinHealthyViolated`].
+//!
+//! An instance is healthy if it is running, and, if the policy names an ELB target
+//! group, if its target in that group is healthy. Instances are deregistered from the
+//! target group before they are taken out of service, and registered again (or their
+//! replacements are registered) afterwards.
+
+#[cfg(feature = "ssm")]
+use std::time::Duration;
+use std::{future::Future, num::NonZeroUsize};
+
+use super::{
+    query::{self, TagQuery},
+    waiter, Error, Instance, InstanceId, InstanceStateName, Protection, RegionClient,
+};
+
+#[derive(Debug, Clone, Copy)]
+pub struct RollingPolicy<'a> {
+    /// How many instances are processed at once
+    pub batch_size: NonZeroUsize,
+    /// How many instances have to stay healthy while a batch is processed
+    pub min_healthy: usize,
+    /// The ARN of an ELB target group the instances are registered in
+    pub target_group: Option<&'a str>,
+}
+
+#[derive(Debug, Clone)]
+pub struct InstanceGroup {
+    pub selector: TagQuery,
+}
+
+fn is_running(instance: &Instance) -> bool {
+    *instance.state().inner() == aws_sdk_ec2::types::InstanceStateName::Running
+}
+
+fn target(instance: &InstanceId) -> aws_sdk_elasticloadbalancingv2::types::TargetDescription {
+    aws_sdk_elasticloadbalancingv2::types::TargetDescription::builder()
+        .id(instance.as_str())
+        .build()
+        .expect("builder has all required fields set")
+}
+
+async fn healthy_targets(
+    client: &RegionClient,
+    target_group: &str,
+) -> Result<Vec<InstanceId>, Error> {
+    Ok(client
+        .main
+        .elbv2
+        .describe_target_health()
+        .target_group_arn(target_group)
+        .send()
+        .await?
+        .target_health_descriptions
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|description| {
+            description
+                .target_health
+                .as_ref()
+                .and_then(|health| health.state.as_ref())
+                == Some(&aws_sdk_elasticloadbalancingv2::types::TargetHealthStateEnum::Healthy)
+        })
+        .filter_map(|description| description.target.map(|target| InstanceId(target.id)))
+        .collect())
+}
+
+async fn deregister(
+    client: &RegionClient,
+    target_group: &str,
+    instances: &[Instance],
+) -> Result<(), Error> {
+    let _output = client
+        .main
+        .elbv2
+        .deregister_targets()
+        .target_group_arn(target_group)
+        .set_targets(Some(
+            instances
+                .iter()
+                .map(|instance| target(instance.instance_id()))
+                .collect(),
+        ))
+        .send()
+        .await?;
+
+    Ok(())
+}
+
+/// Registers `instances` in the target group and waits until all of them are healthy
+async fn register_and_wait(
+    client: &RegionClient,
+    target_group: &str,
+    instances: &[Instance],
+) -> Result<(), Error> {
+    let _output = client
+        .main
+        .elbv2
+        .register_targets()
+        .target_group_arn(target_group)
+        .set_targets(Some(
+            instances
+                .iter()
+                .map(|instance| target(instance.instance_id()))
+                .collect(),
+        ))
+        .send()
+        .await?;
+
+    let max_wait = client.wait_config.target_health;
+
+    waiter::poll(client.waiter_profile, max_wait, || async move {
+        let healthy = healthy_targets(client, target_group).await?;
+        Ok(instances
+            .iter()
+            .all(|instance| healthy.contains(instance.instance_id()))
+            .then_some(()))
+    })
+    .await?
+    .ok_or_else(|| Error::TargetHealthExceededMaxWait {
+        max_wait,
+        target_group: target_group.to_owned(),
+    })
+}
+
+/// Fails if taking `batch` instances out of service leaves fewer than `min_healthy`
+/// of `healthy`. Instances of the batch that are not healthy anyway are counted as
+/// healthy here, to stay on the safe side.
+const fn check_min_healthy(healthy: usize, batch: usize, min_healthy: usize) -> Result<(), Error> {
+    if healthy.saturating_sub(batch) < min_healthy {
+        Err(Error::MinHealthyViolated {
+            healthy,
+            batch,
+            min_healthy,
+        })
+    } else {
+        Ok(())
+    }
+}
+
+/// Fails with [`Error::ResourceProtected`] if any of `instances` carries the
+/// protection tag of `client`, so that a rolling operation does not stop halfway
+/// through at a protected instance
+fn check_protection(
+    client: &RegionClient,
+    instances: &[Instance],
+    protection: Protection,
+) -> Result<(), Error> {
+    for instance in instances {
+        client.check_protection(instance.instance_id(), instance.tags(), protection)?;
+    }
+    Ok(())
+}
+
+/// Runs `process` on `instances` in batches, checking the healthy count of `group`
+/// before each batch. With `surge`, the replacements of a batch are healthy before the
+/// batch is taken out of service, so the batch does not count against `min_healthy`.
+async fn in_batches<T, F, Fut>(
+    group: &InstanceGroup,
+    client: &RegionClient,
+    policy: &RollingPolicy<'_>,
+    instances: &[Instance],
+    surge: bool,
+    mut process: F,
+) -> Result<Vec<T>, Error>
+where
+    F: FnMut(Vec<Instance>) -> Fut,
+    Fut: Future<Output = Result<T, Error>>,
+{
+    let mut results = Vec::new();
+
+    for batch in instances.chunks(policy.batch_size.get()) {
+        check_min_healthy(
+            group.healthy_count(client, policy).await?,
+            if surge { 0 } else { batch.len() },
+            policy.min_healthy,
+        )?;
+
+        results.push(process(batch.to_vec()).await?);
+    }
+
+    Ok(results)
+}
+
+impl InstanceGroup {
+    pub const fn new(selector: TagQuery) -> Self {
+        Self { selector }
+    }
+
+    /// All instances matching the selector that are not terminated
+    pub async fn instances(&self, client: &RegionClient) -> Result<Vec<Instance>, Error> {
+        Ok(query::find_instances(client, &self.selector)
+            .await?
+            .into_iter()
+            .filter(|instance| {
+                !matches!(
+                    *instance.state().inner(),
+                    aws_sdk_ec2::types::InstanceStateName::ShuttingDown
+                        | aws_sdk_ec2::types::InstanceStateName::Terminated
+                )
+            })
+            .collect())
+    }
+
+    /// The number of healthy instances, see the [module documentation](self)
+    pub async fn healthy_count(
+        &self,
+        client: &RegionClient,
+        policy: &RollingPolicy<'_>,
+    ) -> Result<usize, Error> {
+        let running = self.instances(client).await?.into_iter().filter(is_running);
+
+        Ok(match policy.target_group {
+            Some(target_group) => {
+                let healthy = healthy_targets(client, target_group).await?;
+                running
+                    .filter(|instance| healthy.contains(instance.instance_id()))
+                    .count()
+            }
+            None => running.count(),
+        })
+    }
+
+    /// Stops all running instances of the group, batch by batch.
+    ///
+    /// Fails with [`Error::ResourceProtected`] before stopping anything if any of the
+    /// instances carries the protection tag of `client`, unless `protection` is
+    /// [`Protection::Override`].
+    pub async fn rolling_stop(
+        &self,
+        client: &RegionClient,
+        policy: &RollingPolicy<'_>,
+        protection: Protection,
+    ) -> Result<(), Error> {
+        let instances: Vec<Instance> = self
+            .instances(client)
+            .await?
+            .into_iter()
+            .filter(is_running)
+            .collect();
+        check_protection(client, &instances, protection)?;
+
+        in_batches(
+            self,
+            client,
+            policy,
+            &instances,
+            false,
+            |batch| async move {
+                if let Some(target_group) = policy.target_group {
+                    deregister(client, target_group, &batch).await?;
+                }
+                for instance in &batch {
+                    instance.stop(client, protection).await?;
+                }
+                for instance in &batch {
+                    instance.wait_for_stop(client, None).await?;
+                }
+                Ok(())
+            },
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Replaces all instances of the group, batch by batch. For each batch, `launch` is
+    /// called once per instance, the replacements are waited for (and registered in
+    /// the target group), and only then the old instances are terminated.
+    ///
+    /// The replacements have to match the selector of the group themselves, otherwise
+    /// they do not count as healthy for the following batches. As the replacements are
+    /// up before a batch is terminated, `min_healthy` may be the size of the group.
+    ///
+    /// Fails with [`Error::ResourceProtected`] before launching anything if any of the
+    /// instances carries the protection tag of `client`, unless `protection` is
+    /// [`Protection::Override`].
+    pub async fn rolling_replace<F, Fut>(
+        &self,
+        client: &RegionClient,
+        policy: &RollingPolicy<'_>,
+        protection: Protection,
+        launch: F,
+    ) -> Result<Vec<Instance>, Error>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<Instance, Error>>,
+    {
+        let instances = self.instances(client).await?;
+        check_protection(client, &instances, protection)?;
+        let launch = &launch;
+
+        let replacements = in_batches(self, client, policy, &instances, true, |batch| async move {
+            let mut launched = Vec::with_capacity(batch.len());
+            for _instance in &batch {
+                launched.push(launch().await?);
+            }
+
+            let running = InstanceStateName::new(aws_sdk_ec2::types::InstanceStateName::Running);
+            let mut ready = Vec::with_capacity(launched.len());
+            for instance in &launched {
+                ready.push(
+                    instance
+                        .wait_for_state(client, running.clone(), None)
+                        .await?,
+                );
+            }
+
+            if let Some(target_group) = policy.target_group {
+                register_and_wait(client, target_group, &ready).await?;
+                deregister(client, target_group, &batch).await?;
+            }
+
+            for instance in &batch {
+                instance.terminate(client, protection).await?;
+            }
+
+            Ok(ready)
+        })
+        .await?;
+
+        Ok(replacements.into_iter().flatten().collect())
+    }
+
+    /// Runs `commands` as a shell script on all running instances of the group, batch
+    /// by batch. Instances are out of the target group while the script runs. Fails
+    /// with [`Error::CommandFailed`] as soon as the script fails on an instance.
+    #[cfg(feature = "ssm")]
+    pub async fn rolling_command(
+        &self,
+        client: &RegionClient,
+        policy: &RollingPolicy<'_>,
+        commands: &[String],
+        max_wait: Option<Duration>,
+    ) -> Result<(), Error> {
+        let instances: Vec<Instance> = self
+            .instances(client)
+            .await?
+            .into_iter()
+            .filter(is_running)
+            .collect();
+
+        in_batches(
+            self,
+            client,
+            policy,
+            &instances,
+            false,
+            |batch| async move {
+                if let Some(target_group) = policy.target_group {
+                    deregister(client, target_group, &batch).await?;
+                }
+
+                let ids: Vec<InstanceId> = batch
+                    .iter()
+                    .map(|instance| instance.instance_id().clone())
+                    .collect();
+                let command = super::ssm::run_shell_script(client, &ids, commands.to_vec()).await?;
+
+                for id in &ids {
+                    let result =
+                        super::ssm::wait_for_command(client, &command, id, max_wait).await?;
+                    if result.status != super::ssm::CommandStatus::Success {
+                        return Err(Error::CommandFailed {
+                            instance: id.clone(),
+                            stderr: result.stderr,
+                        });
+                    }
+                }
+
+                if let Some(target_group) = policy.target_group {
+                    register_and_wait(client, target_group, &batch).await?;
+                }
+
+                Ok(())
+            },
+        )
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn min_healthy_per_batch() {
+        check_min_healthy(5, 2, 3).unwrap();
+        assert!(matches!(
+            check_min_healthy(5, 3, 3),
+            Err(Error::MinHealthyViolated {
+                healthy: 5,
+                batch: 3,
+                min_healthy: 3
+            })
+        ));
+        check_min_healthy(1, 2, 0).unwrap();
+        assert!(matches!(
+            check_min_healthy(0, 1, 1),
+            Err(Error::MinHealthyViolated {
+                healthy: 0,
+                batch: 1,
+                min_healthy: 1
+            })
+        ));
+        // Surging batches do not count against the healthy instances
+        check_min_healthy(3, 0, 3).unwrap();
+    }
+}
@@ -0,0 +1,181 @@
+//! Access to the instance metadata service (IMDS), for code running on an instance
+//!
+//! All requests use IMDSv2 session tokens, which are fetched and refreshed by the
+//! underlying client. Instance tags are only available if metadata tags are enabled
+//! for the instance, which [`start_ec2_instance()`](crate::start_ec2_instance) does.
+
+use serde::Deserialize;
+
+use super::{
+    tags::{RawTag, TagList},
+    Error, InstanceId, Timestamp,
+};
+
+const INSTANCE_ID_PATH: &str = "/latest/meta-data/instance-id";
+const REGION_PATH: &str = "/latest/meta-data/placement/region";
+const TAGS_PATH: &str = "/latest/meta-data/tags/instance";
+const CREDENTIALS_PATH: &str = "/latest/meta-data/iam/security-credentials";
+const SPOT_INSTANCE_ACTION_PATH: &str = "/latest/meta-data/spot/instance-action";
+
+const HTTP_NOT_FOUND: u16 = 404;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpotInterruptionAction {
+    Terminate,
+    Stop,
+    Hibernate,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpotInterruption {
+    pub action: SpotInterruptionAction,
+    /// When the action will be taken, roughly two minutes after the notice
+    pub time: Timestamp,
+}
+
+#[derive(Deserialize)]
+struct RawSpotInterruption {
+    action: SpotInterruptionAction,
+    time: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct RawCredentials {
+    expiration: String,
+}
+
+fn parse_json<'a, T: Deserialize<'a>>(path: &str, value: &'a str) -> Result<T, Error> {
+    serde_json::from_str(value).map_err(|e| Error::ImdsError {
+        path: path.to_owned(),
+        message: e.to_string(),
+    })
+}
+
+fn parse_timestamp(value: String) -> Result<Timestamp, Error> {
+    Timestamp::parse(&value).map_err(|e| Error::InvalidTimestampError {
+        value,
+        message: e.to_string(),
+    })
+}
+
+/// The service lists one entry per line
+fn lines(value: &str) -> impl Iterator<Item = &str> {
+    value.lines().map(str::trim).filter(|line| !line.is_empty())
+}
+
+#[derive(Debug, Clone)]
+pub struct ImdsClient {
+    client: aws_config::imds::Client,
+}
+
+impl Default for ImdsClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ImdsClient {
+    pub fn new() -> Self {
+        Self {
+            client: aws_config::imds::Client::builder().build(),
+        }
+    }
+
+    /// `Ok(None)` if the path does not exist
+    async fn get_optional(&self, path: &str) -> Result<Option<String>, Error> {
+        match self.client.get(path).await {
+            Ok(value) => Ok(Some(value.into())),
+            Err(aws_config::imds::client::error::ImdsError::ErrorResponse(ref response))
+                if response.response().status().as_u16() == HTTP_NOT_FOUND =>
+            {
+                Ok(None)
+            }
+            Err(e) => Err(Error::ImdsError {
+                path: path.to_owned(),
+                message: e.to_string(),
+            }),
+        }
+    }
+
+    async fn get(&self, path: &str) -> Result<String, Error> {
+        self.get_optional(path)
+            .await?
+            .ok_or_else(|| Error::ImdsError {
+                path: path.to_owned(),
+                message: "not found".to_owned(),
+            })
+    }
+
+    pub async fn instance_id(&self) -> Result<InstanceId, Error> {
+        Ok(InstanceId(self.get(INSTANCE_ID_PATH).await?))
+    }
+
+    /// The region name, e.g. `eu-central-1`. This may be a region that
+    /// [`Region`](crate::Region) does not know about.
+    pub async fn region(&self) -> Result<String, Error> {
+        self.get(REGION_PATH).await
+    }
+
+    /// Fails if metadata tags are not enabled for the instance
+    pub async fn tags(&self) -> Result<TagList, Error> {
+        let keys = self.get(TAGS_PATH).await?;
+
+        let mut tags = TagList::new();
+        for key in lines(&keys) {
+            let value = self.get(&format!("{TAGS_PATH}/{key}")).await?;
+            tags.push(RawTag::new(key.to_owned(), value));
+        }
+
+        Ok(tags)
+    }
+
+    /// When the credentials of the instance profile expire. `Ok(None)` if the instance
+    /// has no instance profile.
+    pub async fn credentials_expiration(&self) -> Result<Option<Timestamp>, Error> {
+        let Some(roles) = self.get_optional(&format!("{CREDENTIALS_PATH}/")).await? else {
+            return Ok(None);
+        };
+        let Some(role) = lines(&roles).next() else {
+            return Ok(None);
+        };
+
+        let path = format!("{CREDENTIALS_PATH}/{role}");
+        let credentials: RawCredentials = parse_json(&path, &self.get(&path).await?)?;
+
+        Ok(Some(parse_timestamp(credentials.expiration)?))
+    }
+
+    /// The pending interruption of the spot instance, if any. Always `Ok(None)` for
+    /// on-demand instances.
+    pub async fn spot_interruption(&self) -> Result<Option<SpotInterruption>, Error> {
+        let Some(notice) = self.get_optional(SPOT_INSTANCE_ACTION_PATH).await? else {
+            return Ok(None);
+        };
+
+        let notice: RawSpotInterruption = parse_json(SPOT_INSTANCE_ACTION_PATH, &notice)?;
+
+        Ok(Some(SpotInterruption {
+            action: notice.action,
+            time: parse_timestamp(notice.time)?,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spot_interruption_notice() {
+        let notice: RawSpotInterruption = parse_json(
+            SPOT_INSTANCE_ACTION_PATH,
+            r#"{"action": "terminate", "time": "2017-09-18T08:22:00Z"}"#,
+        )
+        .unwrap();
+
+        assert_eq!(notice.action, SpotInterruptionAction::Terminate);
+        assert_eq!(notice.time, "2017-09-18T08:22:00Z");
+    }
+}
@@ -0,0 +1,324 @@
+//! Auditing and rotating the SSH key pairs of instances
+//!
+//! EC2 only knows about the key pair an instance was launched with. Replacing it
+//! means rewriting `authorized_keys` on the instance itself, which is done via SSM
+//! Run Command with the `ssm` feature.
+//!
+//! In the other direction, the host keys of an instance can be fetched to prime
+//! `known_hosts`, so connecting does not require trusting the key on first use.
+
+#[cfg(feature = "ssm")]
+use std::time::Duration;
+
+#[cfg(feature = "ssm")]
+use super::ssm::{self, CommandResult};
+use super::{pagination, Error, Instance, InstanceId, InstanceKeypairName, RegionClient};
+
+/// Heredoc delimiter for the new `authorized_keys`, cannot appear in a public key
+#[cfg(feature = "ssm")]
+const KEYS_DELIMITER: &str = "AWS_LIB_AUTHORIZED_KEYS";
+
+/// cloud-init prints the host keys between these lines to the console
+const CONSOLE_HOST_KEYS_BEGIN: &str = "-----BEGIN SSH HOST KEY KEYS-----";
+const CONSOLE_HOST_KEYS_END: &str = "-----END SSH HOST KEY KEYS-----";
+
+/// A public SSH host key, like `ssh-ed25519 AAAA...`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostKey {
+    key_type: String,
+    key: String,
+}
+
+impl HostKey {
+    /// Parses a public key line, ignoring the comment
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut parts = line.split_whitespace();
+        match (parts.next(), parts.next()) {
+            (Some(key_type), Some(key)) => Some(Self {
+                key_type: key_type.to_owned(),
+                key: key.to_owned(),
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn key_type(&self) -> &str {
+        &self.key_type
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+}
+
+fn parse_console_host_keys(output: &str) -> Vec<HostKey> {
+    output
+        .lines()
+        .skip_while(|line| !line.contains(CONSOLE_HOST_KEYS_BEGIN))
+        .skip(1)
+        .take_while(|line| !line.contains(CONSOLE_HOST_KEYS_END))
+        .filter_map(HostKey::parse)
+        .collect()
+}
+
+/// Reads the host keys of `instance` from its console output.
+///
+/// This requires the image to print its host keys on boot, as cloud-init does. The
+/// console output is only available a few minutes after boot, until then the result
+/// is empty.
+pub async fn host_keys_from_console(
+    client: &RegionClient,
+    instance: &InstanceId,
+) -> Result<Vec<HostKey>, Error> {
+    let Some(output) = client
+        .main
+        .ec2
+        .get_console_output()
+        .instance_id(instance.as_str())
+        .send()
+        .await?
+        .output
+    else {
+        return Ok(vec![]);
+    };
+
+    let output =
+        aws_smithy_types::base64::decode(output).map_err(|e| Error::InvalidResponseError {
+            message: format!("invalid console output: {e}"),
+        })?;
+
+    Ok(parse_console_host_keys(&String::from_utf8_lossy(&output)))
+}
+
+/// Reads the host keys of `instance` via SSM, waiting for at most `max_wait` (see
+/// [`ssm::wait_for_command()`]).
+#[cfg(feature = "ssm")]
+pub async fn host_keys_via_ssm(
+    client: &RegionClient,
+    instance: &InstanceId,
+    max_wait: Option<Duration>,
+) -> Result<Vec<HostKey>, Error> {
+    let command = ssm::run_shell_script(
+        client,
+        &[instance.clone()],
+        vec!["cat /etc/ssh/ssh_host_*_key.pub".to_owned()],
+    )
+    .await?;
+
+    let result = ssm::wait_for_command(client, &command, instance, max_wait).await?;
+    if result.status != ssm::CommandStatus::Success {
+        return Err(Error::InvalidResponseError {
+            message: format!("reading host keys failed: {}", result.stderr),
+        });
+    }
+
+    Ok(result.stdout.lines().filter_map(HostKey::parse).collect())
+}
+
+fn known_hosts_lines(hosts: &[String], keys: &[HostKey]) -> Vec<String> {
+    let hosts = hosts.join(",");
+    keys.iter()
+        .map(|key| format!("{hosts} {} {}", key.key_type, key.key))
+        .collect()
+}
+
+/// `known_hosts` lines for `keys`, for the public DNS name and the public and private
+/// IP addresses of `instance`.
+pub fn known_hosts_entries(instance: &Instance, keys: &[HostKey]) -> Vec<String> {
+    let hosts: Vec<String> = instance
+        .public_dns_name()
+        .map(ToOwned::to_owned)
+        .into_iter()
+        .chain(instance.public_ip_address().map(ToString::to_string))
+        .chain(instance.private_ip_address().map(ToString::to_string))
+        .collect();
+
+    if hosts.is_empty() {
+        return vec![];
+    }
+
+    known_hosts_lines(&hosts, keys)
+}
+
+#[derive(Debug, Clone)]
+pub struct KeyPairUsage {
+    pub instance: InstanceId,
+    pub key_name: Option<InstanceKeypairName>,
+}
+
+async fn running_instances(client: &RegionClient) -> Result<Vec<Instance>, Error> {
+    pagination::list_instances(
+        client,
+        vec![aws_sdk_ec2::types::Filter::builder()
+            .name("instance-state-name")
+            .values("running")
+            .build()],
+    )
+    .await?
+    .into_iter()
+    .map(Instance::try_from_aws)
+    .collect()
+}
+
+/// Lists the key pair of every running instance.
+pub async fn key_pair_usage(client: &RegionClient) -> Result<Vec<KeyPairUsage>, Error> {
+    Ok(running_instances(client)
+        .await?
+        .into_iter()
+        .map(|instance| KeyPairUsage {
+            key_name: instance.key_name,
+            instance: instance.instance_id,
+        })
+        .collect())
+}
+
+/// Finds all running instances that were launched with one of the `deprecated` key
+/// pairs.
+pub async fn find_deprecated_key_usage(
+    client: &RegionClient,
+    deprecated: &[InstanceKeypairName],
+) -> Result<Vec<KeyPairUsage>, Error> {
+    Ok(key_pair_usage(client)
+        .await?
+        .into_iter()
+        .filter(|usage| {
+            usage
+                .key_name
+                .as_ref()
+                .is_some_and(|key_name| deprecated.contains(key_name))
+        })
+        .collect())
+}
+
+/// User names end up as arguments of `getent`, `install` and `chown`, so a leading `-`
+/// would be taken as an option
+#[cfg(feature = "ssm")]
+fn validate_user(user: &str) -> Result<(), Error> {
+    if !user.is_empty()
+        && !user.starts_with('-')
+        && user
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        Ok(())
+    } else {
+        Err(Error::InvalidArgument {
+            message: format!("invalid user name \"{user}\""),
+        })
+    }
+}
+
+#[cfg(feature = "ssm")]
+fn validate_public_key(key: &str) -> Result<(), Error> {
+    if key.is_empty() || key.contains('\n') || key.contains(KEYS_DELIMITER) {
+        Err(Error::InvalidArgument {
+            message: format!("invalid public key \"{key}\""),
+        })
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "ssm")]
+fn authorized_keys_script(user: &str, public_keys: &[String]) -> Vec<String> {
+    let mut script = vec![
+        "set -eu".to_owned(),
+        format!("home=\"$(getent passwd {user} | cut -d: -f6)\""),
+        format!("install -d -m 700 -o {user} \"${{home}}/.ssh\""),
+        format!("cat > \"${{home}}/.ssh/authorized_keys.new\" <<'{KEYS_DELIMITER}'"),
+    ];
+    script.extend(public_keys.iter().cloned());
+    script.extend([
+        KEYS_DELIMITER.to_owned(),
+        "chmod 600 \"${home}/.ssh/authorized_keys.new\"".to_owned(),
+        format!("chown {user} \"${{home}}/.ssh/authorized_keys.new\""),
+        "mv \"${home}/.ssh/authorized_keys.new\" \"${home}/.ssh/authorized_keys\"".to_owned(),
+    ]);
+    script
+}
+
+/// Replaces the `authorized_keys` of `user` on all `instances` with `public_keys` and
+/// waits for at most `max_wait` on each instance (see [`ssm::wait_for_command()`]).
+///
+/// The file is replaced atomically, so a failure leaves the old keys in place.
+#[cfg(feature = "ssm")]
+pub async fn push_authorized_keys(
+    client: &RegionClient,
+    instances: &[InstanceId],
+    user: &str,
+    public_keys: &[String],
+    max_wait: Option<Duration>,
+) -> Result<Vec<(InstanceId, CommandResult)>, Error> {
+    validate_user(user)?;
+    for key in public_keys {
+        validate_public_key(key)?;
+    }
+
+    let command =
+        ssm::run_shell_script(client, instances, authorized_keys_script(user, public_keys)).await?;
+
+    let mut results = vec![];
+    for instance in instances {
+        results.push((
+            instance.clone(),
+            ssm::wait_for_command(client, &command, instance, max_wait).await?,
+        ));
+    }
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_host_keys_from_console() {
+        let output = "\
+[   10.0] cloud-init[1]: Cloud-init finished
+-----BEGIN SSH HOST KEY KEYS-----
+ecdsa-sha2-nistp256 AAAAE2VjZHNh root@ip-10-0-0-1
+ssh-ed25519 AAAAC3NzaC1l root@ip-10-0-0-1
+-----END SSH HOST KEY KEYS-----
+[   11.0] login prompt";
+
+        let keys = parse_console_host_keys(output);
+        assert_eq!(
+            known_hosts_lines(&["10.0.0.1".to_owned(), "host".to_owned()], &keys),
+            vec![
+                "10.0.0.1,host ecdsa-sha2-nistp256 AAAAE2VjZHNh".to_owned(),
+                "10.0.0.1,host ssh-ed25519 AAAAC3NzaC1l".to_owned(),
+            ]
+        );
+
+        assert!(parse_console_host_keys("no keys yet").is_empty());
+    }
+
+    #[cfg(feature = "ssm")]
+    #[test]
+    fn rejects_invalid_input() {
+        validate_user("ec2-user").unwrap();
+        for (user, reason) in [
+            ("root; rm -rf /", "shell"),
+            ("", "empty user"),
+            ("-x", "option"),
+        ] {
+            assert!(
+                matches!(
+                    validate_user(user),
+                    Err(Error::InvalidArgument { ref message }) if message.contains("user name")
+                ),
+                "{reason} is accepted"
+            );
+        }
+
+        validate_public_key("ssh-ed25519 AAAA user@host").unwrap();
+        assert!(
+            matches!(
+                validate_public_key(&format!("ssh-ed25519 AAAA\n{KEYS_DELIMITER}")),
+                Err(Error::InvalidArgument { ref message }) if message.contains("public key")
+            ),
+            "multi-line key is accepted"
+        );
+    }
+}
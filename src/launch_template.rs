@@ -0,0 +1,163 @@
+//! Launching instances from launch templates that are managed elsewhere, e.g. in
+//! Terraform
+//!
+//! The template defines everything about the instance. Only the subnet, the tags and
+//! the user data can be overridden per launch.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::{
+    pagination, query,
+    tags::{RawTag, TagList},
+    Error, Instance, RegionClient, SubnetId,
+};
+
+/// The template version that is used if the launch does not name one
+const DEFAULT_VERSION: &str = "$Default";
+
+crate::string_newtype!(LaunchTemplateId);
+
+impl LaunchTemplateId {
+    pub const fn new(value: String) -> Self {
+        Self(value)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct LaunchTemplate {
+    pub id: LaunchTemplateId,
+    pub name: String,
+    pub default_version: Option<i64>,
+    pub latest_version: Option<i64>,
+    pub tags: TagList,
+}
+
+impl TryFrom<aws_sdk_ec2::types::LaunchTemplate> for LaunchTemplate {
+    type Error = Error;
+
+    fn try_from(template: aws_sdk_ec2::types::LaunchTemplate) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: LaunchTemplateId(template.launch_template_id.ok_or_else(|| {
+                Error::UnexpectedNoneValue {
+                    entity: "LaunchTemplate.launch_template_id".to_owned(),
+                }
+            })?),
+            name: template
+                .launch_template_name
+                .ok_or_else(|| Error::UnexpectedNoneValue {
+                    entity: "LaunchTemplate.launch_template_name".to_owned(),
+                })?,
+            default_version: template.default_version_number,
+            latest_version: template.latest_version_number,
+            tags: template.tags.unwrap_or_default().try_into()?,
+        })
+    }
+}
+
+async fn list_launch_templates(
+    client: &RegionClient,
+    filters: Vec<aws_sdk_ec2::types::Filter>,
+) -> Result<Vec<LaunchTemplate>, Error> {
+    let ec2 = &client.main.ec2;
+    let filters = &filters;
+
+    pagination::paginate(|token| async move {
+        let output = ec2
+            .describe_launch_templates()
+            .set_filters(Some(filters.clone()))
+            .set_next_token(token)
+            .send()
+            .await?;
+        Ok(pagination::Page {
+            items: output.launch_templates.unwrap_or_default(),
+            next_token: output.next_token,
+        })
+    })
+    .await?
+    .into_iter()
+    .map(TryInto::try_into)
+    .collect()
+}
+
+impl LaunchTemplate {
+    pub async fn find_by_name(client: &RegionClient, name: &str) -> Result<Option<Self>, Error> {
+        query::find_unique(
+            "launch template",
+            list_launch_templates(
+                client,
+                vec![aws_sdk_ec2::types::Filter::builder()
+                    .name("launch-template-name")
+                    .values(name)
+                    .build()],
+            )
+            .await?,
+        )
+    }
+
+    /// Fails with [`Error::MultipleMatches`] if more than one template carries `tag`
+    pub async fn find_by_tag(client: &RegionClient, tag: &RawTag) -> Result<Option<Self>, Error> {
+        query::find_unique(
+            "launch template",
+            list_launch_templates(client, TagList::from_vec(vec![tag.clone()]).into()).await?,
+        )
+    }
+}
+
+/// What to change compared to the launch template. `None` keeps the value of the
+/// template.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TemplateOverrides<'a> {
+    /// The template version, e.g. `3` or `$Latest`. By default, the default version of
+    /// the template is used.
+    pub version: Option<&'a str>,
+    pub subnet_id: Option<&'a SubnetId>,
+    /// The default tags of the client are added in any case
+    pub tags: Option<&'a TagList>,
+    pub user_data: Option<&'a str>,
+}
+
+/// Launches a single instance from `template`
+pub async fn start_ec2_instance_from_template(
+    client: &RegionClient,
+    template: &LaunchTemplate,
+    overrides: TemplateOverrides<'_>,
+) -> Result<Instance, Error> {
+    let tags = client.tags_with_defaults(overrides.tags.unwrap_or(&TagList::new()));
+
+    Instance::try_from_aws(
+        client
+            .main
+            .ec2
+            .run_instances()
+            .launch_template(
+                aws_sdk_ec2::types::LaunchTemplateSpecification::builder()
+                    .launch_template_id(template.id.as_str())
+                    .version(overrides.version.unwrap_or(DEFAULT_VERSION))
+                    .build(),
+            )
+            .min_count(1)
+            .max_count(1)
+            .set_subnet_id(overrides.subnet_id.map(|subnet| subnet.as_str().to_owned()))
+            .set_user_data(overrides.user_data.map(ToOwned::to_owned))
+            .tag_specifications(
+                aws_sdk_ec2::types::TagSpecification::builder()
+                    .resource_type(aws_sdk_ec2::types::ResourceType::Instance)
+                    .set_tags(Some(tags.into()))
+                    .build(),
+            )
+            .send()
+            .await?
+            .instances
+            .ok_or(Error::UnexpectedNoneValue {
+                entity: "RunInstancesOutput.instances".to_owned(),
+            })?
+            .pop()
+            .ok_or(Error::RunInstancesEmptyResponse)?,
+    )
+}
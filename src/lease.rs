@@ -0,0 +1,314 @@
+//! Cooperative leases on EC2 resources, stored in tags
+//!
+//! A lease marks a resource as being worked on by a certain holder until an expiry
+//! timestamp. Automation runners acquire a lease before operating on a resource and
+//! refuse to touch resources leased by someone else.
+//!
+//! EC2 has no conditional tag writes, so leases are best effort, not a lock. A lease
+//! is acquired by checking the current lease tags, writing the new ones and reading
+//! them back. This catches most races, the holder that gets overwritten fails with
+//! [`Error::LeaseLost`]. But if one holder reads back its lease before the other one
+//! writes, both believe to hold the lease. Use leases to keep cooperating runners
+//! from stepping on each other, not where two concurrent holders would do damage.
+
+use std::time::Duration;
+
+use super::{
+    tags::{RawTag, StaticTagKey, TagList, Tags},
+    Ec2ResourceId, Error, RegionClient, Timestamp,
+};
+
+pub const LEASE_HOLDER_TAG_KEY: StaticTagKey = StaticTagKey::new("lease-holder");
+pub const LEASE_EXPIRES_TAG_KEY: StaticTagKey = StaticTagKey::new("lease-expires");
+
+crate::string_newtype!(LeaseHolder);
+
+impl LeaseHolder {
+    pub const fn new(value: String) -> Self {
+        Self(value)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+#[Tags(default)]
+struct LeaseTags {
+    #[tag(key = LEASE_HOLDER_TAG_KEY)]
+    holder: Option<LeaseHolder>,
+    #[tag(key = LEASE_EXPIRES_TAG_KEY)]
+    expires: Option<Timestamp>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Lease {
+    resource: String,
+    holder: LeaseHolder,
+    expires: Timestamp,
+}
+
+impl Lease {
+    pub fn resource(&self) -> &str {
+        &self.resource
+    }
+
+    pub const fn holder(&self) -> &LeaseHolder {
+        &self.holder
+    }
+
+    pub const fn expires(&self) -> Timestamp {
+        self.expires
+    }
+}
+
+fn expiry(now: Timestamp, ttl: Duration) -> Result<Timestamp, Error> {
+    let ttl = chrono::TimeDelta::from_std(ttl).map_err(|e| Error::InvalidTimestampError {
+        value: format!("{} seconds", ttl.as_secs()),
+        message: e.to_string(),
+    })?;
+
+    now.inner()
+        .checked_add_signed(ttl)
+        .map(Timestamp::new)
+        .ok_or_else(|| Error::InvalidTimestampError {
+            value: ttl.to_string(),
+            message: "lease expiry out of range".to_owned(),
+        })
+}
+
+async fn read_lease(client: &RegionClient, resource: &str) -> Result<LeaseTags, Error> {
+    let tags = client
+        .main
+        .ec2
+        .describe_tags()
+        .filters(
+            aws_sdk_ec2::types::Filter::builder()
+                .name("resource-id")
+                .values(resource)
+                .build(),
+        )
+        .filters(
+            aws_sdk_ec2::types::Filter::builder()
+                .name("key")
+                .values(LEASE_HOLDER_TAG_KEY.as_str())
+                .values(LEASE_EXPIRES_TAG_KEY.as_str())
+                .build(),
+        )
+        .send()
+        .await?
+        .tags
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|tag| match (tag.key, tag.value) {
+            (Some(key), Some(value)) => Some(RawTag::new(key, value)),
+            _ => None,
+        })
+        .collect();
+
+    Ok(LeaseTags::from_tags(TagList::from_vec(tags))?)
+}
+
+async fn write_lease(client: &RegionClient, lease: &Lease) -> Result<(), Error> {
+    let tags = LeaseTags::default()
+        .with_holder(lease.holder.clone())
+        .with_expires(lease.expires);
+
+    let _output = client
+        .main
+        .ec2
+        .create_tags()
+        .resources(&lease.resource)
+        .set_tags(Some(tags.into_tags().into()))
+        .send()
+        .await?;
+
+    Ok(())
+}
+
+/// Fails with [`Error::LeaseLost`] unless `current` names the holder of `lease`
+fn check_held(current: &LeaseTags, lease: &Lease) -> Result<(), Error> {
+    if current.holder.as_ref() == Some(&lease.holder) {
+        Ok(())
+    } else {
+        Err(Error::LeaseLost {
+            resource: lease.resource.clone(),
+        })
+    }
+}
+
+async fn verify_lease(client: &RegionClient, lease: &Lease) -> Result<(), Error> {
+    check_held(&read_lease(client, &lease.resource).await?, lease)
+}
+
+/// Fails with [`Error::LeaseHeld`] if `current` is a lease of another holder that has
+/// not expired at `now`
+fn check_available(
+    current: LeaseTags,
+    resource: &str,
+    holder: &LeaseHolder,
+    now: Timestamp,
+) -> Result<(), Error> {
+    if let (Some(current_holder), Some(expires)) = (current.holder, current.expires) {
+        if current_holder != *holder && expires > now {
+            return Err(Error::LeaseHeld {
+                resource: resource.to_owned(),
+                holder: current_holder.0,
+                expires,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Acquires a lease on `resource` for `holder` that is valid for `ttl`.
+///
+/// Fails with [`Error::LeaseHeld`] if another holder has a lease that has not expired
+/// yet. A holder may re-acquire its own lease. Racing holders are not reliably
+/// excluded, see the [module documentation](self).
+pub async fn acquire_lease(
+    client: &RegionClient,
+    resource: &impl Ec2ResourceId,
+    holder: &LeaseHolder,
+    ttl: Duration,
+) -> Result<Lease, Error> {
+    let resource = resource.resource_id();
+    let now = Timestamp::now();
+    check_available(read_lease(client, resource).await?, resource, holder, now)?;
+
+    let lease = Lease {
+        resource: resource.to_owned(),
+        holder: holder.clone(),
+        expires: expiry(now, ttl)?,
+    };
+
+    write_lease(client, &lease).await?;
+    verify_lease(client, &lease).await?;
+
+    Ok(lease)
+}
+
+/// Extends `lease` to be valid for `ttl` from now on.
+///
+/// Fails with [`Error::LeaseLost`] if the lease was taken over in the meantime.
+pub async fn renew_lease(
+    client: &RegionClient,
+    lease: &Lease,
+    ttl: Duration,
+) -> Result<Lease, Error> {
+    verify_lease(client, lease).await?;
+
+    let renewed = Lease {
+        expires: expiry(Timestamp::now(), ttl)?,
+        ..lease.clone()
+    };
+
+    write_lease(client, &renewed).await?;
+    verify_lease(client, &renewed).await?;
+
+    Ok(renewed)
+}
+
+/// Releases `lease`. Does nothing if the lease was already taken over by another
+/// holder.
+pub async fn release_lease(client: &RegionClient, lease: Lease) -> Result<(), Error> {
+    match verify_lease(client, &lease).await {
+        Ok(()) => (),
+        Err(Error::LeaseLost { .. }) => return Ok(()),
+        Err(e) => return Err(e),
+    }
+
+    let _output = client
+        .main
+        .ec2
+        .delete_tags()
+        .resources(&lease.resource)
+        .tags(
+            aws_sdk_ec2::types::Tag::builder()
+                .key(LEASE_HOLDER_TAG_KEY.as_str())
+                .build(),
+        )
+        .tags(
+            aws_sdk_ec2::types::Tag::builder()
+                .key(LEASE_EXPIRES_TAG_KEY.as_str())
+                .build(),
+        )
+        .send()
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(value: &str) -> Timestamp {
+        Timestamp::parse(value).unwrap()
+    }
+
+    fn lease_tags(holder: &str, expires: &str) -> LeaseTags {
+        LeaseTags::default()
+            .with_holder(LeaseHolder::new(holder.to_owned()))
+            .with_expires(at(expires))
+    }
+
+    #[test]
+    fn leases_of_others_block_until_expiry() {
+        let holder = LeaseHolder::new("runner-1".to_owned());
+        let now = at("2024-05-01T12:00:00Z");
+
+        assert!(matches!(
+            check_available(lease_tags("runner-2", "2024-05-01T12:05:00Z"), "i-1", &holder, now),
+            Err(Error::LeaseHeld { holder: ref current, .. }) if current == "runner-2"
+        ));
+        // Expired, own and missing leases do not block
+        check_available(
+            lease_tags("runner-2", "2024-05-01T11:55:00Z"),
+            "i-1",
+            &holder,
+            now,
+        )
+        .unwrap();
+        check_available(
+            lease_tags("runner-1", "2024-05-01T12:05:00Z"),
+            "i-1",
+            &holder,
+            now,
+        )
+        .unwrap();
+        check_available(LeaseTags::default(), "i-1", &holder, now).unwrap();
+    }
+
+    #[test]
+    fn renewal_requires_holding_the_lease() {
+        let lease = Lease {
+            resource: "i-1".to_owned(),
+            holder: LeaseHolder::new("runner-1".to_owned()),
+            expires: at("2024-05-01T12:05:00Z"),
+        };
+
+        check_held(&lease_tags("runner-1", "2024-05-01T12:05:00Z"), &lease).unwrap();
+        assert!(matches!(
+            check_held(&lease_tags("runner-2", "2024-05-01T12:10:00Z"), &lease),
+            Err(Error::LeaseLost { .. })
+        ));
+        assert!(matches!(
+            check_held(&LeaseTags::default(), &lease),
+            Err(Error::LeaseLost { .. })
+        ));
+    }
+
+    #[test]
+    fn expiry_is_relative_to_now() {
+        assert_eq!(
+            expiry(at("2024-05-01T12:00:00Z"), Duration::from_secs(300)).unwrap(),
+            at("2024-05-01T12:05:00Z")
+        );
+        assert!(matches!(
+            expiry(at("2024-05-01T12:00:00Z"), Duration::MAX),
+            Err(Error::InvalidTimestampError { .. })
+        ));
+    }
+}
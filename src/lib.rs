@@ -5,22 +5,100 @@ extern crate self as aws_lib;
 use std::{
     fmt::{self, Debug},
     net,
+    num::NonZeroU32,
     time::Duration,
 };
 
 use aws_config::retry::RetryConfig;
-use aws_sdk_ec2::client::Waiters;
+use aws_credential_types::provider::ProvideCredentials as _;
+use aws_sdk_ec2::error::ProvideErrorMetadata as _;
 use chrono::{DateTime, Utc};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+mod backoff;
 mod error;
-pub use error::Error;
+pub use error::{Error, ErrorKind};
+mod pagination;
+mod user_agent;
 
 pub mod tags;
 use tags::{ParseTagValueError, RawTag, RawTagValue, Tag, TagKey, TagList};
 
+pub mod accounts;
+pub mod adopt;
+pub mod availability_zone;
+#[cfg(feature = "budgets")]
+pub mod budgets;
+pub mod capacity;
+#[cfg(feature = "cloudtrail")]
+pub mod cloudtrail;
+#[cfg(feature = "codegen")]
+pub mod codegen;
+#[cfg(feature = "config")]
+pub mod config;
+pub mod cost;
+#[cfg(feature = "cost-explorer")]
+pub mod coverage;
+#[cfg(feature = "datasync")]
+pub mod datasync;
+#[cfg(feature = "deploy")]
+pub mod deploy;
+pub mod dhcp;
+#[cfg(feature = "dns")]
+pub mod dns;
+#[cfg(feature = "ecs")]
+pub mod ecs;
+pub mod efs;
+#[cfg(feature = "eks")]
+pub mod eks;
 pub mod export;
+#[cfg(feature = "findings")]
+pub mod findings;
+pub mod flow_logs;
+#[cfg(feature = "groups")]
+pub mod group;
+#[cfg(feature = "imds")]
+pub mod imds;
+pub mod keys;
+pub mod launch_template;
+pub mod lease;
+#[cfg(feature = "lightsail")]
+pub mod lightsail;
+pub mod notify;
+pub mod oplog;
+pub mod orphans;
+pub mod pool;
+#[cfg(feature = "preflight")]
+pub mod preflight;
+pub mod provenance;
+pub mod query;
+pub mod reachability;
+#[cfg(feature = "report")]
+pub mod report;
+pub mod retry;
+#[cfg(feature = "rightsizing")]
+pub mod rightsizing;
+pub mod rollback;
+#[cfg(feature = "scheduler")]
+pub mod scheduler;
+pub mod security_group;
+pub mod snapshot;
+#[cfg(feature = "ssm")]
+pub mod ssm;
+pub mod stack_set;
+#[cfg(feature = "tag-scan")]
+pub mod tag_scan;
+pub mod teardown;
+#[cfg(feature = "metrics")]
+pub mod telemetry;
+pub mod ttl;
+pub mod waiter;
+#[cfg(feature = "windows")]
+pub mod windows;
+#[cfg(feature = "workflow")]
+pub mod workflow;
+use waiter::{WaitConfig, WaiterProfile};
 
 macro_rules! wrap_aws_enum {
     ($name:ident) => {
@@ -42,7 +120,7 @@ macro_rules! wrap_aws_enum {
         }
 
         #[cfg(feature = "serde")]
-        impl Serialize for $name {
+        impl serde::Serialize for $name {
             fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
             where
                 S: serde::Serializer,
@@ -52,39 +130,87 @@ macro_rules! wrap_aws_enum {
         }
 
         #[cfg(feature = "serde")]
-        impl<'de> Deserialize<'de> for $name {
+        impl<'de> serde::Deserialize<'de> for $name {
             fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
             where
                 D: serde::Deserializer<'de>,
             {
-                Ok(Self(String::deserialize(deserializer)?.as_str().into()))
+                Ok(Self(
+                    <String as serde::Deserialize>::deserialize(deserializer)?
+                        .as_str()
+                        .into(),
+                ))
             }
         }
 
-        impl fmt::Display for $name {
-            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
                 write!(f, "{}", self.inner().to_string())
             }
         }
     };
 }
 
+pub(crate) use wrap_aws_enum;
+
 wrap_aws_enum!(InstanceStateName);
 wrap_aws_enum!(InstanceType);
+wrap_aws_enum!(MonitoringState);
+wrap_aws_enum!(ShutdownBehavior);
+
+/// Any EC2 resource that is addressed by its ID, e.g. when creating or describing tags
+pub trait Ec2ResourceId {
+    fn resource_id(&self) -> &str;
+}
+
+macro_rules! impl_ec2_resource_id {
+    ($($name:ident),+) => {
+        $(
+            impl Ec2ResourceId for $name {
+                fn resource_id(&self) -> &str {
+                    &self.0
+                }
+            }
+        )+
+    };
+}
+
+impl_ec2_resource_id!(
+    InstanceId,
+    AmiId,
+    VolumeId,
+    EipAllocationId,
+    SecurityGroupId,
+    SubnetId,
+    SnapshotId
+);
 
 #[derive(Debug)]
 pub struct Instance {
     tags: TagList,
     instance_type: InstanceType,
+    architecture: Option<ArchitectureValues>,
     state: InstanceStateName,
     instance_id: InstanceId,
     image_id: AmiId,
     subnet_id: SubnetId,
     public_ip_address: Option<Ip>,
+    private_ip_address: Option<Ip>,
+    public_dns_name: Option<String>,
+    private_dns_name: Option<String>,
+    key_name: Option<InstanceKeypairName>,
+    monitoring: Option<MonitoringState>,
+    #[cfg(feature = "raw")]
+    raw: aws_sdk_ec2::types::Instance,
 }
 
+const INCORRECT_INSTANCE_STATE_ERROR_CODE: &str = "IncorrectInstanceState";
+
 impl Instance {
     pub fn try_from_aws(instance: aws_sdk_ec2::types::Instance) -> Result<Self, Error> {
+        #[cfg(feature = "raw")]
+        let raw = instance.clone();
+
         macro_rules! extract {
             ($instance:ident, $field:ident) => {
                 $instance
@@ -99,6 +225,7 @@ impl Instance {
         Ok(Self {
             tags: extract!(instance, tags)?.try_into()?,
             instance_type: InstanceType(extract!(instance, instance_type)?),
+            architecture: instance.architecture.clone().map(ArchitectureValues),
             state: InstanceStateName(extract!(instance, state)?.name.ok_or_else(|| {
                 Error::UnexpectedNoneValue {
                     entity: "state.name".to_owned(),
@@ -111,9 +238,35 @@ impl Instance {
                 .public_ip_address
                 .map(|s| -> Result<_, Error> { Ok(Ip(s.parse()?)) })
                 .transpose()?,
+            private_ip_address: instance
+                .private_ip_address
+                .map(|s| -> Result<_, Error> { Ok(Ip(s.parse()?)) })
+                .transpose()?,
+            // EC2 returns empty names instead of none at all
+            public_dns_name: instance.public_dns_name.filter(|name| !name.is_empty()),
+            private_dns_name: instance.private_dns_name.filter(|name| !name.is_empty()),
+            key_name: instance.key_name.map(InstanceKeypairName),
+            monitoring: instance
+                .monitoring
+                .and_then(|monitoring| monitoring.state)
+                .map(MonitoringState),
+            #[cfg(feature = "raw")]
+            raw,
         })
     }
 
+    /// The instance as returned by the SDK, for fields that are not exposed otherwise
+    #[cfg(feature = "raw")]
+    pub const fn as_aws(&self) -> &aws_sdk_ec2::types::Instance {
+        &self.raw
+    }
+
+    /// See [`as_aws()`](Self::as_aws())
+    #[cfg(feature = "raw")]
+    pub fn into_aws(self) -> aws_sdk_ec2::types::Instance {
+        self.raw
+    }
+
     pub fn get_tag(&self, key: TagKey) -> Option<&RawTag> {
         self.tags.get(key)
     }
@@ -122,10 +275,33 @@ impl Instance {
         &self.tags
     }
 
+    /// The value of the `Name` tag
+    pub fn name(&self) -> Option<&str> {
+        name_of(&self.tags)
+    }
+
+    /// Sets the `Name` tag, replacing the current name
+    pub async fn set_name(&self, client: &RegionClient, name: &str) -> Result<(), Error> {
+        let _output = client
+            .main
+            .ec2
+            .create_tags()
+            .resources(self.instance_id().as_str())
+            .tags(RawTag::new(NAME_TAG_KEY, name.to_owned()).into())
+            .send()
+            .await?;
+
+        Ok(())
+    }
+
     pub const fn instance_type(&self) -> &InstanceType {
         &self.instance_type
     }
 
+    pub const fn architecture(&self) -> Option<&ArchitectureValues> {
+        self.architecture.as_ref()
+    }
+
     pub const fn state(&self) -> &InstanceStateName {
         &self.state
     }
@@ -146,7 +322,147 @@ impl Instance {
         self.public_ip_address.as_ref()
     }
 
-    pub async fn stop(&self, client: &RegionClient) -> Result<(), Error> {
+    pub const fn private_ip_address(&self) -> Option<&Ip> {
+        self.private_ip_address.as_ref()
+    }
+
+    pub fn public_dns_name(&self) -> Option<&str> {
+        self.public_dns_name.as_deref()
+    }
+
+    pub fn private_dns_name(&self) -> Option<&str> {
+        self.private_dns_name.as_deref()
+    }
+
+    /// The public DNS name, falling back to the public IP address
+    pub fn public_host(&self) -> Option<String> {
+        self.public_dns_name()
+            .map(ToOwned::to_owned)
+            .or_else(|| self.public_ip_address().map(ToString::to_string))
+    }
+
+    /// `user@host` for connecting via SSH, see [`public_host()`](Self::public_host())
+    pub fn ssh_destination(&self, user: &str) -> Option<String> {
+        self.public_host().map(|host| format!("{user}@{host}"))
+    }
+
+    /// `http://host:port/`, see [`public_host()`](Self::public_host())
+    pub fn http_url(&self, port: u16) -> Option<String> {
+        self.public_host()
+            .map(|host| format!("http://{host}:{port}/"))
+    }
+
+    /// The key pair the instance was launched with, if any
+    pub const fn key_name(&self) -> Option<&InstanceKeypairName> {
+        self.key_name.as_ref()
+    }
+
+    /// Whether detailed (1-minute) monitoring is enabled
+    pub const fn monitoring(&self) -> Option<&MonitoringState> {
+        self.monitoring.as_ref()
+    }
+
+    /// Enables detailed monitoring, which publishes metrics in 1-minute instead of
+    /// 5-minute intervals.
+    pub async fn enable_detailed_monitoring(&self, client: &RegionClient) -> Result<(), Error> {
+        let _output = client
+            .main
+            .ec2
+            .monitor_instances()
+            .instance_ids(self.instance_id().as_str())
+            .send()
+            .await?;
+
+        Ok(())
+    }
+
+    /// Sets whether the instance is stopped or terminated when it is shut down from
+    /// within the operating system.
+    pub async fn set_shutdown_behavior(
+        &self,
+        client: &RegionClient,
+        behavior: ShutdownBehavior,
+    ) -> Result<(), Error> {
+        let _output = client
+            .main
+            .ec2
+            .modify_instance_attribute()
+            .instance_id(self.instance_id().as_str())
+            .instance_initiated_shutdown_behavior(
+                aws_sdk_ec2::types::AttributeValue::builder()
+                    .value(behavior.inner().as_str())
+                    .build(),
+            )
+            .send()
+            .await?;
+
+        Ok(())
+    }
+
+    /// See [`enable_detailed_monitoring()`](Self::enable_detailed_monitoring())
+    pub async fn disable_detailed_monitoring(&self, client: &RegionClient) -> Result<(), Error> {
+        let _output = client
+            .main
+            .ec2
+            .unmonitor_instances()
+            .instance_ids(self.instance_id().as_str())
+            .send()
+            .await?;
+
+        Ok(())
+    }
+
+    /// Starts the stopped instance. Fails with [`Error::IncorrectInstanceState`] if the
+    /// instance cannot be started in its current state, e.g. while it is still stopping.
+    pub async fn start(&self, client: &RegionClient) -> Result<(), Error> {
+        let _state_change_info = client
+            .main
+            .ec2
+            .start_instances()
+            .instance_ids(self.instance_id().as_str())
+            .send()
+            .await
+            .map_err(|e| self.state_error(e))?;
+
+        Ok(())
+    }
+
+    /// Reboots the instance without a stop/start cycle, so it keeps its host and
+    /// instance store. Fails with [`Error::IncorrectInstanceState`] if the instance is
+    /// not running.
+    pub async fn reboot(&self, client: &RegionClient) -> Result<(), Error> {
+        let _output = client
+            .main
+            .ec2
+            .reboot_instances()
+            .instance_ids(self.instance_id().as_str())
+            .send()
+            .await
+            .map_err(|e| self.state_error(e))?;
+
+        Ok(())
+    }
+
+    /// Maps the `IncorrectInstanceState` error code to [`Error::IncorrectInstanceState`]
+    fn state_error<E>(&self, e: aws_sdk_ec2::error::SdkError<E>) -> Error
+    where
+        E: std::error::Error + aws_sdk_ec2::error::ProvideErrorMetadata + Send + 'static,
+    {
+        if e.code() == Some(INCORRECT_INSTANCE_STATE_ERROR_CODE) {
+            Error::IncorrectInstanceState {
+                instance: self.instance_id().clone(),
+                message: e.message().unwrap_or_default().to_owned(),
+            }
+        } else {
+            e.into()
+        }
+    }
+
+    /// Stops the instance. Fails with [`Error::ResourceProtected`] if the instance carries
+    /// the protection tag of `client`, unless `protection` is [`Protection::Override`].
+    pub async fn stop(&self, client: &RegionClient, protection: Protection) -> Result<(), Error> {
+        client.check_protection(self.instance_id(), self.tags(), protection)?;
+
         let _state_change_info = client
             .main
             .ec2
@@ -158,25 +474,221 @@ impl Instance {
         Ok(())
     }
 
-    pub async fn wait_for_stop(
+    /// The instance as currently described by the API, `None` if it is gone
+    async fn describe(&self, client: &RegionClient) -> Result<Option<Self>, Error> {
+        client
+            .main
+            .ec2
+            .describe_instances()
+            .instance_ids(self.instance_id().as_str())
+            .send()
+            .await?
+            .reservations
+            .unwrap_or_default()
+            .into_iter()
+            .flat_map(|reservation| reservation.instances.unwrap_or_default())
+            .next()
+            .map(Self::try_from_aws)
+            .transpose()
+    }
+
+    async fn poll_state(
         &self,
         client: &RegionClient,
+        state: &InstanceStateName,
         max_wait: Duration,
+    ) -> Result<Option<Self>, Error> {
+        waiter::poll(client.waiter_profile, max_wait, || async move {
+            let instance = self.describe(client).await?;
+
+            Ok(instance.filter(|instance| instance.state().inner() == state.inner()))
+        })
+        .await
+    }
+
+    /// Retrieves the administrator password of a Windows instance. `private_key` is the
+    /// PEM encoded private key of the key pair that the instance was launched with.
+    ///
+    /// Returns `None` until EC2Launch has generated the password, which takes a few
+    /// minutes after the first boot.
+    ///
+    /// See the [`windows`] module for the timing side channel of the decryption.
+    #[cfg(feature = "windows-password")]
+    pub async fn get_windows_password(
+        &self,
+        client: &RegionClient,
+        private_key: &str,
+    ) -> Result<Option<String>, Error> {
+        windows::password_data(client, self.instance_id())
+            .await?
+            .map(|data| windows::decrypt_password(self.instance_id(), &data, private_key))
+            .transpose()
+    }
+
+    /// Changes the instance type. The instance has to be stopped, otherwise this fails
+    /// with [`Error::InstanceNotStopped`].
+    pub async fn resize(
+        &self,
+        client: &RegionClient,
+        new_type: &InstanceType,
     ) -> Result<(), Error> {
-        match client
+        self.ensure_stopped(client).await?;
+
+        let _output = client
             .main
             .ec2
-            .wait_until_instance_stopped()
-            .instance_ids(self.instance_id().as_str())
-            .wait(max_wait)
+            .modify_instance_attribute()
+            .instance_id(self.instance_id().as_str())
+            .instance_type(
+                aws_sdk_ec2::types::AttributeValue::builder()
+                    .value(new_type.inner().as_str())
+                    .build(),
+            )
+            .send()
             .await
-        {
-            Ok(_final_response) => Ok(()),
-            Err(e) => match e {
-                aws_sdk_ec2::waiters::instance_stopped::WaitUntilInstanceStoppedError::ExceededMaxWait(_) => Err(Error::InstanceStopExceededMaxWait { max_wait, instance: self.instance_id().clone()}),
-                _ => Err(e.into())
-            },
-        }?;
+            .map_err(|e| self.state_error(e))?;
+
+        Ok(())
+    }
+
+    /// Fails with [`Error::InstanceNotStopped`] unless the instance is stopped right now
+    async fn ensure_stopped(&self, client: &RegionClient) -> Result<(), Error> {
+        let current = self
+            .describe(client)
+            .await?
+            .ok_or_else(|| Error::UnexpectedNoneValue {
+                entity: format!("instance {}", self.instance_id()),
+            })?;
+
+        if *current.state().inner() != aws_sdk_ec2::types::InstanceStateName::Stopped {
+            return Err(Error::InstanceNotStopped {
+                instance: self.instance_id().clone(),
+                state: current.state().clone(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// The user data of the instance, decoded. `None` if the instance has no user data.
+    ///
+    /// Fails if the user data is not UTF-8, e.g. because it is compressed.
+    pub async fn user_data(&self, client: &RegionClient) -> Result<Option<String>, Error> {
+        client
+            .main
+            .ec2
+            .describe_instance_attribute()
+            .instance_id(self.instance_id().as_str())
+            .attribute(aws_sdk_ec2::types::InstanceAttributeName::UserData)
+            .send()
+            .await?
+            .user_data
+            .and_then(|user_data| user_data.value)
+            .map(|value| decode_user_data(self.instance_id(), &value))
+            .transpose()
+    }
+
+    /// Replaces the user data of the instance. It only takes effect on the next boot,
+    /// and only if the instance is configured to run user data on every boot.
+    ///
+    /// The instance has to be stopped, otherwise this fails with
+    /// [`Error::InstanceNotStopped`].
+    pub async fn set_user_data(&self, client: &RegionClient, user_data: &str) -> Result<(), Error> {
+        self.ensure_stopped(client).await?;
+
+        // The SDK encodes the blob as base64
+        let _output = client
+            .main
+            .ec2
+            .modify_instance_attribute()
+            .instance_id(self.instance_id().as_str())
+            .user_data(
+                aws_sdk_ec2::types::BlobAttributeValue::builder()
+                    .value(aws_smithy_types::Blob::new(user_data.as_bytes()))
+                    .build(),
+            )
+            .send()
+            .await
+            .map_err(|e| self.state_error(e))?;
+
+        Ok(())
+    }
+
+    /// Waits until the instance is in `state`, polling according to the
+    /// [`WaiterProfile`] of `client`. Returns the instance as described in that state.
+    ///
+    /// If `max_wait` is `None`, the [`WaitConfig`] of `client` applies.
+    pub async fn wait_for_state(
+        &self,
+        client: &RegionClient,
+        state: InstanceStateName,
+        max_wait: Option<Duration>,
+    ) -> Result<Self, Error> {
+        let max_wait = max_wait.unwrap_or_else(|| client.wait_config.instance_state(&state));
+
+        self.poll_state(client, &state, max_wait)
+            .await?
+            .ok_or_else(|| Error::InstanceWaitExceededMaxWait {
+                max_wait,
+                instance: self.instance_id().clone(),
+                state,
+            })
+    }
+
+    /// If `max_wait` is `None`, the [`WaitConfig`] of `client` applies.
+    pub async fn wait_for_stop(
+        &self,
+        client: &RegionClient,
+        max_wait: Option<Duration>,
+    ) -> Result<(), Error> {
+        let max_wait = max_wait.unwrap_or(client.wait_config.instance_stop);
+
+        self.poll_state(
+            client,
+            &InstanceStateName::new(aws_sdk_ec2::types::InstanceStateName::Stopped),
+            max_wait,
+        )
+        .await?
+        .ok_or_else(|| Error::InstanceStopExceededMaxWait {
+            max_wait,
+            instance: self.instance_id().clone(),
+        })?;
+
+        Ok(())
+    }
+
+    /// Terminates the instance. As instances are launched with termination protection,
+    /// this disables termination protection first.
+    ///
+    /// Fails with [`Error::ResourceProtected`] if the instance carries the protection tag
+    /// of `client`, unless `protection` is [`Protection::Override`].
+    pub async fn terminate(
+        &self,
+        client: &RegionClient,
+        protection: Protection,
+    ) -> Result<(), Error> {
+        client.check_protection(self.instance_id(), self.tags(), protection)?;
+
+        let _output = client
+            .main
+            .ec2
+            .modify_instance_attribute()
+            .instance_id(self.instance_id().as_str())
+            .disable_api_termination(
+                aws_sdk_ec2::types::AttributeBooleanValue::builder()
+                    .value(false)
+                    .build(),
+            )
+            .send()
+            .await?;
+
+        let _state_change_info = client
+            .main
+            .ec2
+            .terminate_instances()
+            .instance_ids(self.instance_id().as_str())
+            .send()
+            .await?;
 
         Ok(())
     }
@@ -199,6 +711,23 @@ impl Instance {
     }
 }
 
+impl fmt::Display for Instance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_named(f, self.instance_id().as_str(), self.name())
+    }
+}
+
+fn decode_user_data(instance: &InstanceId, value: &str) -> Result<String, Error> {
+    let decoded =
+        aws_smithy_types::base64::decode(value).map_err(|e| Error::InvalidResponseError {
+            message: format!("invalid user data of instance {instance}: {e}"),
+        })?;
+
+    String::from_utf8(decoded).map_err(|e| Error::InvalidResponseError {
+        message: format!("user data of instance {instance} is not utf-8: {e}"),
+    })
+}
+
 #[derive(Debug, Copy, Clone)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Region {
@@ -258,100 +787,628 @@ pub struct RegionClientMain {
     pub ec2: aws_sdk_ec2::Client,
     pub efs: aws_sdk_efs::Client,
     pub route53: aws_sdk_route53::Client,
+    #[cfg(feature = "budgets")]
+    pub budgets: aws_sdk_budgets::Client,
+    #[cfg(feature = "cloudtrail")]
+    pub cloudtrail: aws_sdk_cloudtrail::Client,
+    #[cfg(feature = "rightsizing")]
+    pub cloudwatch: aws_sdk_cloudwatch::Client,
+    #[cfg(feature = "ecs")]
+    pub cloudwatchlogs: aws_sdk_cloudwatchlogs::Client,
+    #[cfg(feature = "rightsizing")]
+    pub computeoptimizer: aws_sdk_computeoptimizer::Client,
+    #[cfg(feature = "cost-explorer")]
+    pub costexplorer: aws_sdk_costexplorer::Client,
+    #[cfg(feature = "datasync")]
+    pub datasync: aws_sdk_datasync::Client,
+    #[cfg(feature = "ecs")]
+    pub ecs: aws_sdk_ecs::Client,
+    #[cfg(feature = "eks")]
+    pub eks: aws_sdk_eks::Client,
+    #[cfg(feature = "groups")]
+    pub elbv2: aws_sdk_elasticloadbalancingv2::Client,
+    #[cfg(feature = "findings")]
+    pub guardduty: aws_sdk_guardduty::Client,
+    #[cfg(feature = "preflight")]
+    pub iam: aws_sdk_iam::Client,
+    #[cfg(feature = "findings")]
+    pub inspector2: aws_sdk_inspector2::Client,
+    #[cfg(feature = "lightsail")]
+    pub lightsail: aws_sdk_lightsail::Client,
+    #[cfg(feature = "pricing")]
+    pub pricing: aws_sdk_pricing::Client,
+    #[cfg(feature = "tag-scan")]
+    pub resourcegroupstagging: aws_sdk_resourcegroupstagging::Client,
+    #[cfg(feature = "deploy")]
+    pub s3: aws_sdk_s3::Client,
+    #[cfg(feature = "ssm")]
+    pub ssm: aws_sdk_ssm::Client,
+    pub sts: aws_sdk_sts::Client,
 }
 
-#[derive(Debug, Clone)]
-pub struct RegionClientCdn {
-    pub cloudfront: aws_sdk_cloudfront::Client,
-    pub cloudformation: aws_sdk_cloudformation::Client,
-}
-
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct RegionClient {
     pub region: Region,
     pub main: RegionClientMain,
-    pub cdn: RegionClientCdn,
+    pub protection_tag: RawTag,
+    /// If set, hosted zones are only changed if they carry this tag, see
+    /// [`with_ownership_tag()`](Self::with_ownership_tag())
+    pub ownership_tag: Option<RawTag>,
+    pub waiter_profile: WaiterProfile,
+    pub wait_config: WaitConfig,
+    /// Added to the tags of all resources created through this client, see
+    /// [`with_default_tags()`](Self::with_default_tags())
+    pub default_tags: TagList,
+    pub profile: ClientProfile,
 }
 
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+/// The profile and role a client was loaded with. For a [`CdnClient`], the caller
+/// identity is never looked up.
 #[derive(Debug, Clone)]
-pub struct InstanceProfileName(String);
+pub struct ClientProfile {
+    profile_name: ProfileName,
+    role: Option<RoleArn>,
+    /// Filled by the first [`RegionClient::caller_identity()`] and shared with all
+    /// clones, as the credentials of a client never change their account
+    identity: std::sync::Arc<tokio::sync::OnceCell<CallerIdentity>>,
+}
 
-impl InstanceProfileName {
-    pub fn as_str(&self) -> &str {
-        &self.0
+impl ClientProfile {
+    fn new(profile_name: ProfileName, role: Option<RoleArn>) -> Self {
+        Self {
+            profile_name,
+            role,
+            identity: std::sync::Arc::default(),
+        }
     }
-}
 
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Debug, Clone)]
-pub struct InstanceKeypairName(String);
+    /// The profile the client was loaded from. If a [`role()`](Self::role()) was
+    /// assumed, this is the profile that assumed it.
+    pub const fn profile_name(&self) -> &ProfileName {
+        &self.profile_name
+    }
 
-impl InstanceKeypairName {
-    pub fn as_str(&self) -> &str {
-        &self.0
+    pub const fn role(&self) -> Option<&RoleArn> {
+        self.role.as_ref()
     }
 }
 
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Debug, Clone)]
-pub struct SecurityGroupId(String);
-
-impl SecurityGroupId {
-    pub fn as_str(&self) -> &str {
-        &self.0
+/// Only shows which identity the client was loaded for, not the SDK clients and their
+/// configuration
+impl fmt::Debug for RegionClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RegionClient")
+            .field("region", &self.region)
+            .field("profile_name", &self.profile.profile_name)
+            .field("role", &self.profile.role)
+            .field("protection_tag", &self.protection_tag)
+            .field("ownership_tag", &self.ownership_tag)
+            .field("waiter_profile", &self.waiter_profile)
+            .field("wait_config", &self.wait_config)
+            .field("default_tags", &self.default_tags)
+            .finish_non_exhaustive()
     }
 }
 
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Debug, Clone)]
-pub struct SecurityGroup {
-    id: SecurityGroupId,
+/// The region that CDN resources are managed from. CloudFront is a global service, and
+/// the stacks for it have to live here.
+pub(crate) const CDN_REGION: Region = Region::UsEast1;
+
+/// Clients for CloudFront distributions and CloudFormation stacks, which are not tied
+/// to the region of a [`RegionClient`]. See [`load_cdn_client()`].
+#[derive(Clone)]
+pub struct CdnClient {
+    pub cloudfront: aws_sdk_cloudfront::Client,
+    pub cloudformation: aws_sdk_cloudformation::Client,
+    /// If set, distributions are only changed if they carry this tag, and stacks are
+    /// created with it, see [`with_ownership_tag()`](Self::with_ownership_tag())
+    pub ownership_tag: Option<RawTag>,
+    pub waiter_profile: WaiterProfile,
+    pub wait_config: WaitConfig,
+    /// Added to the tags of all stacks created through this client, see
+    /// [`with_default_tags()`](Self::with_default_tags())
+    pub default_tags: TagList,
+    pub profile: ClientProfile,
 }
 
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Debug, Clone)]
-pub struct SubnetId(String);
+/// Only shows which identity the client was loaded for, not the SDK clients and their
+/// configuration
+impl fmt::Debug for CdnClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CdnClient")
+            .field("profile_name", &self.profile.profile_name)
+            .field("role", &self.profile.role)
+            .field("ownership_tag", &self.ownership_tag)
+            .field("waiter_profile", &self.waiter_profile)
+            .field("wait_config", &self.wait_config)
+            .field("default_tags", &self.default_tags)
+            .finish_non_exhaustive()
+    }
+}
 
-impl SubnetId {
-    pub fn as_str(&self) -> &str {
-        &self.0
+impl CdnClient {
+    /// Restricts changes to distributions to those carrying `tag`, like
+    /// [`RegionClient::with_ownership_tag()`] does for hosted zones. Stacks created
+    /// through the client carry `tag` as well.
+    #[must_use]
+    pub fn with_ownership_tag(self, tag: RawTag) -> Self {
+        Self {
+            ownership_tag: Some(tag),
+            ..self
+        }
     }
 
-    pub const fn from_string(value: String) -> Self {
-        Self(value)
+    /// Fails with [`Error::NotOwned`] if an ownership tag is set and the distribution
+    /// `id` does not carry it
+    pub(crate) async fn check_ownership(&self, id: &CloudfrontDistributionId) -> Result<(), Error> {
+        let Some(ref ownership_tag) = self.ownership_tag else {
+            return Ok(());
+        };
+
+        check_ownership(
+            id.as_str(),
+            &self.distribution_tags(id).await?,
+            ownership_tag,
+        )
+    }
+
+    /// The tags of the distribution `id`
+    pub(crate) async fn distribution_tags(
+        &self,
+        id: &CloudfrontDistributionId,
+    ) -> Result<TagList, Error> {
+        let arn = self
+            .cloudfront
+            .get_distribution()
+            .id(id.as_str())
+            .send()
+            .await?
+            .distribution
+            .ok_or_else(|| Error::UnexpectedNoneValue {
+                entity: "GetDistributionOutput.distribution".to_owned(),
+            })?
+            .arn;
+
+        distribution_tags(self, &arn).await
+    }
+
+    /// Fails with [`Error::NotOwned`] if an ownership tag is set and the stack `name`
+    /// does not carry it
+    pub(crate) async fn check_stack_ownership(&self, name: &str) -> Result<(), Error> {
+        let Some(ref ownership_tag) = self.ownership_tag else {
+            return Ok(());
+        };
+
+        check_ownership(name, &self.stack_tags(name).await?, ownership_tag)
+    }
+
+    /// The tags of the stack `name`
+    pub(crate) async fn stack_tags(&self, name: &str) -> Result<TagList, Error> {
+        let stack = self
+            .cloudformation
+            .describe_stacks()
+            .stack_name(name)
+            .send()
+            .await?
+            .stacks
+            .unwrap_or_default()
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::UnexpectedNoneValue {
+                entity: "DescribeStacksOutput.stacks".to_owned(),
+            })?;
+
+        Ok(TagList::try_from(stack.tags.unwrap_or_default())?)
+    }
+
+    /// See [`RegionClient::with_default_tags()`]
+    #[must_use]
+    pub fn with_default_tags(self, default_tags: TagList) -> Self {
+        Self {
+            default_tags,
+            ..self
+        }
+    }
+
+    /// `tags` with the default tags and the ownership tag of the client added. The
+    /// ownership tag takes precedence over both, so stacks created with these tags,
+    /// and the distributions they propagate them to, stay changeable through this
+    /// client.
+    pub fn tags_with_defaults(&self, tags: &TagList) -> TagList {
+        let tags = tags.with_defaults(&self.default_tags);
+        match self.ownership_tag {
+            Some(ref ownership_tag) => {
+                TagList::from_vec(vec![ownership_tag.clone()]).with_defaults(&tags)
+            }
+            None => tags,
+        }
+    }
+
+    /// See [`RegionClient::with_waiter_profile()`]
+    #[must_use]
+    pub fn with_waiter_profile(self, waiter_profile: WaiterProfile) -> Self {
+        Self {
+            waiter_profile,
+            ..self
+        }
+    }
+
+    /// See [`RegionClient::with_wait_config()`]
+    #[must_use]
+    pub fn with_wait_config(self, wait_config: WaitConfig) -> Self {
+        Self {
+            wait_config,
+            ..self
+        }
+    }
+
+    /// The CDN profile the client was loaded from
+    pub const fn profile_name(&self) -> &ProfileName {
+        self.profile.profile_name()
+    }
+
+    pub const fn role(&self) -> Option<&RoleArn> {
+        self.profile.role()
+    }
+}
+
+/// The identity that the credentials of a [`RegionClient`] belong to
+#[derive(Debug, Clone)]
+pub struct CallerIdentity {
+    account: Account,
+    arn: String,
+}
+
+impl CallerIdentity {
+    pub const fn account(&self) -> &Account {
+        &self.account
     }
+
+    /// ARN of the user or assumed role
+    pub fn arn(&self) -> &str {
+        &self.arn
+    }
+
+    /// e.g. `aws` or `aws-cn`, taken from the ARN
+    pub fn partition(&self) -> Option<&str> {
+        self.arn.split(':').nth(1)
+    }
+}
+
+pub const DEFAULT_PROTECTION_TAG_KEY: &str = "protected";
+
+/// The tag that the console shows as the name of a resource
+pub const NAME_TAG_KEY: tags::StaticTagKey = tags::StaticTagKey::new("Name");
+
+fn name_of(tags: &TagList) -> Option<&str> {
+    tags.get(NAME_TAG_KEY).map(|tag| tag.value().as_str())
 }
 
-impl PartialEq for SubnetId {
-    fn eq(&self, other: &Self) -> bool {
-        self.0 == other.0
+/// Writes `id`, followed by `name` in parentheses if there is one
+fn fmt_named(f: &mut fmt::Formatter<'_>, id: &str, name: Option<&str>) -> fmt::Result {
+    match name {
+        Some(name) => write!(f, "{id} ({name})"),
+        None => write!(f, "{id}"),
     }
 }
 
-impl fmt::Display for SubnetId {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.0)
+/// Fails with [`Error::NotOwned`] unless `tags` contain `ownership_tag`
+fn check_ownership(resource: &str, tags: &TagList, ownership_tag: &RawTag) -> Result<(), Error> {
+    if tags.as_slice().contains(ownership_tag) {
+        Ok(())
+    } else {
+        Err(Error::NotOwned {
+            resource: resource.to_owned(),
+            tag: ownership_tag.clone(),
+        })
+    }
+}
+
+/// Whether destructive operations respect the protection tag of a [`RegionClient`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protection {
+    /// Refuse to touch resources carrying the protection tag
+    Enforce,
+    /// Ignore the protection tag
+    Override,
+}
+
+impl RegionClient {
+    /// Replaces the tag that marks resources as protected against destructive
+    /// operations. By default, this is `protected=true`.
+    #[must_use]
+    pub fn with_protection_tag(self, tag: RawTag) -> Self {
+        Self {
+            protection_tag: tag,
+            ..self
+        }
+    }
+
+    /// Restricts changes to hosted zones to zones carrying `tag`, e.g. `team=payments`,
+    /// for accounts that are shared between teams. Changing any other zone fails with
+    /// [`Error::NotOwned`] before anything is changed. Zones created through the client
+    /// are tagged with it.
+    #[must_use]
+    pub fn with_ownership_tag(self, tag: RawTag) -> Self {
+        Self {
+            ownership_tag: Some(tag),
+            ..self
+        }
+    }
+
+    /// Fails with [`Error::NotOwned`] if an ownership tag is set and the hosted zone
+    /// `zone` does not carry it
+    pub(crate) async fn check_zone_ownership(&self, zone: &HostedZoneId) -> Result<(), Error> {
+        match self.ownership_tag {
+            Some(ref ownership_tag) => check_ownership(
+                zone.as_str(),
+                &hosted_zone_tags(self, zone).await?,
+                ownership_tag,
+            ),
+            None => Ok(()),
+        }
+    }
+
+    /// Sets tags that are added to all resources created through this client, e.g.
+    /// ownership or cost allocation tags. Tags given explicitly to a create operation win
+    /// over defaults with the same key.
+    #[must_use]
+    pub fn with_default_tags(self, default_tags: TagList) -> Self {
+        Self {
+            default_tags,
+            ..self
+        }
+    }
+
+    /// `tags` with the default tags of the client added
+    pub fn tags_with_defaults(&self, tags: &TagList) -> TagList {
+        tags.with_defaults(&self.default_tags)
+    }
+
+    /// The profile the client was loaded from. If a [`role()`](Self::role()) was
+    /// assumed, this is the profile that assumed it.
+    pub const fn profile_name(&self) -> &ProfileName {
+        self.profile.profile_name()
+    }
+
+    pub const fn role(&self) -> Option<&RoleArn> {
+        self.profile.role()
+    }
+
+    /// Asks STS which account and principal the credentials of the client belong to.
+    /// The answer is cached, so only the first call of the client and its clones
+    /// reaches STS.
+    pub async fn caller_identity(&self) -> Result<CallerIdentity, Error> {
+        self.profile
+            .identity
+            .get_or_try_init(|| async {
+                let output = self.main.sts.get_caller_identity().send().await?;
+
+                Ok(CallerIdentity {
+                    account: Account::new(output.account.ok_or_else(|| {
+                        Error::UnexpectedNoneValue {
+                            entity: "GetCallerIdentityOutput.account".to_owned(),
+                        }
+                    })?),
+                    arn: output.arn.ok_or_else(|| Error::UnexpectedNoneValue {
+                        entity: "GetCallerIdentityOutput.arn".to_owned(),
+                    })?,
+                })
+            })
+            .await
+            .cloned()
+    }
+
+    async fn credentials(&self) -> Result<aws_credential_types::Credentials, Error> {
+        self.main
+            .ec2
+            .config()
+            .credentials_provider()
+            .ok_or_else(|| Error::CredentialsError {
+                message: "no credentials provider configured".to_owned(),
+            })?
+            .provide_credentials()
+            .await
+            .map_err(|e| Error::CredentialsError {
+                message: e.to_string(),
+            })
+    }
+
+    /// When the credentials of the main clients expire. `None` if they do not expire,
+    /// e.g. for static access keys.
+    ///
+    /// Refreshable credentials (e.g. assumed roles) are refreshed automatically, so the
+    /// expiry only matters for credentials that cannot be refreshed, like the session
+    /// token of a profile.
+    pub async fn credentials_expiry(&self) -> Result<Option<Timestamp>, Error> {
+        Ok(self
+            .credentials()
+            .await?
+            .expiry()
+            .map(|expiry| Timestamp::new(expiry.into())))
+    }
+
+    /// How much longer the credentials of the main clients are valid. `None` if they do
+    /// not expire.
+    pub async fn credentials_valid_for(&self) -> Result<Option<Duration>, Error> {
+        Ok(self.credentials().await?.expiry().map(|expiry| {
+            expiry
+                .duration_since(std::time::SystemTime::now())
+                .unwrap_or(Duration::ZERO)
+        }))
+    }
+
+    /// Fails with [`Error::CredentialsExpireTooSoon`] if the credentials expire within
+    /// `duration`. Use this before starting long-running orchestrations.
+    pub async fn require_credentials_valid_for(&self, duration: Duration) -> Result<(), Error> {
+        match self.credentials_valid_for().await? {
+            Some(valid_for) if valid_for < duration => Err(Error::CredentialsExpireTooSoon {
+                valid_for,
+                required: duration,
+            }),
+            Some(_) | None => Ok(()),
+        }
+    }
+
+    /// Replaces the poll intervals of waiters. By default, this is derived from the
+    /// endpoint URL, see [`WaiterProfile::for_endpoint()`].
+    #[must_use]
+    pub fn with_waiter_profile(self, waiter_profile: WaiterProfile) -> Self {
+        Self {
+            waiter_profile,
+            ..self
+        }
+    }
+
+    /// Replaces the default durations of waits, for all calls that do not pass an
+    /// explicit `max_wait`.
+    #[must_use]
+    pub fn with_wait_config(self, wait_config: WaitConfig) -> Self {
+        Self {
+            wait_config,
+            ..self
+        }
+    }
+
+    fn route53_vpc(&self, vpc: &VpcId) -> aws_sdk_route53::types::Vpc {
+        aws_sdk_route53::types::Vpc::builder()
+            .vpc_id(vpc.as_str())
+            .vpc_region(self.region.as_str().into())
+            .build()
+    }
+
+    pub fn check_protection(
+        &self,
+        resource: &impl Ec2ResourceId,
+        tags: &TagList,
+        protection: Protection,
+    ) -> Result<(), Error> {
+        self.check_protection_of(resource.resource_id(), tags, protection)
+    }
+
+    /// Like [`check_protection()`](Self::check_protection()), for resources outside
+    /// of EC2
+    pub(crate) fn check_protection_of(
+        &self,
+        resource: &str,
+        tags: &TagList,
+        protection: Protection,
+    ) -> Result<(), Error> {
+        match protection {
+            Protection::Override => Ok(()),
+            Protection::Enforce => {
+                if tags.as_slice().contains(&self.protection_tag) {
+                    Err(Error::ResourceProtected {
+                        resource: resource.to_owned(),
+                        tag: self.protection_tag.clone(),
+                    })
+                } else {
+                    Ok(())
+                }
+            }
+        }
     }
 }
 
+/// Defines a newtype around `String` with the full set of traits for IDs, so they can be
+/// used as keys in collections and be sorted.
+///
+/// With `validated`, `FromStr` is left to the caller.
 macro_rules! string_newtype {
     ($name:ident) => {
-        #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-        #[derive(Debug, Clone, Eq, PartialEq, Tag)]
+        crate::string_newtype!($name, validated);
+
+        impl std::str::FromStr for $name {
+            type Err = std::convert::Infallible;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                Ok(Self(s.to_owned()))
+            }
+        }
+    };
+    ($name:ident, validated) => {
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+        #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, $crate::tags::Tag)]
         #[tag(translate = transparent)]
         pub struct $name(String);
 
         impl std::fmt::Display for $name {
-            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
                 write!(f, "{}", self.0)
             }
         }
     };
 }
 
+pub(crate) use string_newtype;
+
+string_newtype!(InstanceProfileName);
+
+impl InstanceProfileName {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+string_newtype!(InstanceKeypairName);
+
+impl InstanceKeypairName {
+    pub const fn new(value: String) -> Self {
+        Self(value)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+string_newtype!(SecurityGroupId);
+
+impl SecurityGroupId {
+    pub const fn new(value: String) -> Self {
+        Self(value)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// See [`security_group`] for creating security groups and managing their rules
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct SecurityGroup {
+    id: SecurityGroupId,
+    name: String,
+    /// `None` for security groups of EC2-Classic
+    vpc_id: Option<VpcId>,
+    tags: TagList,
+}
+
+string_newtype!(SubnetId);
+
+impl SubnetId {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub const fn from_string(value: String) -> Self {
+        Self(value)
+    }
+}
+
 string_newtype!(AvailabilityZone);
 
+impl AvailabilityZone {
+    pub const fn new(value: String) -> Self {
+        Self(value)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Debug, Clone)]
 pub struct Subnet {
@@ -394,12 +1451,28 @@ impl AmiId {
     }
 }
 
+wrap_aws_enum!(BootModeValues);
+wrap_aws_enum!(TpmSupportValues);
+wrap_aws_enum!(ArchitectureValues);
+
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Debug)]
 pub struct Ami {
     pub id: AmiId,
     pub tags: TagList,
     pub creation_date: Timestamp,
+    /// If `None`, the boot mode depends on the instance type
+    pub boot_mode: Option<BootModeValues>,
+    pub tpm_support: Option<TpmSupportValues>,
+    pub ena_support: bool,
+    /// Whether enhanced networking with the Intel 82599 VF interface is enabled
+    pub sriov_net_support: bool,
+    /// E.g. `/dev/xvda`
+    pub root_device_name: Option<String>,
+    /// Has to be supported by the instance type, see [`check_architecture()`]
+    pub architecture: Option<ArchitectureValues>,
+    /// The operating system AWS bills for, e.g. `Linux/UNIX` or `Windows`
+    pub platform_details: Option<String>,
 }
 
 impl TryFrom<aws_sdk_ec2::types::Image> for Ami {
@@ -418,71 +1491,437 @@ impl TryFrom<aws_sdk_ec2::types::Image> for Ami {
             id: AmiId(extract!(image_id)?),
             tags: extract!(tags)?.try_into()?,
             creation_date: RawImageCreationDate(extract!(creation_date)?).try_into()?,
+            boot_mode: image.boot_mode.map(BootModeValues),
+            tpm_support: image.tpm_support.map(TpmSupportValues),
+            ena_support: image.ena_support.unwrap_or(false),
+            sriov_net_support: image.sriov_net_support.as_deref() == Some(SRIOV_NET_SUPPORT_SIMPLE),
+            root_device_name: image.root_device_name,
+            architecture: image.architecture.map(ArchitectureValues),
+            platform_details: image.platform_details,
         })
     }
 }
 
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Tag, Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone)]
-#[tag(translate = manual)]
-pub struct Timestamp(DateTime<Utc>);
+const SRIOV_NET_SUPPORT_SIMPLE: &str = "simple";
 
-impl Timestamp {
-    pub const fn new(value: DateTime<Utc>) -> Self {
+string_newtype!(SnapshotId);
+
+impl SnapshotId {
+    pub const fn new(value: String) -> Self {
         Self(value)
     }
 
-    pub const fn inner(&self) -> &DateTime<Utc> {
+    pub fn as_str(&self) -> &str {
         &self.0
     }
 }
 
-impl fmt::Display for Timestamp {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.0)
-    }
+/// An AMI to register from a snapshot of its root volume.
+///
+/// Instances launched from the AMI inherit its boot mode and TPM support. Newer
+/// instance families (e.g. Graviton) require [`BootModeValues`] UEFI.
+pub struct NewAmiConfig<'a> {
+    pub name: &'a str,
+    pub architecture: &'a ArchitectureValues,
+    pub root_snapshot: &'a SnapshotId,
+    /// e.g. `/dev/xvda`
+    pub root_device_name: &'a str,
+    pub boot_mode: Option<&'a BootModeValues>,
+    pub tpm_support: Option<&'a TpmSupportValues>,
+    pub ena_support: bool,
+    pub sriov_net_support: bool,
+    pub tags: &'a TagList,
 }
 
-impl TryFrom<RawTagValue> for Timestamp {
-    type Error = ParseTagValueError;
+pub async fn register_ami(
+    client: &RegionClient,
+    config: &NewAmiConfig<'_>,
+) -> Result<AmiId, Error> {
+    client
+        .main
+        .ec2
+        .register_image()
+        .name(config.name)
+        .architecture(config.architecture.inner().clone())
+        .virtualization_type("hvm")
+        .root_device_name(config.root_device_name)
+        .block_device_mappings(
+            aws_sdk_ec2::types::BlockDeviceMapping::builder()
+                .device_name(config.root_device_name)
+                .ebs(
+                    aws_sdk_ec2::types::EbsBlockDevice::builder()
+                        .snapshot_id(config.root_snapshot.as_str())
+                        .delete_on_termination(true)
+                        .build(),
+                )
+                .build(),
+        )
+        .set_boot_mode(config.boot_mode.map(|boot_mode| boot_mode.inner().clone()))
+        .set_tpm_support(
+            config
+                .tpm_support
+                .map(|tpm_support| tpm_support.inner().clone()),
+        )
+        .ena_support(config.ena_support)
+        .set_sriov_net_support(
+            config
+                .sriov_net_support
+                .then(|| SRIOV_NET_SUPPORT_SIMPLE.to_owned()),
+        )
+        .tag_specifications(
+            aws_sdk_ec2::types::TagSpecification::builder()
+                .resource_type(aws_sdk_ec2::types::ResourceType::Image)
+                .set_tags(Some(client.tags_with_defaults(config.tags).into()))
+                .build(),
+        )
+        .send()
+        .await?
+        .image_id
+        .map(AmiId)
+        .ok_or_else(|| Error::UnexpectedNoneValue {
+            entity: "RegisterImageOutput.image_id".to_owned(),
+        })
+}
 
-    fn try_from(value: RawTagValue) -> Result<Self, Self::Error> {
-        Ok(Self(
-            chrono::NaiveDateTime::parse_from_str(value.as_str(), "%Y-%m-%dT%H:%M:%S")
-                .map_err(|e| ParseTagValueError::InvalidValue {
-                    value,
-                    message: format!("failed parsing timestamp: {e}"),
-                })
-                .map(|timestamp| timestamp.and_utc())?,
-        ))
+string_newtype!(KmsKeyId);
+
+impl KmsKeyId {
+    /// Accepts key IDs, key ARNs, alias names and alias ARNs
+    pub const fn new(value: String) -> Self {
+        Self(value)
     }
-}
 
-impl From<Timestamp> for RawTagValue {
-    fn from(value: Timestamp) -> Self {
-        Self::new(value.0.format("%Y-%m-%dT%H:%M:%S").to_string())
+    pub fn as_str(&self) -> &str {
+        &self.0
     }
 }
 
-struct RawImageCreationDate(String);
+async fn describe_image(
+    client: &RegionClient,
+    id: &AmiId,
+) -> Result<Option<aws_sdk_ec2::types::Image>, Error> {
+    Ok(client
+        .main
+        .ec2
+        .describe_images()
+        .image_ids(id.as_str())
+        .send()
+        .await?
+        .images
+        .unwrap_or_default()
+        .pop())
+}
 
-impl TryFrom<RawImageCreationDate> for Timestamp {
-    type Error = Error;
+/// Whether `architecture` is one of `supported`. The types differ in the SDK, but share
+/// their values, e.g. `arm64`.
+fn architecture_supported(
+    architecture: &ArchitectureValues,
+    supported: &[aws_sdk_ec2::types::ArchitectureType],
+) -> bool {
+    supported
+        .iter()
+        .any(|supported| supported.as_str() == architecture.inner().as_str())
+}
 
-    fn try_from(value: RawImageCreationDate) -> Result<Self, Self::Error> {
-        Ok(Self(
-            chrono::NaiveDateTime::parse_from_str(&value.0, "%Y-%m-%dT%H:%M:%S.%.3fZ")
-                .map_err(|e| Error::InvalidTimestampError {
-                    value: value.0,
-                    message: format!("failed parsing timestamp: {e}"),
-                })
-                .map(|timestamp| timestamp.and_utc())?,
-        ))
+/// Checks that instances of `instance_type` can be launched from `ami`, e.g. to catch
+/// x86 AMIs with Graviton instance types before launching. Fails with
+/// [`Error::ArchitectureMismatch`] otherwise.
+///
+/// AMIs without a known architecture always pass.
+pub async fn check_architecture(
+    client: &RegionClient,
+    ami: &Ami,
+    instance_type: &InstanceType,
+) -> Result<(), Error> {
+    let Some(ref architecture) = ami.architecture else {
+        return Ok(());
+    };
+
+    let supported = client
+        .main
+        .ec2
+        .describe_instance_types()
+        .instance_types(instance_type.inner().clone())
+        .send()
+        .await?
+        .instance_types
+        .unwrap_or_default()
+        .pop()
+        .and_then(|info| info.processor_info)
+        .and_then(|processor| processor.supported_architectures)
+        .ok_or_else(|| Error::UnexpectedNoneValue {
+            entity: format!("supported architectures of instance type {instance_type}"),
+        })?;
+
+    if architecture_supported(architecture, &supported) {
+        Ok(())
+    } else {
+        Err(Error::ArchitectureMismatch {
+            ami: ami.id.clone(),
+            architecture: architecture.clone(),
+            instance_type: instance_type.clone(),
+            supported: supported
+                .iter()
+                .map(|architecture| architecture.as_str().to_owned())
+                .collect(),
+        })
     }
 }
 
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Debug, Clone)]
+impl Ami {
+    fn root_device(&self) -> &str {
+        self.root_device_name
+            .as_deref()
+            .unwrap_or(DEFAULT_ROOT_DEVICE_NAME)
+    }
+
+    /// The provenance of AMIs created by [`provenance::create_image()`]. Fails if one of
+    /// the provenance tags is present but cannot be parsed.
+    pub fn provenance(&self) -> Result<provenance::AmiProvenance, Error> {
+        Ok(provenance::AmiProvenance::from_tags(self.tags.clone())?)
+    }
+
+    /// Deregisters the AMI. With `delete_snapshots`, the EBS snapshots backing it are
+    /// deleted afterwards. Returns the deleted snapshots.
+    ///
+    /// Fails with [`Error::ResourceProtected`] before changing anything if the AMI or
+    /// one of its snapshots carries the protection tag of `client`, unless `protection`
+    /// is [`Protection::Override`]. If the AMI was deregistered but some snapshots
+    /// could not be deleted, fails with [`Error::AmiSnapshotsNotDeleted`], which lists
+    /// the deleted and the remaining snapshots.
+    pub async fn deregister(
+        &self,
+        client: &RegionClient,
+        delete_snapshots: bool,
+        protection: Protection,
+    ) -> Result<Vec<SnapshotId>, Error> {
+        client.check_protection(&self.id, &self.tags, protection)?;
+
+        // The block device mappings are gone once the image is deregistered
+        let snapshot_ids: Vec<SnapshotId> = if delete_snapshots {
+            describe_image(client, &self.id)
+                .await?
+                .and_then(|image| image.block_device_mappings)
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|mapping| mapping.ebs?.snapshot_id.map(SnapshotId))
+                .collect()
+        } else {
+            vec![]
+        };
+
+        let mut snapshots = Vec::with_capacity(snapshot_ids.len());
+        for id in &snapshot_ids {
+            if let Some(snapshot) = snapshot::Snapshot::get(client, id).await? {
+                client.check_protection(snapshot.id(), snapshot.tags(), protection)?;
+                snapshots.push(snapshot);
+            }
+        }
+
+        let _output = client
+            .main
+            .ec2
+            .deregister_image()
+            .image_id(self.id.as_str())
+            .send()
+            .await?;
+
+        let mut deleted = Vec::with_capacity(snapshots.len());
+        let mut failed = vec![];
+        for snapshot in snapshots {
+            match snapshot.delete(client, protection).await {
+                Ok(()) => deleted.push(snapshot.id().clone()),
+                Err(e) => failed.push((snapshot.id().clone(), Box::new(e))),
+            }
+        }
+
+        if failed.is_empty() {
+            Ok(deleted)
+        } else {
+            Err(Error::AmiSnapshotsNotDeleted {
+                ami: self.id.clone(),
+                deleted,
+                failed,
+            })
+        }
+    }
+}
+
+/// Copies `ami` from the region of `src` to the region of `dst` and waits for at most
+/// `max_wait` until the copy is available.
+///
+/// With `encrypt`, the snapshots of the copy are encrypted with the given key. The copy
+/// and its snapshots carry `tags` from the start, the tags of `ami` are not copied.
+///
+/// If `max_wait` is `None`, the [`WaitConfig`] of `dst` applies.
+pub async fn copy_ami(
+    src: &RegionClient,
+    dst: &RegionClient,
+    ami: &Ami,
+    encrypt: Option<&KmsKeyId>,
+    tags: &TagList,
+    max_wait: Option<Duration>,
+) -> Result<Ami, Error> {
+    let max_wait = max_wait.unwrap_or(dst.wait_config.ami_copy);
+
+    let source = describe_image(src, &ami.id)
+        .await?
+        .ok_or_else(|| Error::InvalidArgument {
+            message: format!("ami {} not found in {}", ami.id, src.region.as_str()),
+        })?;
+
+    let tags = dst.tags_with_defaults(tags);
+
+    let copy_id = AmiId(
+        dst.main
+            .ec2
+            .copy_image()
+            .source_region(src.region.as_str())
+            .source_image_id(ami.id.as_str())
+            .name(source.name.unwrap_or_else(|| ami.id.to_string()))
+            .set_description(source.description)
+            .set_encrypted(encrypt.map(|_| true))
+            .set_kms_key_id(encrypt.map(|key| key.as_str().to_owned()))
+            .tag_specifications(
+                aws_sdk_ec2::types::TagSpecification::builder()
+                    .resource_type(aws_sdk_ec2::types::ResourceType::Image)
+                    .set_tags(Some(tags.clone().into()))
+                    .build(),
+            )
+            .tag_specifications(
+                aws_sdk_ec2::types::TagSpecification::builder()
+                    .resource_type(aws_sdk_ec2::types::ResourceType::Snapshot)
+                    .set_tags(Some(tags.into()))
+                    .build(),
+            )
+            .send()
+            .await?
+            .image_id
+            .ok_or_else(|| Error::UnexpectedNoneValue {
+                entity: "CopyImageOutput.image_id".to_owned(),
+            })?,
+    );
+
+    let copy_id_ref = &copy_id;
+    let image = waiter::poll(dst.waiter_profile, max_wait, || async move {
+        let Some(image) = describe_image(dst, copy_id_ref).await? else {
+            // The copy shows up with a small delay
+            return Ok(None);
+        };
+        match image.state {
+            Some(aws_sdk_ec2::types::ImageState::Available) => Ok(Some(image)),
+            Some(
+                aws_sdk_ec2::types::ImageState::Failed | aws_sdk_ec2::types::ImageState::Error,
+            ) => Err(Error::AmiCopyFailed {
+                ami: copy_id_ref.clone(),
+                message: image
+                    .state_reason
+                    .and_then(|reason| reason.message)
+                    .unwrap_or_default(),
+            }),
+            _ => Ok(None),
+        }
+    })
+    .await?
+    .ok_or_else(|| Error::AmiCopyExceededMaxWait {
+        max_wait,
+        ami: copy_id.clone(),
+    })?;
+
+    image.try_into()
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Tag, Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone)]
+#[tag(translate = manual)]
+pub struct Timestamp(DateTime<Utc>);
+
+impl Timestamp {
+    pub const fn new(value: DateTime<Utc>) -> Self {
+        Self(value)
+    }
+
+    pub const fn inner(&self) -> &DateTime<Utc> {
+        &self.0
+    }
+
+    pub fn now() -> Self {
+        Self(Utc::now())
+    }
+
+    /// Parses RFC 3339 timestamps, with or without fractional seconds. The offset may
+    /// also be missing, in which case the timestamp is taken as UTC.
+    pub fn parse(value: &str) -> Result<Self, chrono::ParseError> {
+        DateTime::parse_from_rfc3339(value)
+            .map(|timestamp| Self(timestamp.with_timezone(&Utc)))
+            .or_else(|_| {
+                chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f")
+                    .map(|timestamp| Self(timestamp.and_utc()))
+            })
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl TryFrom<RawTagValue> for Timestamp {
+    type Error = ParseTagValueError;
+
+    fn try_from(value: RawTagValue) -> Result<Self, Self::Error> {
+        Self::parse(value.as_str()).map_err(|e| ParseTagValueError::InvalidValue {
+            value,
+            message: format!("failed parsing timestamp: {e}"),
+        })
+    }
+}
+
+/// Tag values only keep whole seconds, so a timestamp like [`Timestamp::now()`] does not
+/// round-trip through a tag: it comes back truncated to the second.
+impl From<Timestamp> for RawTagValue {
+    fn from(value: Timestamp) -> Self {
+        Self::new(value.0.format("%Y-%m-%dT%H:%M:%S").to_string())
+    }
+}
+
+impl TryFrom<aws_sdk_ec2::primitives::DateTime> for Timestamp {
+    type Error = Error;
+
+    fn try_from(value: aws_sdk_ec2::primitives::DateTime) -> Result<Self, Self::Error> {
+        Ok(Self(
+            DateTime::from_timestamp(value.secs(), value.subsec_nanos()).ok_or_else(|| {
+                Error::InvalidTimestampError {
+                    value: value.to_string(),
+                    message: "timestamp out of range".to_owned(),
+                }
+            })?,
+        ))
+    }
+}
+
+impl From<Timestamp> for aws_sdk_ec2::primitives::DateTime {
+    fn from(value: Timestamp) -> Self {
+        Self::from_secs(value.0.timestamp())
+    }
+}
+
+struct RawImageCreationDate(String);
+
+impl TryFrom<RawImageCreationDate> for Timestamp {
+    type Error = Error;
+
+    fn try_from(value: RawImageCreationDate) -> Result<Self, Self::Error> {
+        Self::parse(&value.0).map_err(|e| Error::InvalidTimestampError {
+            value: value.0,
+            message: format!("failed parsing timestamp: {e}"),
+        })
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
 pub struct Ip(net::IpAddr);
 
 impl Ip {
@@ -501,6 +1940,31 @@ impl fmt::Display for Ip {
     }
 }
 
+/// The names of all A and AAAA records in `records` that contain `ip`
+fn referencing_records(
+    records: &[aws_sdk_route53::types::ResourceRecordSet],
+    ip: &Ip,
+) -> Vec<String> {
+    records
+        .iter()
+        .filter(|record| {
+            matches!(
+                record.r#type,
+                aws_sdk_route53::types::RrType::A | aws_sdk_route53::types::RrType::Aaaa
+            )
+        })
+        .filter(|record| {
+            record.resource_records().iter().any(|value| {
+                value
+                    .value()
+                    .parse::<net::IpAddr>()
+                    .is_ok_and(|value| value == ip.0)
+            })
+        })
+        .map(|record| record.name.clone())
+        .collect()
+}
+
 string_newtype!(EipAllocationId);
 
 impl EipAllocationId {
@@ -513,12 +1977,18 @@ impl EipAllocationId {
     }
 }
 
+wrap_aws_enum!(DomainType);
+
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Debug)]
 pub struct Eip {
-    pub allocation_id: EipAllocationId,
-    pub ip: Ip,
-    pub associated_instance: Option<InstanceId>,
+    allocation_id: EipAllocationId,
+    ip: Ip,
+    associated_instance: Option<InstanceId>,
+    association_id: Option<String>,
+    domain: Option<DomainType>,
+    network_border_group: Option<String>,
+    tags: TagList,
 }
 
 impl TryFrom<aws_sdk_ec2::types::Address> for Eip {
@@ -536,55 +2006,564 @@ impl TryFrom<aws_sdk_ec2::types::Address> for Eip {
         Ok(Self {
             ip: Ip(extract!(public_ip)?.parse()?),
             associated_instance: address.instance_id.map(InstanceId),
+            association_id: address.association_id,
             allocation_id: EipAllocationId(extract!(allocation_id)?),
+            domain: address.domain.map(DomainType),
+            network_border_group: address.network_border_group,
+            tags: address.tags.unwrap_or_default().try_into()?,
         })
     }
 }
 
-impl Eip {
-    pub async fn attach_to_instance(
-        &self,
-        client: &RegionClient,
-        new_instance: &Instance,
-    ) -> Result<(), Error> {
-        let _association_id = client
-            .main
-            .ec2
-            .associate_address()
-            .allocation_id(self.allocation_id.as_str())
-            .instance_id(new_instance.instance_id().as_str())
-            .send()
-            .await?;
+impl fmt::Display for Eip {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_named(f, self.allocation_id.as_str(), self.name())
+    }
+}
+
+impl Eip {
+    pub const fn allocation_id(&self) -> &EipAllocationId {
+        &self.allocation_id
+    }
+
+    pub const fn ip(&self) -> &Ip {
+        &self.ip
+    }
+
+    pub const fn associated_instance(&self) -> Option<&InstanceId> {
+        self.associated_instance.as_ref()
+    }
+
+    /// Set while the address is associated, with an instance or with a network
+    /// interface only
+    pub fn association_id(&self) -> Option<&str> {
+        self.association_id.as_deref()
+    }
+
+    /// Whether the address is for use in a VPC or EC2-Classic
+    pub const fn domain(&self) -> Option<&DomainType> {
+        self.domain.as_ref()
+    }
+
+    pub fn network_border_group(&self) -> Option<&str> {
+        self.network_border_group.as_deref()
+    }
+
+    pub const fn tags(&self) -> &TagList {
+        &self.tags
+    }
+
+    /// The value of the `Name` tag
+    pub fn name(&self) -> Option<&str> {
+        name_of(&self.tags)
+    }
+
+    /// Allocates a new VPC address carrying `tags`.
+    pub async fn allocate(client: &RegionClient, tags: &TagList) -> Result<Self, Error> {
+        let tags = client.tags_with_defaults(tags);
+
+        let output = client
+            .main
+            .ec2
+            .allocate_address()
+            .domain(aws_sdk_ec2::types::DomainType::Vpc)
+            .tag_specifications(
+                aws_sdk_ec2::types::TagSpecification::builder()
+                    .resource_type(aws_sdk_ec2::types::ResourceType::ElasticIp)
+                    .set_tags(Some(tags.clone().into()))
+                    .build(),
+            )
+            .send()
+            .await?;
+
+        Ok(Self {
+            allocation_id: EipAllocationId(output.allocation_id.ok_or_else(|| {
+                Error::UnexpectedNoneValue {
+                    entity: "AllocateAddressOutput.allocation_id".to_owned(),
+                }
+            })?),
+            ip: Ip(output
+                .public_ip
+                .ok_or_else(|| Error::UnexpectedNoneValue {
+                    entity: "AllocateAddressOutput.public_ip".to_owned(),
+                })?
+                .parse()?),
+            associated_instance: None,
+            association_id: None,
+            domain: output.domain.map(DomainType),
+            network_border_group: output.network_border_group,
+            tags,
+        })
+    }
+
+    /// Releases the address. Fails with [`Error::ResourceProtected`] if the address carries
+    /// the protection tag of `client`, unless `protection` is [`Protection::Override`].
+    pub async fn release(
+        &self,
+        client: &RegionClient,
+        protection: Protection,
+    ) -> Result<(), Error> {
+        client.check_protection(&self.allocation_id, self.tags(), protection)?;
+
+        let _output = client
+            .main
+            .ec2
+            .release_address()
+            .allocation_id(self.allocation_id.as_str())
+            .send()
+            .await?;
+
+        Ok(())
+    }
+
+    /// Like [`release()`](Self::release()), but fails with [`Error::StillReferenced`] while
+    /// an A record in one of `zones` still points at the address
+    pub async fn release_safe(
+        &self,
+        client: &RegionClient,
+        zones: &[Route53Zone],
+        protection: Protection,
+    ) -> Result<(), Error> {
+        let mut records = Vec::new();
+        for zone in zones {
+            records.extend(referencing_records(
+                &pagination::list_resource_record_sets(client, zone.hosted_zone_id.as_str())
+                    .await?,
+                &self.ip,
+            ));
+        }
+
+        if !records.is_empty() {
+            return Err(Error::StillReferenced {
+                ip: self.ip.clone(),
+                records,
+            });
+        }
+
+        self.release(client, protection).await
+    }
+
+    pub async fn attach_to_instance(
+        &self,
+        client: &RegionClient,
+        new_instance: &Instance,
+    ) -> Result<(), Error> {
+        let _association_id = client
+            .main
+            .ec2
+            .associate_address()
+            .allocation_id(self.allocation_id.as_str())
+            .instance_id(new_instance.instance_id().as_str())
+            .send()
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn set_tags(&self, client: &RegionClient, tags: TagList) -> Result<(), Error> {
+        let _output = client
+            .main
+            .ec2
+            .delete_tags()
+            .resources(self.allocation_id.as_str())
+            .send()
+            .await?;
+
+        let _output = client
+            .main
+            .ec2
+            .create_tags()
+            .resources(self.allocation_id.as_str())
+            .set_tags(Some(client.tags_with_defaults(&tags).into()))
+            .send()
+            .await?;
+
+        Ok(())
+    }
+}
+
+const ADDRESS_LIMIT_EXCEEDED: &str = "AddressLimitExceeded";
+
+/// Allocates an EIP with `tags`, waiting for at most `max_wait` while the address quota
+/// of the account is exhausted.
+///
+/// If `release_unassociated` is given, unassociated addresses matching it are released
+/// whenever the quota is hit, e.g. leftovers of an address pool. Addresses carrying the
+/// protection tag of `client` are never released.
+///
+/// If `max_wait` is `None`, the [`WaitConfig`] of `client` applies.
+pub async fn allocate_eip_or_wait(
+    client: &RegionClient,
+    tags: &TagList,
+    max_wait: Option<Duration>,
+    release_unassociated: Option<&query::TagQuery>,
+) -> Result<Eip, Error> {
+    let max_wait = max_wait.unwrap_or(client.wait_config.eip_quota);
+
+    waiter::poll(client.waiter_profile, max_wait, || async move {
+        match Eip::allocate(client, tags).await {
+            Ok(eip) => Ok(Some(eip)),
+            Err(e) if e.code() == Some(ADDRESS_LIMIT_EXCEEDED) => {
+                if let Some(release_query) = release_unassociated {
+                    for eip in query::find_eips(client, release_query).await? {
+                        // Addresses of e.g. NAT gateways are associated with a
+                        // network interface, but not with an instance
+                        if eip.association_id().is_none() {
+                            match eip.release(client, Protection::Enforce).await {
+                                Ok(()) | Err(Error::ResourceProtected { .. }) => {}
+                                Err(e) => return Err(e),
+                            }
+                        }
+                    }
+                }
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    })
+    .await?
+    .ok_or(Error::EipQuotaExceededMaxWait { max_wait })
+}
+
+wrap_aws_enum!(VolumeType);
+wrap_aws_enum!(VolumeState);
+wrap_aws_enum!(VolumeModificationState);
+
+/// Progress of a [`Volume::modify()`]
+#[derive(Debug, Clone)]
+pub struct VolumeModification {
+    pub state: VolumeModificationState,
+    pub progress_percent: u8,
+    /// Why the modification failed, if it did
+    pub status_message: Option<String>,
+}
+
+impl TryFrom<aws_sdk_ec2::types::VolumeModification> for VolumeModification {
+    type Error = Error;
+
+    fn try_from(modification: aws_sdk_ec2::types::VolumeModification) -> Result<Self, Self::Error> {
+        Ok(Self {
+            state: VolumeModificationState(modification.modification_state.ok_or_else(|| {
+                Error::UnexpectedNoneValue {
+                    entity: "VolumeModification.modification_state".to_owned(),
+                }
+            })?),
+            progress_percent: modification
+                .progress
+                .and_then(|progress| u8::try_from(progress.clamp(0, 100)).ok())
+                .unwrap_or_default(),
+            status_message: modification.status_message,
+        })
+    }
+}
+
+string_newtype!(VolumeId);
+
+impl VolumeId {
+    pub const fn new(value: String) -> Self {
+        Self(value)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Volume {
+    id: VolumeId,
+    size_gib: i32,
+    volume_type: VolumeType,
+    state: VolumeState,
+    availability_zone: AvailabilityZone,
+    attached_instances: Vec<InstanceId>,
+    tags: TagList,
+    #[cfg(feature = "raw")]
+    raw: aws_sdk_ec2::types::Volume,
+}
+
+impl TryFrom<aws_sdk_ec2::types::Volume> for Volume {
+    type Error = Error;
+
+    fn try_from(volume: aws_sdk_ec2::types::Volume) -> Result<Self, Self::Error> {
+        #[cfg(feature = "raw")]
+        let raw = volume.clone();
+
+        macro_rules! extract {
+            ($field:ident) => {
+                volume.$field.ok_or_else(|| Error::UnexpectedNoneValue {
+                    entity: stringify!($field).to_owned(),
+                })
+            };
+        }
+
+        Ok(Self {
+            id: VolumeId(extract!(volume_id)?),
+            size_gib: extract!(size)?,
+            volume_type: VolumeType(extract!(volume_type)?),
+            state: VolumeState(extract!(state)?),
+            availability_zone: AvailabilityZone(extract!(availability_zone)?),
+            attached_instances: volume
+                .attachments
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|attachment| attachment.instance_id.map(InstanceId))
+                .collect(),
+            tags: volume.tags.unwrap_or_default().try_into()?,
+            #[cfg(feature = "raw")]
+            raw,
+        })
+    }
+}
+
+impl fmt::Display for Volume {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_named(f, self.id.as_str(), self.name())
+    }
+}
+
+impl Volume {
+    pub const fn id(&self) -> &VolumeId {
+        &self.id
+    }
+
+    pub const fn size_gib(&self) -> i32 {
+        self.size_gib
+    }
+
+    pub const fn volume_type(&self) -> &VolumeType {
+        &self.volume_type
+    }
+
+    pub const fn state(&self) -> &VolumeState {
+        &self.state
+    }
+
+    pub const fn availability_zone(&self) -> &AvailabilityZone {
+        &self.availability_zone
+    }
+
+    pub fn attached_instances(&self) -> &[InstanceId] {
+        &self.attached_instances
+    }
+
+    pub const fn tags(&self) -> &TagList {
+        &self.tags
+    }
+
+    /// The value of the `Name` tag
+    pub fn name(&self) -> Option<&str> {
+        name_of(&self.tags)
+    }
+
+    /// Changes size, type and IOPS of the volume while it stays in use. Fields that are
+    /// `None` are left unchanged.
+    ///
+    /// The new size and type apply once the modification reaches the `optimizing`
+    /// state, see [`wait_until_optimizing_complete()`](Self::wait_until_optimizing_complete()).
+    /// The file system on the volume has to be extended separately.
+    pub async fn modify(
+        &self,
+        client: &RegionClient,
+        new_size_gib: Option<i32>,
+        new_type: Option<&VolumeType>,
+        new_iops: Option<i32>,
+    ) -> Result<VolumeModification, Error> {
+        if new_size_gib.is_none() && new_type.is_none() && new_iops.is_none() {
+            return Err(Error::InvalidArgument {
+                message: format!("no modification given for volume {}", self.id),
+            });
+        }
+
+        client
+            .main
+            .ec2
+            .modify_volume()
+            .volume_id(self.id.as_str())
+            .set_size(new_size_gib)
+            .set_volume_type(new_type.map(|volume_type| volume_type.inner().clone()))
+            .set_iops(new_iops)
+            .send()
+            .await?
+            .volume_modification
+            .ok_or_else(|| Error::UnexpectedNoneValue {
+                entity: "ModifyVolumeOutput.volume_modification".to_owned(),
+            })?
+            .try_into()
+    }
+
+    /// The latest modification of the volume, or `None` if it was never modified
+    pub async fn modification(
+        &self,
+        client: &RegionClient,
+    ) -> Result<Option<VolumeModification>, Error> {
+        client
+            .main
+            .ec2
+            .describe_volumes_modifications()
+            .volume_ids(self.id.as_str())
+            .send()
+            .await?
+            .volumes_modifications
+            .and_then(|modifications| modifications.into_iter().next())
+            .map(VolumeModification::try_from)
+            .transpose()
+    }
+
+    /// Waits for at most `max_wait` until the latest modification of the volume has
+    /// completed, calling `on_progress` after each poll.
+    ///
+    /// If `max_wait` is `None`, the [`WaitConfig`] of `client` applies.
+    pub async fn wait_until_optimizing_complete(
+        &self,
+        client: &RegionClient,
+        max_wait: Option<Duration>,
+        on_progress: impl Fn(&VolumeModification),
+    ) -> Result<(), Error> {
+        let max_wait = max_wait.unwrap_or(client.wait_config.volume_modification);
+        let on_progress = &on_progress;
+
+        waiter::poll(client.waiter_profile, max_wait, || async move {
+            let Some(modification) = self.modification(client).await? else {
+                return Err(Error::InvalidArgument {
+                    message: format!("volume {} has no modification", self.id),
+                });
+            };
+            on_progress(&modification);
+
+            match *modification.state.inner() {
+                aws_sdk_ec2::types::VolumeModificationState::Completed => Ok(Some(())),
+                aws_sdk_ec2::types::VolumeModificationState::Failed => {
+                    Err(Error::VolumeModificationFailed {
+                        volume: self.id.clone(),
+                        message: modification.status_message.unwrap_or_else(|| {
+                            format!("stopped at {}%", modification.progress_percent)
+                        }),
+                    })
+                }
+                _ => Ok(None),
+            }
+        })
+        .await?
+        .ok_or_else(|| Error::VolumeModificationExceededMaxWait {
+            max_wait,
+            volume: self.id.clone(),
+        })
+    }
+
+    /// The volume as returned by the SDK, for fields that are not exposed otherwise
+    #[cfg(feature = "raw")]
+    pub const fn as_aws(&self) -> &aws_sdk_ec2::types::Volume {
+        &self.raw
+    }
+
+    /// See [`as_aws()`](Self::as_aws())
+    #[cfg(feature = "raw")]
+    pub fn into_aws(self) -> aws_sdk_ec2::types::Volume {
+        self.raw
+    }
+}
+
+string_newtype!(CloudfrontDistributionId, validated);
+
+impl CloudfrontDistributionId {
+    pub const fn new(value: String) -> Self {
+        Self(value)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::str::FromStr for CloudfrontDistributionId {
+    type Err = Error;
+
+    /// Accepts IDs like `E2QWRUHAPOMQZL`, which consist of uppercase letters and digits
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if !s.is_empty()
+            && s.chars()
+                .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+        {
+            Ok(Self(s.to_owned()))
+        } else {
+            Err(Error::InvalidArgument {
+                message: format!("invalid cloudfront distribution id \"{s}\""),
+            })
+        }
+    }
+}
+
+string_newtype!(OriginAccessControlId);
+
+impl OriginAccessControlId {
+    pub const fn new(value: String) -> Self {
+        Self(value)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+string_newtype!(AcmCertificateArn);
 
-        Ok(())
+impl AcmCertificateArn {
+    pub const fn new(value: String) -> Self {
+        Self(value)
     }
 
-    pub async fn set_tags(&self, client: &RegionClient, tags: TagList) -> Result<(), Error> {
-        let _output = client
-            .main
-            .ec2
-            .delete_tags()
-            .resources(self.allocation_id.as_str())
-            .send()
-            .await?;
-
-        let _output = client
-            .main
-            .ec2
-            .create_tags()
-            .resources(self.allocation_id.as_str())
-            .set_tags(Some(tags.into()))
-            .send()
-            .await?;
-
-        Ok(())
+    pub fn as_str(&self) -> &str {
+        &self.0
     }
 }
 
-string_newtype!(CloudfrontDistributionId);
+/// Result of [`CloudfrontDistribution::setup_s3_origin_access_control()`]
+#[derive(Debug, Clone)]
+pub struct S3OriginAccess {
+    pub origin_access_control: OriginAccessControlId,
+    /// Bucket policy that has to be set on the bucket to grant the distribution read
+    /// access
+    pub bucket_policy: String,
+}
+
+/// Creates an origin access control that signs all requests to S3 origins.
+#[expect(
+    clippy::missing_panics_doc,
+    reason = "only expect() on builder instances"
+)]
+pub async fn create_origin_access_control(
+    cdn: &CdnClient,
+    name: &str,
+) -> Result<OriginAccessControlId, Error> {
+    cdn.cloudfront
+        .create_origin_access_control()
+        .origin_access_control_config(
+            aws_sdk_cloudfront::types::OriginAccessControlConfig::builder()
+                .name(name)
+                .signing_protocol(
+                    aws_sdk_cloudfront::types::OriginAccessControlSigningProtocols::Sigv4,
+                )
+                .signing_behavior(
+                    aws_sdk_cloudfront::types::OriginAccessControlSigningBehaviors::Always,
+                )
+                .origin_access_control_origin_type(
+                    aws_sdk_cloudfront::types::OriginAccessControlOriginTypes::S3,
+                )
+                .build()
+                .expect("builder has missing fields"),
+        )
+        .send()
+        .await?
+        .origin_access_control
+        .map(|oac| OriginAccessControlId(oac.id))
+        .ok_or_else(|| Error::UnexpectedNoneValue {
+            entity: "CreateOriginAccessControlOutput.origin_access_control".to_owned(),
+        })
+}
 
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum CloudfrontDistributionStatus {
     Deployed,
@@ -613,9 +2592,17 @@ impl From<String> for CloudfrontDistributionStatus {
     }
 }
 
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Debug, Clone)]
-pub struct EfsId(String);
+string_newtype!(EfsId);
+
+impl EfsId {
+    pub const fn new(value: String) -> Self {
+        Self(value)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
 
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Debug, Clone)]
@@ -625,9 +2612,19 @@ pub struct Efs {
 }
 
 impl Efs {
+    pub const fn id(&self) -> &EfsId {
+        &self.id
+    }
+
     pub fn fs_dns_name(&self) -> String {
         format!("{}.efs.{}.amazonaws.com", self.id.0, self.region.as_str())
     }
+
+    /// The recommended options to mount the file system with efs-utils, optionally
+    /// through `access_point`. See [`efs`] for rendering them.
+    pub fn mount_options(&self, access_point: Option<&efs::AccessPointId>) -> efs::MountOptions {
+        efs::MountOptions::new(self.id.clone(), access_point.cloned())
+    }
 }
 
 impl TryFrom<(aws_sdk_efs::types::FileSystemDescription, Region)> for Efs {
@@ -645,6 +2642,16 @@ impl TryFrom<(aws_sdk_efs::types::FileSystemDescription, Region)> for Efs {
 
 string_newtype!(CloudfrontDistributionDomain);
 
+impl CloudfrontDistributionDomain {
+    pub const fn new(value: String) -> Self {
+        Self(value)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
 impl From<String> for CloudfrontDistributionDomain {
     fn from(value: String) -> Self {
         Self(value)
@@ -652,7 +2659,7 @@ impl From<String> for CloudfrontDistributionDomain {
 }
 
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct CloudfrontOrigin {
     id: CloudfrontOriginId,
     domain: CloudfrontOriginDomain,
@@ -671,6 +2678,16 @@ impl CloudfrontOrigin {
 string_newtype!(CloudfrontOriginId);
 string_newtype!(CloudfrontOriginDomain);
 
+impl CloudfrontOriginId {
+    pub const fn new(value: String) -> Self {
+        Self(value)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
 impl PartialEq<str> for CloudfrontOriginId {
     fn eq(&self, other: &str) -> bool {
         self.0 == other
@@ -678,6 +2695,10 @@ impl PartialEq<str> for CloudfrontOriginId {
 }
 
 impl CloudfrontOriginDomain {
+    pub const fn new(value: String) -> Self {
+        Self(value)
+    }
+
     pub fn as_str(&self) -> &str {
         &self.0
     }
@@ -698,13 +2719,123 @@ impl From<aws_sdk_cloudfront::types::Origin> for CloudfrontOrigin {
     }
 }
 
+/// The edge locations a distribution is served from
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CloudfrontPriceClass {
+    All,
+    /// North America and Europe only
+    Only100,
+    /// Price class 100 plus Asia, Middle East and Africa
+    Only200,
+    /// A price class introduced after this crate was written, e.g. `PriceClass_None`
+    Unknown(String),
+}
+
+impl CloudfrontPriceClass {
+    fn into_aws(self) -> aws_sdk_cloudfront::types::PriceClass {
+        match self {
+            Self::All => aws_sdk_cloudfront::types::PriceClass::PriceClassAll,
+            Self::Only100 => aws_sdk_cloudfront::types::PriceClass::PriceClass100,
+            Self::Only200 => aws_sdk_cloudfront::types::PriceClass::PriceClass200,
+            Self::Unknown(value) => aws_sdk_cloudfront::types::PriceClass::from(value.as_str()),
+        }
+    }
+}
+
+impl From<aws_sdk_cloudfront::types::PriceClass> for CloudfrontPriceClass {
+    fn from(value: aws_sdk_cloudfront::types::PriceClass) -> Self {
+        match value {
+            aws_sdk_cloudfront::types::PriceClass::PriceClassAll => Self::All,
+            aws_sdk_cloudfront::types::PriceClass::PriceClass100 => Self::Only100,
+            aws_sdk_cloudfront::types::PriceClass::PriceClass200 => Self::Only200,
+            other => Self::Unknown(other.as_str().to_owned()),
+        }
+    }
+}
+
+/// Countries, as ISO 3166-1 alpha-2 codes, that may or may not access a distribution
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CloudfrontGeoRestriction {
+    None,
+    Allow(Vec<String>),
+    Deny(Vec<String>),
+    /// A restriction type introduced after this crate was written
+    Unknown {
+        restriction_type: String,
+        countries: Vec<String>,
+    },
+}
+
+impl CloudfrontGeoRestriction {
+    fn to_aws(&self) -> Result<aws_sdk_cloudfront::types::Restrictions, Error> {
+        let (restriction_type, countries) = match *self {
+            Self::None => (aws_sdk_cloudfront::types::GeoRestrictionType::None, vec![]),
+            Self::Allow(ref countries) => (
+                aws_sdk_cloudfront::types::GeoRestrictionType::Whitelist,
+                countries.clone(),
+            ),
+            Self::Deny(ref countries) => (
+                aws_sdk_cloudfront::types::GeoRestrictionType::Blacklist,
+                countries.clone(),
+            ),
+            Self::Unknown {
+                ref restriction_type,
+                ref countries,
+            } => (
+                aws_sdk_cloudfront::types::GeoRestrictionType::from(restriction_type.as_str()),
+                countries.clone(),
+            ),
+        };
+
+        Ok(aws_sdk_cloudfront::types::Restrictions::builder()
+            .geo_restriction(
+                aws_sdk_cloudfront::types::GeoRestriction::builder()
+                    .restriction_type(restriction_type)
+                    .quantity(i32::try_from(countries.len()).map_err(|e| {
+                        Error::InvalidArgument {
+                            message: format!("too many countries: {e}"),
+                        }
+                    })?)
+                    .set_items((!countries.is_empty()).then_some(countries))
+                    .build()
+                    .expect("builder has missing fields"),
+            )
+            .build()
+            .expect("builder has missing fields"))
+    }
+}
+
+impl From<Option<aws_sdk_cloudfront::types::Restrictions>> for CloudfrontGeoRestriction {
+    fn from(value: Option<aws_sdk_cloudfront::types::Restrictions>) -> Self {
+        let Some(restriction) = value.and_then(|restrictions| restrictions.geo_restriction) else {
+            return Self::None;
+        };
+
+        let countries = restriction.items.unwrap_or_default();
+        match restriction.restriction_type {
+            aws_sdk_cloudfront::types::GeoRestrictionType::None => Self::None,
+            aws_sdk_cloudfront::types::GeoRestrictionType::Whitelist => Self::Allow(countries),
+            aws_sdk_cloudfront::types::GeoRestrictionType::Blacklist => Self::Deny(countries),
+            other => Self::Unknown {
+                restriction_type: other.as_str().to_owned(),
+                countries,
+            },
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct CloudfrontDistribution {
-    pub id: CloudfrontDistributionId,
-    pub status: CloudfrontDistributionStatus,
-    pub domain: CloudfrontDistributionDomain,
-    pub origins: Vec<CloudfrontOrigin>,
+    id: CloudfrontDistributionId,
+    arn: String,
+    status: CloudfrontDistributionStatus,
+    domain: CloudfrontDistributionDomain,
+    origins: Vec<CloudfrontOrigin>,
+    price_class: CloudfrontPriceClass,
+    geo_restriction: CloudfrontGeoRestriction,
 }
 
 impl TryFrom<aws_sdk_cloudfront::types::DistributionSummary> for CloudfrontDistribution {
@@ -715,16 +2846,217 @@ impl TryFrom<aws_sdk_cloudfront::types::DistributionSummary> for CloudfrontDistr
     ) -> Result<Self, Self::Error> {
         Ok(Self {
             id: CloudfrontDistributionId(distribution.id),
+            arn: distribution.arn,
             status: distribution.status.into(),
             domain: distribution.domain_name.into(),
             origins: distribution.origins.map_or_else(Vec::new, |origins| {
                 origins.items.into_iter().map(Into::into).collect()
             }),
+            price_class: distribution.price_class.into(),
+            geo_restriction: distribution.restrictions.into(),
         })
     }
 }
 
+pub(crate) async fn distribution_tags(cdn: &CdnClient, arn: &str) -> Result<TagList, Error> {
+    Ok(TagList::from_vec(
+        cdn.cloudfront
+            .list_tags_for_resource()
+            .resource(arn)
+            .send()
+            .await?
+            .tags
+            .and_then(|tags| tags.items)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|tag| RawTag::new(tag.key, tag.value.unwrap_or_default()))
+            .collect(),
+    ))
+}
+
 impl CloudfrontDistribution {
+    /// Applies `update` to the current configuration of the distribution and writes it
+    /// back. The write fails if the distribution was changed concurrently.
+    async fn update_config(
+        &self,
+        cdn: &CdnClient,
+        update: impl FnOnce(&mut aws_sdk_cloudfront::types::DistributionConfig) -> Result<(), Error>
+            + Send,
+    ) -> Result<(), Error> {
+        cdn.check_ownership(&self.id).await?;
+
+        let output = cdn
+            .cloudfront
+            .get_distribution_config()
+            .id(self.id.as_str())
+            .send()
+            .await?;
+
+        let etag = output.e_tag.ok_or_else(|| Error::UnexpectedNoneValue {
+            entity: "GetDistributionConfigOutput.e_tag".to_owned(),
+        })?;
+        let mut config = output
+            .distribution_config
+            .ok_or_else(|| Error::UnexpectedNoneValue {
+                entity: "GetDistributionConfigOutput.distribution_config".to_owned(),
+            })?;
+
+        update(&mut config)?;
+
+        let _output = cdn
+            .cloudfront
+            .update_distribution()
+            .id(self.id.as_str())
+            .if_match(etag)
+            .distribution_config(config)
+            .send()
+            .await?;
+
+        Ok(())
+    }
+
+    /// Makes the distribution access the S3 origin `origin` via the origin access
+    /// control `oac`.
+    pub async fn set_origin_access_control(
+        &self,
+        cdn: &CdnClient,
+        origin: &CloudfrontOriginId,
+        oac: &OriginAccessControlId,
+    ) -> Result<(), Error> {
+        self.update_config(cdn, |config| {
+            let origin_config = config
+                .origins
+                .as_mut()
+                .and_then(|origins| {
+                    origins
+                        .items
+                        .iter_mut()
+                        .find(|item| origin == item.id.as_str())
+                })
+                .ok_or_else(|| Error::InvalidArgument {
+                    message: format!("distribution {} has no origin {origin}", self.id),
+                })?;
+
+            origin_config.origin_access_control_id = Some(oac.as_str().to_owned());
+            // A legacy origin access identity must not be set together with an OAC
+            if let Some(s3_config) = origin_config.s3_origin_config.as_mut() {
+                s3_config.origin_access_identity = String::new();
+            }
+
+            Ok(())
+        })
+        .await
+    }
+
+    /// Bucket policy that grants this distribution read access to all objects in
+    /// `bucket`. The bucket has to be in the partition of the distribution, e.g.
+    /// `aws-cn`.
+    pub fn s3_bucket_policy(&self, bucket: &str) -> String {
+        format!(
+            r#"{{"Version":"2012-10-17","Statement":[{{"Sid":"AllowCloudFrontServicePrincipalReadOnly","Effect":"Allow","Principal":{{"Service":"cloudfront.amazonaws.com"}},"Action":"s3:GetObject","Resource":"arn:{}:s3:::{bucket}/*","Condition":{{"StringEquals":{{"AWS:SourceArn":"{}"}}}}}}]}}"#,
+            self.partition(),
+            self.arn
+        )
+    }
+
+    /// e.g. `aws` or `aws-cn`, taken from the ARN
+    fn partition(&self) -> &str {
+        self.arn.split(':').nth(1).unwrap_or("aws")
+    }
+
+    /// Replaces the alternate domain names of the distribution with `domains`, served
+    /// with the ACM certificate `certificate`. The certificate has to be in us-east-1
+    /// and cover all `domains`.
+    #[expect(
+        clippy::missing_panics_doc,
+        reason = "only expect() on builder instances"
+    )]
+    pub async fn set_aliases(
+        &self,
+        cdn: &CdnClient,
+        domains: &[String],
+        certificate: &AcmCertificateArn,
+    ) -> Result<(), Error> {
+        let quantity = i32::try_from(domains.len()).map_err(|e| Error::InvalidArgument {
+            message: format!("too many aliases: {e}"),
+        })?;
+
+        self.update_config(cdn, |config| {
+            config.aliases = Some(
+                aws_sdk_cloudfront::types::Aliases::builder()
+                    .quantity(quantity)
+                    .set_items(Some(domains.to_vec()))
+                    .build()
+                    .expect("builder has missing fields"),
+            );
+            config.viewer_certificate = Some(
+                aws_sdk_cloudfront::types::ViewerCertificate::builder()
+                    .cloud_front_default_certificate(false)
+                    .acm_certificate_arn(certificate.as_str())
+                    .ssl_support_method(aws_sdk_cloudfront::types::SslSupportMethod::SniOnly)
+                    .minimum_protocol_version(
+                        aws_sdk_cloudfront::types::MinimumProtocolVersion::TlSv122021,
+                    )
+                    .build(),
+            );
+
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn set_geo_restriction(
+        &self,
+        cdn: &CdnClient,
+        geo_restriction: &CloudfrontGeoRestriction,
+    ) -> Result<(), Error> {
+        let restrictions = geo_restriction.to_aws()?;
+
+        self.update_config(cdn, |config| {
+            config.restrictions = Some(restrictions);
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn set_price_class(
+        &self,
+        cdn: &CdnClient,
+        price_class: CloudfrontPriceClass,
+    ) -> Result<(), Error> {
+        self.update_config(cdn, |config| {
+            config.price_class = Some(price_class.into_aws());
+            Ok(())
+        })
+        .await
+    }
+
+    /// Creates an origin access control named `name`, attaches it to the S3 origin
+    /// `origin` and returns the bucket policy that `bucket` needs.
+    pub async fn setup_s3_origin_access_control(
+        &self,
+        cdn: &CdnClient,
+        name: &str,
+        origin: &CloudfrontOriginId,
+        bucket: &str,
+    ) -> Result<S3OriginAccess, Error> {
+        let oac = create_origin_access_control(cdn, name).await?;
+        self.set_origin_access_control(cdn, origin, &oac).await?;
+
+        Ok(S3OriginAccess {
+            bucket_policy: self.s3_bucket_policy(bucket),
+            origin_access_control: oac,
+        })
+    }
+
+    pub const fn id(&self) -> &CloudfrontDistributionId {
+        &self.id
+    }
+
+    pub fn arn(&self) -> &str {
+        &self.arn
+    }
+
     pub fn origins(&self) -> &[CloudfrontOrigin] {
         &self.origins
     }
@@ -736,21 +3068,44 @@ impl CloudfrontDistribution {
     pub const fn status(&self) -> &CloudfrontDistributionStatus {
         &self.status
     }
+
+    pub const fn price_class(&self) -> &CloudfrontPriceClass {
+        &self.price_class
+    }
+
+    pub const fn geo_restriction(&self) -> &CloudfrontGeoRestriction {
+        &self.geo_restriction
+    }
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone)]
 pub struct ProfileName(String);
 
 impl ProfileName {
     pub const fn new(value: String) -> Self {
         Self(value)
     }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
 }
 
 #[derive(Clone)]
 pub struct ProfileConfig {
     pub profile_name_main: ProfileName,
     pub profile_name_cdn: ProfileName,
+    /// Overrides the endpoint of all clients, e.g. for LocalStack
+    pub endpoint_url: Option<String>,
+    /// Identifies the application in the user agent of all requests, e.g. for CloudTrail
+    pub app_name: Option<aws_config::AppName>,
+    /// Appended to the user agent of all requests, e.g. `mytool/1.2.0`. Unlike
+    /// `app_name`, this is not restricted in its characters.
+    pub user_agent_suffix: Option<String>,
+    /// Records all mutating calls of all clients, see [`oplog`]
+    pub operation_log: Option<oplog::OperationLog>,
+    /// Receives lifecycle events of resources, see [`notify`]
+    pub notifier: Option<std::sync::Arc<dyn notify::Notifier>>,
 }
 
 pub async fn load_sdk_clients<const C: usize>(
@@ -760,57 +3115,232 @@ pub async fn load_sdk_clients<const C: usize>(
     let mut region_clients = vec![];
 
     for region in regions {
-        let base_config = || {
-            aws_config::ConfigLoader::default()
-                .retry_config(RetryConfig::standard())
-                .stalled_stream_protection(
-                    aws_sdk_ec2::config::StalledStreamProtectionConfig::enabled()
-                        .grace_period(Duration::from_secs(5))
-                        .build(),
+        region_clients.push(load_region_client(region, &profile_config, None).await);
+    }
+
+    region_clients
+}
+
+const ASSUME_ROLE_SESSION_NAME: &str = "aws-lib";
+
+string_newtype!(RoleArn);
+
+impl RoleArn {
+    pub const fn new(value: String) -> Self {
+        Self(value)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Credentials for assuming `role` with the credentials of `profile_name`
+async fn role_credentials(
+    region: Region,
+    profile_name: &ProfileName,
+    role: Option<&RoleArn>,
+) -> Option<aws_sdk_ec2::config::SharedCredentialsProvider> {
+    match role {
+        Some(role) => Some(aws_sdk_ec2::config::SharedCredentialsProvider::new(
+            aws_config::sts::AssumeRoleProvider::builder(role.as_str())
+                .session_name(ASSUME_ROLE_SESSION_NAME)
+                .configure(
+                    &aws_config::defaults(aws_config::BehaviorVersion::latest())
+                        .profile_name(&profile_name.0)
+                        .region(region.name())
+                        .load()
+                        .await,
                 )
-                .behavior_version(aws_config::BehaviorVersion::latest())
+                .build()
+                .await,
+        )),
+        None => None,
+    }
+}
+
+/// Loads the SDK configuration for `profile_name` in `region`
+async fn load_sdk_config(
+    region: Region,
+    profile_config: &ProfileConfig,
+    profile_name: &ProfileName,
+    credentials: Option<&aws_sdk_ec2::config::SharedCredentialsProvider>,
+) -> aws_config::SdkConfig {
+    let loader = aws_config::ConfigLoader::default()
+        .retry_config(RetryConfig::standard())
+        .stalled_stream_protection(
+            aws_sdk_ec2::config::StalledStreamProtectionConfig::enabled()
+                .grace_period(Duration::from_secs(5))
+                .build(),
+        )
+        .behavior_version(aws_config::BehaviorVersion::latest());
+
+    let loader = match profile_config.endpoint_url {
+        Some(ref endpoint_url) => loader.endpoint_url(endpoint_url),
+        None => loader,
+    };
+
+    let loader = match profile_config.app_name {
+        Some(ref app_name) => loader.app_name(app_name.clone()),
+        None => loader,
+    };
+
+    let loader = match credentials {
+        Some(credentials) => loader.credentials_provider(credentials.clone()),
+        None => loader,
+    };
+
+    loader
+        .profile_name(&profile_name.0)
+        .region(region.name())
+        .load()
+        .await
+}
+
+/// Builds an SDK client from `$config`, with the interceptors of `$profile_config`.
+/// Operations are recorded for `$region`.
+macro_rules! sdk_client {
+    ($sdk:ident, $config:expr, $profile_config:expr, $region:expr) => {{
+        let builder = $sdk::config::Builder::from($config);
+        #[cfg(feature = "metrics")]
+        let builder = builder.interceptor(telemetry::MetricsInterceptor::new($region));
+        let builder = match $profile_config.user_agent_suffix {
+            Some(ref suffix) => {
+                builder.interceptor(user_agent::UserAgentSuffixInterceptor::new(suffix.clone()))
+            }
+            None => builder,
+        };
+        let builder = match $profile_config.operation_log {
+            Some(ref log) => {
+                builder.interceptor(oplog::OperationLogInterceptor::new(log.clone(), $region))
+            }
+            None => builder,
         };
+        let builder = match $profile_config.notifier {
+            Some(ref notifier) => builder.interceptor(notify::NotifierInterceptor::new(
+                std::sync::Arc::clone(notifier),
+                $region,
+            )),
+            None => builder,
+        };
+        $sdk::Client::from_conf(builder.build())
+    }};
+}
 
-        let config = base_config()
-            .profile_name(&profile_config.profile_name_main.0)
-            .region(region.name())
-            .load()
-            .await;
-
-        let config_cdn = base_config()
-            .profile_name(&profile_config.profile_name_cdn.0)
-            .region(region.name())
-            .load()
-            .await;
-
-        // Cloudformation needs always be run in us-east-1
-        let config_cloudformation = base_config()
-            .profile_name(&profile_config.profile_name_cdn.0)
-            .region(Region::UsEast1.as_str())
-            .load()
-            .await;
-
-        let ec2_client = aws_sdk_ec2::Client::new(&config);
-        let cloudfront_client = aws_sdk_cloudfront::Client::new(&config_cdn);
-        let efs_client = aws_sdk_efs::Client::new(&config);
-        let route53_client = aws_sdk_route53::Client::new(&config);
-        let cloudformation_client = aws_sdk_cloudformation::Client::new(&config_cloudformation);
-
-        region_clients.push(RegionClient {
-            region,
-            main: RegionClientMain {
-                ec2: ec2_client,
-                efs: efs_client,
-                route53: route53_client,
-            },
-            cdn: RegionClientCdn {
-                cloudfront: cloudfront_client,
-                cloudformation: cloudformation_client,
-            },
-        });
+/// Loads the clients for `region`. If `role` is given, all clients assume that role,
+/// using the credentials of the main profile to do so.
+///
+/// CloudFront and CloudFormation are not included, see [`load_cdn_client()`].
+pub async fn load_region_client(
+    region: Region,
+    profile_config: &ProfileConfig,
+    role: Option<&RoleArn>,
+) -> RegionClient {
+    let credentials = role_credentials(region, &profile_config.profile_name_main, role).await;
+
+    let config = load_sdk_config(
+        region,
+        profile_config,
+        &profile_config.profile_name_main,
+        credentials.as_ref(),
+    )
+    .await;
+
+    // The pricing API is only available in a few regions
+    #[cfg(feature = "pricing")]
+    let config_pricing = load_sdk_config(
+        Region::UsEast1,
+        profile_config,
+        &profile_config.profile_name_main,
+        credentials.as_ref(),
+    )
+    .await;
+
+    macro_rules! client {
+        ($sdk:ident, $config:expr) => {
+            sdk_client!($sdk, $config, profile_config, region)
+        };
     }
 
-    region_clients
+    RegionClient {
+        region,
+        main: RegionClientMain {
+            ec2: client!(aws_sdk_ec2, &config),
+            efs: client!(aws_sdk_efs, &config),
+            route53: client!(aws_sdk_route53, &config),
+            #[cfg(feature = "budgets")]
+            budgets: client!(aws_sdk_budgets, &config),
+            #[cfg(feature = "cloudtrail")]
+            cloudtrail: client!(aws_sdk_cloudtrail, &config),
+            #[cfg(feature = "rightsizing")]
+            cloudwatch: client!(aws_sdk_cloudwatch, &config),
+            #[cfg(feature = "ecs")]
+            cloudwatchlogs: client!(aws_sdk_cloudwatchlogs, &config),
+            #[cfg(feature = "rightsizing")]
+            computeoptimizer: client!(aws_sdk_computeoptimizer, &config),
+            #[cfg(feature = "cost-explorer")]
+            costexplorer: client!(aws_sdk_costexplorer, &config),
+            #[cfg(feature = "datasync")]
+            datasync: client!(aws_sdk_datasync, &config),
+            #[cfg(feature = "ecs")]
+            ecs: client!(aws_sdk_ecs, &config),
+            #[cfg(feature = "eks")]
+            eks: client!(aws_sdk_eks, &config),
+            #[cfg(feature = "groups")]
+            elbv2: client!(aws_sdk_elasticloadbalancingv2, &config),
+            #[cfg(feature = "findings")]
+            guardduty: client!(aws_sdk_guardduty, &config),
+            #[cfg(feature = "preflight")]
+            iam: client!(aws_sdk_iam, &config),
+            #[cfg(feature = "findings")]
+            inspector2: client!(aws_sdk_inspector2, &config),
+            #[cfg(feature = "lightsail")]
+            lightsail: client!(aws_sdk_lightsail, &config),
+            #[cfg(feature = "pricing")]
+            pricing: client!(aws_sdk_pricing, &config_pricing),
+            #[cfg(feature = "tag-scan")]
+            resourcegroupstagging: client!(aws_sdk_resourcegroupstagging, &config),
+            #[cfg(feature = "deploy")]
+            s3: client!(aws_sdk_s3, &config),
+            #[cfg(feature = "ssm")]
+            ssm: client!(aws_sdk_ssm, &config),
+            sts: client!(aws_sdk_sts, &config),
+        },
+        protection_tag: RawTag::new(DEFAULT_PROTECTION_TAG_KEY.to_owned(), true),
+        ownership_tag: None,
+        waiter_profile: WaiterProfile::for_endpoint(config.endpoint_url()),
+        wait_config: WaitConfig::default(),
+        default_tags: TagList::new(),
+        profile: ClientProfile::new(profile_config.profile_name_main.clone(), role.cloned()),
+    }
+}
+
+/// Loads the clients for CloudFront and CloudFormation from the CDN profile of
+/// `profile_config`. If `role` is given, the clients assume that role, using the
+/// credentials of the CDN profile to do so.
+///
+/// Operations of these clients are recorded for [`Region::UsEast1`], e.g. in an
+/// [`OperationLog`](oplog::OperationLog).
+pub async fn load_cdn_client(profile_config: &ProfileConfig, role: Option<&RoleArn>) -> CdnClient {
+    let credentials = role_credentials(CDN_REGION, &profile_config.profile_name_cdn, role).await;
+
+    let config = load_sdk_config(
+        CDN_REGION,
+        profile_config,
+        &profile_config.profile_name_cdn,
+        credentials.as_ref(),
+    )
+    .await;
+
+    CdnClient {
+        cloudfront: sdk_client!(aws_sdk_cloudfront, &config, profile_config, CDN_REGION),
+        cloudformation: sdk_client!(aws_sdk_cloudformation, &config, profile_config, CDN_REGION),
+        ownership_tag: None,
+        waiter_profile: WaiterProfile::for_endpoint(config.endpoint_url()),
+        wait_config: WaitConfig::default(),
+        default_tags: TagList::new(),
+        profile: ClientProfile::new(profile_config.profile_name_cdn.clone(), role.cloned()),
+    }
 }
 
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -829,11 +3359,21 @@ impl Account {
     }
 }
 
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Debug, Clone)]
-pub struct HostedZoneId(String);
+string_newtype!(HostedZoneId);
+
+impl HostedZoneId {
+    pub const fn new(value: String) -> Self {
+        Self(value)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+string_newtype!(VpcId);
 
-impl HostedZoneId {
+impl VpcId {
     pub const fn new(value: String) -> Self {
         Self(value)
     }
@@ -843,37 +3383,192 @@ impl HostedZoneId {
     }
 }
 
+/// Whether a hosted zone resolves publicly or only inside its associated VPCs
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZoneVisibility {
+    Public,
+    Private,
+}
+
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Debug, Clone)]
 pub struct Route53Zone {
     hosted_zone_id: HostedZoneId,
     name: String,
+    visibility: ZoneVisibility,
 }
 
-impl Route53Zone {
-    pub async fn find_by_name(client: &RegionClient, name: &str) -> Result<Option<Self>, Error> {
-        Ok(client
+pub(crate) async fn hosted_zone_tags(
+    client: &RegionClient,
+    id: &HostedZoneId,
+) -> Result<TagList, Error> {
+    Ok(TagList::from_vec(
+        client
             .main
             .route53
-            .list_hosted_zones()
-            .into_paginator()
-            .items()
+            .list_tags_for_resource()
+            .resource_type(aws_sdk_route53::types::TagResourceType::Hostedzone)
+            .resource_id(id.as_str())
             .send()
-            .try_collect()
+            .await?
+            .resource_tag_set
+            .and_then(|tag_set| tag_set.tags)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|tag| Some(RawTag::new(tag.key?, tag.value.unwrap_or_default())))
+            .collect(),
+    ))
+}
+
+impl Route53Zone {
+    async fn find(
+        client: &RegionClient,
+        name: &str,
+        visibility: Option<ZoneVisibility>,
+    ) -> Result<Option<Self>, Error> {
+        Ok(pagination::list_hosted_zones(client)
             .await?
             .into_iter()
             .filter(|zone| zone.name == name)
-            .map(Into::into)
-            .next())
+            .map(Self::from)
+            .find(|zone| visibility.map_or(true, |visibility| zone.visibility == visibility)))
+    }
+
+    pub async fn find_by_name(client: &RegionClient, name: &str) -> Result<Option<Self>, Error> {
+        Self::find(client, name, None).await
+    }
+
+    /// Like [`find_by_name()`](Self::find_by_name()), but only considers private zones.
+    /// Use this for split-horizon setups, where a public and a private zone share the
+    /// same name.
+    pub async fn find_private_by_name(
+        client: &RegionClient,
+        name: &str,
+    ) -> Result<Option<Self>, Error> {
+        Self::find(client, name, Some(ZoneVisibility::Private)).await
+    }
+
+    /// Creates a private hosted zone that is resolvable only inside `vpc`. `vpc` has to
+    /// be in the region of `client`. The zone carries the default tags and the ownership
+    /// tag of `client`. If tagging fails, the zone is deleted again and the tagging error
+    /// returned.
+    pub async fn create_private(
+        client: &RegionClient,
+        name: &str,
+        vpc: &VpcId,
+    ) -> Result<Self, Error> {
+        let zone: Self = client
+            .main
+            .route53
+            .create_hosted_zone()
+            .name(name)
+            .caller_reference(format!(
+                "{name}-{}",
+                Utc::now().timestamp_nanos_opt().unwrap_or_default()
+            ))
+            .vpc(client.route53_vpc(vpc))
+            .hosted_zone_config(
+                aws_sdk_route53::types::HostedZoneConfig::builder()
+                    .private_zone(true)
+                    .build(),
+            )
+            .send()
+            .await?
+            .hosted_zone
+            .ok_or_else(|| Error::UnexpectedNoneValue {
+                entity: "CreateHostedZoneOutput.hosted_zone".to_owned(),
+            })?
+            .into();
+
+        let tags = client.tags_with_defaults(&TagList::from_vec(
+            client.ownership_tag.iter().cloned().collect(),
+        ));
+
+        if !tags.as_slice().is_empty() {
+            let tagged = client
+                .main
+                .route53
+                .change_tags_for_resource()
+                .resource_type(aws_sdk_route53::types::TagResourceType::Hostedzone)
+                .resource_id(zone.hosted_zone_id.as_str())
+                .set_add_tags(Some(
+                    tags.as_slice()
+                        .iter()
+                        .map(|tag| {
+                            aws_sdk_route53::types::Tag::builder()
+                                .key(tag.key().as_str())
+                                .value(tag.value().as_str())
+                                .build()
+                        })
+                        .collect(),
+                ))
+                .send()
+                .await;
+
+            if let Err(e) = tagged {
+                // An untagged zone could not be changed through `client` afterwards,
+                // so do not leave it behind. The tagging error is the one to report.
+                let _cleanup = client
+                    .main
+                    .route53
+                    .delete_hosted_zone()
+                    .id(zone.hosted_zone_id.as_str())
+                    .send()
+                    .await;
+                return Err(e.into());
+            }
+        }
+
+        Ok(zone)
+    }
+
+    /// Makes this private zone resolvable inside `vpc` as well. `vpc` has to be in the
+    /// region of `client`.
+    pub async fn associate_vpc(&self, client: &RegionClient, vpc: &VpcId) -> Result<(), Error> {
+        client.check_zone_ownership(&self.hosted_zone_id).await?;
+
+        let _output = client
+            .main
+            .route53
+            .associate_vpc_with_hosted_zone()
+            .hosted_zone_id(self.hosted_zone_id.as_str())
+            .vpc(client.route53_vpc(vpc))
+            .send()
+            .await?;
+
+        Ok(())
     }
 
-    pub const fn new(name: String, hosted_zone_id: HostedZoneId) -> Self {
+    /// Use [`get()`](Self::get()) if the visibility of the zone is not known
+    pub const fn new(
+        name: String,
+        hosted_zone_id: HostedZoneId,
+        visibility: ZoneVisibility,
+    ) -> Self {
         Self {
             hosted_zone_id,
             name,
+            visibility,
         }
     }
 
+    /// The zone `hosted_zone_id` as described by the API, including its visibility
+    pub async fn get(client: &RegionClient, hosted_zone_id: &HostedZoneId) -> Result<Self, Error> {
+        Ok(client
+            .main
+            .route53
+            .get_hosted_zone()
+            .id(hosted_zone_id.as_str())
+            .send()
+            .await?
+            .hosted_zone
+            .ok_or_else(|| Error::UnexpectedNoneValue {
+                entity: "GetHostedZoneOutput.hosted_zone".to_owned(),
+            })?
+            .into())
+    }
+
     pub const fn hosted_zone_id(&self) -> &HostedZoneId {
         &self.hosted_zone_id
     }
@@ -881,11 +3576,20 @@ impl Route53Zone {
     pub fn name(&self) -> &str {
         &self.name
     }
+
+    pub const fn visibility(&self) -> ZoneVisibility {
+        self.visibility
+    }
 }
 
 impl From<aws_sdk_route53::types::HostedZone> for Route53Zone {
     fn from(zone: aws_sdk_route53::types::HostedZone) -> Self {
         Self {
+            visibility: if zone.config.is_some_and(|config| config.private_zone) {
+                ZoneVisibility::Private
+            } else {
+                ZoneVisibility::Public
+            },
             hosted_zone_id: HostedZoneId(zone.id),
             name: zone.name,
         }
@@ -901,8 +3605,59 @@ pub struct NewEc2Config<'a> {
     pub subnet_id: &'a SubnetId,
     pub user_data: &'a str,
     pub tags: &'a TagList,
+    pub capacity_reservation: Option<&'a capacity::CapacityReservationId>,
+    /// Publish metrics in 1-minute instead of 5-minute intervals
+    pub detailed_monitoring: bool,
+    /// What happens when the instance is shut down from within the operating system.
+    /// If `None`, the AWS default (stop) applies.
+    pub shutdown_behavior: Option<&'a ShutdownBehavior>,
+    /// If `None`, the root volume is created as defined in the AMI
+    pub root_volume: Option<&'a BlockDeviceConfig>,
+}
+
+/// Used if the AMI does not name its root device
+const DEFAULT_ROOT_DEVICE_NAME: &str = "/dev/xvda";
+
+/// Settings for the root EBS volume of a new instance. `None` keeps the value of the
+/// AMI's block device mapping.
+#[derive(Debug, Clone, Default)]
+pub struct BlockDeviceConfig {
+    pub size_gib: Option<i32>,
+    /// E.g. gp3 or io2
+    pub volume_type: Option<VolumeType>,
+    /// Only for gp3, io1 and io2
+    pub iops: Option<i32>,
+    /// In MiB/s, only for gp3
+    pub throughput: Option<i32>,
+    pub encrypted: Option<bool>,
+    pub delete_on_termination: Option<bool>,
+}
+
+impl BlockDeviceConfig {
+    fn to_mapping(&self, ami: &Ami) -> aws_sdk_ec2::types::BlockDeviceMapping {
+        aws_sdk_ec2::types::BlockDeviceMapping::builder()
+            .device_name(ami.root_device())
+            .ebs(
+                aws_sdk_ec2::types::EbsBlockDevice::builder()
+                    .set_volume_size(self.size_gib)
+                    .set_volume_type(
+                        self.volume_type
+                            .as_ref()
+                            .map(|volume_type| volume_type.inner().clone()),
+                    )
+                    .set_iops(self.iops)
+                    .set_throughput(self.throughput)
+                    .set_encrypted(self.encrypted)
+                    .set_delete_on_termination(self.delete_on_termination)
+                    .build(),
+            )
+            .build()
+    }
 }
 
+/// Launches an instance with the defaults of [`NewEc2Config`]: no capacity
+/// reservation, basic monitoring, the AWS shutdown behavior and the root volume of the
+/// AMI. Use [`launch_ec2_instance()`] for everything else.
 pub async fn start_ec2_instance<'a>(
     client: &RegionClient,
     ami: &'a Ami,
@@ -913,24 +3668,49 @@ pub async fn start_ec2_instance<'a>(
     subnet_id: &'a SubnetId,
     user_data: &'a str,
     tags: &'a TagList,
+) -> Result<Instance, Error> {
+    launch_ec2_instance(
+        client,
+        &NewEc2Config {
+            ami,
+            instance_type,
+            security_group,
+            instance_profile_name,
+            instance_keypair_name,
+            subnet_id,
+            user_data,
+            tags,
+            capacity_reservation: None,
+            detailed_monitoring: false,
+            shutdown_behavior: None,
+            root_volume: None,
+        },
+    )
+    .await
+}
+
+/// Launches an instance as described by `config`
+pub async fn launch_ec2_instance(
+    client: &RegionClient,
+    config: &NewEc2Config<'_>,
 ) -> Result<Instance, Error> {
     Instance::try_from_aws(
         client
             .main
             .ec2
             .run_instances()
-            .image_id(ami.id.as_str())
-            .instance_type(instance_type.clone().into_inner())
-            .key_name(instance_keypair_name.as_str())
+            .image_id(config.ami.id.as_str())
+            .instance_type(config.instance_type.clone().into_inner())
+            .key_name(config.instance_keypair_name.as_str())
             .min_count(1)
             .max_count(1)
-            .security_group_ids(security_group.id.as_str())
-            .subnet_id(subnet_id.as_str())
-            .user_data(user_data)
+            .security_group_ids(config.security_group.id.as_str())
+            .subnet_id(config.subnet_id.as_str())
+            .user_data(config.user_data)
             .tag_specifications(
                 aws_sdk_ec2::types::TagSpecification::builder()
                     .resource_type(aws_sdk_ec2::types::ResourceType::Instance)
-                    .set_tags(Some(tags.clone().into()))
+                    .set_tags(Some(client.tags_with_defaults(config.tags).into()))
                     .build(),
             )
             .metadata_options(
@@ -940,12 +3720,32 @@ pub async fn start_ec2_instance<'a>(
                     .instance_metadata_tags(aws_sdk_ec2::types::InstanceMetadataTagsState::Enabled)
                     .build(),
             )
+            .monitoring(
+                aws_sdk_ec2::types::RunInstancesMonitoringEnabled::builder()
+                    .enabled(config.detailed_monitoring)
+                    .build(),
+            )
+            .set_instance_initiated_shutdown_behavior(
+                config
+                    .shutdown_behavior
+                    .map(|behavior| behavior.inner().clone()),
+            )
             .disable_api_termination(true)
             .iam_instance_profile(
                 aws_sdk_ec2::types::IamInstanceProfileSpecification::builder()
-                    .name(instance_profile_name.as_str())
+                    .name(config.instance_profile_name.as_str())
                     .build(),
             )
+            .set_capacity_reservation_specification(
+                config
+                    .capacity_reservation
+                    .map(capacity::CapacityReservationId::to_specification),
+            )
+            .set_block_device_mappings(
+                config
+                    .root_volume
+                    .map(|root_volume| vec![root_volume.to_mapping(config.ami)]),
+            )
             .send()
             .await?
             .instances
@@ -957,34 +3757,93 @@ pub async fn start_ec2_instance<'a>(
     )
 }
 
-pub async fn create_cloudformation_stack(
+pub struct FallbackPolicy {
+    /// How often each configuration is tried before moving on to the next one
+    pub attempts_per_config: NonZeroU32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for FallbackPolicy {
+    fn default() -> Self {
+        Self {
+            attempts_per_config: NonZeroU32::MIN.saturating_add(1),
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct FallbackLaunch {
+    pub instance: Instance,
+    /// Index into the list of configurations of the one that succeeded
+    pub config_index: usize,
+    /// Failed attempts before the successful one, by configuration index
+    pub failures: Vec<(usize, Error)>,
+}
+
+/// Launches an instance with the first configuration in `configs` that succeeds.
+///
+/// Capacity and quota errors (see [`ErrorKind`]) make this retry with a jittered
+/// backoff, moving on to the next configuration after
+/// [`FallbackPolicy::attempts_per_config`] attempts. All other errors are returned
+/// immediately. Fails with [`Error::InvalidArgument`] if `configs` is empty.
+pub async fn launch_with_fallback(
     client: &RegionClient,
+    configs: &[NewEc2Config<'_>],
+    policy: &FallbackPolicy,
+) -> Result<FallbackLaunch, Error> {
+    if configs.is_empty() {
+        return Err(Error::InvalidArgument {
+            message: "no launch configuration given".to_owned(),
+        });
+    }
+
+    let mut backoff = backoff::Backoff::new(policy.base_delay, policy.max_delay);
+    let mut failures = vec![];
+
+    for (config_index, config) in configs.iter().enumerate() {
+        for _attempt in 0..policy.attempts_per_config.get() {
+            if !failures.is_empty() {
+                backoff.sleep().await;
+            }
+
+            match launch_ec2_instance(client, config).await {
+                Ok(instance) => {
+                    return Ok(FallbackLaunch {
+                        instance,
+                        config_index,
+                        failures,
+                    })
+                }
+                Err(e) => match e.kind() {
+                    ErrorKind::Capacity | ErrorKind::Quota => failures.push((config_index, e)),
+                    ErrorKind::Throttling | ErrorKind::Other => return Err(e),
+                },
+            }
+        }
+    }
+
+    Err(Error::LaunchFallbackExhausted { failures })
+}
+
+pub async fn create_cloudformation_stack(
+    cdn: &CdnClient,
     name: &str,
     template: &str,
     parameters: &CloudformationParameters,
     tags: &TagList,
 ) -> Result<(), Error> {
-    let _create_stack_output = client
-        .cdn
+    let _create_stack_output = cdn
         .cloudformation
         .create_stack()
         .stack_name(name)
         .template_body(template)
-        .set_parameters(Some(
-            parameters
-                .0
-                .iter()
-                .map(|param| {
-                    aws_sdk_cloudformation::types::Parameter::builder()
-                        .parameter_key(param.key.as_str())
-                        .parameter_value(param.value.as_str())
-                        .build()
-                })
-                .collect(),
-        ))
+        .set_parameters(Some(parameters.to_aws()))
         .disable_rollback(true)
         .capabilities(aws_sdk_cloudformation::types::Capability::CapabilityAutoExpand)
-        .set_tags(Some(tags.clone().into()))
+        .set_tags(Some(cdn.tags_with_defaults(tags).into()))
         .send()
         .await?;
 
@@ -1008,6 +3867,59 @@ impl CloudformationParameters {
     pub const fn new(value: Vec<CloudformationParameter>) -> Self {
         Self(value)
     }
+
+    fn to_aws(&self) -> Vec<aws_sdk_cloudformation::types::Parameter> {
+        self.0
+            .iter()
+            .map(|param| {
+                aws_sdk_cloudformation::types::Parameter::builder()
+                    .parameter_key(param.key.as_str())
+                    .parameter_value(param.value.as_str())
+                    .build()
+            })
+            .collect()
+    }
+}
+
+/// The TTL of records created by [`create_route53_record()`], in seconds
+const RECORD_TTL: i64 = 600;
+
+/// What an A record created by [`create_route53_record()`] points to
+#[derive(Debug, Clone, Copy)]
+pub enum RecordTarget<'a> {
+    Eip(&'a Eip),
+    /// The private IP of an instance, for records in a private zone
+    PrivateIp(&'a Instance),
+}
+
+impl RecordTarget<'_> {
+    fn ip(self) -> Result<String, Error> {
+        match self {
+            Self::Eip(eip) => Ok(eip.ip().to_string()),
+            Self::PrivateIp(instance) => instance
+                .private_ip_address()
+                .map(ToString::to_string)
+                .ok_or_else(|| Error::InvalidArgument {
+                    message: format!("instance {} has no private ip", instance.instance_id()),
+                }),
+        }
+    }
+
+    /// Private IPs must not be published in public zones
+    fn check_zone(self, zone: &Route53Zone) -> Result<(), Error> {
+        if let Self::PrivateIp(instance) = self {
+            if zone.visibility != ZoneVisibility::Private {
+                return Err(Error::InvalidArgument {
+                    message: format!(
+                        "refusing to publish private ip of instance {} in public zone {}",
+                        instance.instance_id(),
+                        zone.name
+                    ),
+                });
+            }
+        }
+        Ok(())
+    }
 }
 
 #[expect(
@@ -1016,10 +3928,15 @@ impl CloudformationParameters {
 )]
 pub async fn create_route53_record(
     client: &RegionClient,
-    eip: &Eip,
+    target: RecordTarget<'_>,
     route53_zone: &Route53Zone,
     fqdn: &str,
 ) -> Result<(), Error> {
+    target.check_zone(route53_zone)?;
+    client
+        .check_zone_ownership(&route53_zone.hosted_zone_id)
+        .await?;
+
     let _change_info = client
         .main
         .route53
@@ -1034,10 +3951,10 @@ pub async fn create_route53_record(
                             aws_sdk_route53::types::ResourceRecordSet::builder()
                                 .name(fqdn)
                                 .r#type(aws_sdk_route53::types::RrType::A)
-                                .ttl(600)
+                                .ttl(RECORD_TTL)
                                 .resource_records(
                                     aws_sdk_route53::types::ResourceRecord::builder()
-                                        .value(eip.ip.to_string())
+                                        .value(target.ip()?)
                                         .build()
                                         .expect("builder has missing fields"),
                                 )
@@ -1056,26 +3973,562 @@ pub async fn create_route53_record(
     Ok(())
 }
 
+/// Streams all instances of the region, fetching further pages only as the stream is
+/// consumed. Throttled pages are requested again after a backoff. Other errors of a
+/// page end up as an `Err` item instead of the instances of that page, and end the
+/// stream.
+pub fn list_instances(
+    client: &RegionClient,
+) -> impl futures_util::Stream<Item = Result<Instance, Error>> {
+    use futures_util::StreamExt as _;
+
+    let ec2 = client.main.ec2.clone();
+
+    pagination::paginate_stream(move |token| {
+        let ec2 = ec2.clone();
+        async move {
+            let output = ec2
+                .describe_instances()
+                .set_next_token(token)
+                .send()
+                .await?;
+            Ok(pagination::Page {
+                items: output.reservations.unwrap_or_default(),
+                next_token: output.next_token,
+            })
+        }
+    })
+    .flat_map(|reservations| {
+        futures_util::stream::iter(match reservations {
+            Ok(reservations) => reservations
+                .into_iter()
+                .flat_map(|reservation| reservation.instances.unwrap_or_default())
+                .map(Instance::try_from_aws)
+                .collect(),
+            Err(e) => vec![Err(e)],
+        })
+    })
+}
+
 pub async fn find_efs(client: &RegionClient, tag: &RawTag) -> Result<Option<Efs>, Error> {
-    let mut found = client
+    query::find_unique(
+        "efs",
+        pagination::list_file_systems(client)
+            .await?
+            .into_iter()
+            .filter(|fs| fs.tags.iter().any(|t| t == tag))
+            .map(|fs| (fs, client.region).try_into())
+            .collect::<Result<Vec<Efs>, Error>>()?,
+    )
+}
+
+/// Copies the tags with the given `keys` from `instance` to all of its attached
+/// volumes and network interfaces, e.g. to make cost allocation tags complete.
+///
+/// Keys that the instance does not carry are ignored.
+pub async fn propagate_tags(
+    client: &RegionClient,
+    instance: &Instance,
+    keys: &[TagKey],
+) -> Result<(), Error> {
+    let tags = TagList::from_vec(
+        keys.iter()
+            .filter_map(|key| instance.get_tag(key.clone()).cloned())
+            .collect(),
+    );
+
+    if tags.as_slice().is_empty() {
+        return Ok(());
+    }
+
+    let attachment_filter = || {
+        aws_sdk_ec2::types::Filter::builder()
+            .name("attachment.instance-id")
+            .values(instance.instance_id().as_str())
+            .build()
+    };
+
+    let mut resources: Vec<String> = pagination::list_volumes(client, vec![attachment_filter()])
+        .await?
+        .into_iter()
+        .filter_map(|volume| volume.volume_id)
+        .collect();
+
+    resources.extend(
+        pagination::list_network_interfaces(client, vec![attachment_filter()])
+            .await?
+            .into_iter()
+            .filter_map(|interface| interface.network_interface_id),
+    );
+
+    if resources.is_empty() {
+        return Ok(());
+    }
+
+    let _output = client
+        .main
+        .ec2
+        .create_tags()
+        .set_resources(Some(resources))
+        .set_tags(Some(tags.into()))
+        .send()
+        .await?;
+
+    Ok(())
+}
+
+/// Points the A record `fqdn` in `zone` to the current public IP of `instance`, with a
+/// TTL of `ttl` seconds. The IP is described anew, not taken from `instance`. Keep
+/// `ttl` short, as the IP changes whenever the instance is stopped or replaced.
+///
+/// The record is only written if it does not point to that IP already. Returns
+/// whether the record was updated.
+#[expect(
+    clippy::missing_panics_doc,
+    reason = "only expect() on builder instances"
+)]
+pub async fn update_dynamic_dns(
+    client: &RegionClient,
+    zone: &Route53Zone,
+    fqdn: &str,
+    instance: &Instance,
+    ttl: i64,
+) -> Result<bool, Error> {
+    // `instance` may have been described long ago, and spot instances change their IP
+    // when they are replaced
+    let ip = instance
+        .describe(client)
+        .await?
+        .ok_or_else(|| Error::InvalidArgument {
+            message: format!("instance {} not found", instance.instance_id()),
+        })?
+        .public_ip_address()
+        .ok_or_else(|| Error::InvalidArgument {
+            message: format!("instance {} has no public ip", instance.instance_id()),
+        })?
+        .to_string();
+
+    let current = client
         .main
-        .efs
-        .describe_file_systems()
-        .into_paginator()
-        .items()
+        .route53
+        .list_resource_record_sets()
+        .hosted_zone_id(zone.hosted_zone_id.as_str())
+        .start_record_name(fqdn)
+        .start_record_type(aws_sdk_route53::types::RrType::A)
+        .max_items(1)
         .send()
-        .try_collect()
         .await?
+        .resource_record_sets
         .into_iter()
-        .filter(|fs| fs.tags.iter().any(|t| t == tag))
-        .map(|fs| (fs, client.region).try_into())
-        .collect::<Result<Vec<Efs>, Error>>()?;
-
-    match (found.len(), found.pop()) {
-        (0, _) => Ok(None),
-        (1, Some(found)) => Ok(Some(found)),
-        _ => Err(Error::MultipleMatches {
-            entity: "efs".to_owned(),
-        }),
+        .find(|record| {
+            record.r#type == aws_sdk_route53::types::RrType::A
+                && record.name.trim_end_matches('.') == fqdn.trim_end_matches('.')
+        });
+
+    if current.is_some_and(|record| {
+        record
+            .resource_records()
+            .iter()
+            .map(aws_sdk_route53::types::ResourceRecord::value)
+            .eq([ip.as_str()])
+    }) {
+        return Ok(false);
+    }
+
+    client.check_zone_ownership(&zone.hosted_zone_id).await?;
+
+    let _change_info = client
+        .main
+        .route53
+        .change_resource_record_sets()
+        .hosted_zone_id(zone.hosted_zone_id.as_str())
+        .change_batch(
+            aws_sdk_route53::types::ChangeBatch::builder()
+                .changes(
+                    aws_sdk_route53::types::Change::builder()
+                        .action(aws_sdk_route53::types::ChangeAction::Upsert)
+                        .resource_record_set(
+                            aws_sdk_route53::types::ResourceRecordSet::builder()
+                                .name(fqdn)
+                                .r#type(aws_sdk_route53::types::RrType::A)
+                                .ttl(ttl)
+                                .resource_records(
+                                    aws_sdk_route53::types::ResourceRecord::builder()
+                                        .value(ip)
+                                        .build()
+                                        .expect("builder has missing fields"),
+                                )
+                                .build()
+                                .expect("builder has missing fields"),
+                        )
+                        .build()
+                        .expect("builder has missing fields"),
+                )
+                .build()
+                .expect("builder has missing fields"),
+        )
+        .send()
+        .await?;
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::SubsecRound as _;
+    use proptest::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn ids_are_ordered_and_hashable() {
+        let mut ids: Vec<SubnetId> = ["subnet-b", "subnet-a", "subnet-b"]
+            .into_iter()
+            .map(|id| id.parse().unwrap())
+            .collect();
+        ids.sort();
+        ids.dedup();
+
+        assert_eq!(
+            ids,
+            vec![
+                SubnetId::from_string("subnet-a".to_owned()),
+                SubnetId::from_string("subnet-b".to_owned())
+            ]
+        );
+        assert_eq!(
+            ids.into_iter()
+                .collect::<std::collections::HashSet<_>>()
+                .len(),
+            2
+        );
+    }
+
+    #[cfg(all(feature = "serde", feature = "serde-tags"))]
+    #[test]
+    fn serialize_error() {
+        let error = Error::InvalidArgument {
+            message: "bad".to_owned(),
+        };
+
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            serde_json::json!({
+                "type": "invalid_argument",
+                "kind": "other",
+                "message": error.to_string(),
+                "code": null,
+                "request_id": null,
+                "http_status": null,
+            })
+        );
+    }
+
+    #[test]
+    fn parse_cloudfront_distribution_id() {
+        assert_eq!(
+            "E2QWRUHAPOMQZL"
+                .parse::<CloudfrontDistributionId>()
+                .unwrap(),
+            CloudfrontDistributionId::new("E2QWRUHAPOMQZL".to_owned())
+        );
+        for (value, reason) in [("e2qwruhapomqzl", "lowercase id"), ("", "empty id")] {
+            assert!(
+                matches!(
+                    value.parse::<CloudfrontDistributionId>(),
+                    Err(Error::InvalidArgument { ref message }) if message.contains("distribution id")
+                ),
+                "{reason} is valid"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_timestamp_formats() {
+        let expected = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+
+        for value in [
+            "2023-11-14T22:13:20",
+            "2023-11-14T22:13:20Z",
+            "2023-11-14T22:13:20.000Z",
+            "2023-11-14T23:13:20+01:00",
+        ] {
+            assert_eq!(
+                Timestamp::parse(value).unwrap(),
+                Timestamp::new(expected),
+                "failed for {value}"
+            );
+        }
+
+        assert_eq!(
+            Timestamp::parse("2023-11-14T22:13:20.123Z")
+                .unwrap()
+                .inner()
+                .timestamp_subsec_millis(),
+            123
+        );
+        assert_eq!(
+            Timestamp::parse("2023-11-14").ok(),
+            None,
+            "date only is valid"
+        );
+    }
+
+    #[test]
+    fn ownership() {
+        let owner = RawTag::new("team".to_owned(), "payments".to_owned());
+        let tags = TagList::from_vec(vec![owner.clone()]);
+
+        check_ownership("Z1", &tags, &owner).unwrap();
+        assert!(matches!(
+            check_ownership("Z1", &TagList::new(), &owner),
+            Err(Error::NotOwned { .. })
+        ));
+        assert!(matches!(
+            check_ownership(
+                "Z1",
+                &tags,
+                &RawTag::new("team".to_owned(), "search".to_owned())
+            ),
+            Err(Error::NotOwned { .. })
+        ));
+    }
+
+    /// Answers every request like CloudFront would for a distribution tagged
+    /// `team=search`, and records the requests it received
+    #[derive(Debug, Clone, Default)]
+    struct FakeCloudfront {
+        requests: std::sync::Arc<std::sync::Mutex<Vec<String>>>,
+    }
+
+    impl aws_smithy_runtime_api::client::http::HttpConnector for FakeCloudfront {
+        fn call(
+            &self,
+            request: aws_smithy_runtime_api::client::orchestrator::HttpRequest,
+        ) -> aws_smithy_runtime_api::client::http::HttpConnectorFuture {
+            self.requests
+                .lock()
+                .unwrap()
+                .push(format!("{} {}", request.method(), request.uri()));
+
+            let body = if request.uri().contains("/tagging") {
+                "<Tags xmlns=\"http://cloudfront.amazonaws.com/doc/2020-05-31/\"><Items>\
+                 <Tag><Key>team</Key><Value>search</Value></Tag></Items></Tags>"
+            } else {
+                "<Distribution xmlns=\"http://cloudfront.amazonaws.com/doc/2020-05-31/\">\
+                 <Id>E1</Id><ARN>arn:aws:cloudfront::123456789012:distribution/E1</ARN>\
+                 <Status>Deployed</Status></Distribution>"
+            };
+
+            aws_smithy_runtime_api::client::http::HttpConnectorFuture::ready(Ok(
+                aws_smithy_runtime_api::http::Response::new(
+                    aws_smithy_runtime_api::http::StatusCode::try_from(200_u16).unwrap(),
+                    aws_smithy_types::body::SdkBody::from(body),
+                ),
+            ))
+        }
+    }
+
+    impl aws_smithy_runtime_api::client::http::HttpClient for FakeCloudfront {
+        fn http_connector(
+            &self,
+            _settings: &aws_smithy_runtime_api::client::http::HttpConnectorSettings,
+            _components: &aws_smithy_runtime_api::client::runtime_components::RuntimeComponents,
+        ) -> aws_smithy_runtime_api::client::http::SharedHttpConnector {
+            aws_smithy_runtime_api::client::http::SharedHttpConnector::new(self.clone())
+        }
+    }
+
+    fn fake_cdn_client(fake: &FakeCloudfront) -> CdnClient {
+        let credentials =
+            aws_credential_types::Credentials::new("AKID", "SECRET", None, None, "test");
+
+        CdnClient {
+            cloudfront: aws_sdk_cloudfront::Client::from_conf(
+                aws_sdk_cloudfront::Config::builder()
+                    .behavior_version(aws_sdk_cloudfront::config::BehaviorVersion::latest())
+                    .region(aws_sdk_cloudfront::config::Region::new("us-east-1"))
+                    .credentials_provider(credentials.clone())
+                    .http_client(fake.clone())
+                    .build(),
+            ),
+            cloudformation: aws_sdk_cloudformation::Client::from_conf(
+                aws_sdk_cloudformation::Config::builder()
+                    .behavior_version(aws_sdk_cloudformation::config::BehaviorVersion::latest())
+                    .region(aws_sdk_cloudformation::config::Region::new("us-east-1"))
+                    .credentials_provider(credentials)
+                    .http_client(fake.clone())
+                    .build(),
+            ),
+            ownership_tag: None,
+            waiter_profile: WaiterProfile::Fast,
+            wait_config: WaitConfig::default(),
+            default_tags: TagList::new(),
+            profile: ClientProfile::new(ProfileName::new("test".to_owned()), None),
+        }
+    }
+
+    #[tokio::test]
+    async fn distribution_changes_refused_unless_owned() {
+        let fake = FakeCloudfront::default();
+        let cdn = fake_cdn_client(&fake)
+            .with_ownership_tag(RawTag::new("team".to_owned(), "payments".to_owned()));
+        let distribution = CloudfrontDistribution {
+            id: CloudfrontDistributionId::new("E1".to_owned()),
+            arn: "arn:aws:cloudfront::123456789012:distribution/E1".to_owned(),
+            status: CloudfrontDistributionStatus::Deployed,
+            domain: CloudfrontDistributionDomain::new("d1.cloudfront.net".to_owned()),
+            origins: Vec::new(),
+            price_class: CloudfrontPriceClass::All,
+            geo_restriction: CloudfrontGeoRestriction::None,
+        };
+
+        assert!(matches!(
+            distribution
+                .set_price_class(&cdn, CloudfrontPriceClass::Only100)
+                .await,
+            Err(Error::NotOwned { .. })
+        ));
+
+        let requests = fake.requests.lock().unwrap();
+        assert!(!requests.is_empty(), "ownership was looked up");
+        assert!(
+            requests.iter().all(|request| request.starts_with("GET ")),
+            "nothing was changed"
+        );
+    }
+
+    #[test]
+    fn s3_bucket_policy_partition() {
+        let distribution = CloudfrontDistribution {
+            id: CloudfrontDistributionId::new("E1".to_owned()),
+            arn: "arn:aws-cn:cloudfront::123456789012:distribution/E1".to_owned(),
+            status: CloudfrontDistributionStatus::Deployed,
+            domain: CloudfrontDistributionDomain::new("d1.cloudfront.cn".to_owned()),
+            origins: Vec::new(),
+            price_class: CloudfrontPriceClass::All,
+            geo_restriction: CloudfrontGeoRestriction::None,
+        };
+
+        let policy = distribution.s3_bucket_policy("assets");
+        assert!(
+            policy.contains(r#""Resource":"arn:aws-cn:s3:::assets/*""#),
+            "{policy}"
+        );
+        assert!(
+            policy.contains(
+                r#""AWS:SourceArn":"arn:aws-cn:cloudfront::123456789012:distribution/E1""#
+            ),
+            "{policy}"
+        );
+    }
+
+    #[test]
+    fn stack_tags_carry_ownership_tag() {
+        let owner = RawTag::new("team".to_owned(), "payments".to_owned());
+        let cdn = fake_cdn_client(&FakeCloudfront::default())
+            .with_default_tags(TagList::from_vec(vec![RawTag::new(
+                "team".to_owned(),
+                "search".to_owned(),
+            )]))
+            .with_ownership_tag(owner.clone());
+
+        let tags = cdn.tags_with_defaults(&TagList::from_vec(vec![
+            RawTag::new("team".to_owned(), "other".to_owned()),
+            RawTag::new("app".to_owned(), "web".to_owned()),
+        ]));
+
+        assert!(tags.as_slice().contains(&owner), "ownership tag wins");
+        assert_eq!(tags.as_slice().len(), 2);
+    }
+
+    #[test]
+    fn user_data_decoding() {
+        let instance = InstanceId("i-1".to_owned());
+
+        assert_eq!(
+            decode_user_data(&instance, "IyEvYmluL3NoCmVjaG8gaGk=").unwrap(),
+            "#!/bin/sh\necho hi"
+        );
+        // Gzipped user data is not decompressed
+        assert!(matches!(
+            decode_user_data(&instance, "H4sI//8="),
+            Err(Error::InvalidResponseError { ref message }) if message.contains("not utf-8")
+        ));
+    }
+
+    #[test]
+    fn architecture_compatibility() {
+        let arm64 = ArchitectureValues::new(aws_sdk_ec2::types::ArchitectureValues::Arm64);
+        let x86_64 = ArchitectureValues::new(aws_sdk_ec2::types::ArchitectureValues::X8664);
+        let graviton = [aws_sdk_ec2::types::ArchitectureType::Arm64];
+
+        assert!(architecture_supported(&arm64, &graviton));
+        assert!(!architecture_supported(&x86_64, &graviton));
+        assert!(architecture_supported(
+            &x86_64,
+            &[
+                aws_sdk_ec2::types::ArchitectureType::I386,
+                aws_sdk_ec2::types::ArchitectureType::X8664,
+            ]
+        ));
+    }
+
+    #[test]
+    fn records_referencing_ip() {
+        let record = |name: &str, record_type, value: &str| {
+            aws_sdk_route53::types::ResourceRecordSet::builder()
+                .name(name)
+                .r#type(record_type)
+                .resource_records(
+                    aws_sdk_route53::types::ResourceRecord::builder()
+                        .value(value)
+                        .build()
+                        .unwrap(),
+                )
+                .build()
+                .unwrap()
+        };
+
+        let records = [
+            record(
+                "web.example.com.",
+                aws_sdk_route53::types::RrType::A,
+                "203.0.113.10",
+            ),
+            record(
+                "db.example.com.",
+                aws_sdk_route53::types::RrType::A,
+                "203.0.113.11",
+            ),
+            record(
+                "txt.example.com.",
+                aws_sdk_route53::types::RrType::Txt,
+                "203.0.113.10",
+            ),
+        ];
+
+        assert_eq!(
+            referencing_records(&records, &Ip::new("203.0.113.10".parse().unwrap())),
+            vec!["web.example.com.".to_owned()]
+        );
+        assert!(
+            referencing_records(&records, &Ip::new("198.51.100.1".parse().unwrap())).is_empty()
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn timestamp_tag_round_trip(
+            secs in 0_i64..4_102_444_800_i64,
+            nanos in 0_u32..1_000_000_000_u32,
+        ) {
+            let timestamp = Timestamp::new(DateTime::from_timestamp(secs, nanos).unwrap());
+            let value: RawTagValue = timestamp.into();
+            prop_assert_eq!(
+                Timestamp::try_from(value).unwrap(),
+                Timestamp::new(timestamp.inner().trunc_subsecs(0))
+            );
+        }
     }
 }
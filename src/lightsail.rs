@@ -0,0 +1,340 @@
+//! Lightsail instances, for small environments that do not need the full EC2 setup
+//!
+//! Lightsail instances are addressed by name instead of an ID. Like EC2 instances,
+//! they carry tags, and stopping or deleting them respects the protection tag of the
+//! [`RegionClient`].
+
+use std::net;
+
+use aws_sdk_lightsail::error::ProvideErrorMetadata as _;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::{
+    tags::{RawTag, TagList},
+    AvailabilityZone, Error, Ip, Protection, RegionClient,
+};
+
+const NOT_FOUND_ERROR_CODE: &str = "NotFoundException";
+
+crate::string_newtype!(InstanceName);
+
+impl InstanceName {
+    pub const fn new(value: String) -> Self {
+        Self(value)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+crate::string_newtype!(StaticIpName);
+
+impl StaticIpName {
+    pub const fn new(value: String) -> Self {
+        Self(value)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InstanceState {
+    Pending,
+    Running,
+    Stopping,
+    Stopped,
+    ShuttingDown,
+    Terminated,
+    /// Lightsail reports more states than EC2, e.g. `rebooting` or `starting`, and
+    /// may add new ones
+    Unknown(String),
+}
+
+impl InstanceState {
+    fn from_name(name: &str) -> Self {
+        match name {
+            "pending" => Self::Pending,
+            "running" => Self::Running,
+            "stopping" => Self::Stopping,
+            "stopped" => Self::Stopped,
+            "shutting-down" => Self::ShuttingDown,
+            "terminated" => Self::Terminated,
+            other => Self::Unknown(other.to_owned()),
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    Tcp,
+    Udp,
+    All,
+}
+
+impl Protocol {
+    fn to_aws(self) -> aws_sdk_lightsail::types::NetworkProtocol {
+        match self {
+            Self::Tcp => aws_sdk_lightsail::types::NetworkProtocol::Tcp,
+            Self::Udp => aws_sdk_lightsail::types::NetworkProtocol::Udp,
+            Self::All => aws_sdk_lightsail::types::NetworkProtocol::All,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PortRule {
+    pub protocol: Protocol,
+    pub from_port: u16,
+    pub to_port: u16,
+    /// The source ranges, e.g. `203.0.113.0/24`. If empty, the port is open to
+    /// everyone.
+    pub cidrs: Vec<String>,
+}
+
+impl PortRule {
+    fn to_aws(&self) -> aws_sdk_lightsail::types::PortInfo {
+        aws_sdk_lightsail::types::PortInfo::builder()
+            .protocol(self.protocol.to_aws())
+            .from_port(i32::from(self.from_port))
+            .to_port(i32::from(self.to_port))
+            .set_cidrs((!self.cidrs.is_empty()).then(|| self.cidrs.clone()))
+            .build()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LaunchConfig<'a> {
+    pub name: &'a InstanceName,
+    pub availability_zone: &'a AvailabilityZone,
+    /// The operating system image, e.g. `ubuntu_24_04`
+    pub blueprint_id: &'a str,
+    /// The instance size, e.g. `nano_3_0`
+    pub bundle_id: &'a str,
+    pub key_pair_name: Option<&'a str>,
+    pub user_data: Option<&'a str>,
+    pub tags: &'a TagList,
+}
+
+fn lightsail_tags(tags: &TagList) -> Vec<aws_sdk_lightsail::types::Tag> {
+    tags.as_slice()
+        .iter()
+        .map(|tag| {
+            aws_sdk_lightsail::types::Tag::builder()
+                .key(tag.key().as_str())
+                .value(tag.value().as_str())
+                .build()
+        })
+        .collect()
+}
+
+fn parse_ip(value: Option<String>) -> Result<Option<Ip>, Error> {
+    value
+        .map(|value| {
+            value
+                .parse::<net::IpAddr>()
+                .map(Ip::new)
+                .map_err(|e| Error::InvalidResponseError {
+                    message: format!("invalid ip address {value}: {e}"),
+                })
+        })
+        .transpose()
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct LightsailInstance {
+    pub name: InstanceName,
+    pub state: InstanceState,
+    pub public_ip: Option<Ip>,
+    pub private_ip: Option<Ip>,
+    /// Whether [`public_ip`](Self::public_ip) is a static IP
+    pub has_static_ip: bool,
+    pub tags: TagList,
+}
+
+impl TryFrom<aws_sdk_lightsail::types::Instance> for LightsailInstance {
+    type Error = Error;
+
+    fn try_from(instance: aws_sdk_lightsail::types::Instance) -> Result<Self, Self::Error> {
+        let state = instance.state.and_then(|state| state.name).ok_or_else(|| {
+            Error::UnexpectedNoneValue {
+                entity: "Instance.state.name".to_owned(),
+            }
+        })?;
+
+        Ok(Self {
+            name: InstanceName(instance.name.ok_or_else(|| Error::UnexpectedNoneValue {
+                entity: "Instance.name".to_owned(),
+            })?),
+            state: InstanceState::from_name(&state),
+            public_ip: parse_ip(instance.public_ip_address)?,
+            private_ip: parse_ip(instance.private_ip_address)?,
+            has_static_ip: instance.is_static_ip.unwrap_or(false),
+            tags: TagList::from_vec(
+                instance
+                    .tags
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter_map(|tag| Some(RawTag::new(tag.key?, tag.value.unwrap_or_default())))
+                    .collect(),
+            ),
+        })
+    }
+}
+
+impl LightsailInstance {
+    /// Creates the instance. It is still pending when this returns, use
+    /// [`get()`](Self::get()) to follow its state.
+    pub async fn create(client: &RegionClient, config: LaunchConfig<'_>) -> Result<(), Error> {
+        let _operations = client
+            .main
+            .lightsail
+            .create_instances()
+            .instance_names(config.name.as_str())
+            .availability_zone(config.availability_zone.to_string())
+            .blueprint_id(config.blueprint_id)
+            .bundle_id(config.bundle_id)
+            .set_key_pair_name(config.key_pair_name.map(ToOwned::to_owned))
+            .set_user_data(config.user_data.map(ToOwned::to_owned))
+            .set_tags(Some(lightsail_tags(
+                &client.tags_with_defaults(config.tags),
+            )))
+            .send()
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn get(client: &RegionClient, name: &InstanceName) -> Result<Option<Self>, Error> {
+        match client
+            .main
+            .lightsail
+            .get_instance()
+            .instance_name(name.as_str())
+            .send()
+            .await
+        {
+            Ok(output) => output.instance.map(TryInto::try_into).transpose(),
+            Err(e) if e.code() == Some(NOT_FOUND_ERROR_CODE) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn check_protection(&self, client: &RegionClient, protection: Protection) -> Result<(), Error> {
+        client.check_protection_of(self.name.as_str(), &self.tags, protection)
+    }
+
+    pub async fn start(&self, client: &RegionClient) -> Result<(), Error> {
+        let _output = client
+            .main
+            .lightsail
+            .start_instance()
+            .instance_name(self.name.as_str())
+            .send()
+            .await?;
+
+        Ok(())
+    }
+
+    /// Fails with [`Error::ResourceProtected`] if the instance carries the protection tag
+    /// of `client`, unless `protection` is [`Protection::Override`].
+    pub async fn stop(&self, client: &RegionClient, protection: Protection) -> Result<(), Error> {
+        self.check_protection(client, protection)?;
+
+        let _output = client
+            .main
+            .lightsail
+            .stop_instance()
+            .instance_name(self.name.as_str())
+            .send()
+            .await?;
+
+        Ok(())
+    }
+
+    /// Fails with [`Error::ResourceProtected`] if the instance carries the protection tag
+    /// of `client`, unless `protection` is [`Protection::Override`].
+    pub async fn delete(self, client: &RegionClient, protection: Protection) -> Result<(), Error> {
+        self.check_protection(client, protection)?;
+
+        let _output = client
+            .main
+            .lightsail
+            .delete_instance()
+            .instance_name(self.name.as_str())
+            .send()
+            .await?;
+
+        Ok(())
+    }
+
+    /// Opens the ports in addition to the ones that are already open
+    pub async fn open_ports(&self, client: &RegionClient, rules: &[PortRule]) -> Result<(), Error> {
+        for rule in rules {
+            let _output = client
+                .main
+                .lightsail
+                .open_instance_public_ports()
+                .instance_name(self.name.as_str())
+                .port_info(rule.to_aws())
+                .send()
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Attaches a static IP allocated with [`allocate_static_ip()`], replacing the
+    /// dynamic public IP of the instance
+    pub async fn attach_static_ip(
+        &self,
+        client: &RegionClient,
+        static_ip: &StaticIpName,
+    ) -> Result<(), Error> {
+        let _output = client
+            .main
+            .lightsail
+            .attach_static_ip()
+            .static_ip_name(static_ip.as_str())
+            .instance_name(self.name.as_str())
+            .send()
+            .await?;
+
+        Ok(())
+    }
+}
+
+pub async fn allocate_static_ip(client: &RegionClient, name: &StaticIpName) -> Result<(), Error> {
+    let _output = client
+        .main
+        .lightsail
+        .allocate_static_ip()
+        .static_ip_name(name.as_str())
+        .send()
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn instance_state_names() {
+        assert_eq!(
+            InstanceState::from_name("shutting-down"),
+            InstanceState::ShuttingDown
+        );
+        assert_eq!(
+            InstanceState::from_name("rebooting"),
+            InstanceState::Unknown("rebooting".to_owned())
+        );
+    }
+}
@@ -0,0 +1,310 @@
+//! Notifications about lifecycle events of resources, e.g. for chat integrations
+//!
+//! Pass a [`Notifier`] in [`ProfileConfig::notifier`](crate::ProfileConfig), and it is
+//! called after every successful call that launches, starts, stops or terminates
+//! instances, changes DNS records or creates stacks, regardless of whether the call
+//! was made through this crate or directly through the SDK clients.
+//!
+//! With the `webhook` feature, [`WebhookNotifier`] posts each event as JSON to a URL.
+
+use std::{fmt, sync::Arc};
+
+use aws_smithy_runtime_api::{
+    box_error::BoxError,
+    client::{
+        interceptors::{
+            context::{
+                BeforeSerializationInterceptorContextRef, FinalizerInterceptorContextRef, Input,
+                Output,
+            },
+            Intercept,
+        },
+        runtime_components::RuntimeComponents,
+    },
+};
+use aws_smithy_types::config_bag::{ConfigBag, Storable, StoreReplace};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::{HostedZoneId, InstanceId, Region, Timestamp};
+
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "snake_case", tag = "kind")
+)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EventKind {
+    InstanceLaunched {
+        instance: InstanceId,
+    },
+    InstanceStarted {
+        instance: InstanceId,
+    },
+    InstanceStopped {
+        instance: InstanceId,
+    },
+    InstanceTerminated {
+        instance: InstanceId,
+    },
+    RecordChanged {
+        zone: HostedZoneId,
+        name: String,
+        /// E.g. `A` or `CNAME`
+        record_type: String,
+        /// `CREATE`, `DELETE` or `UPSERT`
+        action: String,
+    },
+    StackCreated {
+        stack_id: String,
+    },
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct Event {
+    pub region: Region,
+    pub time: Timestamp,
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub kind: EventKind,
+}
+
+/// Receives lifecycle events
+///
+/// [`notify()`](Self::notify()) is called while the SDK call finishes, so it must not
+/// block. Slow deliveries have to be moved off the calling thread.
+pub trait Notifier: fmt::Debug + Send + Sync {
+    fn notify(&self, event: &Event);
+}
+
+/// Events that can only be derived from the request
+fn input_events(input: &Input) -> Vec<EventKind> {
+    let Some(input) = input.downcast_ref::<
+        aws_sdk_route53::operation::change_resource_record_sets::ChangeResourceRecordSetsInput,
+    >() else {
+        return vec![];
+    };
+    let Some(ref zone) = input.hosted_zone_id else {
+        return vec![];
+    };
+
+    input
+        .change_batch
+        .iter()
+        .flat_map(|batch| batch.changes.iter())
+        .filter_map(|change| {
+            let record = change.resource_record_set.as_ref()?;
+            Some(EventKind::RecordChanged {
+                zone: HostedZoneId::new(zone.clone()),
+                name: record.name.clone(),
+                record_type: record.r#type.as_str().to_owned(),
+                action: change.action.as_str().to_owned(),
+            })
+        })
+        .collect()
+}
+
+fn instance_events(
+    instances: Option<&Vec<aws_sdk_ec2::types::InstanceStateChange>>,
+    event: fn(InstanceId) -> EventKind,
+) -> Vec<EventKind> {
+    instances
+        .into_iter()
+        .flatten()
+        .filter_map(|change| change.instance_id.clone())
+        .map(|id| event(InstanceId(id)))
+        .collect()
+}
+
+fn output_events(output: &Output) -> Vec<EventKind> {
+    if let Some(output) =
+        output.downcast_ref::<aws_sdk_ec2::operation::run_instances::RunInstancesOutput>()
+    {
+        return output
+            .instances
+            .iter()
+            .flatten()
+            .filter_map(|instance| instance.instance_id.clone())
+            .map(|id| EventKind::InstanceLaunched {
+                instance: InstanceId(id),
+            })
+            .collect();
+    }
+
+    if let Some(output) =
+        output.downcast_ref::<aws_sdk_ec2::operation::start_instances::StartInstancesOutput>()
+    {
+        return instance_events(output.starting_instances.as_ref(), |instance| {
+            EventKind::InstanceStarted { instance }
+        });
+    }
+
+    if let Some(output) =
+        output.downcast_ref::<aws_sdk_ec2::operation::stop_instances::StopInstancesOutput>()
+    {
+        return instance_events(output.stopping_instances.as_ref(), |instance| {
+            EventKind::InstanceStopped { instance }
+        });
+    }
+
+    if let Some(output) = output
+        .downcast_ref::<aws_sdk_ec2::operation::terminate_instances::TerminateInstancesOutput>(
+    ) {
+        return instance_events(output.terminating_instances.as_ref(), |instance| {
+            EventKind::InstanceTerminated { instance }
+        });
+    }
+
+    if let Some(output) =
+        output.downcast_ref::<aws_sdk_cloudformation::operation::create_stack::CreateStackOutput>()
+    {
+        return output
+            .stack_id
+            .iter()
+            .map(|stack_id| EventKind::StackCreated {
+                stack_id: stack_id.clone(),
+            })
+            .collect();
+    }
+
+    vec![]
+}
+
+#[derive(Debug)]
+struct PendingInputEvents(Vec<EventKind>);
+
+impl Storable for PendingInputEvents {
+    type Storer = StoreReplace<Self>;
+}
+
+#[derive(Debug)]
+pub(crate) struct NotifierInterceptor {
+    notifier: Arc<dyn Notifier>,
+    region: Region,
+}
+
+impl NotifierInterceptor {
+    pub(crate) const fn new(notifier: Arc<dyn Notifier>, region: Region) -> Self {
+        Self { notifier, region }
+    }
+}
+
+impl Intercept for NotifierInterceptor {
+    fn name(&self) -> &'static str {
+        "NotifierInterceptor"
+    }
+
+    fn read_before_execution(
+        &self,
+        context: &BeforeSerializationInterceptorContextRef<'_>,
+        cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        cfg.interceptor_state()
+            .store_put(PendingInputEvents(input_events(context.input())));
+        Ok(())
+    }
+
+    fn read_after_execution(
+        &self,
+        context: &FinalizerInterceptorContextRef<'_>,
+        _runtime_components: &RuntimeComponents,
+        cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        let Some(Ok(output)) = context.output_or_error() else {
+            return Ok(());
+        };
+
+        let time = Timestamp::now();
+        let input_events = cfg
+            .load::<PendingInputEvents>()
+            .map(|events| events.0.clone())
+            .unwrap_or_default();
+
+        for kind in output_events(output).into_iter().chain(input_events) {
+            self.notifier.notify(&Event {
+                region: self.region,
+                time,
+                kind,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Events that wait for delivery by a [`WebhookNotifier`]. Further events are dropped
+/// while the queue is full.
+#[cfg(feature = "webhook")]
+const WEBHOOK_QUEUE_SIZE: usize = 100;
+
+/// Keeps a hanging endpoint from stalling all later deliveries
+#[cfg(feature = "webhook")]
+const WEBHOOK_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Posts every event as JSON to a URL, e.g. an incoming webhook of a chat tool
+///
+/// Events are queued and delivered one after another by a single background thread,
+/// which exits once the notifier and all its clones are dropped. Failed deliveries, and
+/// events that do not fit into the queue, are dropped, as notifications must never fail
+/// or slow down the operation they are about.
+#[cfg(feature = "webhook")]
+#[derive(Debug, Clone)]
+pub struct WebhookNotifier {
+    queue: std::sync::mpsc::SyncSender<String>,
+}
+
+#[cfg(feature = "webhook")]
+impl WebhookNotifier {
+    pub fn new(url: String) -> Self {
+        let (queue, events) = std::sync::mpsc::sync_channel::<String>(WEBHOOK_QUEUE_SIZE);
+
+        let _worker = std::thread::spawn(move || {
+            let agent = ureq::AgentBuilder::new().timeout(WEBHOOK_TIMEOUT).build();
+            for body in events {
+                let _delivered = agent
+                    .post(&url)
+                    .set("Content-Type", "application/json")
+                    .send_string(&body)
+                    .is_ok();
+            }
+        });
+
+        Self { queue }
+    }
+}
+
+#[cfg(feature = "webhook")]
+impl Notifier for WebhookNotifier {
+    fn notify(&self, event: &Event) {
+        let Ok(body) = serde_json::to_string(event) else {
+            return;
+        };
+
+        let _queued = self.queue.try_send(body).is_ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_for_stopped_instances() {
+        let output = Output::erase(
+            aws_sdk_ec2::operation::stop_instances::StopInstancesOutput::builder()
+                .stopping_instances(
+                    aws_sdk_ec2::types::InstanceStateChange::builder()
+                        .instance_id("i-0123")
+                        .build(),
+                )
+                .build(),
+        );
+
+        assert_eq!(
+            output_events(&output),
+            vec![EventKind::InstanceStopped {
+                instance: InstanceId("i-0123".to_owned())
+            }]
+        );
+    }
+}
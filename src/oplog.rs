@@ -0,0 +1,515 @@
+//! A log of all mutating AWS calls of a run, to help with manual cleanup
+//!
+//! Pass an [`OperationLog`] in [`ProfileConfig::operation_log`](crate::ProfileConfig) and
+//! all clients record every call that is not read-only. For calls that can be
+//! reversed, the entry contains a hint how, e.g. "terminate instances i-0123". There is
+//! no automatic rollback, but dumping the log when a run fails shows what was left
+//! behind:
+//!
+//! ```rust,no_run
+//! # use aws_lib::oplog::OperationLog;
+//! # fn f(log: &OperationLog) {
+//! for operation in log.operations().iter().rev() {
+//!     if let Some(ref undo) = operation.undo {
+//!         eprintln!("{}: {undo}", operation.operation);
+//!     }
+//! }
+//! # }
+//! ```
+
+use std::{
+    fmt,
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+};
+
+use aws_smithy_runtime_api::{
+    box_error::BoxError,
+    client::{
+        interceptors::{
+            context::{
+                BeforeSerializationInterceptorContextRef, FinalizerInterceptorContextRef, Input,
+                Output,
+            },
+            Intercept,
+        },
+        orchestrator::Metadata,
+        runtime_components::RuntimeComponents,
+    },
+};
+use aws_smithy_types::config_bag::{ConfigBag, Storable, StoreReplace};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::{
+    teardown::TeardownStep, EfsId, EipAllocationId, HostedZoneId, InstanceId, Region, Timestamp,
+    VolumeId,
+};
+
+/// Operations starting with one of these do not change anything
+const READ_ONLY_PREFIXES: [&str; 6] = ["Describe", "List", "Get", "Lookup", "Search", "Simulate"];
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct Operation {
+    /// E.g. `EC2`
+    pub service: String,
+    /// E.g. `RunInstances`
+    pub operation: String,
+    pub region: Region,
+    pub time: Timestamp,
+    /// Failed calls are recorded too, as some of them have partial effects
+    pub succeeded: bool,
+    /// How to reverse the operation, if it succeeded and is known to be reversible
+    pub undo: Option<String>,
+    /// The steps that reverse the operation automatically, for operations creating
+    /// resources. See [`RollbackGuard`](crate::rollback::RollbackGuard).
+    pub compensation: Vec<TeardownStep>,
+    /// The rollback guards the operation was made under
+    pub scopes: Vec<ScopeId>,
+}
+
+/// Identifies the operations made under a
+/// [`RollbackGuard`](crate::rollback::RollbackGuard)
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ScopeId(u64);
+
+impl ScopeId {
+    /// A scope no operation was made under yet
+    pub(crate) fn next() -> Self {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+        Self(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+tokio::task_local! {
+    /// The scopes of the current task, innermost last
+    static SCOPES: Vec<ScopeId>;
+}
+
+/// Runs `future` under `scope`, in addition to the scopes of the current task. The
+/// interceptor runs in the task making the call, so all operations of `future` are
+/// recorded under `scope`, except for those of tasks it spawns.
+pub(crate) fn in_scope<F: Future>(scope: ScopeId, future: F) -> impl Future<Output = F::Output> {
+    let mut scopes = current_scopes();
+    scopes.push(scope);
+    SCOPES.scope(scopes, future)
+}
+
+pub(crate) fn current_scopes() -> Vec<ScopeId> {
+    SCOPES.try_with(Clone::clone).unwrap_or_default()
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {}:{} in {}",
+            self.time,
+            self.service,
+            self.operation,
+            self.region.as_str()
+        )?;
+        if !self.succeeded {
+            write!(f, " (failed)")?;
+        }
+        if let Some(ref undo) = self.undo {
+            write!(f, ", undo: {undo}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Shared between all clients it is passed to, clones refer to the same log
+#[derive(Debug, Clone, Default)]
+pub struct OperationLog {
+    operations: Arc<Mutex<Vec<Operation>>>,
+}
+
+impl OperationLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// All recorded operations, oldest first
+    pub fn operations(&self) -> Vec<Operation> {
+        self.operations
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    fn push(&self, operation: Operation) {
+        self.operations
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(operation);
+    }
+}
+
+fn is_read_only(operation: &str) -> bool {
+    READ_ONLY_PREFIXES
+        .iter()
+        .any(|prefix| operation.starts_with(prefix))
+}
+
+fn join(ids: impl IntoIterator<Item = String>) -> String {
+    ids.into_iter().collect::<Vec<String>>().join(", ")
+}
+
+/// Undo hints that can only be derived from the request
+fn input_undo_hint(input: &Input) -> Option<String> {
+    if let Some(input) =
+        input.downcast_ref::<aws_sdk_ec2::operation::stop_instances::StopInstancesInput>()
+    {
+        return Some(format!(
+            "start instances {}",
+            join(input.instance_ids.clone().unwrap_or_default())
+        ));
+    }
+
+    if let Some(input) =
+        input.downcast_ref::<aws_sdk_ec2::operation::start_instances::StartInstancesInput>()
+    {
+        return Some(format!(
+            "stop instances {}",
+            join(input.instance_ids.clone().unwrap_or_default())
+        ));
+    }
+
+    if let Some(input) = input.downcast_ref::<
+        aws_sdk_route53::operation::change_resource_record_sets::ChangeResourceRecordSetsInput,
+    >() {
+        let zone = input.hosted_zone_id.as_deref().unwrap_or_default();
+        let changes = input
+            .change_batch
+            .iter()
+            .flat_map(|batch| batch.changes.iter())
+            .filter_map(|change| {
+                let record = change.resource_record_set.as_ref()?;
+                let undo = match change.action {
+                    aws_sdk_route53::types::ChangeAction::Create => "delete",
+                    aws_sdk_route53::types::ChangeAction::Delete => "recreate",
+                    _ => "restore the previous value of",
+                };
+                Some(format!("{undo} record {} {}", record.r#type.as_str(), record.name))
+            });
+        return Some(format!("in zone {zone}: {}", join(changes)));
+    }
+
+    None
+}
+
+/// Created A records are deleted again. Other changes cannot be reversed, as the
+/// previous values are unknown.
+fn input_compensation(input: &Input) -> Vec<TeardownStep> {
+    let Some(input) = input.downcast_ref::<
+        aws_sdk_route53::operation::change_resource_record_sets::ChangeResourceRecordSetsInput,
+    >() else {
+        return vec![];
+    };
+    let Some(ref zone) = input.hosted_zone_id else {
+        return vec![];
+    };
+
+    input
+        .change_batch
+        .iter()
+        .flat_map(|batch| batch.changes.iter())
+        .filter(|change| change.action == aws_sdk_route53::types::ChangeAction::Create)
+        .filter_map(|change| change.resource_record_set.as_ref())
+        .filter(|record| record.r#type == aws_sdk_route53::types::RrType::A)
+        .map(|record| TeardownStep::DeleteRecord {
+            zone: HostedZoneId::new(zone.clone()),
+            fqdn: record.name.clone(),
+            ips: record
+                .resource_records()
+                .iter()
+                .map(|value| value.value().to_owned())
+                .collect(),
+        })
+        .collect()
+}
+
+/// Undo hints for operations that create resources, whose IDs are only known
+/// afterwards
+fn output_undo_hint(output: &Output) -> Option<String> {
+    if let Some(output) =
+        output.downcast_ref::<aws_sdk_ec2::operation::run_instances::RunInstancesOutput>()
+    {
+        return Some(format!(
+            "terminate instances {}",
+            join(
+                output
+                    .instances
+                    .iter()
+                    .flatten()
+                    .filter_map(|instance| instance.instance_id.clone())
+            )
+        ));
+    }
+
+    if let Some(output) =
+        output.downcast_ref::<aws_sdk_ec2::operation::allocate_address::AllocateAddressOutput>()
+    {
+        return output
+            .allocation_id
+            .as_ref()
+            .map(|id| format!("release address {id}"));
+    }
+
+    if let Some(output) =
+        output.downcast_ref::<aws_sdk_ec2::operation::associate_address::AssociateAddressOutput>()
+    {
+        return output
+            .association_id
+            .as_ref()
+            .map(|id| format!("disassociate address association {id}"));
+    }
+
+    if let Some(output) =
+        output.downcast_ref::<aws_sdk_ec2::operation::create_volume::CreateVolumeOutput>()
+    {
+        return output
+            .volume_id
+            .as_ref()
+            .map(|id| format!("delete volume {id}"));
+    }
+
+    if let Some(output) =
+        output.downcast_ref::<aws_sdk_ec2::operation::create_snapshot::CreateSnapshotOutput>()
+    {
+        return output
+            .snapshot_id
+            .as_ref()
+            .map(|id| format!("delete snapshot {id}"));
+    }
+
+    if let Some(output) =
+        output.downcast_ref::<aws_sdk_ec2::operation::create_image::CreateImageOutput>()
+    {
+        return output
+            .image_id
+            .as_ref()
+            .map(|id| format!("deregister image {id} and delete its snapshots"));
+    }
+
+    if let Some(output) =
+        output.downcast_ref::<aws_sdk_ec2::operation::copy_image::CopyImageOutput>()
+    {
+        return output
+            .image_id
+            .as_ref()
+            .map(|id| format!("deregister image {id} and delete its snapshots"));
+    }
+
+    if let Some(output) =
+        output.downcast_ref::<aws_sdk_cloudformation::operation::create_stack::CreateStackOutput>()
+    {
+        return output
+            .stack_id
+            .as_ref()
+            .map(|id| format!("delete stack {id}"));
+    }
+
+    if let Some(output) =
+        output.downcast_ref::<aws_sdk_efs::operation::create_file_system::CreateFileSystemOutput>()
+    {
+        return Some(format!(
+            "delete file system {} and its mount targets",
+            output.file_system_id
+        ));
+    }
+
+    None
+}
+
+fn output_compensation(output: &Output) -> Vec<TeardownStep> {
+    if let Some(output) =
+        output.downcast_ref::<aws_sdk_ec2::operation::run_instances::RunInstancesOutput>()
+    {
+        return output
+            .instances
+            .iter()
+            .flatten()
+            .filter_map(|instance| instance.instance_id.clone())
+            .map(|id| TeardownStep::TerminateInstance(InstanceId(id)))
+            .collect();
+    }
+
+    if let Some(output) =
+        output.downcast_ref::<aws_sdk_ec2::operation::allocate_address::AllocateAddressOutput>()
+    {
+        return output
+            .allocation_id
+            .iter()
+            .map(|id| TeardownStep::ReleaseEip(EipAllocationId(id.clone())))
+            .collect();
+    }
+
+    if let Some(output) =
+        output.downcast_ref::<aws_sdk_ec2::operation::create_volume::CreateVolumeOutput>()
+    {
+        return output
+            .volume_id
+            .iter()
+            .map(|id| TeardownStep::DeleteVolume(VolumeId(id.clone())))
+            .collect();
+    }
+
+    if let Some(output) =
+        output.downcast_ref::<aws_sdk_cloudformation::operation::create_stack::CreateStackOutput>()
+    {
+        return output
+            .stack_id
+            .iter()
+            .map(|id| TeardownStep::DeleteStack(id.clone()))
+            .collect();
+    }
+
+    if let Some(output) =
+        output.downcast_ref::<aws_sdk_efs::operation::create_file_system::CreateFileSystemOutput>()
+    {
+        return vec![TeardownStep::DeleteEfs(EfsId(
+            output.file_system_id.clone(),
+        ))];
+    }
+
+    vec![]
+}
+
+#[derive(Debug)]
+struct InputUndo {
+    hint: Option<String>,
+    compensation: Vec<TeardownStep>,
+}
+
+impl Storable for InputUndo {
+    type Storer = StoreReplace<Self>;
+}
+
+#[derive(Debug)]
+pub(crate) struct OperationLogInterceptor {
+    log: OperationLog,
+    region: Region,
+}
+
+impl OperationLogInterceptor {
+    pub(crate) const fn new(log: OperationLog, region: Region) -> Self {
+        Self { log, region }
+    }
+}
+
+impl Intercept for OperationLogInterceptor {
+    fn name(&self) -> &'static str {
+        "OperationLogInterceptor"
+    }
+
+    fn read_before_execution(
+        &self,
+        context: &BeforeSerializationInterceptorContextRef<'_>,
+        cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        cfg.interceptor_state().store_put(InputUndo {
+            hint: input_undo_hint(context.input()),
+            compensation: input_compensation(context.input()),
+        });
+        Ok(())
+    }
+
+    fn read_after_execution(
+        &self,
+        context: &FinalizerInterceptorContextRef<'_>,
+        _runtime_components: &RuntimeComponents,
+        cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        let Some(metadata) = cfg.load::<Metadata>() else {
+            return Ok(());
+        };
+        if is_read_only(metadata.name()) {
+            return Ok(());
+        }
+
+        let input_undo = cfg.load::<InputUndo>();
+
+        let (succeeded, undo, compensation) = match context.output_or_error() {
+            Some(Ok(output)) => {
+                let mut compensation = output_compensation(output);
+                if let Some(input_undo) = input_undo {
+                    compensation.extend(input_undo.compensation.iter().cloned());
+                }
+                (
+                    true,
+                    output_undo_hint(output)
+                        .or_else(|| input_undo.and_then(|input_undo| input_undo.hint.clone())),
+                    compensation,
+                )
+            }
+            Some(Err(_)) | None => (false, None, vec![]),
+        };
+
+        self.log.push(Operation {
+            service: metadata.service().to_owned(),
+            operation: metadata.name().to_owned(),
+            region: self.region,
+            time: Timestamp::now(),
+            succeeded,
+            undo,
+            compensation,
+            scopes: current_scopes(),
+        });
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_only_operations() {
+        assert!(is_read_only("DescribeInstances"));
+        assert!(is_read_only("GetCallerIdentity"));
+        assert!(!is_read_only("RunInstances"));
+        assert!(!is_read_only("ChangeResourceRecordSets"));
+    }
+
+    #[test]
+    fn undo_hint_for_created_resources() {
+        let output = Output::erase(
+            aws_sdk_ec2::operation::allocate_address::AllocateAddressOutput::builder()
+                .allocation_id("eipalloc-0123")
+                .build(),
+        );
+
+        assert_eq!(
+            output_undo_hint(&output).as_deref(),
+            Some("release address eipalloc-0123")
+        );
+    }
+
+    #[test]
+    fn compensation_for_created_resources() {
+        let output = Output::erase(
+            aws_sdk_ec2::operation::run_instances::RunInstancesOutput::builder()
+                .instances(
+                    aws_sdk_ec2::types::Instance::builder()
+                        .instance_id("i-0123")
+                        .build(),
+                )
+                .build(),
+        );
+
+        assert_eq!(
+            output_compensation(&output),
+            vec![TeardownStep::TerminateInstance(InstanceId(
+                "i-0123".to_owned()
+            ))]
+        );
+    }
+}
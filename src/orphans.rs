@@ -0,0 +1,271 @@
+//! Detection of owned resources that are no longer in use
+//!
+//! All lookups are restricted to resources carrying the given ownership tag, so
+//! resources managed by others are never reported.
+
+use std::collections::HashSet;
+
+use super::{
+    cost::{self, EstimatedCost},
+    pagination,
+    tags::{RawTag, TagList},
+    Ami, Eip, Error, RegionClient, SecurityGroupId, Volume,
+};
+
+#[derive(Debug)]
+pub enum OrphanedResource {
+    /// An elastic IP that is not associated with any instance or network interface
+    Eip(Eip),
+    /// A volume that is not attached to any instance
+    Volume(Volume),
+    /// A security group that is not used by any network interface, referenced by rules
+    /// of other groups or assigned by a launch template
+    SecurityGroup(SecurityGroupId),
+    /// An AMI that no instance was launched from
+    Ami(Ami),
+}
+
+#[derive(Debug)]
+pub struct Orphan {
+    resource: OrphanedResource,
+    estimated_cost: EstimatedCost,
+}
+
+impl Orphan {
+    pub const fn resource(&self) -> &OrphanedResource {
+        &self.resource
+    }
+
+    pub const fn estimated_cost(&self) -> EstimatedCost {
+        self.estimated_cost
+    }
+}
+
+#[derive(Debug)]
+pub struct OrphanReport {
+    orphans: Vec<Orphan>,
+}
+
+impl OrphanReport {
+    pub fn orphans(&self) -> &[Orphan] {
+        &self.orphans
+    }
+
+    pub fn into_orphans(self) -> Vec<Orphan> {
+        self.orphans
+    }
+
+    pub fn estimated_cost(&self) -> EstimatedCost {
+        self.orphans
+            .iter()
+            .map(|orphan| orphan.estimated_cost)
+            .sum()
+    }
+}
+
+fn owner_filters(owner_tag: &RawTag) -> Vec<aws_sdk_ec2::types::Filter> {
+    TagList::from_vec(vec![owner_tag.clone()]).into()
+}
+
+async fn orphaned_eips(client: &RegionClient, owner_tag: &RawTag) -> Result<Vec<Orphan>, Error> {
+    client
+        .main
+        .ec2
+        .describe_addresses()
+        .set_filters(Some(owner_filters(owner_tag)))
+        .send()
+        .await?
+        .addresses
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|address| address.association_id.is_none() && address.instance_id.is_none())
+        .map(|address| {
+            Ok(Orphan {
+                resource: OrphanedResource::Eip(address.try_into()?),
+                estimated_cost: cost::eip_cost(),
+            })
+        })
+        .collect()
+}
+
+async fn orphaned_volumes(client: &RegionClient, owner_tag: &RawTag) -> Result<Vec<Orphan>, Error> {
+    let mut filters = owner_filters(owner_tag);
+    filters.push(
+        aws_sdk_ec2::types::Filter::builder()
+            .name("status")
+            .values("available")
+            .build(),
+    );
+
+    pagination::list_volumes(client, filters)
+        .await?
+        .into_iter()
+        .map(|volume| {
+            let volume: Volume = volume.try_into()?;
+            Ok(Orphan {
+                estimated_cost: cost::volume_cost(volume.volume_type().inner(), volume.size_gib()),
+                resource: OrphanedResource::Volume(volume),
+            })
+        })
+        .collect()
+}
+
+/// IDs of groups that rules of other groups refer to. Rules of a group referring to
+/// the group itself do not count, as they are deleted together with it.
+fn referenced_groups(groups: &[aws_sdk_ec2::types::SecurityGroup]) -> HashSet<String> {
+    groups
+        .iter()
+        .flat_map(|group| {
+            group
+                .ip_permissions()
+                .iter()
+                .chain(group.ip_permissions_egress())
+                .flat_map(aws_sdk_ec2::types::IpPermission::user_id_group_pairs)
+                .filter_map(aws_sdk_ec2::types::UserIdGroupPair::group_id)
+                .filter(move |id| group.group_id() != Some(*id))
+        })
+        .map(ToOwned::to_owned)
+        .collect()
+}
+
+/// IDs of the groups a launch template version assigns, directly or to its network
+/// interfaces
+fn template_groups(data: aws_sdk_ec2::types::ResponseLaunchTemplateData) -> Vec<String> {
+    data.security_group_ids
+        .unwrap_or_default()
+        .into_iter()
+        .chain(
+            data.network_interfaces
+                .unwrap_or_default()
+                .into_iter()
+                .flat_map(|interface| interface.groups.unwrap_or_default()),
+        )
+        .collect()
+}
+
+async fn orphaned_security_groups(
+    client: &RegionClient,
+    owner_tag: &RawTag,
+) -> Result<Vec<Orphan>, Error> {
+    let mut used: HashSet<String> = pagination::list_network_interfaces(client, vec![])
+        .await?
+        .into_iter()
+        .flat_map(|interface| interface.groups.unwrap_or_default())
+        .filter_map(|group| group.group_id)
+        .collect();
+
+    // Groups referenced by rules of other groups cannot be deleted
+    used.extend(referenced_groups(
+        &pagination::list_security_groups(client, vec![]).await?,
+    ));
+
+    // Instances launched from a template later would use them
+    used.extend(
+        pagination::list_current_launch_template_versions(client)
+            .await?
+            .into_iter()
+            .filter_map(|version| version.launch_template_data)
+            .flat_map(template_groups),
+    );
+
+    pagination::list_security_groups(client, owner_filters(owner_tag))
+        .await?
+        .into_iter()
+        // The default group of a VPC cannot be deleted, so it is never an orphan
+        .filter(|group| group.group_name.as_deref() != Some("default"))
+        .filter_map(|group| group.group_id)
+        .filter(|id| !used.contains(id))
+        .map(|id| {
+            Ok(Orphan {
+                resource: OrphanedResource::SecurityGroup(SecurityGroupId(id)),
+                estimated_cost: EstimatedCost::default(),
+            })
+        })
+        .collect()
+}
+
+async fn orphaned_amis(client: &RegionClient, owner_tag: &RawTag) -> Result<Vec<Orphan>, Error> {
+    let used: HashSet<String> = pagination::list_instances(client, vec![])
+        .await?
+        .into_iter()
+        .filter_map(|instance| instance.image_id)
+        .collect();
+
+    pagination::list_images(client, vec!["self".to_owned()], owner_filters(owner_tag))
+        .await?
+        .into_iter()
+        .filter(|image| {
+            image
+                .image_id
+                .as_ref()
+                .is_some_and(|image_id| !used.contains(image_id))
+        })
+        .map(|image| {
+            let estimated_cost: EstimatedCost = image
+                .block_device_mappings()
+                .iter()
+                .filter_map(|mapping| mapping.ebs().and_then(|ebs| ebs.volume_size()))
+                .map(cost::snapshot_cost)
+                .sum();
+
+            Ok(Orphan {
+                resource: OrphanedResource::Ami(image.try_into()?),
+                estimated_cost,
+            })
+        })
+        .collect()
+}
+
+/// Finds all resources carrying `owner_tag` that are not in use anymore.
+///
+/// This covers unassociated elastic IPs, unattached volumes, unused security groups
+/// and AMIs that are not referenced by any instance. Each orphan comes with a rough
+/// estimate of its monthly cost, based on the list prices of the [`cost`] module.
+pub async fn find_orphans(
+    client: &RegionClient,
+    owner_tag: &RawTag,
+) -> Result<OrphanReport, Error> {
+    let mut orphans = vec![];
+
+    orphans.extend(orphaned_eips(client, owner_tag).await?);
+    orphans.extend(orphaned_volumes(client, owner_tag).await?);
+    orphans.extend(orphaned_security_groups(client, owner_tag).await?);
+    orphans.extend(orphaned_amis(client, owner_tag).await?);
+
+    Ok(OrphanReport { orphans })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groups_referenced_by_rules() {
+        let rule = |group_id: &str| {
+            aws_sdk_ec2::types::IpPermission::builder()
+                .user_id_group_pairs(
+                    aws_sdk_ec2::types::UserIdGroupPair::builder()
+                        .group_id(group_id)
+                        .build(),
+                )
+                .build()
+        };
+
+        let groups = [
+            aws_sdk_ec2::types::SecurityGroup::builder()
+                .group_id("sg-web")
+                .ip_permissions(rule("sg-lb"))
+                .ip_permissions(rule("sg-web"))
+                .build(),
+            aws_sdk_ec2::types::SecurityGroup::builder()
+                .group_id("sg-db")
+                .ip_permissions_egress(rule("sg-backup"))
+                .build(),
+        ];
+
+        assert_eq!(
+            referenced_groups(&groups),
+            HashSet::from(["sg-lb".to_owned(), "sg-backup".to_owned()])
+        );
+    }
+}
@@ -0,0 +1,533 @@
+use std::{future::Future, time::Duration};
+
+use futures_util::Stream;
+
+use super::{backoff::Backoff, CdnClient, Error, ErrorKind, RegionClient};
+
+/// How often a single page is retried when throttled, on top of the retries of the SDK
+const MAX_THROTTLED_ATTEMPTS: u32 = 8;
+const THROTTLING_BASE_DELAY: Duration = Duration::from_secs(1);
+const THROTTLING_MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// A single page of a listing, with the token for the next page. Most APIs use a
+/// string token, some (like Route53 record sets) a composite one.
+pub(crate) struct Page<T, K = String> {
+    pub(crate) items: Vec<T>,
+    pub(crate) next_token: Option<K>,
+}
+
+/// Collects all pages returned by `fetch_page`.
+///
+/// In contrast to the paginators of the SDK, throttling does not fail the whole
+/// listing. Instead, the page is requested again after a backoff, resuming from the
+/// last token.
+pub(crate) async fn paginate<T, K, F, Fut>(mut fetch_page: F) -> Result<Vec<T>, Error>
+where
+    K: Clone,
+    F: FnMut(Option<K>) -> Fut,
+    Fut: Future<Output = Result<Page<T, K>, Error>>,
+{
+    let mut items = vec![];
+    let mut token = None;
+
+    loop {
+        let page = fetch_with_retries(&mut fetch_page, token).await?;
+        items.extend(page.items);
+        match page.next_token {
+            Some(next_token) => token = Some(next_token),
+            None => return Ok(items),
+        }
+    }
+}
+
+/// Like [`paginate()`], but yields the items of each page as soon as it is fetched.
+/// The next page is only requested when the stream is polled again. After an error,
+/// the stream ends.
+pub(crate) fn paginate_stream<T, K, F, Fut>(
+    fetch_page: F,
+) -> impl Stream<Item = Result<Vec<T>, Error>>
+where
+    K: Clone,
+    F: FnMut(Option<K>) -> Fut,
+    Fut: Future<Output = Result<Page<T, K>, Error>>,
+{
+    futures_util::stream::unfold(Some((fetch_page, None)), |state| async move {
+        let (mut fetch_page, token) = state?;
+        match fetch_with_retries(&mut fetch_page, token).await {
+            Ok(page) => Some((
+                Ok(page.items),
+                page.next_token
+                    .map(|next_token| (fetch_page, Some(next_token))),
+            )),
+            Err(e) => Some((Err(e), None)),
+        }
+    })
+}
+
+/// Requests the page for `token`, again after a backoff as long as it is throttled
+async fn fetch_with_retries<T, K, F, Fut>(
+    fetch_page: &mut F,
+    token: Option<K>,
+) -> Result<Page<T, K>, Error>
+where
+    K: Clone,
+    F: FnMut(Option<K>) -> Fut,
+    Fut: Future<Output = Result<Page<T, K>, Error>>,
+{
+    let mut backoff = Backoff::new(THROTTLING_BASE_DELAY, THROTTLING_MAX_DELAY);
+    let mut throttled_attempts = 0_u32;
+
+    loop {
+        match fetch_page(token.clone()).await {
+            Ok(page) => return Ok(page),
+            Err(e) => {
+                if e.kind() != ErrorKind::Throttling || throttled_attempts >= MAX_THROTTLED_ATTEMPTS
+                {
+                    return Err(e);
+                }
+                throttled_attempts = throttled_attempts.saturating_add(1);
+                backoff.sleep().await;
+            }
+        }
+    }
+}
+
+pub(crate) async fn list_instances(
+    client: &RegionClient,
+    filters: Vec<aws_sdk_ec2::types::Filter>,
+) -> Result<Vec<aws_sdk_ec2::types::Instance>, Error> {
+    let ec2 = &client.main.ec2;
+    let filters = &filters;
+
+    let reservations = paginate(|token| async move {
+        let output = ec2
+            .describe_instances()
+            .set_filters(Some(filters.clone()))
+            .set_next_token(token)
+            .send()
+            .await?;
+        Ok(Page {
+            items: output.reservations.unwrap_or_default(),
+            next_token: output.next_token,
+        })
+    })
+    .await?;
+
+    Ok(reservations
+        .into_iter()
+        .flat_map(|reservation| reservation.instances.unwrap_or_default())
+        .collect())
+}
+
+pub(crate) async fn list_volumes(
+    client: &RegionClient,
+    filters: Vec<aws_sdk_ec2::types::Filter>,
+) -> Result<Vec<aws_sdk_ec2::types::Volume>, Error> {
+    let ec2 = &client.main.ec2;
+    let filters = &filters;
+
+    paginate(|token| async move {
+        let output = ec2
+            .describe_volumes()
+            .set_filters(Some(filters.clone()))
+            .set_next_token(token)
+            .send()
+            .await?;
+        Ok(Page {
+            items: output.volumes.unwrap_or_default(),
+            next_token: output.next_token,
+        })
+    })
+    .await
+}
+
+pub(crate) async fn list_snapshots(
+    client: &RegionClient,
+    owner_ids: Vec<String>,
+    filters: Vec<aws_sdk_ec2::types::Filter>,
+) -> Result<Vec<aws_sdk_ec2::types::Snapshot>, Error> {
+    let ec2 = &client.main.ec2;
+    let (owner_ids, filters) = (&owner_ids, &filters);
+
+    paginate(|token| async move {
+        let output = ec2
+            .describe_snapshots()
+            .set_owner_ids(Some(owner_ids.clone()))
+            .set_filters(Some(filters.clone()))
+            .set_next_token(token)
+            .send()
+            .await?;
+        Ok(Page {
+            items: output.snapshots.unwrap_or_default(),
+            next_token: output.next_token,
+        })
+    })
+    .await
+}
+
+pub(crate) async fn list_network_interfaces(
+    client: &RegionClient,
+    filters: Vec<aws_sdk_ec2::types::Filter>,
+) -> Result<Vec<aws_sdk_ec2::types::NetworkInterface>, Error> {
+    let ec2 = &client.main.ec2;
+    let filters = &filters;
+
+    paginate(|token| async move {
+        let output = ec2
+            .describe_network_interfaces()
+            .set_filters(Some(filters.clone()))
+            .set_next_token(token)
+            .send()
+            .await?;
+        Ok(Page {
+            items: output.network_interfaces.unwrap_or_default(),
+            next_token: output.next_token,
+        })
+    })
+    .await
+}
+
+pub(crate) async fn list_security_groups(
+    client: &RegionClient,
+    filters: Vec<aws_sdk_ec2::types::Filter>,
+) -> Result<Vec<aws_sdk_ec2::types::SecurityGroup>, Error> {
+    let ec2 = &client.main.ec2;
+    let filters = &filters;
+
+    paginate(|token| async move {
+        let output = ec2
+            .describe_security_groups()
+            .set_filters(Some(filters.clone()))
+            .set_next_token(token)
+            .send()
+            .await?;
+        Ok(Page {
+            items: output.security_groups.unwrap_or_default(),
+            next_token: output.next_token,
+        })
+    })
+    .await
+}
+
+pub(crate) async fn list_dhcp_options(
+    client: &RegionClient,
+    filters: Vec<aws_sdk_ec2::types::Filter>,
+) -> Result<Vec<aws_sdk_ec2::types::DhcpOptions>, Error> {
+    let ec2 = &client.main.ec2;
+    let filters = &filters;
+
+    paginate(|token| async move {
+        let output = ec2
+            .describe_dhcp_options()
+            .set_filters(Some(filters.clone()))
+            .set_next_token(token)
+            .send()
+            .await?;
+        Ok(Page {
+            items: output.dhcp_options.unwrap_or_default(),
+            next_token: output.next_token,
+        })
+    })
+    .await
+}
+
+pub(crate) async fn list_capacity_reservations(
+    client: &RegionClient,
+    filters: Vec<aws_sdk_ec2::types::Filter>,
+) -> Result<Vec<aws_sdk_ec2::types::CapacityReservation>, Error> {
+    let ec2 = &client.main.ec2;
+    let filters = &filters;
+
+    paginate(|token| async move {
+        let output = ec2
+            .describe_capacity_reservations()
+            .set_filters(Some(filters.clone()))
+            .set_next_token(token)
+            .send()
+            .await?;
+        Ok(Page {
+            items: output.capacity_reservations.unwrap_or_default(),
+            next_token: output.next_token,
+        })
+    })
+    .await
+}
+
+/// The `$Latest` and `$Default` versions of all launch templates
+pub(crate) async fn list_current_launch_template_versions(
+    client: &RegionClient,
+) -> Result<Vec<aws_sdk_ec2::types::LaunchTemplateVersion>, Error> {
+    let ec2 = &client.main.ec2;
+
+    paginate(|token| async move {
+        let output = ec2
+            .describe_launch_template_versions()
+            .versions("$Latest")
+            .versions("$Default")
+            .set_next_token(token)
+            .send()
+            .await?;
+        Ok(Page {
+            items: output.launch_template_versions.unwrap_or_default(),
+            next_token: output.next_token,
+        })
+    })
+    .await
+}
+
+pub(crate) async fn list_images(
+    client: &RegionClient,
+    owners: Vec<String>,
+    filters: Vec<aws_sdk_ec2::types::Filter>,
+) -> Result<Vec<aws_sdk_ec2::types::Image>, Error> {
+    let ec2 = &client.main.ec2;
+    let (owners, filters) = (&owners, &filters);
+
+    paginate(|token| async move {
+        let output = ec2
+            .describe_images()
+            .set_owners(Some(owners.clone()))
+            .set_filters(Some(filters.clone()))
+            .set_next_token(token)
+            .send()
+            .await?;
+        Ok(Page {
+            items: output.images.unwrap_or_default(),
+            next_token: output.next_token,
+        })
+    })
+    .await
+}
+
+pub(crate) async fn list_hosted_zones(
+    client: &RegionClient,
+) -> Result<Vec<aws_sdk_route53::types::HostedZone>, Error> {
+    let route53 = &client.main.route53;
+
+    paginate(|token| async move {
+        let output = route53.list_hosted_zones().set_marker(token).send().await?;
+        Ok(Page {
+            items: output.hosted_zones,
+            next_token: if output.is_truncated {
+                output.next_marker
+            } else {
+                None
+            },
+        })
+    })
+    .await
+}
+
+/// Route53 continues record set listings at a name, type and set identifier
+#[derive(Clone)]
+pub(crate) struct RecordSetToken {
+    name: String,
+    record_type: Option<aws_sdk_route53::types::RrType>,
+    identifier: Option<String>,
+}
+
+pub(crate) async fn list_resource_record_sets(
+    client: &RegionClient,
+    hosted_zone_id: &str,
+) -> Result<Vec<aws_sdk_route53::types::ResourceRecordSet>, Error> {
+    let route53 = &client.main.route53;
+
+    paginate(|token: Option<RecordSetToken>| async move {
+        let (name, record_type, identifier) = match token {
+            Some(token) => (Some(token.name), token.record_type, token.identifier),
+            None => (None, None, None),
+        };
+
+        let output = route53
+            .list_resource_record_sets()
+            .hosted_zone_id(hosted_zone_id)
+            .set_start_record_name(name)
+            .set_start_record_type(record_type)
+            .set_start_record_identifier(identifier)
+            .send()
+            .await?;
+
+        let next_token = if output.is_truncated {
+            output.next_record_name.map(|name| RecordSetToken {
+                name,
+                record_type: output.next_record_type,
+                identifier: output.next_record_identifier,
+            })
+        } else {
+            None
+        };
+
+        Ok(Page {
+            items: output.resource_record_sets,
+            next_token,
+        })
+    })
+    .await
+}
+
+pub(crate) async fn list_file_systems(
+    client: &RegionClient,
+) -> Result<Vec<aws_sdk_efs::types::FileSystemDescription>, Error> {
+    let efs = &client.main.efs;
+
+    paginate(|token| async move {
+        let output = efs.describe_file_systems().set_marker(token).send().await?;
+        Ok(Page {
+            items: output.file_systems.unwrap_or_default(),
+            next_token: output.next_marker,
+        })
+    })
+    .await
+}
+
+pub(crate) async fn list_distributions(
+    cdn: &CdnClient,
+) -> Result<Vec<aws_sdk_cloudfront::types::DistributionSummary>, Error> {
+    let cloudfront = &cdn.cloudfront;
+
+    paginate(|token| async move {
+        let list = cloudfront
+            .list_distributions()
+            .set_marker(token)
+            .send()
+            .await?
+            .distribution_list;
+        Ok(match list {
+            Some(list) => Page {
+                items: list.items.unwrap_or_default(),
+                next_token: if list.is_truncated {
+                    list.next_marker
+                } else {
+                    None
+                },
+            },
+            None => Page {
+                items: vec![],
+                next_token: None,
+            },
+        })
+    })
+    .await
+}
+
+pub(crate) async fn list_flow_logs(
+    client: &RegionClient,
+    filters: Vec<aws_sdk_ec2::types::Filter>,
+) -> Result<Vec<aws_sdk_ec2::types::FlowLog>, Error> {
+    let ec2 = &client.main.ec2;
+    let filters = &filters;
+
+    paginate(|token| async move {
+        let output = ec2
+            .describe_flow_logs()
+            .set_filter(Some(filters.clone()))
+            .set_next_token(token)
+            .send()
+            .await?;
+        Ok(Page {
+            items: output.flow_logs.unwrap_or_default(),
+            next_token: output.next_token,
+        })
+    })
+    .await
+}
+
+pub(crate) async fn list_stacks(
+    cdn: &CdnClient,
+) -> Result<Vec<aws_sdk_cloudformation::types::Stack>, Error> {
+    let cloudformation = &cdn.cloudformation;
+
+    paginate(|token| async move {
+        let output = cloudformation
+            .describe_stacks()
+            .set_next_token(token)
+            .send()
+            .await?;
+        Ok(Page {
+            items: output.stacks.unwrap_or_default(),
+            next_token: output.next_token,
+        })
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt as _;
+
+    use super::*;
+
+    fn throttled() -> Error {
+        Error::SdkError {
+            code: Some("Throttling".to_owned()),
+            request_id: None,
+            http_status: None,
+            source: Box::new(Error::RunInstancesEmptyResponse),
+        }
+    }
+
+    /// Two pages, the second one is throttled on its first request
+    fn fetch_page(
+        requests: &mut Vec<Option<String>>,
+        token: Option<String>,
+    ) -> impl Future<Output = Result<Page<u32>, Error>> {
+        requests.push(token.clone());
+        let throttle = requests.len() == 2;
+
+        async move {
+            match token.as_deref() {
+                None => Ok(Page {
+                    items: vec![1, 2],
+                    next_token: Some("page-2".to_owned()),
+                }),
+                Some(_) if throttle => Err(throttled()),
+                Some(_) => Ok(Page {
+                    items: vec![3],
+                    next_token: None,
+                }),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn throttled_page_is_resumed() {
+        let mut requests = vec![];
+
+        let items = paginate(|token| fetch_page(&mut requests, token))
+            .await
+            .unwrap();
+
+        assert_eq!(items, vec![1, 2, 3]);
+        assert_eq!(
+            requests,
+            vec![None, Some("page-2".to_owned()), Some("page-2".to_owned())]
+        );
+    }
+
+    #[tokio::test]
+    async fn stream_yields_pages() {
+        let mut requests = vec![];
+
+        let pages: Vec<Vec<u32>> = paginate_stream(|token| fetch_page(&mut requests, token))
+            .map(Result::unwrap)
+            .collect()
+            .await;
+
+        assert_eq!(pages, vec![vec![1, 2], vec![3]]);
+        assert_eq!(requests.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn other_errors_are_not_retried() {
+        let mut requests = 0_u32;
+
+        let result = paginate(|_token: Option<String>| {
+            requests = requests.saturating_add(1);
+            async { Err::<Page<u32>, _>(Error::RunInstancesEmptyResponse) }
+        })
+        .await;
+
+        assert!(matches!(result, Err(Error::RunInstancesEmptyResponse)));
+        assert_eq!(requests, 1);
+    }
+}
@@ -0,0 +1,270 @@
+//! Warm pools of pre-provisioned, stopped instances
+//!
+//! Starting a stopped instance is a lot faster than launching a new one from an AMI.
+//! A warm pool keeps a number of launched but stopped instances around, identified by
+//! a pool tag. Claiming an instance from the pool marks it as claimed and starts it.
+
+use std::time::Duration;
+
+use super::{
+    launch_ec2_instance, pagination,
+    tags::{RawTag, Tag, TagList},
+    Error, Instance, InstanceStateName, NewEc2Config, Protection, RegionClient,
+};
+
+pub const POOL_STATE_TAG_KEY: &str = "pool-state";
+
+#[derive(Tag, Debug, Clone, Copy, PartialEq, Eq)]
+#[tag(translate = transparent)]
+pub enum PoolState {
+    #[tag(rename = "available")]
+    Available,
+    #[tag(rename = "claimed")]
+    Claimed,
+}
+
+fn pool_state_tag(state: PoolState) -> RawTag {
+    RawTag::new(POOL_STATE_TAG_KEY.to_owned(), state)
+}
+
+async fn find_members(
+    client: &RegionClient,
+    pool: &RawTag,
+    state: PoolState,
+    instance_states: &[&str],
+) -> Result<Vec<Instance>, Error> {
+    let mut filters: Vec<aws_sdk_ec2::types::Filter> =
+        TagList::from_vec(vec![pool.clone(), pool_state_tag(state)]).into();
+    filters.push(
+        aws_sdk_ec2::types::Filter::builder()
+            .name("instance-state-name")
+            .set_values(Some(
+                instance_states
+                    .iter()
+                    .map(|&state| state.to_owned())
+                    .collect(),
+            ))
+            .build(),
+    );
+
+    pagination::list_instances(client, filters)
+        .await?
+        .into_iter()
+        .map(Instance::try_from_aws)
+        .collect()
+}
+
+async fn set_pool_state(
+    client: &RegionClient,
+    instance: &Instance,
+    state: PoolState,
+) -> Result<(), Error> {
+    let _output = client
+        .main
+        .ec2
+        .create_tags()
+        .resources(instance.instance_id().as_str())
+        .tags(pool_state_tag(state).into())
+        .send()
+        .await?;
+
+    Ok(())
+}
+
+/// The tags of a new member of `pool`, on top of the tags of its launch configuration
+fn member_tags(tags: &TagList, pool: &RawTag) -> TagList {
+    let mut tags = tags.clone();
+    tags.push(pool.clone());
+    tags.push(pool_state_tag(PoolState::Available));
+    tags
+}
+
+/// Terminates instances that would otherwise be left behind after an error. Only the
+/// original error is returned to the caller, so failures here are ignored.
+async fn terminate_all(client: &RegionClient, instances: &[Instance]) {
+    for instance in instances {
+        let _cleanup = instance.terminate(client, Protection::Override).await;
+    }
+}
+
+/// Launches `count` instances with `config` as members of `pool`, and stops them as
+/// soon as they are running.
+///
+/// If any step fails, all instances launched so far are terminated again and the error
+/// is returned. `max_wait` applies to each of the waits for the instances to start and
+/// stop.
+pub async fn launch_pool_members(
+    client: &RegionClient,
+    config: &NewEc2Config<'_>,
+    pool: &RawTag,
+    count: usize,
+    max_wait: Option<Duration>,
+) -> Result<Vec<Instance>, Error> {
+    let tags = member_tags(config.tags, pool);
+
+    let config = NewEc2Config {
+        tags: &tags,
+        ..*config
+    };
+
+    let mut instances = vec![];
+    for _ in 0..count {
+        match launch_ec2_instance(client, &config).await {
+            Ok(instance) => instances.push(instance),
+            Err(e) => {
+                terminate_all(client, &instances).await;
+                return Err(e);
+            }
+        }
+    }
+
+    if let Err(e) = stop_members(client, &instances, max_wait).await {
+        terminate_all(client, &instances).await;
+        return Err(e);
+    }
+
+    Ok(instances)
+}
+
+async fn stop_members(
+    client: &RegionClient,
+    instances: &[Instance],
+    max_wait: Option<Duration>,
+) -> Result<(), Error> {
+    for instance in instances {
+        let _running = instance
+            .wait_for_state(
+                client,
+                InstanceStateName::new(aws_sdk_ec2::types::InstanceStateName::Running),
+                max_wait,
+            )
+            .await?;
+
+        instance.stop(client, Protection::Enforce).await?;
+    }
+
+    for instance in instances {
+        instance.wait_for_stop(client, max_wait).await?;
+    }
+
+    Ok(())
+}
+
+/// Claims a stopped instance from `pool` and starts it.
+///
+/// Returns `None` if the pool has no available instances, otherwise the started
+/// instance. The instance is marked as claimed before it is started, so concurrent
+/// claims do not pick the same instance unless they run at exactly the same time.
+///
+/// If the instance cannot be started, it is returned to the pool. If it started but
+/// does not become running in time, it is terminated, as it can neither be used by
+/// the caller nor be claimed again.
+pub async fn claim_from_pool(
+    client: &RegionClient,
+    pool: &RawTag,
+    max_wait: Option<Duration>,
+) -> Result<Option<Instance>, Error> {
+    let Some(instance) = find_members(client, pool, PoolState::Available, &["stopped"])
+        .await?
+        .into_iter()
+        .next()
+    else {
+        return Ok(None);
+    };
+
+    set_pool_state(client, &instance, PoolState::Claimed).await?;
+
+    if let Err(e) = instance.start(client).await {
+        let _cleanup = set_pool_state(client, &instance, PoolState::Available).await;
+        return Err(e);
+    }
+
+    // The instance in its running state includes its new public IP address
+    match instance
+        .wait_for_state(
+            client,
+            InstanceStateName::new(aws_sdk_ec2::types::InstanceStateName::Running),
+            max_wait,
+        )
+        .await
+    {
+        Ok(instance) => Ok(Some(instance)),
+        Err(e) => {
+            terminate_all(client, &[instance]).await;
+            Err(e)
+        }
+    }
+}
+
+/// Launches new members into `pool` until it contains `size` available instances.
+///
+/// Returns the newly launched instances.
+pub async fn replenish_pool(
+    client: &RegionClient,
+    config: &NewEc2Config<'_>,
+    pool: &RawTag,
+    size: usize,
+    max_wait: Option<Duration>,
+) -> Result<Vec<Instance>, Error> {
+    let available = find_members(
+        client,
+        pool,
+        PoolState::Available,
+        &["pending", "running", "stopping", "stopped"],
+    )
+    .await?
+    .len();
+
+    launch_pool_members(
+        client,
+        config,
+        pool,
+        missing_members(size, available),
+        max_wait,
+    )
+    .await
+}
+
+const fn missing_members(size: usize, available: usize) -> usize {
+    size.saturating_sub(available)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tags::RawTagValue;
+
+    #[test]
+    fn pool_states() {
+        for (state, value) in [
+            (PoolState::Available, "available"),
+            (PoolState::Claimed, "claimed"),
+        ] {
+            let raw = RawTagValue::new(value.to_owned());
+            assert_eq!(RawTagValue::from(state), raw);
+            assert_eq!(PoolState::try_from(raw).unwrap(), state);
+        }
+    }
+
+    #[test]
+    fn members_are_tagged_as_available() {
+        let pool = RawTag::new("pool".to_owned(), "builders".to_owned());
+        let base = RawTag::new("Name".to_owned(), "builder".to_owned());
+
+        assert_eq!(
+            member_tags(&TagList::from_vec(vec![base.clone()]), &pool),
+            TagList::from_vec(vec![
+                base,
+                pool,
+                RawTag::new(POOL_STATE_TAG_KEY.to_owned(), "available".to_owned()),
+            ])
+        );
+    }
+
+    #[test]
+    fn replenish_only_missing_members() {
+        assert_eq!(missing_members(3, 1), 2);
+        assert_eq!(missing_members(3, 3), 0);
+        assert_eq!(missing_members(3, 5), 0);
+    }
+}
@@ -0,0 +1,147 @@
+//! Checking up front whether the current principal may perform the calls of an
+//! orchestration, instead of failing halfway with `AccessDenied`
+//!
+//! The check uses the IAM policy simulator, so the principal additionally needs
+//! `iam:SimulatePrincipalPolicy` and, for assumed roles, `iam:GetRole`. Service control
+//! policies and resource policies are not evaluated by the simulator.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::{pagination, Error, RegionClient};
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Denial {
+    /// No policy allows the action
+    Implicit,
+    /// A policy denies the action explicitly
+    Explicit,
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeniedAction {
+    /// E.g. `ec2:RunInstances`
+    pub action: String,
+    pub denial: Denial,
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PreflightReport {
+    /// The IAM user or role that was simulated
+    pub principal: String,
+    pub denied: Vec<DeniedAction>,
+}
+
+impl PreflightReport {
+    pub fn is_allowed(&self) -> bool {
+        self.denied.is_empty()
+    }
+}
+
+/// The name of the role of an assumed-role session, e.g.
+/// `arn:aws:sts::111111111111:assumed-role/deploy/session` becomes `deploy`.
+fn assumed_role_name(arn: &str) -> Option<&str> {
+    let (_prefix, resource) = arn.split_once(":assumed-role/")?;
+    resource.split('/').next()
+}
+
+/// The simulator only accepts IAM users and roles, not STS sessions. The role ARN
+/// is looked up instead of constructed, as it may contain a path.
+async fn principal_arn(client: &RegionClient) -> Result<String, Error> {
+    let identity = client.caller_identity().await?;
+
+    match assumed_role_name(identity.arn()) {
+        Some(role_name) => client
+            .main
+            .iam
+            .get_role()
+            .role_name(role_name)
+            .send()
+            .await?
+            .role
+            .map(|role| role.arn)
+            .ok_or_else(|| Error::UnexpectedNoneValue {
+                entity: "GetRoleOutput.role".to_owned(),
+            }),
+        None => Ok(identity.arn().to_owned()),
+    }
+}
+
+/// Simulates `actions` (like `ec2:RunInstances`) for the principal of `client`, on all
+/// resources, and reports the actions that would be denied.
+pub async fn preflight_permissions(
+    client: &RegionClient,
+    actions: &[&str],
+) -> Result<PreflightReport, Error> {
+    let principal = principal_arn(client).await?;
+
+    if actions.is_empty() {
+        return Ok(PreflightReport {
+            principal,
+            denied: vec![],
+        });
+    }
+
+    let iam = &client.main.iam;
+    let principal_ref = principal.as_str();
+    let action_names: Vec<String> = actions.iter().map(|&action| action.to_owned()).collect();
+    let action_names = &action_names;
+
+    let results = pagination::paginate(|marker| async move {
+        let response = iam
+            .simulate_principal_policy()
+            .policy_source_arn(principal_ref)
+            .set_action_names(Some(action_names.clone()))
+            .set_marker(marker)
+            .send()
+            .await?;
+
+        Ok(pagination::Page {
+            items: response.evaluation_results.unwrap_or_default(),
+            next_token: if response.is_truncated {
+                response.marker
+            } else {
+                None
+            },
+        })
+    })
+    .await?;
+
+    let denied = results
+        .into_iter()
+        .filter_map(|result| {
+            let denial = match result.eval_decision {
+                aws_sdk_iam::types::PolicyEvaluationDecisionType::Allowed => return None,
+                aws_sdk_iam::types::PolicyEvaluationDecisionType::ExplicitDeny => Denial::Explicit,
+                _ => Denial::Implicit,
+            };
+
+            Some(DeniedAction {
+                action: result.eval_action_name,
+                denial,
+            })
+        })
+        .collect();
+
+    Ok(PreflightReport { principal, denied })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn role_of_assumed_role_session() {
+        assert_eq!(
+            assumed_role_name("arn:aws:sts::111111111111:assumed-role/deploy/ci-1234"),
+            Some("deploy")
+        );
+        assert_eq!(
+            assumed_role_name("arn:aws:iam::111111111111:user/alice"),
+            None
+        );
+    }
+}
@@ -0,0 +1,108 @@
+//! Provenance of AMIs created from instances
+//!
+//! [`create_image()`] tags each AMI with where it came from: the source instance, the
+//! creation time, the version of this crate and the principal that created it.
+//! [`Ami::provenance()`](crate::Ami::provenance()) reads the tags back. The schema is
+//! the same for all tools built on this crate, so AMIs can be traced regardless of
+//! which tool baked them.
+
+use super::{
+    tags::{TagList, Tags},
+    AmiId, Error, Instance, InstanceId, RegionClient, Timestamp,
+};
+
+const CRATE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// All fields are optional, as AMIs that were not created by [`create_image()`] do not
+/// carry the tags
+#[Tags(default)]
+pub struct AmiProvenance {
+    #[tag(key = "provenance:source-instance")]
+    pub source_instance: Option<InstanceId>,
+    #[tag(key = "provenance:created")]
+    pub created: Option<Timestamp>,
+    #[tag(key = "provenance:crate-version")]
+    pub crate_version: Option<String>,
+    /// The ARN of the principal that created the AMI
+    #[tag(key = "provenance:builder")]
+    pub builder: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct NewImageConfig<'a> {
+    pub name: &'a str,
+    pub description: Option<&'a str>,
+    /// Without a reboot, the file system of the image may be inconsistent
+    pub reboot: bool,
+    /// The provenance tags take precedence over tags with the same keys
+    pub tags: &'a TagList,
+}
+
+/// Creates an AMI from `instance`, tagged with its provenance. The AMI and its
+/// snapshots carry the same tags.
+pub async fn create_image(
+    client: &RegionClient,
+    instance: &Instance,
+    config: &NewImageConfig<'_>,
+) -> Result<AmiId, Error> {
+    let provenance = AmiProvenance::default()
+        .with_source_instance(instance.instance_id().clone())
+        .with_created(Timestamp::now())
+        .with_crate_version(CRATE_VERSION.to_owned())
+        .with_builder(client.caller_identity().await?.arn().to_owned());
+
+    let tags = client.tags_with_defaults(&provenance.into_tags().with_defaults(config.tags));
+
+    let tag_specification = |resource_type| {
+        aws_sdk_ec2::types::TagSpecification::builder()
+            .resource_type(resource_type)
+            .set_tags(Some(tags.clone().into()))
+            .build()
+    };
+
+    client
+        .main
+        .ec2
+        .create_image()
+        .instance_id(instance.instance_id().as_str())
+        .name(config.name)
+        .set_description(config.description.map(ToOwned::to_owned))
+        .no_reboot(!config.reboot)
+        .tag_specifications(tag_specification(aws_sdk_ec2::types::ResourceType::Image))
+        .tag_specifications(tag_specification(
+            aws_sdk_ec2::types::ResourceType::Snapshot,
+        ))
+        .send()
+        .await?
+        .image_id
+        .map(AmiId)
+        .ok_or_else(|| Error::UnexpectedNoneValue {
+            entity: "CreateImageOutput.image_id".to_owned(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tags::RawTag;
+
+    #[test]
+    fn provenance_round_trip() {
+        let tags = AmiProvenance::default()
+            .with_source_instance(InstanceId("i-0123456789abcdef0".to_owned()))
+            .with_created(Timestamp::now())
+            .with_crate_version(CRATE_VERSION.to_owned())
+            .into_tags();
+
+        let mut with_others = TagList::new();
+        with_others.push(RawTag::new("Name".to_owned(), "base".to_owned()));
+        let provenance = AmiProvenance::from_tags(tags.with_defaults(&with_others)).unwrap();
+
+        assert_eq!(
+            provenance.source_instance,
+            Some(InstanceId("i-0123456789abcdef0".to_owned()))
+        );
+        assert_eq!(provenance.crate_version.as_deref(), Some(CRATE_VERSION));
+        assert!(provenance.builder.is_none());
+    }
+}
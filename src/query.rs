@@ -0,0 +1,651 @@
+//! Finding resources by their tags
+//!
+//! A [`TagQuery`] combines several [`TagPredicate`]s, all of which have to match. For
+//! EC2 resources, the query is translated into API filters, so it is evaluated on the
+//! AWS side.
+//!
+//! A [`FilterSet`] additionally filters EC2 resources by other attributes, like the
+//! instance state or the availability zone.
+
+use std::collections::BTreeMap;
+
+use super::{
+    distribution_tags, pagination,
+    tags::{RawTag, TagKey, TagList},
+    Ami, AmiId, AvailabilityZone, CdnClient, CloudfrontDistribution, Eip, Error, Instance,
+    InstanceStateName, Ip, RegionClient, Volume, VpcId, NAME_TAG_KEY,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TagPredicate {
+    /// The tag exists with exactly this value
+    Equals(RawTag),
+    /// The tag exists, with any value
+    Exists(TagKey),
+    /// The tag exists and its value starts with `prefix`
+    Prefix { key: TagKey, prefix: String },
+}
+
+impl TagPredicate {
+    pub fn matches(&self, tags: &TagList) -> bool {
+        match *self {
+            Self::Equals(ref tag) => tags.as_slice().contains(tag),
+            Self::Exists(ref key) => tags.get(key.clone()).is_some(),
+            Self::Prefix {
+                ref key,
+                ref prefix,
+            } => tags
+                .get(key.clone())
+                .is_some_and(|tag| tag.value().as_str().starts_with(prefix.as_str())),
+        }
+    }
+
+    fn to_ec2_filter(&self) -> aws_sdk_ec2::types::Filter {
+        let (name, value) = match *self {
+            Self::Equals(ref tag) => (
+                format!("tag:{}", tag.key()),
+                escape_filter_value(tag.value().as_str()),
+            ),
+            Self::Exists(ref key) => ("tag-key".to_owned(), escape_filter_value(key.as_str())),
+            Self::Prefix {
+                ref key,
+                ref prefix,
+            } => (
+                format!("tag:{key}"),
+                format!("{}*", escape_filter_value(prefix)),
+            ),
+        };
+
+        aws_sdk_ec2::types::Filter::builder()
+            .name(name)
+            .values(value)
+            .build()
+    }
+}
+
+/// EC2 filter values treat `*` and `?` as wildcards
+fn escape_filter_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '*' | '?' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// A conjunction of [`TagPredicate`]s
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TagQuery {
+    predicates: Vec<TagPredicate>,
+}
+
+impl TagQuery {
+    pub const fn new() -> Self {
+        Self { predicates: vec![] }
+    }
+
+    #[must_use]
+    pub fn predicate(mut self, predicate: TagPredicate) -> Self {
+        self.predicates.push(predicate);
+        self
+    }
+
+    #[must_use]
+    pub fn equals(self, tag: RawTag) -> Self {
+        self.predicate(TagPredicate::Equals(tag))
+    }
+
+    #[must_use]
+    pub fn exists(self, key: TagKey) -> Self {
+        self.predicate(TagPredicate::Exists(key))
+    }
+
+    #[must_use]
+    pub fn prefix(self, key: TagKey, prefix: String) -> Self {
+        self.predicate(TagPredicate::Prefix { key, prefix })
+    }
+
+    pub fn predicates(&self) -> &[TagPredicate] {
+        &self.predicates
+    }
+
+    /// An empty query matches every resource
+    pub fn is_empty(&self) -> bool {
+        self.predicates.is_empty()
+    }
+
+    pub fn matches(&self, tags: &TagList) -> bool {
+        self.predicates
+            .iter()
+            .all(|predicate| predicate.matches(tags))
+    }
+
+    pub(crate) fn ec2_filters(&self) -> Vec<aws_sdk_ec2::types::Filter> {
+        self.predicates
+            .iter()
+            .map(TagPredicate::to_ec2_filter)
+            .collect()
+    }
+}
+
+impl From<RawTag> for TagQuery {
+    fn from(tag: RawTag) -> Self {
+        Self::new().equals(tag)
+    }
+}
+
+/// All of `tags`, with exactly these values
+impl From<&TagList> for TagQuery {
+    fn from(tags: &TagList) -> Self {
+        tags.as_slice()
+            .iter()
+            .cloned()
+            .fold(Self::new(), Self::equals)
+    }
+}
+
+/// The describe call a [`FilterSet`] is used for. The same attribute has different
+/// filter names depending on the call, and unknown names are silently ignored by some
+/// calls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterTarget {
+    Instances,
+    Addresses,
+    Volumes,
+}
+
+impl FilterTarget {
+    const fn label(self) -> &'static str {
+        match self {
+            Self::Instances => "instances",
+            Self::Addresses => "addresses",
+            Self::Volumes => "volumes",
+        }
+    }
+}
+
+/// A filter on an attribute of an EC2 resource, see [`FilterSet`]
+#[derive(Debug, Clone)]
+pub enum AttributeFilter {
+    InstanceState(InstanceStateName),
+    AvailabilityZone(AvailabilityZone),
+    Vpc(VpcId),
+    Image(AmiId),
+    /// The public IP of an instance or an EIP
+    PublicIp(Ip),
+}
+
+impl AttributeFilter {
+    const fn label(&self) -> &'static str {
+        match *self {
+            Self::InstanceState(_) => "instance state",
+            Self::AvailabilityZone(_) => "availability zone",
+            Self::Vpc(_) => "VPC",
+            Self::Image(_) => "image",
+            Self::PublicIp(_) => "public IP",
+        }
+    }
+
+    /// `None` if `target` cannot be filtered by this attribute
+    const fn name(&self, target: FilterTarget) -> Option<&'static str> {
+        match (self, target) {
+            (&Self::InstanceState(_), FilterTarget::Instances) => Some("instance-state-name"),
+            (&Self::AvailabilityZone(_), FilterTarget::Instances | FilterTarget::Volumes) => {
+                Some("availability-zone")
+            }
+            (&Self::Vpc(_), FilterTarget::Instances) => Some("vpc-id"),
+            (&Self::Image(_), FilterTarget::Instances) => Some("image-id"),
+            (&Self::PublicIp(_), FilterTarget::Instances) => Some("ip-address"),
+            (&Self::PublicIp(_), FilterTarget::Addresses) => Some("public-ip"),
+            _ => None,
+        }
+    }
+
+    fn value(&self) -> String {
+        match *self {
+            Self::InstanceState(ref state) => state.inner().as_str().to_owned(),
+            Self::AvailabilityZone(ref zone) => zone.to_string(),
+            Self::Vpc(ref vpc) => vpc.as_str().to_owned(),
+            Self::Image(ref ami) => ami.as_str().to_owned(),
+            Self::PublicIp(ref ip) => ip.into_string(),
+        }
+    }
+}
+
+/// Filters for EC2 describe calls: a [`TagQuery`] and [`AttributeFilter`]s. All of
+/// them have to match. Filters on the same attribute match if any of their values
+/// matches, e.g. instances that are either running or stopped:
+///
+/// ```rust
+/// # use aws_lib::{query::FilterSet, InstanceStateName};
+/// let filters = FilterSet::new()
+///     .instance_state(InstanceStateName::new(aws_sdk_ec2::types::InstanceStateName::Running))
+///     .instance_state(InstanceStateName::new(aws_sdk_ec2::types::InstanceStateName::Stopped));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct FilterSet {
+    tags: TagQuery,
+    attributes: Vec<AttributeFilter>,
+}
+
+impl FilterSet {
+    pub const fn new() -> Self {
+        Self {
+            tags: TagQuery::new(),
+            attributes: vec![],
+        }
+    }
+
+    #[must_use]
+    pub fn tags(self, tags: TagQuery) -> Self {
+        Self { tags, ..self }
+    }
+
+    #[must_use]
+    pub fn attribute(mut self, filter: AttributeFilter) -> Self {
+        self.attributes.push(filter);
+        self
+    }
+
+    #[must_use]
+    pub fn instance_state(self, state: InstanceStateName) -> Self {
+        self.attribute(AttributeFilter::InstanceState(state))
+    }
+
+    #[must_use]
+    pub fn availability_zone(self, zone: AvailabilityZone) -> Self {
+        self.attribute(AttributeFilter::AvailabilityZone(zone))
+    }
+
+    #[must_use]
+    pub fn vpc(self, vpc: VpcId) -> Self {
+        self.attribute(AttributeFilter::Vpc(vpc))
+    }
+
+    #[must_use]
+    pub fn image(self, ami: AmiId) -> Self {
+        self.attribute(AttributeFilter::Image(ami))
+    }
+
+    #[must_use]
+    pub fn public_ip(self, ip: Ip) -> Self {
+        self.attribute(AttributeFilter::PublicIp(ip))
+    }
+
+    /// Fails with [`Error::InvalidArgument`] if an attribute filter does not apply to
+    /// `target`, instead of returning filters that would match nothing or everything.
+    pub fn ec2_filters(
+        &self,
+        target: FilterTarget,
+    ) -> Result<Vec<aws_sdk_ec2::types::Filter>, Error> {
+        let mut values: BTreeMap<&'static str, Vec<String>> = BTreeMap::new();
+
+        for filter in &self.attributes {
+            let name = filter.name(target).ok_or_else(|| Error::InvalidArgument {
+                message: format!(
+                    "{} filter cannot be used for {}",
+                    filter.label(),
+                    target.label()
+                ),
+            })?;
+            values.entry(name).or_default().push(filter.value());
+        }
+
+        let mut filters = self.tags.ec2_filters();
+        filters.extend(values.into_iter().map(|(name, values)| {
+            aws_sdk_ec2::types::Filter::builder()
+                .name(name)
+                .set_values(Some(values))
+                .build()
+        }));
+        Ok(filters)
+    }
+}
+
+impl From<TagQuery> for FilterSet {
+    fn from(tags: TagQuery) -> Self {
+        Self::new().tags(tags)
+    }
+}
+
+/// Returns the only element of `items`, `None` if there is none, and
+/// [`Error::MultipleMatches`] if there is more than one.
+pub fn find_unique<T>(
+    entity: &str,
+    items: impl IntoIterator<Item = T>,
+) -> Result<Option<T>, Error> {
+    let mut items = items.into_iter();
+
+    match (items.next(), items.next()) {
+        (None, _) => Ok(None),
+        (Some(found), None) => Ok(Some(found)),
+        (Some(_), Some(_)) => Err(Error::MultipleMatches {
+            entity: entity.to_owned(),
+        }),
+    }
+}
+
+pub async fn find_instances(
+    client: &RegionClient,
+    query: &TagQuery,
+) -> Result<Vec<Instance>, Error> {
+    pagination::list_instances(client, query.ec2_filters())
+        .await?
+        .into_iter()
+        .map(Instance::try_from_aws)
+        .collect()
+}
+
+pub async fn find_instance(
+    client: &RegionClient,
+    query: &TagQuery,
+) -> Result<Option<Instance>, Error> {
+    find_unique("instance", find_instances(client, query).await?)
+}
+
+/// Finds the instances carrying all of `tags`, with exactly these values. Like
+/// [`find_instances()`] with a [`TagQuery`] of `tags`, so wildcards in the values are
+/// matched literally.
+pub async fn find_instances_by_tags(
+    client: &RegionClient,
+    tags: &TagList,
+) -> Result<Vec<Instance>, Error> {
+    find_instances(client, &TagQuery::from(tags)).await
+}
+
+/// Like [`find_instances_by_tags()`], but fails with [`Error::MultipleMatches`] if more
+/// than one instance matches
+pub async fn find_single_instance(
+    client: &RegionClient,
+    tags: &TagList,
+) -> Result<Option<Instance>, Error> {
+    find_unique("instance", find_instances_by_tags(client, tags).await?)
+}
+
+/// Finds the instance whose `Name` tag is `name`
+pub async fn find_instance_by_name(
+    client: &RegionClient,
+    name: &str,
+) -> Result<Option<Instance>, Error> {
+    find_instance(
+        client,
+        &TagQuery::new().equals(RawTag::new(NAME_TAG_KEY, name.to_owned())),
+    )
+    .await
+}
+
+/// Like [`find_instances()`], with attribute filters
+pub async fn filter_instances(
+    client: &RegionClient,
+    filters: &FilterSet,
+) -> Result<Vec<Instance>, Error> {
+    pagination::list_instances(client, filters.ec2_filters(FilterTarget::Instances)?)
+        .await?
+        .into_iter()
+        .map(Instance::try_from_aws)
+        .collect()
+}
+
+/// Like [`find_eips()`], with attribute filters
+pub async fn filter_eips(client: &RegionClient, filters: &FilterSet) -> Result<Vec<Eip>, Error> {
+    client
+        .main
+        .ec2
+        .describe_addresses()
+        .set_filters(Some(filters.ec2_filters(FilterTarget::Addresses)?))
+        .send()
+        .await?
+        .addresses
+        .unwrap_or_default()
+        .into_iter()
+        .map(TryInto::try_into)
+        .collect()
+}
+
+pub async fn find_eips(client: &RegionClient, query: &TagQuery) -> Result<Vec<Eip>, Error> {
+    client
+        .main
+        .ec2
+        .describe_addresses()
+        .set_filters(Some(query.ec2_filters()))
+        .send()
+        .await?
+        .addresses
+        .unwrap_or_default()
+        .into_iter()
+        .map(TryInto::try_into)
+        .collect()
+}
+
+pub async fn find_eip(client: &RegionClient, query: &TagQuery) -> Result<Option<Eip>, Error> {
+    find_unique("eip", find_eips(client, query).await?)
+}
+
+/// The owner of AMIs of the current account
+const OWNER_SELF: &str = "self";
+
+/// Without any `owners`, the API would search all public AMIs, so the search falls
+/// back to the current account instead
+async fn describe_amis(
+    client: &RegionClient,
+    filters: Vec<aws_sdk_ec2::types::Filter>,
+    owners: &[&str],
+) -> Result<Vec<Ami>, Error> {
+    let owners: &[&str] = if owners.is_empty() {
+        &[OWNER_SELF]
+    } else {
+        owners
+    };
+
+    pagination::list_images(
+        client,
+        owners.iter().map(|&owner| owner.to_owned()).collect(),
+        filters,
+    )
+    .await?
+    .into_iter()
+    .map(TryInto::try_into)
+    .collect()
+}
+
+/// Finds AMIs owned by the current account.
+pub async fn find_amis(client: &RegionClient, query: &TagQuery) -> Result<Vec<Ami>, Error> {
+    describe_amis(client, query.ec2_filters(), &[OWNER_SELF]).await
+}
+
+pub async fn find_ami(client: &RegionClient, query: &TagQuery) -> Result<Option<Ami>, Error> {
+    find_unique("ami", find_amis(client, query).await?)
+}
+
+/// Finds the AMIs carrying all of `tags`, with exactly these values. `owners` are
+/// account IDs or aliases like `self` and `amazon`, and default to `self` if empty.
+///
+/// Like [`find_amis()`], wildcards in the values are matched literally.
+pub async fn find_amis_by_tags(
+    client: &RegionClient,
+    tags: &TagList,
+    owners: &[&str],
+) -> Result<Vec<Ami>, Error> {
+    describe_amis(client, TagQuery::from(tags).ec2_filters(), owners).await
+}
+
+/// Like [`find_amis_by_tags()`], but returns only the AMI with the latest creation date
+pub async fn find_latest_ami(
+    client: &RegionClient,
+    tags: &TagList,
+    owners: &[&str],
+) -> Result<Option<Ami>, Error> {
+    Ok(latest(find_amis_by_tags(client, tags, owners).await?))
+}
+
+fn latest(amis: Vec<Ami>) -> Option<Ami> {
+    amis.into_iter().max_by_key(|ami| ami.creation_date)
+}
+
+pub async fn find_volumes(client: &RegionClient, query: &TagQuery) -> Result<Vec<Volume>, Error> {
+    pagination::list_volumes(client, query.ec2_filters())
+        .await?
+        .into_iter()
+        .map(TryInto::try_into)
+        .collect()
+}
+
+pub async fn find_volume(client: &RegionClient, query: &TagQuery) -> Result<Option<Volume>, Error> {
+    find_unique("volume", find_volumes(client, query).await?)
+}
+
+/// Like [`find_volumes()`], with attribute filters
+pub async fn filter_volumes(
+    client: &RegionClient,
+    filters: &FilterSet,
+) -> Result<Vec<Volume>, Error> {
+    pagination::list_volumes(client, filters.ec2_filters(FilterTarget::Volumes)?)
+        .await?
+        .into_iter()
+        .map(TryInto::try_into)
+        .collect()
+}
+
+/// Finds CloudFront distributions. CloudFront has no server-side tag filters, so this
+/// fetches the tags of every distribution.
+pub async fn find_distributions(
+    cdn: &CdnClient,
+    query: &TagQuery,
+) -> Result<Vec<CloudfrontDistribution>, Error> {
+    Ok(find_distributions_with_tags(cdn, query)
+        .await?
+        .into_iter()
+        .map(|(distribution, _tags)| distribution)
+        .collect())
+}
+
+/// Like [`find_distributions()`], together with the tags of each distribution
+pub(crate) async fn find_distributions_with_tags(
+    cdn: &CdnClient,
+    query: &TagQuery,
+) -> Result<Vec<(CloudfrontDistribution, TagList)>, Error> {
+    let mut found = vec![];
+
+    for distribution in pagination::list_distributions(cdn).await? {
+        let tags = distribution_tags(cdn, &distribution.arn).await?;
+
+        if query.matches(&tags) {
+            found.push((distribution.try_into()?, tags));
+        }
+    }
+
+    Ok(found)
+}
+
+pub async fn find_distribution(
+    cdn: &CdnClient,
+    query: &TagQuery,
+) -> Result<Option<CloudfrontDistribution>, Error> {
+    find_unique("distribution", find_distributions(cdn, query).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn predicates() {
+        let tags = TagList::from_vec(vec![
+            RawTag::new("env".to_owned(), "prod-eu".to_owned()),
+            RawTag::new("team".to_owned(), "infra".to_owned()),
+        ]);
+
+        let query = TagQuery::new()
+            .prefix(TagKey::new("env".to_owned()), "prod".to_owned())
+            .exists(TagKey::new("team".to_owned()));
+        assert!(query.matches(&tags), "all predicates match");
+
+        let query = query.equals(RawTag::new("team".to_owned(), "web".to_owned()));
+        assert!(!query.matches(&tags), "equals predicate does not match");
+    }
+
+    #[test]
+    fn latest_ami() {
+        let ami = |id: &str, secs| Ami {
+            id: AmiId(id.to_owned()),
+            tags: TagList::new(),
+            creation_date: crate::Timestamp(chrono::DateTime::from_timestamp(secs, 0).unwrap()),
+            boot_mode: None,
+            tpm_support: None,
+            ena_support: true,
+            sriov_net_support: false,
+            root_device_name: None,
+            architecture: None,
+            platform_details: None,
+        };
+
+        assert!(latest(vec![]).is_none());
+        assert_eq!(
+            latest(vec![
+                ami("ami-old", 1_700_000_000),
+                ami("ami-new", 1_750_000_000),
+                ami("ami-mid", 1_720_000_000),
+            ])
+            .unwrap()
+            .id,
+            AmiId("ami-new".to_owned())
+        );
+    }
+
+    #[test]
+    fn unique() {
+        assert!(matches!(find_unique("x", Vec::<u8>::new()), Ok(None)));
+        assert!(matches!(find_unique("x", vec![1_u8]), Ok(Some(1))));
+        assert!(matches!(
+            find_unique("x", vec![1_u8, 2_u8]),
+            Err(Error::MultipleMatches { .. })
+        ));
+    }
+
+    #[test]
+    fn filter_set() {
+        let filters = FilterSet::from(TagQuery::new().exists(TagKey::new("team".to_owned())))
+            .instance_state(InstanceStateName::new(
+                aws_sdk_ec2::types::InstanceStateName::Running,
+            ))
+            .instance_state(InstanceStateName::new(
+                aws_sdk_ec2::types::InstanceStateName::Stopped,
+            ));
+
+        let ec2_filters = filters.ec2_filters(FilterTarget::Instances).unwrap();
+        assert_eq!(ec2_filters.len(), 2, "one filter for tags, one for states");
+        let states = ec2_filters.last().unwrap();
+        assert_eq!(states.name(), Some("instance-state-name"));
+        assert_eq!(states.values(), ["running", "stopped"]);
+
+        assert!(matches!(
+            filters.ec2_filters(FilterTarget::Volumes),
+            Err(Error::InvalidArgument { .. })
+        ));
+    }
+
+    #[test]
+    fn escapes_wildcards() {
+        assert_eq!(escape_filter_value("a*b?c"), "a\\*b\\?c");
+    }
+
+    #[test]
+    fn tag_lists_are_escaped_like_queries() {
+        let tags = TagList::from_vec(vec![RawTag::new("env".to_owned(), "prod-*".to_owned())]);
+        let query = TagQuery::from(&tags);
+
+        assert_eq!(
+            query,
+            TagQuery::new().equals(RawTag::new("env".to_owned(), "prod-*".to_owned()))
+        );
+        assert_eq!(
+            query.ec2_filters(),
+            [aws_sdk_ec2::types::Filter::builder()
+                .name("tag:env")
+                .values("prod-\\*")
+                .build()]
+        );
+    }
+}
@@ -0,0 +1,166 @@
+//! Diagnosing network connectivity with the EC2 Reachability Analyzer
+//!
+//! The analyzer evaluates the configuration (route tables, security groups, network
+//! ACLs, ...) instead of sending packets, so it also explains why a path is blocked.
+//! Each analysis is billed by AWS.
+
+use std::time::Duration;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::{waiter, Error, Instance, Ip, RegionClient};
+
+/// Where the analyzed traffic goes to
+#[derive(Debug, Clone, Copy)]
+pub enum ReachabilityTarget<'a> {
+    Instance(&'a Instance),
+    Ip(&'a Ip),
+}
+
+/// Why a path is blocked, or a component that was relevant for the result
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReachabilityFinding {
+    /// E.g. `ENI_SG_RULES_MISMATCH`
+    pub code: String,
+    /// The ID of the blocking component, e.g. a security group
+    pub component: Option<String>,
+    /// `ingress` or `egress`, if relevant
+    pub direction: Option<String>,
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReachabilityResult {
+    pub reachable: bool,
+    /// Empty if the destination is reachable
+    pub findings: Vec<ReachabilityFinding>,
+}
+
+impl From<aws_sdk_ec2::types::Explanation> for ReachabilityFinding {
+    fn from(explanation: aws_sdk_ec2::types::Explanation) -> Self {
+        Self {
+            code: explanation.explanation_code.unwrap_or_default(),
+            component: explanation.component.and_then(|component| component.id),
+            direction: explanation.direction,
+        }
+    }
+}
+
+async fn run_analysis(
+    client: &RegionClient,
+    path_id: &str,
+    max_wait: Duration,
+) -> Result<ReachabilityResult, Error> {
+    let ec2 = &client.main.ec2;
+
+    let analysis_id = ec2
+        .start_network_insights_analysis()
+        .network_insights_path_id(path_id)
+        .send()
+        .await?
+        .network_insights_analysis
+        .and_then(|analysis| analysis.network_insights_analysis_id)
+        .ok_or_else(|| Error::UnexpectedNoneValue {
+            entity: "StartNetworkInsightsAnalysisOutput.network_insights_analysis_id".to_owned(),
+        })?;
+    let analysis_id = analysis_id.as_str();
+
+    let result = waiter::poll(client.waiter_profile, max_wait, || async move {
+        let Some(analysis) = ec2
+            .describe_network_insights_analyses()
+            .network_insights_analysis_ids(analysis_id)
+            .send()
+            .await?
+            .network_insights_analyses
+            .and_then(|analyses| analyses.into_iter().next())
+        else {
+            return Ok(None);
+        };
+
+        match analysis.status {
+            Some(aws_sdk_ec2::types::AnalysisStatus::Succeeded) => Ok(Some(ReachabilityResult {
+                reachable: analysis.network_path_found.unwrap_or_default(),
+                findings: analysis
+                    .explanations
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(Into::into)
+                    .collect(),
+            })),
+            Some(aws_sdk_ec2::types::AnalysisStatus::Failed) => {
+                Err(Error::ReachabilityAnalysisFailed {
+                    message: analysis.status_message.unwrap_or_default(),
+                })
+            }
+            _ => Ok(None),
+        }
+    })
+    .await
+    .and_then(|result| result.ok_or(Error::ReachabilityAnalysisExceededMaxWait { max_wait }));
+
+    // Runs on timeouts and failures as well. A failed cleanup is only reported if the
+    // analysis itself succeeded, so it never hides why the analysis failed.
+    let deleted = ec2
+        .delete_network_insights_analysis()
+        .network_insights_analysis_id(analysis_id)
+        .send()
+        .await;
+
+    let result = result?;
+    let _output = deleted?;
+    Ok(result)
+}
+
+/// Checks whether `source` can reach `destination` on TCP port `port`.
+///
+/// The temporary analysis path is deleted afterwards, even if the analysis fails or
+/// exceeds `max_wait`. If both the analysis and the cleanup fail, the error of the
+/// analysis is returned.
+///
+/// If `max_wait` is `None`, the [`WaitConfig`](crate::waiter::WaitConfig) of `client`
+/// applies.
+pub async fn check_reachability(
+    client: &RegionClient,
+    source: &Instance,
+    destination: ReachabilityTarget<'_>,
+    port: u16,
+    max_wait: Option<Duration>,
+) -> Result<ReachabilityResult, Error> {
+    let max_wait = max_wait.unwrap_or(client.wait_config.reachability_analysis);
+    let ec2 = &client.main.ec2;
+
+    let request = ec2
+        .create_network_insights_path()
+        .source(source.instance_id().as_str())
+        .protocol(aws_sdk_ec2::types::Protocol::Tcp)
+        .destination_port(i32::from(port));
+    let request = match destination {
+        ReachabilityTarget::Instance(instance) => {
+            request.destination(instance.instance_id().as_str())
+        }
+        ReachabilityTarget::Ip(ip) => request.destination_ip(ip.to_string()),
+    };
+
+    let path_id = request
+        .send()
+        .await?
+        .network_insights_path
+        .and_then(|path| path.network_insights_path_id)
+        .ok_or_else(|| Error::UnexpectedNoneValue {
+            entity: "CreateNetworkInsightsPathOutput.network_insights_path_id".to_owned(),
+        })?;
+
+    let result = run_analysis(client, &path_id, max_wait).await;
+
+    let deleted = ec2
+        .delete_network_insights_path()
+        .network_insights_path_id(&path_id)
+        .send()
+        .await;
+
+    let result = result?;
+    let _output = deleted?;
+    Ok(result)
+}
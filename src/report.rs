@@ -0,0 +1,216 @@
+//! Fleet-wide tag reports
+//!
+//! A report has one row per resource, with a column for each selected tag key.
+//! Instance rows are written as soon as their page is fetched, so memory use does not
+//! grow with the size of the fleet.
+
+use std::io;
+
+use futures_util::StreamExt as _;
+
+use super::{
+    pagination,
+    tags::{TagKey, TagList},
+    Eip, Error, RegionClient, Volume,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// With a header row
+    Csv,
+    /// One JSON object per line
+    JsonLines,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResourceType {
+    Instance,
+    Volume,
+    Eip,
+}
+
+impl ResourceType {
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::Instance => "instance",
+            Self::Volume => "volume",
+            Self::Eip => "eip",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ReportConfig<'a> {
+    pub tag_columns: &'a [TagKey],
+    pub format: Format,
+    pub include_volumes: bool,
+    pub include_eips: bool,
+}
+
+fn report_error(e: impl std::fmt::Display) -> Error {
+    Error::ReportError {
+        message: e.to_string(),
+    }
+}
+
+/// Quotes `field` if it contains characters that are special in CSV
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+struct ReportWriter<'a, W> {
+    writer: W,
+    config: &'a ReportConfig<'a>,
+    region: &'a str,
+}
+
+impl<W: io::Write> ReportWriter<'_, W> {
+    fn header(&mut self) -> Result<(), Error> {
+        if self.config.format == Format::Csv {
+            let columns: Vec<String> = ["resource_type", "id", "region"]
+                .into_iter()
+                .map(ToOwned::to_owned)
+                .chain(
+                    self.config
+                        .tag_columns
+                        .iter()
+                        .map(|key| csv_field(key.as_str())),
+                )
+                .collect();
+            writeln!(self.writer, "{}", columns.join(",")).map_err(report_error)?;
+        }
+        Ok(())
+    }
+
+    fn row(&mut self, resource_type: ResourceType, id: &str, tags: &TagList) -> Result<(), Error> {
+        let values = self.config.tag_columns.iter().map(|key| {
+            tags.get(key.clone())
+                .map(|tag| tag.value().as_str().to_owned())
+        });
+
+        match self.config.format {
+            Format::Csv => {
+                let fields: Vec<String> = [resource_type.as_str(), id, self.region]
+                    .into_iter()
+                    .map(csv_field)
+                    .chain(values.map(|value| value.as_deref().map(csv_field).unwrap_or_default()))
+                    .collect();
+                writeln!(self.writer, "{}", fields.join(",")).map_err(report_error)
+            }
+            Format::JsonLines => {
+                let row = serde_json::json!({
+                    "resource_type": resource_type.as_str(),
+                    "id": id,
+                    "region": self.region,
+                    "tags": self
+                        .config
+                        .tag_columns
+                        .iter()
+                        .map(|key| key.as_str().to_owned())
+                        .zip(values.map(|value| {
+                            value.map_or(serde_json::Value::Null, serde_json::Value::String)
+                        }))
+                        .collect::<serde_json::Map<String, serde_json::Value>>(),
+                });
+                serde_json::to_writer(&mut self.writer, &row).map_err(report_error)?;
+                writeln!(self.writer).map_err(report_error)
+            }
+        }
+    }
+}
+
+/// Writes the tag report of all instances of the region, and of volumes and EIPs if
+/// configured, to `writer`. Tags that a resource does not carry are empty in CSV and
+/// `null` in JSON.
+pub async fn write_tag_report(
+    client: &RegionClient,
+    config: &ReportConfig<'_>,
+    writer: impl io::Write,
+) -> Result<(), Error> {
+    let mut report = ReportWriter {
+        writer,
+        config,
+        region: client.region.as_str(),
+    };
+
+    report.header()?;
+
+    let mut instances = std::pin::pin!(super::list_instances(client));
+    while let Some(instance) = instances.next().await {
+        let instance = instance?;
+        report.row(
+            ResourceType::Instance,
+            instance.instance_id().as_str(),
+            instance.tags(),
+        )?;
+    }
+
+    if config.include_volumes {
+        for volume in pagination::list_volumes(client, vec![]).await? {
+            let volume = Volume::try_from(volume)?;
+            report.row(ResourceType::Volume, volume.id().as_str(), volume.tags())?;
+        }
+    }
+
+    if config.include_eips {
+        for address in client
+            .main
+            .ec2
+            .describe_addresses()
+            .send()
+            .await?
+            .addresses
+            .unwrap_or_default()
+        {
+            let eip = Eip::try_from(address)?;
+            report.row(ResourceType::Eip, eip.allocation_id().as_str(), eip.tags())?;
+        }
+    }
+
+    report.writer.flush().map_err(report_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tags::RawTag;
+
+    #[test]
+    fn csv_rows() {
+        let columns = [
+            TagKey::new("Name".to_owned()),
+            TagKey::new("cost-center".to_owned()),
+        ];
+        let config = ReportConfig {
+            tag_columns: &columns,
+            format: Format::Csv,
+            include_volumes: false,
+            include_eips: false,
+        };
+        let mut report = ReportWriter {
+            writer: Vec::new(),
+            config: &config,
+            region: "eu-central-1",
+        };
+
+        let tags = TagList::from_vec(vec![RawTag::new(
+            TagKey::new("Name".to_owned()),
+            "web, \"primary\"".to_owned(),
+        )]);
+
+        report.header().unwrap();
+        report
+            .row(ResourceType::Instance, "i-0123456789abcdef0", &tags)
+            .unwrap();
+
+        assert_eq!(
+            String::from_utf8(report.writer).unwrap(),
+            "resource_type,id,region,Name,cost-center\n\
+             instance,i-0123456789abcdef0,eu-central-1,\"web, \"\"primary\"\"\",\n"
+        );
+    }
+}
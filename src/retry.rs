@@ -0,0 +1,110 @@
+//! Retrying whole operations instead of single SDK calls
+//!
+//! The SDK retries each API call on its own. When an operation consists of several
+//! calls that depend on each other, it is often only safe to retry the operation as a
+//! whole:
+//!
+//! ```rust,no_run
+//! # use aws_lib::{retry::{retry, RetryPolicy}, Error, RegionClient};
+//! # async fn f(client: &RegionClient) -> Result<(), Error> {
+//! let usage = retry(&RetryPolicy::default(), || async {
+//!     let usage = aws_lib::keys::key_pair_usage(client).await?;
+//!     // ...
+//!     Ok(usage)
+//! })
+//! .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{future::Future, time::Duration};
+
+use super::{backoff::Backoff, Error, ErrorKind};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first one
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Errors of these kinds are retried, all others are returned immediately
+    pub retry_on: Vec<ErrorKind>,
+}
+
+impl RetryPolicy {
+    /// Retries [`ErrorKind::Throttling`] only
+    pub fn throttling() -> Self {
+        Self {
+            max_attempts: 5,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+            retry_on: vec![ErrorKind::Throttling],
+        }
+    }
+
+    /// Retries [`ErrorKind::Throttling`] and [`ErrorKind::Capacity`], as capacity
+    /// shortages are usually resolved after a while
+    pub fn transient() -> Self {
+        Self {
+            retry_on: vec![ErrorKind::Throttling, ErrorKind::Capacity],
+            ..Self::throttling()
+        }
+    }
+
+    pub fn is_retryable(&self, error: &Error) -> bool {
+        self.retry_on.contains(&error.kind())
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::throttling()
+    }
+}
+
+/// Calls `operation` until it succeeds, fails with an error that `policy` does not
+/// retry, or runs out of attempts. Waits with exponential backoff and full jitter
+/// between attempts.
+///
+/// Returns the last error if all attempts failed.
+pub async fn retry<T, F, Fut>(policy: &RetryPolicy, mut operation: F) -> Result<T, Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, Error>>,
+{
+    let mut backoff = Backoff::new(policy.base_delay, policy.max_delay);
+    let mut attempt = 1_u32;
+
+    loop {
+        match operation().await {
+            Ok(value) => return Ok(value),
+            Err(e) => {
+                if attempt >= policy.max_attempts || !policy.is_retryable(&e) {
+                    return Err(e);
+                }
+                attempt = attempt.saturating_add(1);
+                backoff.sleep().await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_errors() {
+        let throttled = Error::SdkError {
+            code: Some("RequestLimitExceeded".to_owned()),
+            request_id: None,
+            http_status: None,
+            source: Box::new(Error::RunInstancesEmptyResponse),
+        };
+
+        assert!(RetryPolicy::throttling().is_retryable(&throttled));
+        assert!(!RetryPolicy::throttling().is_retryable(&Error::RunInstanceNoCapacity));
+        assert!(RetryPolicy::transient().is_retryable(&Error::RunInstanceNoCapacity));
+        assert!(!RetryPolicy::transient().is_retryable(&Error::RunInstancesEmptyResponse));
+    }
+}
@@ -0,0 +1,288 @@
+//! Instance type recommendations, based on Compute Optimizer and CloudWatch metrics
+//!
+//! Compute Optimizer has to be enabled for the account. Memory utilization is only
+//! available if the CloudWatch agent publishes `mem_used_percent` for the instance.
+//! A recommended type can be applied with [`Instance::resize()`].
+
+use std::time::{Duration, SystemTime};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::{Error, Instance, InstanceType, RegionClient};
+
+const CPU_NAMESPACE: &str = "AWS/EC2";
+const CPU_METRIC: &str = "CPUUtilization";
+const MEMORY_NAMESPACE: &str = "CWAgent";
+const MEMORY_METRIC: &str = "mem_used_percent";
+const INSTANCE_DIMENSION: &str = "InstanceId";
+
+/// `GetMetricStatistics` returns at most this many datapoints per call
+const MAX_DATAPOINTS: u64 = 1440;
+const MIN_PERIOD_SECS: u64 = 3600;
+/// Periods have to be a multiple of a minute
+const PERIOD_GRANULARITY_SECS: u64 = 60;
+
+/// Peak utilization over the lookback period, in percent. `None` if there is no data.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Utilization {
+    pub cpu_max_percent: Option<f64>,
+    pub memory_max_percent: Option<f64>,
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Finding {
+    Overprovisioned,
+    Underprovisioned,
+    Optimized,
+}
+
+impl Finding {
+    fn from_aws(finding: &aws_sdk_computeoptimizer::types::Finding) -> Option<Self> {
+        match *finding {
+            aws_sdk_computeoptimizer::types::Finding::Overprovisioned => {
+                Some(Self::Overprovisioned)
+            }
+            aws_sdk_computeoptimizer::types::Finding::Underprovisioned => {
+                Some(Self::Underprovisioned)
+            }
+            aws_sdk_computeoptimizer::types::Finding::Optimized => Some(Self::Optimized),
+            _ => None,
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Savings {
+    pub amount: f64,
+    /// E.g. `USD`
+    pub currency: String,
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct Recommendation {
+    pub instance_type: InstanceType,
+    /// 1 is the best option
+    pub rank: i32,
+    /// From 0 (very low) to 4 (very high), the risk that the instance type does not
+    /// meet the needs of the workload
+    pub performance_risk: f64,
+    pub estimated_monthly_savings: Option<Savings>,
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct Rightsizing {
+    /// `None` if Compute Optimizer has no recommendation for the instance yet, e.g.
+    /// because it has not been running long enough
+    pub finding: Option<Finding>,
+    /// Ordered by rank, best first
+    pub recommendations: Vec<Recommendation>,
+    pub utilization: Utilization,
+}
+
+/// A period that covers `lookback` within [`MAX_DATAPOINTS`]
+fn period_secs(lookback: Duration) -> u64 {
+    lookback
+        .as_secs()
+        .div_ceil(MAX_DATAPOINTS)
+        .div_ceil(PERIOD_GRANULARITY_SECS)
+        .saturating_mul(PERIOD_GRANULARITY_SECS)
+        .max(MIN_PERIOD_SECS)
+}
+
+fn peak(datapoints: &[aws_sdk_cloudwatch::types::Datapoint]) -> Option<f64> {
+    datapoints
+        .iter()
+        .filter_map(|datapoint| datapoint.maximum)
+        .reduce(f64::max)
+}
+
+async fn max_utilization(
+    client: &RegionClient,
+    namespace: &str,
+    metric: &str,
+    dimensions: Vec<aws_sdk_cloudwatch::types::Dimension>,
+    lookback: Duration,
+) -> Result<Option<f64>, Error> {
+    let end = SystemTime::now();
+    let start = end
+        .checked_sub(lookback)
+        .ok_or_else(|| Error::InvalidArgument {
+            message: format!("lookback of {}s is too long", lookback.as_secs()),
+        })?;
+
+    let period = i32::try_from(period_secs(lookback)).map_err(|e| Error::InvalidArgument {
+        message: format!("lookback of {}s is too long: {e}", lookback.as_secs()),
+    })?;
+
+    Ok(peak(
+        &client
+            .main
+            .cloudwatch
+            .get_metric_statistics()
+            .namespace(namespace)
+            .metric_name(metric)
+            .set_dimensions(Some(dimensions))
+            .start_time(start.into())
+            .end_time(end.into())
+            .period(period)
+            .statistics(aws_sdk_cloudwatch::types::Statistic::Maximum)
+            .send()
+            .await?
+            .datapoints
+            .unwrap_or_default(),
+    ))
+}
+
+/// The agent may add dimensions besides the instance ID, depending on its
+/// configuration. Statistics need all of them, so they are looked up first.
+async fn memory_dimensions(
+    client: &RegionClient,
+    instance: &Instance,
+) -> Result<Option<Vec<aws_sdk_cloudwatch::types::Dimension>>, Error> {
+    Ok(client
+        .main
+        .cloudwatch
+        .list_metrics()
+        .namespace(MEMORY_NAMESPACE)
+        .metric_name(MEMORY_METRIC)
+        .dimensions(
+            aws_sdk_cloudwatch::types::DimensionFilter::builder()
+                .name(INSTANCE_DIMENSION)
+                .value(instance.instance_id().as_str())
+                .build(),
+        )
+        .send()
+        .await?
+        .metrics
+        .unwrap_or_default()
+        .into_iter()
+        .next()
+        .and_then(|metric| metric.dimensions))
+}
+
+/// Recommends instance types for `instance`. `lookback` applies to the utilization
+/// metrics only, Compute Optimizer uses the lookback period configured for the
+/// account.
+pub async fn recommend_instance_type(
+    client: &RegionClient,
+    instance: &Instance,
+    lookback: Duration,
+) -> Result<Rightsizing, Error> {
+    let identity = client.caller_identity().await?;
+    let arn = format!(
+        "arn:{}:ec2:{}:{}:instance/{}",
+        identity.partition().unwrap_or("aws"),
+        client.region.as_str(),
+        identity.account().id(),
+        instance.instance_id()
+    );
+
+    let recommendation = client
+        .main
+        .computeoptimizer
+        .get_ec2_instance_recommendations()
+        .instance_arns(arn)
+        .send()
+        .await?
+        .instance_recommendations
+        .unwrap_or_default()
+        .pop();
+
+    let (finding, mut recommendations) = match recommendation {
+        Some(recommendation) => (
+            recommendation.finding.as_ref().and_then(Finding::from_aws),
+            recommendation
+                .recommendation_options
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|option| {
+                    Some(Recommendation {
+                        instance_type: InstanceType(option.instance_type?.as_str().into()),
+                        rank: option.rank,
+                        performance_risk: option.performance_risk,
+                        estimated_monthly_savings: option
+                            .savings_opportunity
+                            .and_then(|opportunity| opportunity.estimated_monthly_savings)
+                            .map(|savings| Savings {
+                                amount: savings.value,
+                                currency: savings
+                                    .currency
+                                    .map(|currency| currency.as_str().to_owned())
+                                    .unwrap_or_default(),
+                            }),
+                    })
+                })
+                .collect(),
+        ),
+        None => (None, vec![]),
+    };
+    recommendations.sort_by_key(|recommendation| recommendation.rank);
+
+    let cpu_max_percent = max_utilization(
+        client,
+        CPU_NAMESPACE,
+        CPU_METRIC,
+        vec![aws_sdk_cloudwatch::types::Dimension::builder()
+            .name(INSTANCE_DIMENSION)
+            .value(instance.instance_id().as_str())
+            .build()],
+        lookback,
+    )
+    .await?;
+
+    let memory_max_percent = match memory_dimensions(client, instance).await? {
+        Some(dimensions) => {
+            max_utilization(
+                client,
+                MEMORY_NAMESPACE,
+                MEMORY_METRIC,
+                dimensions,
+                lookback,
+            )
+            .await?
+        }
+        None => None,
+    };
+
+    Ok(Rightsizing {
+        finding,
+        recommendations,
+        utilization: Utilization {
+            cpu_max_percent,
+            memory_max_percent,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn periods_stay_within_datapoint_limit() {
+        for days in [1, 14, 32, 93, 455] {
+            let lookback = Duration::from_secs(86_400).saturating_mul(days);
+            let period = period_secs(lookback);
+
+            assert!(
+                period >= MIN_PERIOD_SECS,
+                "period too short for {days} days"
+            );
+            assert_eq!(
+                period.checked_rem(PERIOD_GRANULARITY_SECS),
+                Some(0),
+                "not whole minutes"
+            );
+            assert!(
+                lookback.as_secs().div_ceil(period) <= MAX_DATAPOINTS,
+                "too many datapoints for {days} days"
+            );
+        }
+    }
+}
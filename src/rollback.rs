@@ -0,0 +1,253 @@
+//! Reverting the resources created by a failed orchestration
+//!
+//! A [`RollbackGuard`] runs an orchestration and records the operations it makes in
+//! an [`OperationLog`]. If the orchestration fails, everything it created is deleted
+//! again: instances are terminated, EIPs released, created A records deleted, and so
+//! on. Changes to existing resources are not reverted, the log only contains hints for
+//! them.
+//!
+//! ```rust,no_run
+//! # use aws_lib::{oplog::OperationLog, rollback::{OnFailure, RollbackGuard}, Error, RegionClient};
+//! # async fn launch(client: &RegionClient) -> Result<(), Error> { Ok(()) }
+//! # async fn f(client: &RegionClient, log: &OperationLog, keep: bool) -> Result<(), Error> {
+//! let on_failure = if keep { OnFailure::Keep } else { OnFailure::Rollback };
+//!
+//! RollbackGuard::new(log, on_failure)
+//!     .run(client, None, launch(client))
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The log has to be passed to the client in
+//! [`ProfileConfig::operation_log`](crate::ProfileConfig), otherwise nothing is
+//! recorded and nothing rolled back. Distributions and stacks are only rolled back if
+//! a [`CdnClient`] is given, as its operations are recorded for
+//! [`Region::UsEast1`](crate::Region::UsEast1) regardless of the region of `client`.
+//!
+//! The log can be shared with other tasks: only operations made inside the
+//! orchestration are rolled back, not those that other tasks record in the meantime.
+//! Tasks spawned by the orchestration have to be wrapped in
+//! [`RollbackGuard::scope()`] for their operations to be included. Resources carrying
+//! the protection tag of the client are never rolled back.
+
+use std::future::Future;
+
+use super::{
+    oplog::{self, Operation, OperationLog, ScopeId},
+    teardown::{self, Confirmation, TeardownPlan, TeardownStep},
+    CdnClient, Error, Region, RegionClient, CDN_REGION,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnFailure {
+    /// Delete everything created since the guard was set up
+    Rollback,
+    /// Leave everything in place, e.g. for debugging
+    Keep,
+}
+
+#[derive(Debug)]
+pub struct RollbackGuard<'l> {
+    log: &'l OperationLog,
+    scope: ScopeId,
+    on_failure: OnFailure,
+}
+
+impl<'l> RollbackGuard<'l> {
+    /// Only operations made under the guard, see [`scope()`](Self::scope()), are
+    /// rolled back
+    pub fn new(log: &'l OperationLog, on_failure: OnFailure) -> Self {
+        Self {
+            log,
+            scope: ScopeId::next(),
+            on_failure,
+        }
+    }
+
+    /// Runs `future` under the guard, so its operations are rolled back together with
+    /// those of the orchestration. [`run()`](Self::run()) does this for the
+    /// orchestration itself, but not for tasks it spawns.
+    pub fn scope<F: Future>(&self, future: F) -> impl Future<Output = F::Output> {
+        oplog::in_scope(self.scope, future)
+    }
+
+    /// The steps to revert the successful operations made under the guard in the
+    /// region of `client`, plus those of `cdn` if given. The latest operation comes
+    /// first.
+    pub fn compensation(
+        &self,
+        client: &RegionClient,
+        cdn: Option<&CdnClient>,
+    ) -> Vec<TeardownStep> {
+        compensation(
+            &self.log.operations(),
+            self.scope,
+            client.region,
+            cdn.is_some(),
+        )
+    }
+
+    /// Deletes everything created under the guard, regardless of [`OnFailure`].
+    /// Resources that carry the protection tag of `client` by now are left alone.
+    /// Returns the executed plan.
+    pub async fn rollback(
+        self,
+        client: &RegionClient,
+        cdn: Option<&CdnClient>,
+    ) -> Result<TeardownPlan, Error> {
+        let steps =
+            teardown::without_protected(client, cdn, self.compensation(client, cdn)).await?;
+        let mut plan = TeardownPlan::new(steps);
+        teardown::execute_teardown(client, cdn, &mut plan, |_step| Confirmation::Proceed).await?;
+        Ok(plan)
+    }
+
+    /// Awaits `orchestration`, and rolls back if it fails and the guard is set to
+    /// [`OnFailure::Rollback`]. The error of `orchestration` is returned in any case.
+    /// If the rollback fails too, both errors are returned as
+    /// [`Error::RollbackFailed`].
+    pub async fn run<T>(
+        self,
+        client: &RegionClient,
+        cdn: Option<&CdnClient>,
+        orchestration: impl Future<Output = Result<T, Error>>,
+    ) -> Result<T, Error> {
+        match self.scope(orchestration).await {
+            Ok(value) => Ok(value),
+            Err(e) => match self.on_failure {
+                OnFailure::Keep => Err(e),
+                OnFailure::Rollback => match self.rollback(client, cdn).await {
+                    Ok(_plan) => Err(e),
+                    Err(rollback) => Err(Error::RollbackFailed {
+                        error: Box::new(e),
+                        rollback: Box::new(rollback),
+                    }),
+                },
+            },
+        }
+    }
+}
+
+fn compensation(
+    operations: &[Operation],
+    scope: ScopeId,
+    region: Region,
+    with_cdn: bool,
+) -> Vec<TeardownStep> {
+    operations
+        .iter()
+        .rev()
+        .filter(|operation| operation.succeeded && operation.scopes.contains(&scope))
+        .flat_map(|operation| {
+            operation.compensation.iter().filter(move |step| {
+                if step.is_cdn() {
+                    with_cdn && operation.region.as_str() == CDN_REGION.as_str()
+                } else {
+                    operation.region.as_str() == region.as_str()
+                }
+            })
+        })
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EipAllocationId, InstanceId, Timestamp};
+
+    fn operation(region: Region, scopes: &[ScopeId], step: TeardownStep) -> Operation {
+        Operation {
+            service: "EC2".to_owned(),
+            operation: "RunInstances".to_owned(),
+            region,
+            time: Timestamp::now(),
+            succeeded: true,
+            undo: None,
+            compensation: vec![step],
+            scopes: scopes.to_vec(),
+        }
+    }
+
+    fn terminate(id: &str) -> TeardownStep {
+        TeardownStep::TerminateInstance(InstanceId(id.to_owned()))
+    }
+
+    #[test]
+    fn latest_operation_first() {
+        let scope = ScopeId::next();
+        let operations = [
+            operation(Region::EuCentral1, &[scope], terminate("i-1")),
+            operation(
+                Region::EuCentral1,
+                &[scope],
+                TeardownStep::ReleaseEip(EipAllocationId("eipalloc-2".to_owned())),
+            ),
+        ];
+
+        assert_eq!(
+            compensation(&operations, scope, Region::EuCentral1, false),
+            vec![
+                TeardownStep::ReleaseEip(EipAllocationId("eipalloc-2".to_owned())),
+                terminate("i-1"),
+            ]
+        );
+    }
+
+    #[test]
+    fn only_operations_under_the_guard() {
+        let outer = ScopeId::next();
+        let scope = ScopeId::next();
+        let mut failed = operation(Region::EuCentral1, &[scope], terminate("i-failed"));
+        failed.succeeded = false;
+        let operations = [
+            operation(Region::EuCentral1, &[], terminate("i-before")),
+            operation(Region::EuCentral1, &[outer, scope], terminate("i-nested")),
+            operation(Region::EuCentral1, &[ScopeId::next()], terminate("i-other")),
+            failed,
+        ];
+
+        assert_eq!(
+            compensation(&operations, scope, Region::EuCentral1, false),
+            vec![terminate("i-nested")]
+        );
+        assert_eq!(
+            compensation(&operations, outer, Region::EuCentral1, false),
+            vec![terminate("i-nested")]
+        );
+    }
+
+    #[test]
+    fn filtered_by_client() {
+        let scope = ScopeId::next();
+        let stack = TeardownStep::DeleteStack("stack".to_owned());
+        let operations = [
+            operation(Region::EuCentral1, &[scope], terminate("i-eu")),
+            operation(Region::UsEast1, &[scope], terminate("i-us")),
+            operation(CDN_REGION, &[scope], stack.clone()),
+        ];
+
+        assert_eq!(
+            compensation(&operations, scope, Region::EuCentral1, false),
+            vec![terminate("i-eu")]
+        );
+        assert_eq!(
+            compensation(&operations, scope, Region::EuCentral1, true),
+            vec![stack, terminate("i-eu")]
+        );
+    }
+
+    #[tokio::test]
+    async fn nested_scopes() {
+        let outer = ScopeId::next();
+        let inner = ScopeId::next();
+
+        let scopes = oplog::in_scope(outer, async {
+            oplog::in_scope(inner, async { oplog::current_scopes() }).await
+        })
+        .await;
+
+        assert_eq!(scopes, vec![outer, inner]);
+    }
+}
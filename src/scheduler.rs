@@ -0,0 +1,435 @@
+//! Start and stop instances according to schedules stored in their tags
+//!
+//! An instance opts into scheduling by carrying a [`SCHEDULE_TAG_KEY`] tag. Its value
+//! names the days, the local time range and the timezone in which the instance should
+//! be running, e.g. `Mon-Fri 08:00-18:00 Europe/Berlin`. Outside of that window, the
+//! instance is stopped by [`apply_schedules()`]. A time range that crosses midnight,
+//! e.g. `Mon-Fri 22:00-06:00 UTC`, belongs to the day it starts on, so the instance in
+//! this example runs from Monday night until Saturday morning.
+//!
+//! The same format is used for [`MaintenanceWindow`]s in the
+//! [`MAINTENANCE_WINDOW_TAG_KEY`] tag, which limit when disruptive actions may be taken
+//! on an instance, see [`assert_in_maintenance_window()`].
+
+use chrono::{Datelike as _, NaiveTime, Weekday};
+
+use super::{
+    pagination,
+    tags::{ParseTagError, ParseTagValueError, RawTagValue, StaticTagKey, Tag, TagKey},
+    Error, Instance, InstanceId, Protection, RegionClient, Timestamp,
+};
+
+pub const SCHEDULE_TAG_KEY: StaticTagKey = StaticTagKey::new("schedule");
+pub const MAINTENANCE_WINDOW_TAG_KEY: StaticTagKey = StaticTagKey::new("maintenance-window");
+
+const TIME_FORMAT: &str = "%H:%M";
+
+#[derive(Tag, Debug, Clone, PartialEq, Eq)]
+#[tag(translate = manual)]
+pub struct Schedule {
+    first_day: Weekday,
+    last_day: Weekday,
+    start: NaiveTime,
+    end: NaiveTime,
+    timezone: chrono_tz::Tz,
+}
+
+impl Schedule {
+    pub const fn new(
+        first_day: Weekday,
+        last_day: Weekday,
+        start: NaiveTime,
+        end: NaiveTime,
+        timezone: chrono_tz::Tz,
+    ) -> Self {
+        Self {
+            first_day,
+            last_day,
+            start,
+            end,
+            timezone,
+        }
+    }
+
+    fn includes_day(&self, day: Weekday) -> bool {
+        let day = day.num_days_from_monday();
+        let first = self.first_day.num_days_from_monday();
+        let last = self.last_day.num_days_from_monday();
+
+        if first <= last {
+            first <= day && day <= last
+        } else {
+            // The range wraps around the end of the week, e.g. `Fri-Mon`
+            day >= first || day <= last
+        }
+    }
+
+    /// Whether an instance following this schedule should be running at `now`.
+    ///
+    /// Days and times are evaluated in the timezone of the schedule. If the time range
+    /// crosses midnight, the part after midnight belongs to the day before.
+    pub fn is_active(&self, now: Timestamp) -> bool {
+        let local = now.inner().with_timezone(&self.timezone);
+        let (day, time) = (local.weekday(), local.time());
+
+        if self.start <= self.end {
+            self.includes_day(day) && self.start <= time && time < self.end
+        } else if time >= self.start {
+            self.includes_day(day)
+        } else {
+            // The range spans midnight, e.g. `22:00-06:00`, and started yesterday
+            time < self.end && self.includes_day(day.pred())
+        }
+    }
+}
+
+fn invalid_schedule(value: RawTagValue, message: impl Into<String>) -> ParseTagValueError {
+    ParseTagValueError::InvalidValue {
+        value,
+        message: message.into(),
+    }
+}
+
+impl TryFrom<RawTagValue> for Schedule {
+    type Error = ParseTagValueError;
+
+    fn try_from(value: RawTagValue) -> Result<Self, Self::Error> {
+        let input = value.clone().into_string();
+        let mut parts = input.split_whitespace();
+
+        let (Some(days), Some(times), Some(timezone), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid_schedule(
+                value,
+                "expected schedule of the form \"<days> <start>-<end> <timezone>\"",
+            ));
+        };
+
+        let (first_day, last_day) = days.split_once('-').unwrap_or((days, days));
+        let (first_day, last_day) = match (first_day.parse(), last_day.parse()) {
+            (Ok(first_day), Ok(last_day)) => (first_day, last_day),
+            _ => return Err(invalid_schedule(value, format!("invalid days \"{days}\""))),
+        };
+
+        let Some((start, end)) = times.split_once('-') else {
+            return Err(invalid_schedule(
+                value,
+                format!("invalid time range \"{times}\""),
+            ));
+        };
+        let (start, end) = match (
+            NaiveTime::parse_from_str(start, TIME_FORMAT),
+            NaiveTime::parse_from_str(end, TIME_FORMAT),
+        ) {
+            (Ok(start), Ok(end)) => (start, end),
+            _ => {
+                return Err(invalid_schedule(
+                    value,
+                    format!("invalid time range \"{times}\""),
+                ))
+            }
+        };
+
+        let timezone = match timezone.parse() {
+            Ok(timezone) => timezone,
+            Err(e) => {
+                return Err(invalid_schedule(
+                    value,
+                    format!("invalid timezone \"{timezone}\": {e}"),
+                ))
+            }
+        };
+
+        Ok(Self {
+            first_day,
+            last_day,
+            start,
+            end,
+            timezone,
+        })
+    }
+}
+
+impl From<Schedule> for RawTagValue {
+    fn from(value: Schedule) -> Self {
+        let days = if value.first_day == value.last_day {
+            value.first_day.to_string()
+        } else {
+            format!("{}-{}", value.first_day, value.last_day)
+        };
+
+        Self::new(format!(
+            "{days} {}-{} {}",
+            value.start.format(TIME_FORMAT),
+            value.end.format(TIME_FORMAT),
+            value.timezone.name()
+        ))
+    }
+}
+
+/// When disruptive actions like stops, reboots or replacements may be taken on an
+/// instance
+#[derive(Tag, Debug, Clone, PartialEq, Eq)]
+#[tag(translate = manual)]
+pub struct MaintenanceWindow(Schedule);
+
+impl MaintenanceWindow {
+    pub const fn new(schedule: Schedule) -> Self {
+        Self(schedule)
+    }
+
+    pub const fn schedule(&self) -> &Schedule {
+        &self.0
+    }
+
+    pub fn contains(&self, now: Timestamp) -> bool {
+        self.0.is_active(now)
+    }
+}
+
+impl TryFrom<RawTagValue> for MaintenanceWindow {
+    type Error = ParseTagValueError;
+
+    fn try_from(value: RawTagValue) -> Result<Self, Self::Error> {
+        Schedule::try_from(value).map(Self)
+    }
+}
+
+impl From<MaintenanceWindow> for RawTagValue {
+    fn from(value: MaintenanceWindow) -> Self {
+        value.0.into()
+    }
+}
+
+/// Fails with [`Error::OutsideMaintenanceWindow`] if `instance` carries a
+/// [`MAINTENANCE_WINDOW_TAG_KEY`] tag and `now` is outside of that window.
+///
+/// Instances without the tag are not restricted.
+pub fn assert_in_maintenance_window(instance: &Instance, now: Timestamp) -> Result<(), Error> {
+    let key = TagKey::from(MAINTENANCE_WINDOW_TAG_KEY);
+
+    let Some(tag) = instance.get_tag(key.clone()) else {
+        return Ok(());
+    };
+
+    let window = MaintenanceWindow::try_from(tag.value().clone())
+        .map_err(|inner| ParseTagError::InvalidTagValue { key, inner })?;
+
+    if window.contains(now) {
+        Ok(())
+    } else {
+        Err(Error::OutsideMaintenanceWindow {
+            instance: instance.instance_id().clone(),
+            window: RawTagValue::from(window).into_string(),
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum ScheduleAction {
+    Started,
+    Stopped,
+    /// The schedule tag of the instance could not be parsed, the instance was not touched
+    InvalidSchedule(ParseTagValueError),
+    /// The instance should be stopped, but carries the protection tag
+    Protected,
+}
+
+#[derive(Debug)]
+pub struct ScheduleResult {
+    pub instance: InstanceId,
+    pub action: ScheduleAction,
+}
+
+/// Starts and stops all instances carrying a [`SCHEDULE_TAG_KEY`] tag according to their
+/// schedule, and returns the actions that were taken.
+///
+/// Instances that are already in the desired state or are currently transitioning
+/// between states are skipped.
+pub async fn apply_schedules(
+    client: &RegionClient,
+    now: Timestamp,
+) -> Result<Vec<ScheduleResult>, Error> {
+    let instances = pagination::list_instances(
+        client,
+        vec![aws_sdk_ec2::types::Filter::builder()
+            .name("tag-key")
+            .values(SCHEDULE_TAG_KEY.as_str())
+            .build()],
+    )
+    .await?
+    .into_iter()
+    .map(Instance::try_from_aws)
+    .collect::<Result<Vec<Instance>, Error>>()?;
+
+    let mut results = vec![];
+
+    for instance in instances {
+        let Some(tag) = instance.get_tag(SCHEDULE_TAG_KEY.into()) else {
+            continue;
+        };
+
+        let schedule = match Schedule::try_from(tag.value().clone()) {
+            Ok(schedule) => schedule,
+            Err(e) => {
+                results.push(ScheduleResult {
+                    instance: instance.instance_id().clone(),
+                    action: ScheduleAction::InvalidSchedule(e),
+                });
+                continue;
+            }
+        };
+
+        let action = match (instance.state().inner(), schedule.is_active(now)) {
+            (&aws_sdk_ec2::types::InstanceStateName::Stopped, true) => {
+                let _state_change_info = client
+                    .main
+                    .ec2
+                    .start_instances()
+                    .instance_ids(instance.instance_id().as_str())
+                    .send()
+                    .await?;
+                ScheduleAction::Started
+            }
+            (&aws_sdk_ec2::types::InstanceStateName::Running, false) => {
+                match instance.stop(client, Protection::Enforce).await {
+                    Ok(()) => ScheduleAction::Stopped,
+                    Err(Error::ResourceProtected { .. }) => ScheduleAction::Protected,
+                    Err(e) => return Err(e),
+                }
+            }
+            _ => continue,
+        };
+
+        results.push(ScheduleResult {
+            instance: instance.instance_id().clone(),
+            action,
+        });
+    }
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone as _;
+
+    use super::*;
+
+    fn office_hours() -> Schedule {
+        Schedule::new(
+            Weekday::Mon,
+            Weekday::Fri,
+            NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+            NaiveTime::from_hms_opt(18, 0, 0).unwrap(),
+            chrono_tz::Europe::Berlin,
+        )
+    }
+
+    fn at(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> Timestamp {
+        Timestamp::new(
+            chrono::Utc
+                .with_ymd_and_hms(year, month, day, hour, minute, 0)
+                .unwrap(),
+        )
+    }
+
+    #[test]
+    fn parse_and_render() {
+        let value = RawTagValue::new("Mon-Fri 08:00-18:00 Europe/Berlin".to_owned());
+        let schedule = Schedule::try_from(value.clone()).unwrap();
+
+        assert_eq!(schedule, office_hours());
+        assert_eq!(RawTagValue::from(schedule), value);
+    }
+
+    #[test]
+    fn parse_single_day() {
+        let value = RawTagValue::new("Sat 10:00-12:00 UTC".to_owned());
+        let schedule = Schedule::try_from(value.clone()).unwrap();
+
+        assert!(schedule.is_active(at(2024, 6, 1, 11, 0)));
+        assert!(!schedule.is_active(at(2024, 6, 2, 11, 0)));
+        assert_eq!(RawTagValue::from(schedule), value);
+    }
+
+    #[test]
+    fn parse_invalid() {
+        for value in [
+            "",
+            "Mon-Fri",
+            "Mon-Fri 08:00-18:00",
+            "Mon-Fri 08:00 Europe/Berlin",
+            "Mon-Foo 08:00-18:00 Europe/Berlin",
+            "Mon-Fri 08:00-25:00 Europe/Berlin",
+            "Mon-Fri 08:00-18:00 Europe/Nowhere",
+            "Mon-Fri 08:00-18:00 Europe/Berlin extra",
+        ] {
+            assert!(
+                matches!(
+                    Schedule::try_from(RawTagValue::new(value.to_owned())),
+                    Err(ParseTagValueError::InvalidValue { .. })
+                ),
+                "\"{value}\" must not parse"
+            );
+        }
+    }
+
+    #[test]
+    fn office_hours_in_local_time() {
+        let schedule = office_hours();
+
+        // 2024-06-03 is a Monday, Berlin is at UTC+2 in summer
+        assert!(!schedule.is_active(at(2024, 6, 3, 5, 59)));
+        assert!(schedule.is_active(at(2024, 6, 3, 6, 0)));
+        assert!(schedule.is_active(at(2024, 6, 3, 15, 59)));
+        assert!(!schedule.is_active(at(2024, 6, 3, 16, 0)));
+
+        // Saturday
+        assert!(!schedule.is_active(at(2024, 6, 8, 10, 0)));
+    }
+
+    #[test]
+    fn maintenance_window_round_trip() {
+        let value = RawTagValue::new("Sun 02:00-04:00 UTC".to_owned());
+        let window = MaintenanceWindow::try_from(value.clone()).unwrap();
+
+        assert!(window.contains(at(2024, 6, 9, 3, 0)));
+        assert!(!window.contains(at(2024, 6, 10, 3, 0)));
+        assert_eq!(RawTagValue::from(window), value);
+
+        let value = RawTagValue::new("Sat-Sun 02:00-04:00 UTC".to_owned());
+        let window = MaintenanceWindow::try_from(value.clone()).unwrap();
+
+        assert!(window.contains(at(2024, 6, 8, 3, 0)));
+        assert_eq!(RawTagValue::from(window), value);
+    }
+
+    #[test]
+    fn wrapping_ranges() {
+        let schedule =
+            Schedule::try_from(RawTagValue::new("Fri-Mon 22:00-06:00 UTC".to_owned())).unwrap();
+
+        assert!(schedule.is_active(at(2024, 6, 7, 23, 0)));
+        assert!(schedule.is_active(at(2024, 6, 10, 5, 0)));
+        assert!(!schedule.is_active(at(2024, 6, 10, 12, 0)));
+        assert!(!schedule.is_active(at(2024, 6, 11, 23, 0)));
+    }
+
+    #[test]
+    fn window_crossing_midnight() {
+        let schedule =
+            Schedule::try_from(RawTagValue::new("Mon-Fri 22:00-06:00 UTC".to_owned())).unwrap();
+
+        // 2024-06-03 is a Monday, the morning still belongs to Sunday night
+        assert!(!schedule.is_active(at(2024, 6, 3, 5, 0)));
+        assert!(schedule.is_active(at(2024, 6, 3, 22, 0)));
+        assert!(schedule.is_active(at(2024, 6, 4, 5, 59)));
+        assert!(!schedule.is_active(at(2024, 6, 4, 6, 0)));
+
+        // Friday night runs into Saturday morning, but not into Saturday night
+        assert!(schedule.is_active(at(2024, 6, 8, 5, 0)));
+        assert!(!schedule.is_active(at(2024, 6, 8, 23, 0)));
+    }
+}
@@ -0,0 +1,608 @@
+//! Security groups and their rules
+//!
+//! Rules are managed one direction at a time, see [`Direction`]. AWS merges rules with
+//! the same protocol and ports into a single permission, so the rules read back from
+//! a security group may be grouped differently than they were authorized. Each
+//! [`Rule`] has exactly one source, which keeps them comparable.
+
+use std::{fmt, net::IpAddr, str::FromStr};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::{
+    pagination, query,
+    tags::{RawTag, TagList},
+    Error, Protection, RegionClient, SecurityGroup, SecurityGroupId, VpcId,
+};
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Ingress,
+    Egress,
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Protocol {
+    Tcp,
+    Udp,
+    Icmp,
+    /// All protocols and ports
+    All,
+    /// Any other protocol by name or number, e.g. `icmpv6` or `50` for ESP
+    Other(String),
+}
+
+impl Protocol {
+    fn as_aws(&self) -> &str {
+        match *self {
+            Self::Tcp => "tcp",
+            Self::Udp => "udp",
+            Self::Icmp => "icmp",
+            Self::All => "-1",
+            Self::Other(ref value) => value,
+        }
+    }
+
+    fn from_aws(value: &str) -> Self {
+        match value {
+            "tcp" | "6" => Self::Tcp,
+            "udp" | "17" => Self::Udp,
+            "icmp" | "1" => Self::Icmp,
+            "-1" => Self::All,
+            other => Self::Other(other.to_owned()),
+        }
+    }
+}
+
+/// The port range that AWS uses for "all ports" of TCP and UDP
+const ALL_PORTS: PortRange = PortRange {
+    from: 0,
+    to: u16::MAX,
+};
+
+/// An inclusive range of ports. For [`Protocol::Icmp`], `from` is the ICMP type and
+/// `to` the ICMP code.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PortRange {
+    pub from: u16,
+    pub to: u16,
+}
+
+impl PortRange {
+    pub const fn single(port: u16) -> Self {
+        Self {
+            from: port,
+            to: port,
+        }
+    }
+}
+
+/// An IPv4 or IPv6 network, e.g. `10.0.0.0/16`
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cidr {
+    network: IpAddr,
+    prefix_len: u8,
+}
+
+impl Cidr {
+    pub fn new(network: IpAddr, prefix_len: u8) -> Result<Self, Error> {
+        let max_prefix_len = match network {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+
+        if prefix_len > max_prefix_len {
+            return Err(Error::InvalidArgument {
+                message: format!(
+                    "prefix length {prefix_len} of {network} exceeds {max_prefix_len}"
+                ),
+            });
+        }
+
+        Ok(Self {
+            network,
+            prefix_len,
+        })
+    }
+
+    pub const fn network(&self) -> IpAddr {
+        self.network
+    }
+
+    pub const fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix_len)
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: String| Error::InvalidArgument {
+            message: format!("invalid cidr \"{s}\": {reason}"),
+        };
+
+        let (network, prefix_len) = s
+            .split_once('/')
+            .ok_or_else(|| invalid("missing prefix length".to_owned()))?;
+
+        Self::new(
+            network.parse().map_err(|e| invalid(format!("{e}")))?,
+            prefix_len.parse().map_err(|e| invalid(format!("{e}")))?,
+        )
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuleSource {
+    Cidr(Cidr),
+    /// Instances that are members of the security group
+    SecurityGroup(SecurityGroupId),
+}
+
+/// A single rule. For ingress rules, the source is where traffic comes from, for
+/// egress rules, where it goes to.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    pub protocol: Protocol,
+    /// `None` for all ports, or all ICMP types and codes. Ignored for
+    /// [`Protocol::All`].
+    pub ports: Option<PortRange>,
+    pub source: RuleSource,
+    pub description: Option<String>,
+}
+
+impl Rule {
+    fn to_aws(&self) -> aws_sdk_ec2::types::IpPermission {
+        let (from_port, to_port) = match (&self.protocol, self.ports) {
+            (&Protocol::All, _) => (None, None),
+            (&Protocol::Tcp | &Protocol::Udp, None) => (
+                Some(i32::from(ALL_PORTS.from)),
+                Some(i32::from(ALL_PORTS.to)),
+            ),
+            (_, Some(ports)) => (Some(i32::from(ports.from)), Some(i32::from(ports.to))),
+            // For ICMP, -1 means all types and codes
+            (&Protocol::Icmp, None) => (Some(-1), Some(-1)),
+            (&Protocol::Other(_), None) => (None, None),
+        };
+
+        let permission = aws_sdk_ec2::types::IpPermission::builder()
+            .ip_protocol(self.protocol.as_aws())
+            .set_from_port(from_port)
+            .set_to_port(to_port);
+
+        match self.source {
+            RuleSource::Cidr(cidr) => match cidr.network {
+                IpAddr::V4(_) => permission.ip_ranges(
+                    aws_sdk_ec2::types::IpRange::builder()
+                        .cidr_ip(cidr.to_string())
+                        .set_description(self.description.clone())
+                        .build(),
+                ),
+                IpAddr::V6(_) => permission.ipv6_ranges(
+                    aws_sdk_ec2::types::Ipv6Range::builder()
+                        .cidr_ipv6(cidr.to_string())
+                        .set_description(self.description.clone())
+                        .build(),
+                ),
+            },
+            RuleSource::SecurityGroup(ref group) => permission.user_id_group_pairs(
+                aws_sdk_ec2::types::UserIdGroupPair::builder()
+                    .group_id(group.as_str())
+                    .set_description(self.description.clone())
+                    .build(),
+            ),
+        }
+        .build()
+    }
+
+    /// Splits a permission into one rule per source. Prefix list sources are skipped.
+    fn from_aws(permission: aws_sdk_ec2::types::IpPermission) -> Result<Vec<Self>, Error> {
+        let protocol = Protocol::from_aws(permission.ip_protocol.as_deref().ok_or_else(|| {
+            Error::UnexpectedNoneValue {
+                entity: "IpPermission.ip_protocol".to_owned(),
+            }
+        })?);
+
+        // Port -1 does not fit into `u16` and means all ports, as does the full range
+        // for TCP and UDP
+        let ports = match (permission.from_port, permission.to_port) {
+            (Some(from), Some(to)) => u16::try_from(from)
+                .ok()
+                .zip(u16::try_from(to).ok())
+                .map(|(from, to)| PortRange { from, to })
+                .filter(|&ports| {
+                    !(matches!(protocol, Protocol::Tcp | Protocol::Udp) && ports == ALL_PORTS)
+                }),
+            _ => None,
+        };
+
+        let rule = |source, description| Self {
+            protocol: protocol.clone(),
+            ports,
+            source,
+            description,
+        };
+
+        let mut rules = vec![];
+
+        for range in permission.ip_ranges.unwrap_or_default() {
+            if let Some(cidr) = range.cidr_ip {
+                rules.push(rule(RuleSource::Cidr(cidr.parse()?), range.description));
+            }
+        }
+
+        for range in permission.ipv6_ranges.unwrap_or_default() {
+            if let Some(cidr) = range.cidr_ipv6 {
+                rules.push(rule(RuleSource::Cidr(cidr.parse()?), range.description));
+            }
+        }
+
+        for pair in permission.user_id_group_pairs.unwrap_or_default() {
+            if let Some(group) = pair.group_id {
+                rules.push(rule(
+                    RuleSource::SecurityGroup(SecurityGroupId(group)),
+                    pair.description,
+                ));
+            }
+        }
+
+        Ok(rules)
+    }
+}
+
+impl TryFrom<aws_sdk_ec2::types::SecurityGroup> for SecurityGroup {
+    type Error = Error;
+
+    fn try_from(group: aws_sdk_ec2::types::SecurityGroup) -> Result<Self, Self::Error> {
+        macro_rules! extract {
+            ($field:ident) => {
+                group.$field.ok_or_else(|| Error::UnexpectedNoneValue {
+                    entity: stringify!($field).to_owned(),
+                })
+            };
+        }
+
+        Ok(Self {
+            id: SecurityGroupId(extract!(group_id)?),
+            name: extract!(group_name)?,
+            vpc_id: group.vpc_id.map(VpcId),
+            tags: group.tags.unwrap_or_default().try_into()?,
+        })
+    }
+}
+
+async fn describe(
+    client: &RegionClient,
+    id: &SecurityGroupId,
+) -> Result<Option<aws_sdk_ec2::types::SecurityGroup>, Error> {
+    Ok(client
+        .main
+        .ec2
+        .describe_security_groups()
+        .group_ids(id.as_str())
+        .send()
+        .await?
+        .security_groups
+        .unwrap_or_default()
+        .pop())
+}
+
+async fn list(
+    client: &RegionClient,
+    filters: Vec<aws_sdk_ec2::types::Filter>,
+) -> Result<Vec<SecurityGroup>, Error> {
+    pagination::list_security_groups(client, filters)
+        .await?
+        .into_iter()
+        .map(TryInto::try_into)
+        .collect()
+}
+
+fn filter(name: &str, value: &str) -> aws_sdk_ec2::types::Filter {
+    aws_sdk_ec2::types::Filter::builder()
+        .name(name)
+        .values(value)
+        .build()
+}
+
+impl SecurityGroup {
+    /// Creates the security group in `vpc`. A new security group has no ingress rules
+    /// and a single egress rule that allows all outbound traffic.
+    pub async fn create(
+        client: &RegionClient,
+        vpc: &VpcId,
+        name: &str,
+        description: &str,
+        tags: &TagList,
+    ) -> Result<Self, Error> {
+        let tags = client.tags_with_defaults(tags);
+
+        let id = client
+            .main
+            .ec2
+            .create_security_group()
+            .vpc_id(vpc.as_str())
+            .group_name(name)
+            .description(description)
+            .tag_specifications(
+                aws_sdk_ec2::types::TagSpecification::builder()
+                    .resource_type(aws_sdk_ec2::types::ResourceType::SecurityGroup)
+                    .set_tags(Some(tags.clone().into()))
+                    .build(),
+            )
+            .send()
+            .await?
+            .group_id
+            .ok_or_else(|| Error::UnexpectedNoneValue {
+                entity: "CreateSecurityGroupOutput.group_id".to_owned(),
+            })?;
+
+        Ok(Self {
+            id: SecurityGroupId(id),
+            name: name.to_owned(),
+            vpc_id: Some(vpc.clone()),
+            tags,
+        })
+    }
+
+    pub async fn get(client: &RegionClient, id: &SecurityGroupId) -> Result<Option<Self>, Error> {
+        describe(client, id)
+            .await?
+            .map(TryInto::try_into)
+            .transpose()
+    }
+
+    /// Fails with [`Error::MultipleMatches`] if more than one security group carries
+    /// `tag`
+    pub async fn find_by_tag(client: &RegionClient, tag: &RawTag) -> Result<Option<Self>, Error> {
+        query::find_unique(
+            "security group",
+            list(client, TagList::from_vec(vec![tag.clone()]).into()).await?,
+        )
+    }
+
+    /// Names are unique within a VPC, so this finds at most one security group
+    pub async fn find_by_name(
+        client: &RegionClient,
+        vpc: &VpcId,
+        name: &str,
+    ) -> Result<Option<Self>, Error> {
+        query::find_unique(
+            "security group",
+            list(
+                client,
+                vec![filter("vpc-id", vpc.as_str()), filter("group-name", name)],
+            )
+            .await?,
+        )
+    }
+
+    /// The current rules of the security group in `direction`
+    pub async fn rules(
+        &self,
+        client: &RegionClient,
+        direction: Direction,
+    ) -> Result<Vec<Rule>, Error> {
+        let group = describe(client, &self.id)
+            .await?
+            .ok_or_else(|| Error::InvalidArgument {
+                message: format!("security group {} not found", self.id),
+            })?;
+
+        let permissions = match direction {
+            Direction::Ingress => group.ip_permissions,
+            Direction::Egress => group.ip_permissions_egress,
+        };
+
+        let mut rules = vec![];
+        for permission in permissions.unwrap_or_default() {
+            rules.extend(Rule::from_aws(permission)?);
+        }
+        Ok(rules)
+    }
+
+    /// Adds `rules` in `direction`. Fails if one of the rules exists already.
+    pub async fn authorize(
+        &self,
+        client: &RegionClient,
+        direction: Direction,
+        rules: &[Rule],
+    ) -> Result<(), Error> {
+        let permissions = Some(rules.iter().map(Rule::to_aws).collect());
+
+        match direction {
+            Direction::Ingress => {
+                let _output = client
+                    .main
+                    .ec2
+                    .authorize_security_group_ingress()
+                    .group_id(self.id.as_str())
+                    .set_ip_permissions(permissions)
+                    .send()
+                    .await?;
+            }
+            Direction::Egress => {
+                let _output = client
+                    .main
+                    .ec2
+                    .authorize_security_group_egress()
+                    .group_id(self.id.as_str())
+                    .set_ip_permissions(permissions)
+                    .send()
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Removes `rules` in `direction`. Fails if one of the rules does not exist.
+    pub async fn revoke(
+        &self,
+        client: &RegionClient,
+        direction: Direction,
+        rules: &[Rule],
+    ) -> Result<(), Error> {
+        let permissions = Some(rules.iter().map(Rule::to_aws).collect());
+
+        match direction {
+            Direction::Ingress => {
+                let _output = client
+                    .main
+                    .ec2
+                    .revoke_security_group_ingress()
+                    .group_id(self.id.as_str())
+                    .set_ip_permissions(permissions)
+                    .send()
+                    .await?;
+            }
+            Direction::Egress => {
+                let _output = client
+                    .main
+                    .ec2
+                    .revoke_security_group_egress()
+                    .group_id(self.id.as_str())
+                    .set_ip_permissions(permissions)
+                    .send()
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Deletes the security group. Fails with [`Error::ResourceProtected`] if the
+    /// security group carries the protection tag of `client`, unless `protection` is
+    /// [`Protection::Override`].
+    ///
+    /// Also fails while instances or rules of other security groups still reference it.
+    pub async fn delete(self, client: &RegionClient, protection: Protection) -> Result<(), Error> {
+        client.check_protection(&self.id, &self.tags, protection)?;
+
+        let _output = client
+            .main
+            .ec2
+            .delete_security_group()
+            .group_id(self.id.as_str())
+            .send()
+            .await?;
+
+        Ok(())
+    }
+
+    pub const fn id(&self) -> &SecurityGroupId {
+        &self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub const fn vpc_id(&self) -> Option<&VpcId> {
+        self.vpc_id.as_ref()
+    }
+
+    pub const fn tags(&self) -> &TagList {
+        &self.tags
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_cidr() {
+        let cidr: Cidr = "10.0.0.0/16".parse().unwrap();
+        assert_eq!(cidr.prefix_len(), 16);
+        assert_eq!(cidr.to_string(), "10.0.0.0/16");
+
+        assert_eq!("2001:db8::/32".parse::<Cidr>().unwrap().prefix_len(), 32);
+        for (value, reason) in [
+            ("10.0.0.0", "missing prefix"),
+            ("10.0.0.0/33", "too long prefix"),
+        ] {
+            assert!(
+                matches!(value.parse::<Cidr>(), Err(Error::InvalidArgument { .. })),
+                "{reason} is accepted"
+            );
+        }
+    }
+
+    #[test]
+    fn rule_round_trip() {
+        let rules = [
+            Rule {
+                protocol: Protocol::Tcp,
+                ports: Some(PortRange::single(443)),
+                source: RuleSource::Cidr("0.0.0.0/0".parse().unwrap()),
+                description: Some("https".to_owned()),
+            },
+            Rule {
+                protocol: Protocol::Udp,
+                ports: Some(PortRange {
+                    from: 60000,
+                    to: 61000,
+                }),
+                source: RuleSource::Cidr("2001:db8::/32".parse().unwrap()),
+                description: None,
+            },
+            Rule {
+                protocol: Protocol::All,
+                ports: None,
+                source: RuleSource::SecurityGroup(SecurityGroupId::new("sg-0123".to_owned())),
+                description: None,
+            },
+            Rule {
+                protocol: Protocol::Icmp,
+                ports: None,
+                source: RuleSource::Cidr("10.0.0.0/8".parse().unwrap()),
+                description: None,
+            },
+            Rule {
+                protocol: Protocol::Tcp,
+                ports: None,
+                source: RuleSource::Cidr("10.0.0.0/8".parse().unwrap()),
+                description: None,
+            },
+            Rule {
+                protocol: Protocol::Other("50".to_owned()),
+                ports: None,
+                source: RuleSource::Cidr("10.0.0.0/8".parse().unwrap()),
+                description: None,
+            },
+        ];
+
+        for rule in rules {
+            assert_eq!(Rule::from_aws(rule.to_aws()).unwrap(), vec![rule]);
+        }
+    }
+
+    #[test]
+    fn all_tcp_ports() {
+        let permission = Rule {
+            protocol: Protocol::Tcp,
+            ports: None,
+            source: RuleSource::Cidr("10.0.0.0/8".parse().unwrap()),
+            description: None,
+        }
+        .to_aws();
+
+        assert_eq!(permission.from_port, Some(0));
+        assert_eq!(permission.to_port, Some(65535));
+    }
+}
@@ -0,0 +1,266 @@
+//! EBS snapshots, e.g. for backups of volumes
+
+use std::time::Duration;
+
+use aws_sdk_ec2::error::ProvideErrorMetadata as _;
+
+use super::{
+    pagination,
+    tags::{RawTag, TagList},
+    waiter, Error, Protection, RegionClient, SnapshotId, Timestamp, VolumeId,
+};
+
+crate::wrap_aws_enum!(SnapshotState);
+
+/// Restricts listings to snapshots of the account, instead of all public ones
+const OWNER_SELF: &str = "self";
+
+/// Describing a snapshot ID that does not exist fails instead of returning nothing
+const NOT_FOUND_ERROR_CODE: &str = "InvalidSnapshot.NotFound";
+
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    id: SnapshotId,
+    /// `None` for snapshots that were copied, or whose volume does not exist anymore
+    volume_id: Option<VolumeId>,
+    state: SnapshotState,
+    /// Why the snapshot failed, if it did
+    state_message: Option<String>,
+    progress: Option<String>,
+    size_gib: Option<i32>,
+    start_time: Option<Timestamp>,
+    description: Option<String>,
+    tags: TagList,
+}
+
+impl TryFrom<aws_sdk_ec2::types::Snapshot> for Snapshot {
+    type Error = Error;
+
+    fn try_from(snapshot: aws_sdk_ec2::types::Snapshot) -> Result<Self, Self::Error> {
+        macro_rules! extract {
+            ($field:ident) => {
+                snapshot.$field.ok_or_else(|| Error::UnexpectedNoneValue {
+                    entity: stringify!($field).to_owned(),
+                })
+            };
+        }
+
+        Ok(Self {
+            id: SnapshotId(extract!(snapshot_id)?),
+            volume_id: snapshot.volume_id.map(VolumeId),
+            state: SnapshotState(extract!(state)?),
+            state_message: snapshot.state_message,
+            progress: snapshot.progress,
+            size_gib: snapshot.volume_size,
+            start_time: snapshot.start_time.map(TryInto::try_into).transpose()?,
+            description: snapshot.description,
+            tags: snapshot.tags.unwrap_or_default().try_into()?,
+        })
+    }
+}
+
+impl Snapshot {
+    /// Starts a snapshot of `volume`. The snapshot is usable once it is completed, see
+    /// [`wait_until_completed()`](Self::wait_until_completed()).
+    pub async fn create_from_volume(
+        client: &RegionClient,
+        volume: &VolumeId,
+        description: Option<&str>,
+        tags: &TagList,
+    ) -> Result<Self, Error> {
+        let output = client
+            .main
+            .ec2
+            .create_snapshot()
+            .volume_id(volume.as_str())
+            .set_description(description.map(ToOwned::to_owned))
+            .tag_specifications(
+                aws_sdk_ec2::types::TagSpecification::builder()
+                    .resource_type(aws_sdk_ec2::types::ResourceType::Snapshot)
+                    .set_tags(Some(client.tags_with_defaults(tags).into()))
+                    .build(),
+            )
+            .send()
+            .await?;
+
+        Ok(Self {
+            id: SnapshotId(
+                output
+                    .snapshot_id
+                    .ok_or_else(|| Error::UnexpectedNoneValue {
+                        entity: "CreateSnapshotOutput.snapshot_id".to_owned(),
+                    })?,
+            ),
+            volume_id: output.volume_id.map(VolumeId),
+            state: SnapshotState(output.state.ok_or_else(|| Error::UnexpectedNoneValue {
+                entity: "CreateSnapshotOutput.state".to_owned(),
+            })?),
+            state_message: output.state_message,
+            progress: output.progress,
+            size_gib: output.volume_size,
+            start_time: output.start_time.map(TryInto::try_into).transpose()?,
+            description: output.description,
+            tags: output.tags.unwrap_or_default().try_into()?,
+        })
+    }
+
+    pub async fn get(client: &RegionClient, id: &SnapshotId) -> Result<Option<Self>, Error> {
+        match client
+            .main
+            .ec2
+            .describe_snapshots()
+            .snapshot_ids(id.as_str())
+            .send()
+            .await
+        {
+            Ok(output) => output
+                .snapshots
+                .unwrap_or_default()
+                .pop()
+                .map(TryInto::try_into)
+                .transpose(),
+            Err(e) if e.code() == Some(NOT_FOUND_ERROR_CODE) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// All snapshots of the account that carry `tag`
+    pub async fn list_by_tag(client: &RegionClient, tag: &RawTag) -> Result<Vec<Self>, Error> {
+        pagination::list_snapshots(
+            client,
+            vec![OWNER_SELF.to_owned()],
+            TagList::from_vec(vec![tag.clone()]).into(),
+        )
+        .await?
+        .into_iter()
+        .map(TryInto::try_into)
+        .collect()
+    }
+
+    /// Waits until the snapshot is completed. Returns the snapshot as described in that
+    /// state.
+    ///
+    /// If `max_wait` is `None`, the [`WaitConfig`](crate::waiter::WaitConfig) of
+    /// `client` applies.
+    pub async fn wait_until_completed(
+        &self,
+        client: &RegionClient,
+        max_wait: Option<Duration>,
+    ) -> Result<Self, Error> {
+        let max_wait = max_wait.unwrap_or(client.wait_config.snapshot);
+
+        waiter::poll(client.waiter_profile, max_wait, || async move {
+            // A new snapshot may not be visible right away
+            let Some(snapshot) = Self::get(client, &self.id).await? else {
+                return Ok(None);
+            };
+            match *snapshot.state.inner() {
+                aws_sdk_ec2::types::SnapshotState::Completed => Ok(Some(snapshot)),
+                aws_sdk_ec2::types::SnapshotState::Error => Err(Error::SnapshotFailed {
+                    snapshot: self.id.clone(),
+                    message: snapshot.state_message.unwrap_or_default(),
+                }),
+                _ => Ok(None),
+            }
+        })
+        .await?
+        .ok_or_else(|| Error::SnapshotExceededMaxWait {
+            max_wait,
+            snapshot: self.id.clone(),
+        })
+    }
+
+    /// Deletes the snapshot. Fails with [`Error::ResourceProtected`] if the snapshot
+    /// carries the protection tag of `client`, unless `protection` is
+    /// [`Protection::Override`].
+    pub async fn delete(&self, client: &RegionClient, protection: Protection) -> Result<(), Error> {
+        client.check_protection(&self.id, &self.tags, protection)?;
+
+        let _output = client
+            .main
+            .ec2
+            .delete_snapshot()
+            .snapshot_id(self.id.as_str())
+            .send()
+            .await?;
+
+        Ok(())
+    }
+
+    pub const fn id(&self) -> &SnapshotId {
+        &self.id
+    }
+
+    pub const fn volume_id(&self) -> Option<&VolumeId> {
+        self.volume_id.as_ref()
+    }
+
+    pub const fn state(&self) -> &SnapshotState {
+        &self.state
+    }
+
+    /// E.g. `42%`
+    pub fn progress(&self) -> Option<&str> {
+        self.progress.as_deref()
+    }
+
+    pub const fn size_gib(&self) -> Option<i32> {
+        self.size_gib
+    }
+
+    pub const fn start_time(&self) -> Option<&Timestamp> {
+        self.start_time.as_ref()
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    pub const fn tags(&self) -> &TagList {
+        &self.tags
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_aws() {
+        let snapshot = Snapshot::try_from(
+            aws_sdk_ec2::types::Snapshot::builder()
+                .snapshot_id("snap-1")
+                .volume_id("vol-1")
+                .state(aws_sdk_ec2::types::SnapshotState::Pending)
+                .progress("42%")
+                .tags(
+                    aws_sdk_ec2::types::Tag::builder()
+                        .key("backup")
+                        .value("daily")
+                        .build(),
+                )
+                .build(),
+        )
+        .unwrap();
+
+        assert_eq!(snapshot.id().as_str(), "snap-1");
+        assert_eq!(snapshot.volume_id().map(VolumeId::as_str), Some("vol-1"));
+        assert_eq!(snapshot.progress(), Some("42%"));
+        assert_eq!(
+            snapshot.tags().as_slice(),
+            [RawTag::new("backup".to_owned(), "daily".to_owned())]
+        );
+    }
+
+    #[test]
+    fn missing_state() {
+        assert!(matches!(
+            Snapshot::try_from(
+                aws_sdk_ec2::types::Snapshot::builder()
+                    .snapshot_id("snap-1")
+                    .build()
+            ),
+            Err(Error::UnexpectedNoneValue { ref entity }) if entity == "state"
+        ));
+    }
+}
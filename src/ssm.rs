@@ -0,0 +1,325 @@
+//! Running shell commands on instances via SSM Run Command
+//!
+//! Instances need the SSM agent and an instance profile that allows it to talk to
+//! SSM.
+
+use std::time::Duration;
+
+use super::{waiter, Error, Instance, InstanceId, RegionClient, Timestamp};
+
+const SHELL_SCRIPT_DOCUMENT: &str = "AWS-RunShellScript";
+const PATCH_BASELINE_DOCUMENT: &str = "AWS-RunPatchBaseline";
+
+crate::string_newtype!(CommandId);
+
+impl CommandId {
+    pub const fn new(value: String) -> Self {
+        Self(value)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandStatus {
+    Success,
+    Failed,
+    Cancelled,
+    TimedOut,
+}
+
+#[derive(Debug, Clone)]
+pub struct CommandResult {
+    pub status: CommandStatus,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// Runs `commands` as a shell script on all `instances`.
+///
+/// This only submits the command, use [`wait_for_command()`] to get the result for
+/// each instance.
+pub async fn run_shell_script(
+    client: &RegionClient,
+    instances: &[InstanceId],
+    commands: Vec<String>,
+) -> Result<CommandId, Error> {
+    client
+        .main
+        .ssm
+        .send_command()
+        .document_name(SHELL_SCRIPT_DOCUMENT)
+        .set_instance_ids(Some(
+            instances.iter().map(|id| id.as_str().to_owned()).collect(),
+        ))
+        .parameters("commands", commands)
+        .send()
+        .await?
+        .command
+        .and_then(|command| command.command_id)
+        .map(CommandId)
+        .ok_or_else(|| Error::UnexpectedNoneValue {
+            entity: "SendCommandOutput.command.command_id".to_owned(),
+        })
+}
+
+/// The final status of an invocation, `None` while it is still running
+const fn command_status(
+    status: &aws_sdk_ssm::types::CommandInvocationStatus,
+) -> Option<CommandStatus> {
+    match *status {
+        aws_sdk_ssm::types::CommandInvocationStatus::Success => Some(CommandStatus::Success),
+        aws_sdk_ssm::types::CommandInvocationStatus::Failed => Some(CommandStatus::Failed),
+        aws_sdk_ssm::types::CommandInvocationStatus::Cancelled => Some(CommandStatus::Cancelled),
+        aws_sdk_ssm::types::CommandInvocationStatus::TimedOut => Some(CommandStatus::TimedOut),
+        _ => None,
+    }
+}
+
+/// The result of `command` on `instance`, `None` while it is still running
+pub async fn command_result(
+    client: &RegionClient,
+    command: &CommandId,
+    instance: &InstanceId,
+) -> Result<Option<CommandResult>, Error> {
+    match client
+        .main
+        .ssm
+        .get_command_invocation()
+        .command_id(command.as_str())
+        .instance_id(instance.as_str())
+        .send()
+        .await
+    {
+        Ok(invocation) => {
+            let Some(status) = invocation.status.as_ref().and_then(command_status) else {
+                return Ok(None);
+            };
+
+            Ok(Some(CommandResult {
+                status,
+                stdout: invocation.standard_output_content.unwrap_or_default(),
+                stderr: invocation.standard_error_content.unwrap_or_default(),
+            }))
+        }
+        Err(e) => {
+            let e = Error::from(e);
+            // The invocation shows up with a small delay after sending the command
+            if e.code() == Some("InvocationDoesNotExist") {
+                Ok(None)
+            } else {
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Waits until `command` has finished on `instance`, polling for at most `max_wait`
+/// according to the [`WaiterProfile`](crate::waiter::WaiterProfile) of `client`.
+///
+/// If `max_wait` is `None`, the [`WaitConfig`](crate::waiter::WaitConfig) of `client`
+/// applies.
+pub async fn wait_for_command(
+    client: &RegionClient,
+    command: &CommandId,
+    instance: &InstanceId,
+    max_wait: Option<Duration>,
+) -> Result<CommandResult, Error> {
+    let max_wait = max_wait.unwrap_or(client.wait_config.ssm_command);
+
+    waiter::poll(client.waiter_profile, max_wait, || async move {
+        command_result(client, command, instance).await
+    })
+    .await?
+    .ok_or_else(|| Error::CommandExceededMaxWait {
+        max_wait,
+        command: command.as_str().to_owned(),
+        instance: instance.clone(),
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchCompliance {
+    Compliant,
+    /// Patches are missing, failed to install, or are waiting for a reboot
+    NonCompliant,
+}
+
+/// Patch counts of the last patch scan or installation on an instance
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatchState {
+    pub installed: u32,
+    /// Installed patches that are not part of the patch baseline
+    pub installed_other: u32,
+    pub installed_pending_reboot: u32,
+    pub missing: u32,
+    pub failed: u32,
+    pub not_applicable: u32,
+    pub last_operation: Timestamp,
+    pub compliance: PatchCompliance,
+}
+
+/// SSM reports counts as signed integers, but they are never negative
+fn patch_count(count: i32) -> u32 {
+    u32::try_from(count).unwrap_or_default()
+}
+
+impl TryFrom<aws_sdk_ssm::types::InstancePatchState> for PatchState {
+    type Error = Error;
+
+    fn try_from(state: aws_sdk_ssm::types::InstancePatchState) -> Result<Self, Self::Error> {
+        let installed_pending_reboot =
+            patch_count(state.installed_pending_reboot_count.unwrap_or_default());
+        let missing = patch_count(state.missing_count);
+        let failed = patch_count(state.failed_count);
+
+        Ok(Self {
+            installed: patch_count(state.installed_count),
+            installed_other: patch_count(state.installed_other_count),
+            installed_pending_reboot,
+            missing,
+            failed,
+            not_applicable: patch_count(state.not_applicable_count),
+            last_operation: state.operation_end_time.try_into()?,
+            compliance: if missing == 0 && failed == 0 && installed_pending_reboot == 0 {
+                PatchCompliance::Compliant
+            } else {
+                PatchCompliance::NonCompliant
+            },
+        })
+    }
+}
+
+/// Returns the patch state of `instance`, or `None` if it was never scanned or
+/// patched by Patch Manager.
+pub async fn patch_state(
+    client: &RegionClient,
+    instance: &Instance,
+) -> Result<Option<PatchState>, Error> {
+    client
+        .main
+        .ssm
+        .describe_instance_patch_states()
+        .instance_ids(instance.instance_id().as_str())
+        .send()
+        .await?
+        .instance_patch_states
+        .and_then(|states| states.into_iter().next())
+        .map(PatchState::try_from)
+        .transpose()
+}
+
+/// Installs missing patches on `instance` according to its patch baseline, rebooting
+/// it if needed, and waits for at most `max_wait` for the installation to finish.
+///
+/// If `max_wait` is `None`, the [`WaitConfig`](crate::waiter::WaitConfig) of `client`
+/// applies. Use [`patch_state()`] afterwards to check the compliance.
+pub async fn trigger_patch_now(
+    client: &RegionClient,
+    instance: &Instance,
+    max_wait: Option<Duration>,
+) -> Result<CommandResult, Error> {
+    let command = client
+        .main
+        .ssm
+        .send_command()
+        .document_name(PATCH_BASELINE_DOCUMENT)
+        .instance_ids(instance.instance_id().as_str())
+        .parameters("Operation", vec!["Install".to_owned()])
+        .parameters("RebootOption", vec!["RebootIfNeeded".to_owned()])
+        .send()
+        .await?
+        .command
+        .and_then(|command| command.command_id)
+        .map(CommandId)
+        .ok_or_else(|| Error::UnexpectedNoneValue {
+            entity: "SendCommandOutput.command.command_id".to_owned(),
+        })?;
+
+    wait_for_command(
+        client,
+        &command,
+        instance.instance_id(),
+        Some(max_wait.unwrap_or(client.wait_config.patch_run)),
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instance_patch_state(
+        missing: i32,
+        failed: i32,
+        pending_reboot: i32,
+    ) -> aws_sdk_ssm::types::InstancePatchState {
+        aws_sdk_ssm::types::InstancePatchState::builder()
+            .instance_id("i-1")
+            .patch_group("default")
+            .baseline_id("pb-1")
+            .operation(aws_sdk_ssm::types::PatchOperationType::Install)
+            .operation_start_time(aws_smithy_types::DateTime::from_secs(1_700_000_000))
+            .operation_end_time(aws_smithy_types::DateTime::from_secs(1_700_000_600))
+            .installed_count(12)
+            .installed_other_count(-1)
+            .missing_count(missing)
+            .failed_count(failed)
+            .installed_pending_reboot_count(pending_reboot)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn patch_state_conversion() {
+        let state = PatchState::try_from(instance_patch_state(0, 0, 0)).unwrap();
+
+        assert_eq!(state.installed, 12);
+        assert_eq!(state.installed_other, 0, "negative counts are clamped");
+        assert_eq!(
+            state.last_operation,
+            Timestamp::parse("2023-11-14T22:23:20Z").unwrap()
+        );
+        assert_eq!(state.compliance, PatchCompliance::Compliant);
+    }
+
+    #[test]
+    fn patch_compliance() {
+        for (missing, failed, pending_reboot) in [(1, 0, 0), (0, 1, 0), (0, 0, 1)] {
+            assert_eq!(
+                PatchState::try_from(instance_patch_state(missing, failed, pending_reboot))
+                    .unwrap()
+                    .compliance,
+                PatchCompliance::NonCompliant,
+                "{missing} missing, {failed} failed, {pending_reboot} pending reboot"
+            );
+        }
+    }
+
+    #[test]
+    fn command_statuses() {
+        assert_eq!(
+            command_status(&aws_sdk_ssm::types::CommandInvocationStatus::Success),
+            Some(CommandStatus::Success)
+        );
+        assert_eq!(
+            command_status(&aws_sdk_ssm::types::CommandInvocationStatus::TimedOut),
+            Some(CommandStatus::TimedOut)
+        );
+        for status in [
+            aws_sdk_ssm::types::CommandInvocationStatus::Pending,
+            aws_sdk_ssm::types::CommandInvocationStatus::InProgress,
+            aws_sdk_ssm::types::CommandInvocationStatus::Delayed,
+            aws_sdk_ssm::types::CommandInvocationStatus::Cancelling,
+        ] {
+            assert_eq!(
+                command_status(&status),
+                None,
+                "{} is still running",
+                status.as_str()
+            );
+        }
+    }
+}
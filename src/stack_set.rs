@@ -0,0 +1,210 @@
+//! CloudFormation stack sets, to roll out the same stack to several accounts and
+//! regions
+//!
+//! Like single stacks, stack sets are managed through the [`CdnClient`]. They use the
+//! self-managed permission model: the administration role in the calling account
+//! assumes the execution role in each target account. Both roles have to exist
+//! already.
+//!
+//! Operations on a stack set run asynchronously. Each returns a [`StackSetOperationId`]
+//! to follow it with [`operation_status()`].
+
+use std::fmt;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::{tags::TagList, Account, CdnClient, CloudformationParameters, Error, Region};
+
+crate::string_newtype!(StackSetOperationId);
+
+impl StackSetOperationId {
+    pub const fn new(value: String) -> Self {
+        Self(value)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct NewStackSetConfig<'a> {
+    pub name: &'a str,
+    pub template: &'a str,
+    pub parameters: &'a CloudformationParameters,
+    /// If `None`, `AWSCloudFormationStackSetAdministrationRole` in the calling account
+    pub administration_role_arn: Option<&'a str>,
+    /// If `None`, `AWSCloudFormationStackSetExecutionRole` in each target account
+    pub execution_role_name: Option<&'a str>,
+    pub tags: &'a TagList,
+}
+
+/// How an operation spreads over accounts and regions. `None` keeps the CloudFormation
+/// default, which is one account and region at a time, stopping at the first failure.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OperationPreferences {
+    pub max_concurrent_count: Option<i32>,
+    pub failure_tolerance_count: Option<i32>,
+    /// Deploy to all regions of an account at the same time
+    pub parallel_regions: bool,
+}
+
+impl OperationPreferences {
+    fn to_aws(self) -> aws_sdk_cloudformation::types::StackSetOperationPreferences {
+        aws_sdk_cloudformation::types::StackSetOperationPreferences::builder()
+            .set_max_concurrent_count(self.max_concurrent_count)
+            .set_failure_tolerance_count(self.failure_tolerance_count)
+            .region_concurrency_type(if self.parallel_regions {
+                aws_sdk_cloudformation::types::RegionConcurrencyType::Parallel
+            } else {
+                aws_sdk_cloudformation::types::RegionConcurrencyType::Sequential
+            })
+            .build()
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperationStatus {
+    Queued,
+    Running,
+    Stopping,
+    Stopped,
+    Succeeded,
+    Failed,
+}
+
+impl OperationStatus {
+    fn from_aws(status: &aws_sdk_cloudformation::types::StackSetOperationStatus) -> Option<Self> {
+        match *status {
+            aws_sdk_cloudformation::types::StackSetOperationStatus::Queued => Some(Self::Queued),
+            aws_sdk_cloudformation::types::StackSetOperationStatus::Running => Some(Self::Running),
+            aws_sdk_cloudformation::types::StackSetOperationStatus::Stopping => {
+                Some(Self::Stopping)
+            }
+            aws_sdk_cloudformation::types::StackSetOperationStatus::Stopped => Some(Self::Stopped),
+            aws_sdk_cloudformation::types::StackSetOperationStatus::Succeeded => {
+                Some(Self::Succeeded)
+            }
+            aws_sdk_cloudformation::types::StackSetOperationStatus::Failed => Some(Self::Failed),
+            _ => None,
+        }
+    }
+
+    pub const fn is_finished(self) -> bool {
+        matches!(self, Self::Stopped | Self::Succeeded | Self::Failed)
+    }
+}
+
+impl fmt::Display for OperationStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                Self::Queued => "queued",
+                Self::Running => "running",
+                Self::Stopping => "stopping",
+                Self::Stopped => "stopped",
+                Self::Succeeded => "succeeded",
+                Self::Failed => "failed",
+            }
+        )
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StackSetOperation {
+    pub id: StackSetOperationId,
+    pub status: OperationStatus,
+    pub status_reason: Option<String>,
+}
+
+/// Creates the stack set without any stack instances, see [`add_stack_instances()`].
+/// Returns the ID of the stack set.
+pub async fn create_stack_set(
+    cdn: &CdnClient,
+    config: &NewStackSetConfig<'_>,
+) -> Result<String, Error> {
+    cdn.cloudformation
+        .create_stack_set()
+        .stack_set_name(config.name)
+        .template_body(config.template)
+        .set_parameters(Some(config.parameters.to_aws()))
+        .permission_model(aws_sdk_cloudformation::types::PermissionModels::SelfManaged)
+        .set_administration_role_arn(config.administration_role_arn.map(ToOwned::to_owned))
+        .set_execution_role_name(config.execution_role_name.map(ToOwned::to_owned))
+        .capabilities(aws_sdk_cloudformation::types::Capability::CapabilityAutoExpand)
+        .set_tags(Some(cdn.tags_with_defaults(config.tags).into()))
+        .send()
+        .await?
+        .stack_set_id
+        .ok_or_else(|| Error::UnexpectedNoneValue {
+            entity: "CreateStackSetOutput.stack_set_id".to_owned(),
+        })
+}
+
+/// Deploys the stack set to each combination of `accounts` and `regions`
+pub async fn add_stack_instances(
+    cdn: &CdnClient,
+    stack_set: &str,
+    accounts: &[Account],
+    regions: &[Region],
+    preferences: OperationPreferences,
+) -> Result<StackSetOperationId, Error> {
+    cdn.cloudformation
+        .create_stack_instances()
+        .stack_set_name(stack_set)
+        .set_accounts(Some(
+            accounts
+                .iter()
+                .map(|account| account.id().to_owned())
+                .collect(),
+        ))
+        .set_regions(Some(
+            regions
+                .iter()
+                .map(|region| region.as_str().to_owned())
+                .collect(),
+        ))
+        .operation_preferences(preferences.to_aws())
+        .send()
+        .await?
+        .operation_id
+        .map(StackSetOperationId)
+        .ok_or_else(|| Error::UnexpectedNoneValue {
+            entity: "CreateStackInstancesOutput.operation_id".to_owned(),
+        })
+}
+
+pub async fn operation_status(
+    cdn: &CdnClient,
+    stack_set: &str,
+    operation: &StackSetOperationId,
+) -> Result<StackSetOperation, Error> {
+    let described = cdn
+        .cloudformation
+        .describe_stack_set_operation()
+        .stack_set_name(stack_set)
+        .operation_id(operation.as_str())
+        .send()
+        .await?
+        .stack_set_operation
+        .ok_or_else(|| Error::UnexpectedNoneValue {
+            entity: "DescribeStackSetOperationOutput.stack_set_operation".to_owned(),
+        })?;
+
+    let status = described.status.ok_or_else(|| Error::UnexpectedNoneValue {
+        entity: "StackSetOperation.status".to_owned(),
+    })?;
+
+    Ok(StackSetOperation {
+        id: operation.clone(),
+        status: OperationStatus::from_aws(&status).ok_or_else(|| Error::InvalidResponseError {
+            message: format!("unknown stack set operation status {}", status.as_str()),
+        })?,
+        status_reason: described.status_reason,
+    })
+}
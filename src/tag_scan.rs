@@ -0,0 +1,235 @@
+//! Finding tagged resources across regions
+//!
+//! [`scan_all_tags()`] answers questions like "where is `environment=staging`
+//! running?" without describing each resource type in each region one after the
+//! other. It uses the Resource Groups Tagging API, which returns the tags of the
+//! resources of all services that support tagging, e.g. EC2 instances, volumes and
+//! security groups, load balancers or S3 buckets, in one paginated call per region and
+//! tag key.
+
+use std::collections::BTreeMap;
+
+use futures_util::StreamExt as _;
+
+use super::{
+    pagination,
+    tags::{RawTag, TagKey},
+    Error, Region, RegionClient,
+};
+
+#[derive(Debug, Clone)]
+pub struct ScanConfig {
+    /// How many regions are scanned at the same time. 0 is treated as 1. Throttled
+    /// pages are requested again after a backoff, so a high concurrency slows the scan
+    /// down instead of failing it.
+    pub max_concurrency: usize,
+}
+
+impl Default for ScanConfig {
+    fn default() -> Self {
+        Self { max_concurrency: 4 }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TaggedResource {
+    pub region: Region,
+    /// The service, followed by the resource type if the ARN contains one, e.g.
+    /// `ec2:instance` or `s3`
+    pub resource_type: String,
+    pub arn: String,
+}
+
+impl TaggedResource {
+    fn is_same(&self, other: &Self) -> bool {
+        self.region.as_str() == other.region.as_str() && self.arn == other.arn
+    }
+}
+
+/// Derives [`TaggedResource::resource_type`] from an ARN like
+/// `arn:aws:ec2:eu-central-1:111111111111:instance/i-0123`
+fn resource_type(arn: &str) -> String {
+    let mut parts = arn.splitn(6, ':').skip(2);
+    let service = parts.next().unwrap_or_default();
+    let resource = parts.nth(2).unwrap_or_default();
+
+    match resource.split_once(['/', ':']) {
+        Some((resource_type, _id)) => format!("{service}:{resource_type}"),
+        None => service.to_owned(),
+    }
+}
+
+/// Maps each tag to the resources that carry it
+#[derive(Debug, Clone, Default)]
+pub struct TagIndex {
+    resources: BTreeMap<(String, String), Vec<TaggedResource>>,
+}
+
+impl TagIndex {
+    /// Ignores resources that are indexed under `tag` already, e.g. when the same region
+    /// is scanned twice
+    fn insert(&mut self, tag: RawTag, resource: TaggedResource) {
+        let resources = self
+            .resources
+            .entry((
+                tag.key().as_str().to_owned(),
+                tag.value().as_str().to_owned(),
+            ))
+            .or_default();
+
+        if !resources.iter().any(|existing| existing.is_same(&resource)) {
+            resources.push(resource);
+        }
+    }
+
+    pub fn resources(&self, tag: &RawTag) -> &[TaggedResource] {
+        self.resources
+            .get(&(
+                tag.key().as_str().to_owned(),
+                tag.value().as_str().to_owned(),
+            ))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// All distinct values of `key`, sorted
+    pub fn values<'a>(&'a self, key: &'a TagKey) -> impl Iterator<Item = &'a str> + 'a {
+        self.resources
+            .keys()
+            .filter(move |&&(ref indexed_key, _)| indexed_key == key.as_str())
+            .map(|&(_, ref value)| value.as_str())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.resources.is_empty()
+    }
+}
+
+/// All resources in the region of `client` carrying `key`, with all their tags
+async fn scan_key(
+    client: &RegionClient,
+    key: &TagKey,
+) -> Result<Vec<aws_sdk_resourcegroupstagging::types::ResourceTagMapping>, Error> {
+    let tagging = &client.main.resourcegroupstagging;
+
+    pagination::paginate(|token| async move {
+        let output = tagging
+            .get_resources()
+            .tag_filters(
+                aws_sdk_resourcegroupstagging::types::TagFilter::builder()
+                    .key(key.as_str())
+                    .build(),
+            )
+            .set_pagination_token(token)
+            .send()
+            .await?;
+
+        Ok(pagination::Page {
+            items: output.resource_tag_mapping_list.unwrap_or_default(),
+            // An empty token marks the last page
+            next_token: output.pagination_token.filter(|token| !token.is_empty()),
+        })
+    })
+    .await
+}
+
+async fn scan_region(
+    client: &RegionClient,
+    keys: &[TagKey],
+) -> Result<Vec<(RawTag, TaggedResource)>, Error> {
+    let mut found = vec![];
+
+    // Multiple tag filters only match resources carrying all keys, so each key is
+    // listed on its own
+    for key in keys {
+        for mapping in scan_key(client, key).await? {
+            let Some(arn) = mapping.resource_arn else {
+                continue;
+            };
+            let resource = TaggedResource {
+                region: client.region,
+                resource_type: resource_type(&arn),
+                arn,
+            };
+
+            found.extend(
+                mapping
+                    .tags
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(|tag| keys.iter().any(|key| key.as_str() == tag.key))
+                    .map(|tag| (RawTag::new(tag.key, tag.value), resource.clone())),
+            );
+        }
+    }
+
+    Ok(found)
+}
+
+/// Collects the values of `keys` in the regions of all `clients`. Fails if the scan of
+/// any region fails.
+pub async fn scan_all_tags(
+    clients: &[RegionClient],
+    keys: &[TagKey],
+    config: &ScanConfig,
+) -> Result<TagIndex, Error> {
+    let mut index = TagIndex::default();
+
+    if keys.is_empty() {
+        return Ok(index);
+    }
+
+    let mut scans = futures_util::stream::iter(clients)
+        .map(|client| scan_region(client, keys))
+        .buffer_unordered(config.max_concurrency.max(1));
+
+    while let Some(found) = scans.next().await {
+        for (tag, resource) in found? {
+            index.insert(tag, resource);
+        }
+    }
+
+    Ok(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn index_deduplicates_resources() {
+        let resource = |region, id: &str| TaggedResource {
+            region,
+            resource_type: "ec2:instance".to_owned(),
+            arn: format!("arn:aws:ec2:{region}:111111111111:instance/{id}"),
+        };
+        let staging = RawTag::new("environment".to_owned(), "staging".to_owned());
+        let production = RawTag::new("environment".to_owned(), "production".to_owned());
+
+        let mut index = TagIndex::default();
+        index.insert(staging.clone(), resource(Region::EuCentral1, "i-1"));
+        index.insert(staging.clone(), resource(Region::EuCentral1, "i-1"));
+        index.insert(staging.clone(), resource(Region::UsEast1, "i-1"));
+        index.insert(production, resource(Region::EuCentral1, "i-2"));
+
+        assert_eq!(index.resources(&staging).len(), 2);
+        assert_eq!(
+            index
+                .values(&TagKey::new("environment".to_owned()))
+                .collect::<Vec<_>>(),
+            vec!["production", "staging"]
+        );
+    }
+
+    #[test]
+    fn resource_type_from_arn() {
+        assert_eq!(
+            resource_type("arn:aws:ec2:eu-central-1:111111111111:instance/i-0123"),
+            "ec2:instance"
+        );
+        assert_eq!(
+            resource_type("arn:aws:rds:eu-central-1:111111111111:db:main"),
+            "rds:db"
+        );
+        assert_eq!(resource_type("arn:aws:s3:::assets"), "s3");
+    }
+}
@@ -2,8 +2,26 @@
 
 use std::{convert::Infallible, fmt};
 
+#[cfg(feature = "serde")]
+use serde::{ser::SerializeStruct as _, Serialize, Serializer};
+
 use super::{RawTagValue, TagKey};
 
+/// Serializes an error as its stable `error_type()` and display message
+#[cfg(feature = "serde")]
+macro_rules! impl_serialize {
+    ($name:ident) => {
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                let mut state = serializer.serialize_struct(stringify!($name), 2)?;
+                state.serialize_field("type", self.error_type())?;
+                state.serialize_field("message", &self.to_string())?;
+                state.end()
+            }
+        }
+    };
+}
+
 #[derive(Debug, Clone)]
 pub enum ParseTagAwsError {
     AwsKeyNone,
@@ -12,6 +30,19 @@ pub enum ParseTagAwsError {
 
 impl std::error::Error for ParseTagAwsError {}
 
+impl ParseTagAwsError {
+    /// A stable identifier of the error variant
+    pub const fn error_type(&self) -> &'static str {
+        match *self {
+            Self::AwsKeyNone => "aws_key_none",
+            Self::AwsValueNone { .. } => "aws_value_none",
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl_serialize!(ParseTagAwsError);
+
 impl fmt::Display for ParseTagAwsError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match *self {
@@ -40,6 +71,20 @@ pub enum ParseTagValueError {
 
 impl std::error::Error for ParseTagValueError {}
 
+impl ParseTagValueError {
+    /// A stable identifier of the error variant
+    pub const fn error_type(&self) -> &'static str {
+        match *self {
+            Self::InvalidValue { .. } => "invalid_value",
+            Self::InvalidBoolValue { .. } => "invalid_bool_value",
+            Self::Aws(ref inner) => inner.error_type(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl_serialize!(ParseTagValueError);
+
 impl fmt::Display for ParseTagValueError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match *self {
@@ -74,6 +119,19 @@ pub enum ParseTagError {
 
 impl std::error::Error for ParseTagError {}
 
+impl ParseTagError {
+    /// A stable identifier of the error variant
+    pub const fn error_type(&self) -> &'static str {
+        match *self {
+            Self::InvalidTagValue { ref inner, .. } => inner.error_type(),
+            Self::Aws(ref inner) => inner.error_type(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl_serialize!(ParseTagError);
+
 impl fmt::Display for ParseTagError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match *self {
@@ -107,15 +165,32 @@ pub enum ParseTagsError {
     TagNotFound { key: TagKey },
     /// A single tag failed to parse
     ParseTag(ParseTagError),
+    /// A tag key appeared more than once, see [`TagList::into_strict()`](super::TagList::into_strict())
+    DuplicateTagKey { key: TagKey },
 }
 
 impl std::error::Error for ParseTagsError {}
 
+impl ParseTagsError {
+    /// A stable identifier of the error variant
+    pub const fn error_type(&self) -> &'static str {
+        match *self {
+            Self::TagNotFound { .. } => "tag_not_found",
+            Self::ParseTag(ref inner) => inner.error_type(),
+            Self::DuplicateTagKey { .. } => "duplicate_tag_key",
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl_serialize!(ParseTagsError);
+
 impl fmt::Display for ParseTagsError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match *self {
             Self::TagNotFound { ref key } => write!(f, "tag {key} not found in input"),
             Self::ParseTag(ref err) => write!(f, "failed parsing tag: {err}"),
+            Self::DuplicateTagKey { ref key } => write!(f, "duplicate tag key \"{key}\""),
         }
     }
 }
@@ -0,0 +1,112 @@
+use std::fmt;
+
+use super::TagKey;
+
+/// A tag key that is known at compile time
+///
+/// Usually defined via [`tag_keys!`](crate::tag_keys), so key strings are not
+/// scattered across a codebase. Converts into a [`TagKey`] wherever one is expected:
+///
+/// ```rust
+/// # use aws_lib::tags::{RawTag, StaticTagKey, TagList};
+/// const NAME: StaticTagKey = StaticTagKey::new("Name");
+///
+/// let tags = TagList::from_vec(vec![RawTag::new(NAME, "web".to_owned())]);
+/// assert!(tags.get(NAME).is_some());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StaticTagKey(&'static str);
+
+impl StaticTagKey {
+    pub const fn new(value: &'static str) -> Self {
+        Self(value)
+    }
+
+    pub const fn as_str(self) -> &'static str {
+        self.0
+    }
+}
+
+impl From<StaticTagKey> for TagKey {
+    fn from(value: StaticTagKey) -> Self {
+        Self::new(value.0.to_owned())
+    }
+}
+
+impl PartialEq<StaticTagKey> for TagKey {
+    fn eq(&self, other: &StaticTagKey) -> bool {
+        self.as_str() == other.0
+    }
+}
+
+impl fmt::Display for StaticTagKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Defines tag keys in one place
+///
+/// Either as [`StaticTagKey`](crate::tags::StaticTagKey) constants:
+///
+/// ```rust
+/// aws_lib::tag_keys! {
+///     pub NAME = "Name",
+///     ENVIRONMENT = "environment",
+/// }
+///
+/// assert_eq!(ENVIRONMENT.as_str(), "environment");
+/// ```
+///
+/// Or as a closed enum, for when only a fixed set of keys is valid:
+///
+/// ```rust
+/// # use aws_lib::tags::TagKey;
+/// aws_lib::tag_keys! {
+///     pub enum KnownKey {
+///         Name = "Name",
+///         Environment = "environment",
+///     }
+/// }
+///
+/// let key = TagKey::new("environment".to_owned());
+/// assert_eq!(KnownKey::from_key(&key), Some(KnownKey::Environment));
+/// assert_eq!(KnownKey::ALL.len(), 2);
+/// ```
+#[macro_export]
+macro_rules! tag_keys {
+    ($vis:vis enum $name:ident { $($variant:ident = $key:literal),* $(,)? }) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        $vis enum $name {
+            $($variant),*
+        }
+
+        impl $name {
+            $vis const ALL: &'static [Self] = &[$(Self::$variant),*];
+
+            $vis const fn key(self) -> $crate::tags::StaticTagKey {
+                match self {
+                    $(Self::$variant => $crate::tags::StaticTagKey::new($key)),*
+                }
+            }
+
+            $vis fn from_key(key: &$crate::tags::TagKey) -> ::std::option::Option<Self> {
+                Self::ALL
+                    .iter()
+                    .copied()
+                    .find(|variant| *key == variant.key())
+            }
+        }
+
+        impl ::std::convert::From<$name> for $crate::tags::TagKey {
+            fn from(value: $name) -> Self {
+                value.key().into()
+            }
+        }
+    };
+    ($($vis:vis $name:ident = $key:literal),* $(,)?) => {
+        $(
+            $vis const $name: $crate::tags::StaticTagKey = $crate::tags::StaticTagKey::new($key);
+        )*
+    };
+}
@@ -1,5 +1,8 @@
 #![doc = include_str!("README.md")]
-use std::fmt::{self, Debug};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::{self, Debug},
+};
 
 #[cfg(feature = "serde-tags")]
 use serde::de::DeserializeOwned;
@@ -10,11 +13,13 @@ use serde::Serialize;
 
 mod error;
 mod helpers;
+mod keys;
 mod predefined_types;
 mod svc;
 
 pub use aws_macros::{Tag, Tags};
 pub use error::{ParseTagAwsError, ParseTagError, ParseTagValueError, ParseTagsError};
+pub use keys::StaticTagKey;
 
 #[derive(Debug, PartialEq, Eq)]
 struct InnerTagValue<T>(T)
@@ -300,6 +305,140 @@ impl TagList {
     pub fn as_slice(&self) -> &[RawTag] {
         &self.0
     }
+
+    /// Adds all tags of `defaults` whose key is not already present. The defaults come
+    /// first, followed by the tags of `self`.
+    #[must_use]
+    pub fn with_defaults(&self, defaults: &Self) -> Self {
+        Self(
+            defaults
+                .0
+                .iter()
+                .filter(|default| self.0.iter().all(|tag| tag.key != default.key))
+                .chain(&self.0)
+                .cloned()
+                .collect(),
+        )
+    }
+
+    /// Fails if any key appears more than once.
+    ///
+    /// Converting from AWS tags accepts duplicate keys, which should never be returned by
+    /// AWS. This detects such corrupted tag state early. Keys are compared
+    /// case-sensitively, like AWS does.
+    pub fn into_strict(self) -> Result<Self, ParseTagsError> {
+        for (i, tag) in self.0.iter().enumerate() {
+            if self
+                .0
+                .iter()
+                .skip(i.saturating_add(1))
+                .any(|other| other.key == tag.key)
+            {
+                return Err(ParseTagsError::DuplicateTagKey {
+                    key: tag.key.clone(),
+                });
+            }
+        }
+        Ok(self)
+    }
+
+    /// Removes duplicate keys, keeping the value of the last occurrence at the position
+    /// of the first one.
+    pub fn into_lenient(self) -> Self {
+        let mut tags: Vec<RawTag> = Vec::with_capacity(self.0.len());
+        for tag in self.0 {
+            match tags.iter_mut().find(|existing| existing.key == tag.key) {
+                Some(existing) => existing.value = tag.value,
+                None => tags.push(tag),
+            }
+        }
+        Self(tags)
+    }
+}
+
+/// Fails with [`ParseTagsError::DuplicateTagKey`] instead of silently dropping all but
+/// one value of a duplicate key, see [`TagList::into_lenient()`] to resolve them first.
+impl TryFrom<TagList> for BTreeMap<String, String> {
+    type Error = ParseTagsError;
+
+    fn try_from(tags: TagList) -> Result<Self, Self::Error> {
+        Ok(tags
+            .into_strict()?
+            .0
+            .into_iter()
+            .map(|tag| (tag.key.0, tag.value.0))
+            .collect())
+    }
+}
+
+impl From<BTreeMap<String, String>> for TagList {
+    fn from(map: BTreeMap<String, String>) -> Self {
+        Self(
+            map.into_iter()
+                .map(|(key, value)| RawTag::new(key, value))
+                .collect(),
+        )
+    }
+}
+
+/// Fails with [`ParseTagsError::DuplicateTagKey`] instead of silently dropping all but
+/// one value of a duplicate key, see [`TagList::into_lenient()`] to resolve them first.
+impl TryFrom<TagList> for HashMap<String, String> {
+    type Error = ParseTagsError;
+
+    fn try_from(tags: TagList) -> Result<Self, Self::Error> {
+        Ok(tags
+            .into_strict()?
+            .0
+            .into_iter()
+            .map(|tag| (tag.key.0, tag.value.0))
+            .collect())
+    }
+}
+
+/// The order of the resulting list is unspecified. Convert via a [`BTreeMap`] to get
+/// the tags sorted by key.
+impl From<HashMap<String, String>> for TagList {
+    fn from(map: HashMap<String, String>) -> Self {
+        Self(
+            map.into_iter()
+                .map(|(key, value)| RawTag::new(key, value))
+                .collect(),
+        )
+    }
+}
+
+/// (De)serializes a [`TagList`] as a map from keys to values instead of a list of
+/// key/value structs, for use with `#[serde(with = "aws_lib::tags::as_map")]`:
+///
+/// ```rust
+/// # use aws_lib::tags::TagList;
+/// #[derive(serde::Deserialize)]
+/// struct Config {
+///     #[serde(with = "aws_lib::tags::as_map")]
+///     tags: TagList,
+/// }
+/// ```
+///
+/// Keys are serialized in sorted order.
+#[cfg(feature = "serde")]
+pub mod as_map {
+    use std::collections::BTreeMap;
+
+    use serde::{ser::Error as _, Deserialize as _, Deserializer, Serialize as _, Serializer};
+
+    use super::TagList;
+
+    /// Fails if a key appears more than once, as a map cannot represent that
+    pub fn serialize<S: Serializer>(tags: &TagList, serializer: S) -> Result<S::Ok, S::Error> {
+        BTreeMap::<String, String>::try_from(tags.clone())
+            .map_err(S::Error::custom)?
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<TagList, D::Error> {
+        BTreeMap::<String, String>::deserialize(deserializer).map(Into::into)
+    }
 }
 
 #[cfg(test)]
@@ -424,6 +563,114 @@ mod tests {
         );
     }
 
+    #[test]
+    fn attribute_macro_builder() {
+        #[Tags(default)]
+        struct MyOptionalTags {
+            tag1: Option<String>,
+            #[tag(key = "myname", default)]
+            tag2: bool,
+            tag3: Option<bool>,
+        }
+
+        let tags = MyOptionalTags::default()
+            .with_tag1("value".to_owned())
+            .with_tag2(true);
+
+        assert!(tags.tag3.is_none());
+        assert_eq!(
+            tags.into_tags(),
+            TagList::from_vec(vec![
+                RawTag::new("tag1".to_owned(), "value".to_owned()),
+                RawTag::new("myname".to_owned(), "true".to_owned()),
+            ])
+        );
+
+        let tags = MyOptionalTags::from_tags(TagList::from_vec(vec![])).unwrap();
+        assert!(tags.tag1.is_none());
+        assert!(!tags.tag2);
+    }
+
+    #[test]
+    fn static_tag_keys() {
+        crate::tag_keys! {
+            NAME = "Name",
+        }
+
+        crate::tag_keys! {
+            enum KnownKey {
+                Name = "Name",
+                Environment = "environment",
+            }
+        }
+
+        let tags = TagList::from_vec(vec![RawTag::new(NAME, "web".to_owned())]);
+        assert_eq!(tags.get(KnownKey::Name).unwrap().key(), &NAME);
+        assert_eq!(
+            KnownKey::from_key(&TagKey::new("environment".to_owned())),
+            Some(KnownKey::Environment)
+        );
+        assert_eq!(KnownKey::from_key(&TagKey::new("other".to_owned())), None);
+    }
+
+    #[test]
+    fn attribute_macro_static_key() {
+        crate::tag_keys! {
+            NAME = "Name",
+        }
+
+        #[Tags]
+        struct MyKeyedTags {
+            #[tag(key = NAME)]
+            name: String,
+        }
+
+        let tags = TagList::from_vec(vec![RawTag::new(NAME, "web".to_owned())]);
+        let parsed = MyKeyedTags::from_tags(tags.clone()).unwrap();
+
+        assert_eq!(parsed.name, "web");
+        assert_eq!(parsed.into_tags(), tags);
+    }
+
+    #[test]
+    fn explicit_tags_win_over_defaults() {
+        let defaults = TagList::from_vec(vec![
+            RawTag::new("owner".to_owned(), "infra".to_owned()),
+            RawTag::new("env".to_owned(), "prod".to_owned()),
+        ]);
+        let tags = TagList::from_vec(vec![RawTag::new("env".to_owned(), "dev".to_owned())]);
+
+        assert_eq!(
+            tags.with_defaults(&defaults),
+            TagList::from_vec(vec![
+                RawTag::new("owner".to_owned(), "infra".to_owned()),
+                RawTag::new("env".to_owned(), "dev".to_owned()),
+            ])
+        );
+    }
+
+    #[test]
+    fn duplicate_keys() {
+        let tags = TagList::from_vec(vec![
+            RawTag::new("a".to_owned(), "1".to_owned()),
+            RawTag::new("A".to_owned(), "2".to_owned()),
+            RawTag::new("a".to_owned(), "3".to_owned()),
+        ]);
+
+        assert!(matches!(
+            tags.clone().into_strict(),
+            Err(ParseTagsError::DuplicateTagKey { key }) if key == *"a"
+        ));
+
+        assert_eq!(
+            tags.into_lenient(),
+            TagList::from_vec(vec![
+                RawTag::new("a".to_owned(), "3".to_owned()),
+                RawTag::new("A".to_owned(), "2".to_owned()),
+            ])
+        );
+    }
+
     #[test]
     fn test_transparent_tag() {
         #[derive(Tag, PartialEq, Debug)]
@@ -468,4 +715,43 @@ mod tests {
             MyCoolioTag::B
         );
     }
+
+    #[test]
+    fn map_conversion() {
+        let tags = TagList::from_vec(vec![
+            RawTag::new("a".to_owned(), "1".to_owned()),
+            RawTag::new("b".to_owned(), "2".to_owned()),
+        ]);
+
+        let map = BTreeMap::<String, String>::try_from(tags.clone()).unwrap();
+        assert_eq!(map.get("b").map(String::as_str), Some("2"));
+        assert_eq!(TagList::from(map), tags);
+
+        let duplicate = TagList::from_vec(vec![
+            RawTag::new("a".to_owned(), "1".to_owned()),
+            RawTag::new("a".to_owned(), "2".to_owned()),
+        ]);
+        assert!(matches!(
+            HashMap::<String, String>::try_from(duplicate),
+            Err(ParseTagsError::DuplicateTagKey { .. })
+        ));
+    }
+
+    #[cfg(all(feature = "serde", feature = "serde-tags"))]
+    #[test]
+    fn serde_as_map() {
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Config {
+            #[serde(with = "as_map")]
+            tags: TagList,
+        }
+
+        let config = Config {
+            tags: TagList::from_vec(vec![RawTag::new("a".to_owned(), "1".to_owned())]),
+        };
+
+        let json = serde_json::to_string(&config).unwrap();
+        assert_eq!(json, r#"{"tags":{"a":"1"}}"#);
+        assert_eq!(serde_json::from_str::<Config>(&json).unwrap(), config);
+    }
 }
@@ -0,0 +1,1138 @@
+//! Tearing down instances and whole environments
+//!
+//! [`terminate_preserving_data()`] is the counterpart to
+//! [`start_ec2_instance()`](crate::start_ec2_instance()): volumes are kept (and
+//! optionally snapshotted), the EIP is detached but stays allocated, and DNS records
+//! pointing to the instance are removed before the instance is terminated.
+//!
+//! [`plan_teardown()`] and [`execute_teardown()`] delete everything carrying certain
+//! tags, in an order that never leaves dangling references behind:
+//!
+//! ```rust,no_run
+//! # use aws_lib::{query::TagQuery, tags::RawTag, teardown, CdnClient, Error, Protection, RegionClient};
+//! # async fn f(client: &RegionClient, cdn: &CdnClient) -> Result<(), Error> {
+//! let query = TagQuery::new().equals(RawTag::new("environment".to_owned(), "staging".to_owned()));
+//! let mut plan =
+//!     teardown::plan_teardown(client, Some(cdn), &query, &[], Protection::Enforce).await?;
+//!
+//! for step in plan.steps() {
+//!     println!("{}", step.step);
+//! }
+//!
+//! teardown::execute_teardown(client, Some(cdn), &mut plan, |_step| {
+//!     teardown::Confirmation::Proceed
+//! })
+//! .await?;
+//! assert!(plan.is_complete());
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{fmt, future::Future, time::Duration};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::{
+    pagination, query,
+    tags::{RawTag, TagList},
+    waiter, CdnClient, CloudfrontDistributionId, CloudfrontDistributionStatus, EfsId, Eip,
+    EipAllocationId, Error, HostedZoneId, Instance, InstanceId, InstanceStateName, Protection,
+    RegionClient, Route53Zone, SnapshotId, Volume, VolumeId,
+};
+
+/// Which volumes of an instance survive its termination
+#[derive(Debug, Clone, Copy)]
+pub enum PreserveVolumes<'a> {
+    All,
+    /// All volumes except the root volume
+    NonRoot,
+    /// Only the volumes attached as these devices, e.g. `/dev/sdf`
+    Devices(&'a [&'a str]),
+    None,
+}
+
+impl PreserveVolumes<'_> {
+    fn includes(&self, device_name: &str, root_device_name: Option<&str>) -> bool {
+        match *self {
+            Self::All => true,
+            Self::NonRoot => root_device_name != Some(device_name),
+            Self::Devices(devices) => devices.contains(&device_name),
+            Self::None => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TeardownPolicy<'a> {
+    pub preserve_volumes: PreserveVolumes<'a>,
+    /// Snapshot each preserved volume before terminating, carrying the tags of the
+    /// instance
+    pub snapshot_preserved: bool,
+    /// A records to delete, as zone and FQDN. Records are only deleted if they point
+    /// to an IP of the instance.
+    pub dns_records: &'a [(&'a Route53Zone, &'a str)],
+    pub protection: Protection,
+}
+
+/// What [`terminate_preserving_data()`] did besides terminating the instance
+#[derive(Debug, Clone, Default)]
+pub struct TeardownReport {
+    pub preserved_volumes: Vec<VolumeId>,
+    pub snapshots: Vec<SnapshotId>,
+    /// The EIP that was detached from the instance and is free for reuse
+    pub detached_eip: Option<EipAllocationId>,
+    pub deleted_records: Vec<String>,
+}
+
+struct PreservedVolume {
+    device_name: String,
+    volume_id: VolumeId,
+}
+
+async fn preserve_volumes(
+    client: &RegionClient,
+    instance: &Instance,
+    selection: PreserveVolumes<'_>,
+) -> Result<Vec<PreservedVolume>, Error> {
+    let Some(description) = client
+        .main
+        .ec2
+        .describe_instances()
+        .instance_ids(instance.instance_id().as_str())
+        .send()
+        .await?
+        .reservations
+        .unwrap_or_default()
+        .into_iter()
+        .flat_map(|reservation| reservation.instances.unwrap_or_default())
+        .next()
+    else {
+        return Err(Error::InvalidArgument {
+            message: format!("instance {} not found", instance.instance_id()),
+        });
+    };
+
+    let root_device_name = description.root_device_name.as_deref();
+    let volumes: Vec<PreservedVolume> = description
+        .block_device_mappings
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|mapping| {
+            let device_name = mapping.device_name?;
+            let volume_id = mapping.ebs?.volume_id?;
+            selection
+                .includes(&device_name, root_device_name)
+                .then_some(PreservedVolume {
+                    device_name,
+                    volume_id: VolumeId(volume_id),
+                })
+        })
+        .collect();
+
+    if volumes.is_empty() {
+        return Ok(volumes);
+    }
+
+    let _output = client
+        .main
+        .ec2
+        .modify_instance_attribute()
+        .instance_id(instance.instance_id().as_str())
+        .set_block_device_mappings(Some(
+            volumes
+                .iter()
+                .map(|volume| {
+                    aws_sdk_ec2::types::InstanceBlockDeviceMappingSpecification::builder()
+                        .device_name(&volume.device_name)
+                        .ebs(
+                            aws_sdk_ec2::types::EbsInstanceBlockDeviceSpecification::builder()
+                                .delete_on_termination(false)
+                                .build(),
+                        )
+                        .build()
+                })
+                .collect(),
+        ))
+        .send()
+        .await?;
+
+    Ok(volumes)
+}
+
+async fn snapshot_volume(
+    client: &RegionClient,
+    instance: &Instance,
+    volume: &PreservedVolume,
+) -> Result<SnapshotId, Error> {
+    let tags = client.tags_with_defaults(instance.tags());
+
+    client
+        .main
+        .ec2
+        .create_snapshot()
+        .volume_id(volume.volume_id.as_str())
+        .description(format!(
+            "{} of instance {}",
+            volume.device_name,
+            instance.instance_id()
+        ))
+        .tag_specifications(
+            aws_sdk_ec2::types::TagSpecification::builder()
+                .resource_type(aws_sdk_ec2::types::ResourceType::Snapshot)
+                .set_tags(Some(tags.into()))
+                .build(),
+        )
+        .send()
+        .await?
+        .snapshot_id
+        .map(SnapshotId)
+        .ok_or_else(|| Error::UnexpectedNoneValue {
+            entity: "CreateSnapshotOutput.snapshot_id".to_owned(),
+        })
+}
+
+/// Disassociates the EIP of `instance`, if any. The address stays allocated.
+async fn detach_eip(
+    client: &RegionClient,
+    instance: &Instance,
+) -> Result<Option<EipAllocationId>, Error> {
+    let Some(address) = client
+        .main
+        .ec2
+        .describe_addresses()
+        .filters(
+            aws_sdk_ec2::types::Filter::builder()
+                .name("instance-id")
+                .values(instance.instance_id().as_str())
+                .build(),
+        )
+        .send()
+        .await?
+        .addresses
+        .unwrap_or_default()
+        .into_iter()
+        .next()
+    else {
+        return Ok(None);
+    };
+
+    let association_id =
+        address
+            .association_id
+            .clone()
+            .ok_or_else(|| Error::UnexpectedNoneValue {
+                entity: "Address.association_id".to_owned(),
+            })?;
+    let eip = Eip::try_from(address)?;
+
+    let _output = client
+        .main
+        .ec2
+        .disassociate_address()
+        .association_id(association_id)
+        .send()
+        .await?;
+
+    Ok(Some(eip.allocation_id().clone()))
+}
+
+async fn find_a_record(
+    client: &RegionClient,
+    zone: &HostedZoneId,
+    fqdn: &str,
+) -> Result<Option<aws_sdk_route53::types::ResourceRecordSet>, Error> {
+    Ok(client
+        .main
+        .route53
+        .list_resource_record_sets()
+        .hosted_zone_id(zone.as_str())
+        .start_record_name(fqdn)
+        .start_record_type(aws_sdk_route53::types::RrType::A)
+        .max_items(1)
+        .send()
+        .await?
+        .resource_record_sets
+        .into_iter()
+        .find(|record| {
+            record.r#type == aws_sdk_route53::types::RrType::A
+                && record.name.trim_end_matches('.') == fqdn.trim_end_matches('.')
+        }))
+}
+
+/// Whether `record` points to one of `ips` only
+fn points_to(record: &aws_sdk_route53::types::ResourceRecordSet, ips: &[String]) -> bool {
+    !record.resource_records().is_empty()
+        && record
+            .resource_records()
+            .iter()
+            .all(|value| ips.iter().any(|ip| ip == value.value()))
+}
+
+async fn delete_record_set(
+    client: &RegionClient,
+    zone: &HostedZoneId,
+    record: aws_sdk_route53::types::ResourceRecordSet,
+) -> Result<(), Error> {
+    client.check_zone_ownership(zone).await?;
+
+    let _change_info = client
+        .main
+        .route53
+        .change_resource_record_sets()
+        .hosted_zone_id(zone.as_str())
+        .change_batch(
+            aws_sdk_route53::types::ChangeBatch::builder()
+                .changes(
+                    aws_sdk_route53::types::Change::builder()
+                        .action(aws_sdk_route53::types::ChangeAction::Delete)
+                        .resource_record_set(record)
+                        .build()
+                        .expect("builder has missing fields"),
+                )
+                .build()
+                .expect("builder has missing fields"),
+        )
+        .send()
+        .await?;
+
+    Ok(())
+}
+
+/// Terminates `instance` after preserving its data according to `policy`.
+///
+/// In order:
+///
+/// 1. `DeleteOnTermination` is disabled for the selected volumes
+/// 2. The selected volumes are snapshotted, if requested
+/// 3. DNS records pointing to the instance are deleted
+/// 4. The EIP of the instance is detached, so it can be attached elsewhere
+/// 5. The instance is terminated
+///
+/// Fails with [`Error::ResourceProtected`] before changing anything if the instance
+/// carries the protection tag of `client`, unless the policy overrides the protection.
+pub async fn terminate_preserving_data(
+    client: &RegionClient,
+    instance: &Instance,
+    policy: &TeardownPolicy<'_>,
+) -> Result<TeardownReport, Error> {
+    client.check_protection(instance.instance_id(), instance.tags(), policy.protection)?;
+
+    let mut report = TeardownReport::default();
+
+    let volumes = preserve_volumes(client, instance, policy.preserve_volumes).await?;
+    if policy.snapshot_preserved {
+        for volume in &volumes {
+            report
+                .snapshots
+                .push(snapshot_volume(client, instance, volume).await?);
+        }
+    }
+    report.preserved_volumes = volumes.into_iter().map(|volume| volume.volume_id).collect();
+
+    let ips: Vec<String> = [instance.public_ip_address(), instance.private_ip_address()]
+        .into_iter()
+        .flatten()
+        .map(ToString::to_string)
+        .collect();
+    for &(zone, fqdn) in policy.dns_records {
+        if let Some(record) = find_a_record(client, &zone.hosted_zone_id, fqdn).await? {
+            if points_to(&record, &ips) {
+                delete_record_set(client, &zone.hosted_zone_id, record).await?;
+                report.deleted_records.push(fqdn.to_owned());
+            }
+        }
+    }
+
+    report.detached_eip = detach_eip(client, instance).await?;
+
+    instance.terminate(client, policy.protection).await?;
+
+    Ok(report)
+}
+
+/// Error codes meaning that the resource to delete is gone already
+const NOT_FOUND_CODES: [&str; 6] = [
+    "InvalidInstanceID.NotFound",
+    "InvalidAllocationID.NotFound",
+    "InvalidVolume.NotFound",
+    "FileSystemNotFound",
+    "MountTargetNotFound",
+    "NoSuchDistribution",
+];
+
+fn is_not_found(e: &Error) -> bool {
+    e.code().is_some_and(|code| NOT_FOUND_CODES.contains(&code))
+}
+
+/// Steps are idempotent: deleting something that does not exist anymore succeeds
+fn ignore_not_found(result: Result<(), Error>) -> Result<(), Error> {
+    match result {
+        Err(e) if is_not_found(&e) => Ok(()),
+        result => result,
+    }
+}
+
+/// A single deletion in a [`TeardownPlan`]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TeardownStep {
+    /// Only deletes the record if it still points to some of `ips` only
+    DeleteRecord {
+        zone: HostedZoneId,
+        fqdn: String,
+        ips: Vec<String>,
+    },
+    /// Disables the distribution first, as only disabled distributions can be deleted
+    DeleteDistribution(CloudfrontDistributionId),
+    DeleteStack(String),
+    TerminateInstance(InstanceId),
+    ReleaseEip(EipAllocationId),
+    /// Deletes the mount targets of the file system first
+    DeleteEfs(EfsId),
+    DeleteVolume(VolumeId),
+}
+
+impl TeardownStep {
+    /// Steps run in ascending order, so everything referring to a resource is gone
+    /// before the resource itself is deleted
+    const fn order(&self) -> u8 {
+        match *self {
+            Self::DeleteRecord { .. } => 0,
+            Self::DeleteDistribution(_) => 1,
+            Self::DeleteStack(_) => 2,
+            Self::TerminateInstance(_) => 3,
+            Self::ReleaseEip(_) => 4,
+            Self::DeleteEfs(_) => 5,
+            Self::DeleteVolume(_) => 6,
+        }
+    }
+
+    /// Whether the step needs a [`CdnClient`]
+    pub(crate) const fn is_cdn(&self) -> bool {
+        matches!(*self, Self::DeleteDistribution(_) | Self::DeleteStack(_))
+    }
+
+    /// Whether the resource of the step carries the protection tag of `client`.
+    /// Records have no tags, and resources that are gone already are not protected.
+    async fn is_protected(
+        &self,
+        client: &RegionClient,
+        cdn: Option<&CdnClient>,
+    ) -> Result<bool, Error> {
+        let tags = match *self {
+            Self::DeleteRecord { .. } => return Ok(false),
+            // Without a cdn client, the step fails anyway
+            Self::DeleteDistribution(ref id) => match cdn {
+                Some(cdn) => cdn.distribution_tags(id).await,
+                None => return Ok(false),
+            },
+            Self::DeleteStack(ref name) => match cdn {
+                Some(cdn) => match cdn.stack_tags(name).await {
+                    // Deleted stacks cannot be described by name anymore
+                    Err(e) if e.code() == Some("ValidationError") => return Ok(false),
+                    tags => tags,
+                },
+                None => return Ok(false),
+            },
+            Self::TerminateInstance(ref id) => ec2_tags(client, id.as_str()).await,
+            Self::ReleaseEip(ref id) => ec2_tags(client, id.as_str()).await,
+            Self::DeleteVolume(ref id) => ec2_tags(client, id.as_str()).await,
+            Self::DeleteEfs(ref id) => efs_tags(client, id).await,
+        };
+
+        match tags {
+            Ok(tags) => Ok(tags.as_slice().contains(&client.protection_tag)),
+            Err(e) if is_not_found(&e) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn execute(&self, client: &RegionClient, cdn: Option<&CdnClient>) -> Result<(), Error> {
+        let cdn = || {
+            cdn.ok_or_else(|| Error::InvalidArgument {
+                message: format!("cannot {self} without a cdn client"),
+            })
+        };
+
+        match *self {
+            Self::DeleteRecord {
+                ref zone,
+                ref fqdn,
+                ref ips,
+            } => {
+                // The record may point elsewhere by now, e.g. to a replacement instance
+                if let Some(record) = find_a_record(client, zone, fqdn).await? {
+                    if points_to(&record, ips) {
+                        delete_record_set(client, zone, record).await?;
+                    }
+                }
+                Ok(())
+            }
+            Self::DeleteDistribution(ref id) => {
+                ignore_not_found(delete_distribution(cdn()?, self, id).await)
+            }
+            Self::DeleteStack(ref name) => delete_stack(cdn()?, self, name).await,
+            Self::TerminateInstance(ref id) => terminate_instance(client, id).await,
+            Self::ReleaseEip(ref id) => ignore_not_found(release_eip(client, id).await),
+            Self::DeleteEfs(ref id) => ignore_not_found(delete_efs(client, self, id).await),
+            Self::DeleteVolume(ref id) => ignore_not_found(delete_volume(client, self, id).await),
+        }
+    }
+}
+
+impl fmt::Display for TeardownStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::DeleteRecord {
+                ref zone, ref fqdn, ..
+            } => {
+                write!(f, "delete record {fqdn} in zone {zone}")
+            }
+            Self::DeleteDistribution(ref id) => write!(f, "delete distribution {id}"),
+            Self::DeleteStack(ref name) => write!(f, "delete stack {name}"),
+            Self::TerminateInstance(ref id) => write!(f, "terminate instance {id}"),
+            Self::ReleaseEip(ref id) => write!(f, "release eip {id}"),
+            Self::DeleteEfs(ref id) => write!(f, "delete efs {id}"),
+            Self::DeleteVolume(ref id) => write!(f, "delete volume {id}"),
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepState {
+    Pending,
+    Done,
+    /// Declined during confirmation, the resource was left alone
+    Skipped,
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedStep {
+    pub step: TeardownStep,
+    pub state: StepState,
+}
+
+/// Deletions in dependency order, with the progress of their execution
+///
+/// A plan can be serialized and executed again after a failure or an abort. Steps that
+/// are done already are not repeated.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TeardownPlan {
+    steps: Vec<PlannedStep>,
+}
+
+impl TeardownPlan {
+    pub fn new(mut steps: Vec<TeardownStep>) -> Self {
+        steps.sort_by(|a, b| a.order().cmp(&b.order()).then_with(|| a.cmp(b)));
+        steps.dedup();
+
+        Self {
+            steps: steps
+                .into_iter()
+                .map(|step| PlannedStep {
+                    step,
+                    state: StepState::Pending,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn steps(&self) -> &[PlannedStep] {
+        &self.steps
+    }
+
+    /// Whether no step is pending anymore
+    pub fn is_complete(&self) -> bool {
+        self.steps
+            .iter()
+            .all(|step| step.state != StepState::Pending)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Confirmation {
+    Proceed,
+    /// Leave the resource alone and continue with the next step
+    Skip,
+    /// Stop the execution, the plan can be resumed later
+    Abort,
+}
+
+/// Plans the deletion of everything matching `query`: instances, EIPs, volumes,
+/// distributions, stacks and EFS file systems. Of `records`, the A records pointing to
+/// an IP of a matching instance or EIP are deleted as well. Distributions and stacks
+/// are only planned if `cdn` is given.
+///
+/// Protection is checked here: with [`Protection::Enforce`], resources carrying the
+/// protection tag of `client` are left out of the plan. So are EIPs and volumes
+/// attached to a protected instance, even if the instance does not match `query`, and
+/// records pointing to them.
+///
+/// An empty `query` would match everything in the account and is rejected.
+pub async fn plan_teardown(
+    client: &RegionClient,
+    cdn: Option<&CdnClient>,
+    query: &query::TagQuery,
+    records: &[(&Route53Zone, &str)],
+    protection: Protection,
+) -> Result<TeardownPlan, Error> {
+    if query.is_empty() {
+        return Err(Error::InvalidArgument {
+            message: "refusing to plan a teardown for an empty tag query".to_owned(),
+        });
+    }
+
+    let protected = |tags: &TagList| {
+        protection == Protection::Enforce && tags.as_slice().contains(&client.protection_tag)
+    };
+
+    let eips = query::find_eips(client, query).await?;
+    let volumes = query::find_volumes(client, query).await?;
+    let protected_instances =
+        protected_attached_instances(client, &eips, &volumes, protection).await?;
+
+    let mut steps = vec![];
+    let mut ips = vec![];
+
+    for instance in query::find_instances(client, query).await? {
+        if *instance.state().inner() == aws_sdk_ec2::types::InstanceStateName::Terminated
+            || protected(instance.tags())
+        {
+            continue;
+        }
+        ips.extend(
+            [instance.public_ip_address(), instance.private_ip_address()]
+                .into_iter()
+                .flatten()
+                .map(ToString::to_string),
+        );
+        steps.push(TeardownStep::TerminateInstance(
+            instance.instance_id().clone(),
+        ));
+    }
+
+    for eip in eips {
+        let attached_to_protected = eip
+            .associated_instance()
+            .is_some_and(|id| protected_instances.contains(id));
+        if !protected(eip.tags()) && !attached_to_protected {
+            ips.push(eip.ip().to_string());
+            steps.push(TeardownStep::ReleaseEip(eip.allocation_id().clone()));
+        }
+    }
+
+    for volume in volumes {
+        let attached_to_protected = volume
+            .attached_instances()
+            .iter()
+            .any(|id| protected_instances.contains(id));
+        if !protected(volume.tags()) && !attached_to_protected {
+            steps.push(TeardownStep::DeleteVolume(volume.id().clone()));
+        }
+    }
+
+    if let Some(cdn) = cdn {
+        for (distribution, tags) in query::find_distributions_with_tags(cdn, query).await? {
+            if !protected(&tags) {
+                steps.push(TeardownStep::DeleteDistribution(distribution.id));
+            }
+        }
+
+        for stack in pagination::list_stacks(cdn).await? {
+            let tags = TagList::try_from(stack.tags.unwrap_or_default())?;
+            if query.matches(&tags) && !protected(&tags) {
+                if let Some(name) = stack.stack_name {
+                    steps.push(TeardownStep::DeleteStack(name));
+                }
+            }
+        }
+    }
+
+    for fs in pagination::list_file_systems(client).await? {
+        let tags = TagList::try_from(fs.tags)?;
+        if query.matches(&tags) && !protected(&tags) {
+            steps.push(TeardownStep::DeleteEfs(EfsId(fs.file_system_id)));
+        }
+    }
+
+    ips.sort();
+    ips.dedup();
+
+    for &(zone, fqdn) in records {
+        if find_a_record(client, &zone.hosted_zone_id, fqdn)
+            .await?
+            .is_some_and(|record| points_to(&record, &ips))
+        {
+            steps.push(TeardownStep::DeleteRecord {
+                zone: zone.hosted_zone_id.clone(),
+                fqdn: fqdn.to_owned(),
+                ips: ips.clone(),
+            });
+        }
+    }
+
+    Ok(TeardownPlan::new(steps))
+}
+
+/// The instances that `eips` and `volumes` are attached to and that carry the
+/// protection tag of `client`. Empty unless `protection` is [`Protection::Enforce`].
+async fn protected_attached_instances(
+    client: &RegionClient,
+    eips: &[Eip],
+    volumes: &[Volume],
+    protection: Protection,
+) -> Result<Vec<InstanceId>, Error> {
+    let attached: Vec<String> = eips
+        .iter()
+        .filter_map(Eip::associated_instance)
+        .chain(volumes.iter().flat_map(Volume::attached_instances))
+        .map(|id| id.as_str().to_owned())
+        .collect();
+
+    if protection == Protection::Override || attached.is_empty() {
+        return Ok(vec![]);
+    }
+
+    pagination::list_instances(
+        client,
+        vec![aws_sdk_ec2::types::Filter::builder()
+            .name("instance-id")
+            .set_values(Some(attached))
+            .build()],
+    )
+    .await?
+    .into_iter()
+    .map(Instance::try_from_aws)
+    .filter_map(|instance| match instance {
+        Ok(instance) => instance
+            .tags()
+            .as_slice()
+            .contains(&client.protection_tag)
+            .then(|| Ok(instance.instance_id().clone())),
+        Err(e) => Some(Err(e)),
+    })
+    .collect()
+}
+
+/// Leaves out the steps whose resources carry the protection tag of `client`.
+///
+/// [`plan_teardown()`] checks protection while finding the resources. Steps that come
+/// from elsewhere, like the compensation steps of a
+/// [`RollbackGuard`](crate::rollback::RollbackGuard), have to be checked before they
+/// are executed, as execution does not check protection anymore.
+pub(crate) async fn without_protected(
+    client: &RegionClient,
+    cdn: Option<&CdnClient>,
+    steps: Vec<TeardownStep>,
+) -> Result<Vec<TeardownStep>, Error> {
+    let mut unprotected = vec![];
+    for step in steps {
+        if !step.is_protected(client, cdn).await? {
+            unprotected.push(step);
+        }
+    }
+    Ok(unprotected)
+}
+
+/// Executes the pending steps of `plan` in order, asking `confirm` before each one.
+///
+/// Stops at the first failing step, which stays pending. `plan` records the progress
+/// either way, so executing it again resumes where it stopped. Distributions and
+/// stacks can only be deleted if `cdn` is given, their steps fail otherwise.
+pub async fn execute_teardown(
+    client: &RegionClient,
+    cdn: Option<&CdnClient>,
+    plan: &mut TeardownPlan,
+    mut confirm: impl FnMut(&TeardownStep) -> Confirmation,
+) -> Result<(), Error> {
+    for planned in &mut plan.steps {
+        if planned.state != StepState::Pending {
+            continue;
+        }
+
+        match confirm(&planned.step) {
+            Confirmation::Proceed => {
+                planned.step.execute(client, cdn).await?;
+                planned.state = StepState::Done;
+            }
+            Confirmation::Skip => planned.state = StepState::Skipped,
+            Confirmation::Abort => return Ok(()),
+        }
+    }
+
+    Ok(())
+}
+
+/// Polls for at most `max_wait`, failing with
+/// [`Error::TeardownStepExceededMaxWait`] for `step` afterwards
+async fn poll_step<T, F, Fut>(
+    profile: waiter::WaiterProfile,
+    step: &TeardownStep,
+    max_wait: Duration,
+    poll: F,
+) -> Result<T, Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<Option<T>, Error>>,
+{
+    waiter::poll(profile, max_wait, poll)
+        .await?
+        .ok_or_else(|| Error::TeardownStepExceededMaxWait {
+            max_wait,
+            step: step.to_string(),
+        })
+}
+
+async fn delete_distribution(
+    cdn: &CdnClient,
+    step: &TeardownStep,
+    id: &CloudfrontDistributionId,
+) -> Result<(), Error> {
+    cdn.check_ownership(id).await?;
+
+    let cloudfront = &cdn.cloudfront;
+
+    let output = cloudfront
+        .get_distribution_config()
+        .id(id.as_str())
+        .send()
+        .await?;
+    let etag = output.e_tag.ok_or_else(|| Error::UnexpectedNoneValue {
+        entity: "GetDistributionConfigOutput.e_tag".to_owned(),
+    })?;
+    let mut config = output
+        .distribution_config
+        .ok_or_else(|| Error::UnexpectedNoneValue {
+            entity: "GetDistributionConfigOutput.distribution_config".to_owned(),
+        })?;
+
+    if config.enabled {
+        config.enabled = false;
+        let _output = cloudfront
+            .update_distribution()
+            .id(id.as_str())
+            .if_match(etag)
+            .distribution_config(config)
+            .send()
+            .await?;
+    }
+
+    // Only distributions that are deployed as disabled can be deleted
+    let etag = poll_step(
+        cdn.waiter_profile,
+        step,
+        cdn.wait_config.cloudfront_deploy,
+        || async move {
+            let output = cloudfront.get_distribution().id(id.as_str()).send().await?;
+            let deployed = output.distribution.is_some_and(|distribution| {
+                CloudfrontDistributionStatus::from(distribution.status)
+                    == CloudfrontDistributionStatus::Deployed
+            });
+            Ok(deployed.then_some(output.e_tag))
+        },
+    )
+    .await?
+    .ok_or_else(|| Error::UnexpectedNoneValue {
+        entity: "GetDistributionOutput.e_tag".to_owned(),
+    })?;
+
+    let _output = cloudfront
+        .delete_distribution()
+        .id(id.as_str())
+        .if_match(etag)
+        .send()
+        .await?;
+
+    Ok(())
+}
+
+async fn delete_stack(cdn: &CdnClient, step: &TeardownStep, name: &str) -> Result<(), Error> {
+    cdn.check_stack_ownership(name).await?;
+
+    let cloudformation = &cdn.cloudformation;
+
+    let _output = cloudformation
+        .delete_stack()
+        .stack_name(name)
+        .send()
+        .await?;
+
+    poll_step(
+        cdn.waiter_profile,
+        step,
+        cdn.wait_config.teardown_step,
+        || async move {
+            let stack = match cloudformation
+                .describe_stacks()
+                .stack_name(name)
+                .send()
+                .await
+            {
+                Ok(output) => output.stacks.unwrap_or_default().into_iter().next(),
+                Err(e) => {
+                    let e = Error::from(e);
+                    // Deleted stacks cannot be described by name anymore
+                    if e.code() == Some("ValidationError") {
+                        None
+                    } else {
+                        return Err(e);
+                    }
+                }
+            };
+
+            let Some(stack) = stack else {
+                return Ok(Some(()));
+            };
+            match stack.stack_status {
+                Some(aws_sdk_cloudformation::types::StackStatus::DeleteComplete) => Ok(Some(())),
+                Some(aws_sdk_cloudformation::types::StackStatus::DeleteFailed) => {
+                    Err(Error::TeardownStepFailed {
+                        step: step.to_string(),
+                        message: stack.stack_status_reason.unwrap_or_default(),
+                    })
+                }
+                _ => Ok(None),
+            }
+        },
+    )
+    .await
+}
+
+async fn ec2_tags(client: &RegionClient, resource: &str) -> Result<TagList, Error> {
+    Ok(TagList::from_vec(
+        client
+            .main
+            .ec2
+            .describe_tags()
+            .filters(
+                aws_sdk_ec2::types::Filter::builder()
+                    .name("resource-id")
+                    .values(resource)
+                    .build(),
+            )
+            .send()
+            .await?
+            .tags
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|tag| match (tag.key, tag.value) {
+                (Some(key), Some(value)) => Some(RawTag::new(key, value)),
+                _ => None,
+            })
+            .collect(),
+    ))
+}
+
+async fn efs_tags(client: &RegionClient, id: &EfsId) -> Result<TagList, Error> {
+    match client
+        .main
+        .efs
+        .describe_file_systems()
+        .file_system_id(id.as_str())
+        .send()
+        .await?
+        .file_systems
+        .unwrap_or_default()
+        .into_iter()
+        .next()
+    {
+        Some(fs) => Ok(TagList::try_from(fs.tags)?),
+        None => Ok(TagList::from_vec(vec![])),
+    }
+}
+
+async fn terminate_instance(client: &RegionClient, id: &InstanceId) -> Result<(), Error> {
+    let Some(instance) = pagination::list_instances(
+        client,
+        vec![aws_sdk_ec2::types::Filter::builder()
+            .name("instance-id")
+            .values(id.as_str())
+            .build()],
+    )
+    .await?
+    .into_iter()
+    .next()
+    .map(Instance::try_from_aws)
+    .transpose()?
+    else {
+        return Ok(());
+    };
+
+    if *instance.state().inner() == aws_sdk_ec2::types::InstanceStateName::Terminated {
+        return Ok(());
+    }
+
+    // Protection was checked when planning
+    instance.terminate(client, Protection::Override).await?;
+
+    // Until the instance is terminated, its EIP and volumes are still attached
+    let _instance = instance
+        .wait_for_state(
+            client,
+            InstanceStateName::new(aws_sdk_ec2::types::InstanceStateName::Terminated),
+            None,
+        )
+        .await?;
+
+    Ok(())
+}
+
+async fn release_eip(client: &RegionClient, id: &EipAllocationId) -> Result<(), Error> {
+    let address = client
+        .main
+        .ec2
+        .describe_addresses()
+        .allocation_ids(id.as_str())
+        .send()
+        .await?
+        .addresses
+        .unwrap_or_default()
+        .into_iter()
+        .next();
+
+    match address {
+        Some(address) => {
+            Eip::try_from(address)?
+                .release(client, Protection::Override)
+                .await
+        }
+        None => Ok(()),
+    }
+}
+
+async fn delete_efs(client: &RegionClient, step: &TeardownStep, id: &EfsId) -> Result<(), Error> {
+    let efs = &client.main.efs;
+
+    let mount_targets = efs
+        .describe_mount_targets()
+        .file_system_id(id.as_str())
+        .send()
+        .await?
+        .mount_targets
+        .unwrap_or_default();
+
+    for mount_target in mount_targets {
+        ignore_not_found(
+            efs.delete_mount_target()
+                .mount_target_id(mount_target.mount_target_id)
+                .send()
+                .await
+                .map(|_output| ())
+                .map_err(Error::from),
+        )?;
+    }
+
+    // The file system cannot be deleted while mount targets are still being deleted
+    poll_step(
+        client.waiter_profile,
+        step,
+        client.wait_config.teardown_step,
+        || async move {
+            let remaining = efs
+                .describe_mount_targets()
+                .file_system_id(id.as_str())
+                .send()
+                .await?
+                .mount_targets
+                .unwrap_or_default();
+            Ok(remaining.is_empty().then_some(()))
+        },
+    )
+    .await?;
+
+    let _output = efs
+        .delete_file_system()
+        .file_system_id(id.as_str())
+        .send()
+        .await?;
+
+    Ok(())
+}
+
+async fn delete_volume(
+    client: &RegionClient,
+    step: &TeardownStep,
+    id: &VolumeId,
+) -> Result<(), Error> {
+    // Volumes of terminated instances take a moment to become available
+    let exists = poll_step(
+        client.waiter_profile,
+        step,
+        client.wait_config.teardown_step,
+        || async move {
+            let volume = pagination::list_volumes(
+                client,
+                vec![aws_sdk_ec2::types::Filter::builder()
+                    .name("volume-id")
+                    .values(id.as_str())
+                    .build()],
+            )
+            .await?
+            .into_iter()
+            .next();
+
+            Ok(match volume.and_then(|volume| volume.state) {
+                Some(aws_sdk_ec2::types::VolumeState::Available) => Some(true),
+                Some(
+                    aws_sdk_ec2::types::VolumeState::Deleting
+                    | aws_sdk_ec2::types::VolumeState::Deleted,
+                )
+                | None => Some(false),
+                Some(_) => None,
+            })
+        },
+    )
+    .await?;
+
+    if exists {
+        let _output = client
+            .main
+            .ec2
+            .delete_volume()
+            .volume_id(id.as_str())
+            .send()
+            .await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn volume_selection() {
+        let root = Some("/dev/xvda");
+
+        assert!(PreserveVolumes::All.includes("/dev/xvda", root));
+        assert!(!PreserveVolumes::NonRoot.includes("/dev/xvda", root));
+        assert!(PreserveVolumes::NonRoot.includes("/dev/sdf", root));
+        assert!(PreserveVolumes::Devices(&["/dev/sdf"]).includes("/dev/sdf", root));
+        assert!(!PreserveVolumes::Devices(&["/dev/sdf"]).includes("/dev/sdg", root));
+        assert!(!PreserveVolumes::None.includes("/dev/sdf", root));
+    }
+
+    #[test]
+    fn plan_is_ordered_by_dependencies() {
+        let plan = TeardownPlan::new(vec![
+            TeardownStep::DeleteVolume(VolumeId::new("vol-1".to_owned())),
+            TeardownStep::ReleaseEip(EipAllocationId::new("eipalloc-1".to_owned())),
+            TeardownStep::TerminateInstance("i-1".parse().unwrap()),
+            TeardownStep::DeleteRecord {
+                zone: HostedZoneId::new("Z1".to_owned()),
+                fqdn: "web.example.com".to_owned(),
+                ips: vec!["192.0.2.1".to_owned()],
+            },
+            TeardownStep::ReleaseEip(EipAllocationId::new("eipalloc-1".to_owned())),
+        ]);
+
+        let order: Vec<u8> = plan.steps().iter().map(|step| step.step.order()).collect();
+        assert_eq!(order, vec![0, 3, 4, 6]);
+        assert!(!plan.is_complete());
+    }
+}
@@ -0,0 +1,140 @@
+//! Metrics about all AWS API calls, emitted via the [`metrics`] facade
+//!
+//! All clients built by [`load_sdk_clients()`](crate::load_sdk_clients()) record the
+//! following metrics, labeled by `service`, `operation` and `region`:
+//!
+//! * `aws_operations_total`: Counter of finished operations, additionally labeled by
+//!   `outcome` (`success` or `error`)
+//! * `aws_operation_duration_seconds`: Histogram of operation latencies, including
+//!   all retries
+//! * `aws_throttled_attempts_total`: Counter of attempts that were throttled by AWS
+//!
+//! Installing a recorder (e.g. a Prometheus exporter) is up to the application.
+
+use std::time::Instant;
+
+use aws_smithy_runtime_api::{
+    box_error::BoxError,
+    client::{
+        interceptors::{
+            context::{BeforeSerializationInterceptorContextRef, FinalizerInterceptorContextRef},
+            Intercept,
+        },
+        orchestrator::Metadata,
+        retries::classifiers::{ClassifyRetry as _, RetryAction, RetryReason},
+        runtime_components::RuntimeComponents,
+    },
+};
+use aws_smithy_types::{
+    config_bag::{ConfigBag, Storable, StoreReplace},
+    retry::ErrorKind,
+};
+
+use super::Region;
+
+const OPERATIONS_TOTAL: &str = "aws_operations_total";
+const OPERATION_DURATION_SECONDS: &str = "aws_operation_duration_seconds";
+const THROTTLED_ATTEMPTS_TOTAL: &str = "aws_throttled_attempts_total";
+
+#[derive(Debug)]
+struct OperationStart(Instant);
+
+impl Storable for OperationStart {
+    type Storer = StoreReplace<Self>;
+}
+
+#[derive(Debug)]
+pub(crate) struct MetricsInterceptor {
+    region: Region,
+}
+
+impl MetricsInterceptor {
+    pub(crate) const fn new(region: Region) -> Self {
+        Self { region }
+    }
+
+    fn labels(&self, cfg: &ConfigBag) -> [(&'static str, String); 3] {
+        let (service, operation) = cfg.load::<Metadata>().map_or_else(
+            || ("unknown".to_owned(), "unknown".to_owned()),
+            |metadata| (metadata.service().to_owned(), metadata.name().to_owned()),
+        );
+
+        [
+            ("service", service),
+            ("operation", operation),
+            ("region", self.region.as_str().to_owned()),
+        ]
+    }
+}
+
+/// Whether the attempt was throttled. The retry classifiers of the SDK know the
+/// throttling errors of each service, e.g. `RequestLimitExceeded` of EC2, which come
+/// with various HTTP status codes.
+fn is_throttled(
+    context: &FinalizerInterceptorContextRef<'_>,
+    runtime_components: &RuntimeComponents,
+) -> bool {
+    matches!(context.output_or_error(), Some(Err(_)))
+        && runtime_components.retry_classifiers().any(|classifier| {
+            matches!(
+                classifier.classify_retry(context.inner()),
+                RetryAction::RetryIndicated(RetryReason::RetryableError {
+                    kind: ErrorKind::ThrottlingError,
+                    ..
+                })
+            )
+        })
+}
+
+impl Intercept for MetricsInterceptor {
+    fn name(&self) -> &'static str {
+        "MetricsInterceptor"
+    }
+
+    fn read_before_execution(
+        &self,
+        _context: &BeforeSerializationInterceptorContextRef<'_>,
+        cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        cfg.interceptor_state()
+            .store_put(OperationStart(Instant::now()));
+        Ok(())
+    }
+
+    fn read_after_attempt(
+        &self,
+        context: &FinalizerInterceptorContextRef<'_>,
+        runtime_components: &RuntimeComponents,
+        cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        if is_throttled(context, runtime_components) {
+            metrics::counter!(THROTTLED_ATTEMPTS_TOTAL, &self.labels(cfg)).increment(1);
+        }
+        Ok(())
+    }
+
+    fn read_after_execution(
+        &self,
+        context: &FinalizerInterceptorContextRef<'_>,
+        _runtime_components: &RuntimeComponents,
+        cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        let labels = self.labels(cfg);
+
+        let outcome = match context.output_or_error() {
+            Some(Ok(_)) => "success",
+            Some(Err(_)) | None => "error",
+        };
+
+        let mut outcome_labels = labels.to_vec();
+        outcome_labels.push(("outcome", outcome.to_owned()));
+        metrics::counter!(OPERATIONS_TOTAL, &outcome_labels).increment(1);
+
+        if let Some(start) = cfg.load::<OperationStart>() {
+            metrics::histogram!(OPERATION_DURATION_SECONDS, &labels)
+                .record(start.0.elapsed().as_secs_f64());
+        }
+
+        Ok(())
+    }
+}
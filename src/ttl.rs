@@ -0,0 +1,189 @@
+//! Enforcing a uniform TTL on the records of a Route53 zone
+//!
+//! Long TTLs that were set once and forgotten make every later change slow to take
+//! effect. [`set_zone_ttl_policy()`] finds all records whose TTL deviates from the
+//! policy and, unless running as a dry run, updates them.
+//!
+//! Alias records have no TTL of their own and are skipped, as are the `SOA` and `NS`
+//! records at the zone apex, which are managed by Route53.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::{pagination, Error, RegionClient, Route53Zone};
+
+/// Each upsert counts twice against the Route53 limit of 1000 changes per batch, and
+/// records may contain several values, so stay well below
+const CHANGES_PER_BATCH: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Only report the records that would be changed
+    DryRun,
+    Apply,
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TtlChange {
+    pub name: String,
+    /// E.g. `A` or `CNAME`
+    pub record_type: String,
+    /// Only set for weighted, latency, failover and similar routing policies
+    pub set_identifier: Option<String>,
+    pub current_ttl: i64,
+    pub new_ttl: i64,
+}
+
+fn is_apex_record(record: &aws_sdk_route53::types::ResourceRecordSet, zone_name: &str) -> bool {
+    matches!(
+        record.r#type,
+        aws_sdk_route53::types::RrType::Soa | aws_sdk_route53::types::RrType::Ns
+    ) && record.name.trim_end_matches('.') == zone_name.trim_end_matches('.')
+}
+
+/// The records whose TTL deviates from `ttl`, each with the TTL already replaced
+fn deviating_records(
+    records: Vec<aws_sdk_route53::types::ResourceRecordSet>,
+    zone_name: &str,
+    ttl: i64,
+) -> Vec<(TtlChange, aws_sdk_route53::types::ResourceRecordSet)> {
+    records
+        .into_iter()
+        .filter(|record| !is_apex_record(record, zone_name))
+        .filter_map(|mut record| {
+            let current_ttl = record.ttl.filter(|&current_ttl| current_ttl != ttl)?;
+
+            let change = TtlChange {
+                name: record.name.clone(),
+                record_type: record.r#type.as_str().to_owned(),
+                set_identifier: record.set_identifier.clone(),
+                current_ttl,
+                new_ttl: ttl,
+            };
+            record.ttl = Some(ttl);
+
+            Some((change, record))
+        })
+        .collect()
+}
+
+/// Sets the TTL of all records of `zone` to `default_ttl` (in seconds). Returns the
+/// records whose TTL deviated, which are left untouched with [`Mode::DryRun`].
+#[expect(
+    clippy::missing_panics_doc,
+    reason = "only expect() on builder instances"
+)]
+pub async fn set_zone_ttl_policy(
+    client: &RegionClient,
+    zone: &Route53Zone,
+    default_ttl: i64,
+    mode: Mode,
+) -> Result<Vec<TtlChange>, Error> {
+    let records =
+        pagination::list_resource_record_sets(client, zone.hosted_zone_id.as_str()).await?;
+
+    let (changes, records): (Vec<TtlChange>, Vec<_>) =
+        deviating_records(records, &zone.name, default_ttl)
+            .into_iter()
+            .unzip();
+
+    if mode == Mode::DryRun {
+        return Ok(changes);
+    }
+
+    client.check_zone_ownership(&zone.hosted_zone_id).await?;
+
+    for batch in records.chunks(CHANGES_PER_BATCH) {
+        let _change_info = client
+            .main
+            .route53
+            .change_resource_record_sets()
+            .hosted_zone_id(zone.hosted_zone_id.as_str())
+            .change_batch(
+                aws_sdk_route53::types::ChangeBatch::builder()
+                    .comment(format!("set TTL to {default_ttl}"))
+                    .set_changes(Some(
+                        batch
+                            .iter()
+                            .map(|record| {
+                                aws_sdk_route53::types::Change::builder()
+                                    .action(aws_sdk_route53::types::ChangeAction::Upsert)
+                                    .resource_record_set(record.clone())
+                                    .build()
+                                    .expect("builder has missing fields")
+                            })
+                            .collect(),
+                    ))
+                    .build()
+                    .expect("builder has missing fields"),
+            )
+            .send()
+            .await?;
+    }
+
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(
+        name: &str,
+        r#type: aws_sdk_route53::types::RrType,
+        ttl: Option<i64>,
+    ) -> aws_sdk_route53::types::ResourceRecordSet {
+        aws_sdk_route53::types::ResourceRecordSet::builder()
+            .name(name)
+            .r#type(r#type)
+            .set_ttl(ttl)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn only_deviating_records_change() {
+        let records = vec![
+            record(
+                "example.com.",
+                aws_sdk_route53::types::RrType::Ns,
+                Some(172_800),
+            ),
+            record(
+                "www.example.com.",
+                aws_sdk_route53::types::RrType::A,
+                Some(300),
+            ),
+            record(
+                "old.example.com.",
+                aws_sdk_route53::types::RrType::A,
+                Some(86_400),
+            ),
+            record(
+                "alias.example.com.",
+                aws_sdk_route53::types::RrType::A,
+                None,
+            ),
+        ];
+
+        let changes: Vec<TtlChange> = deviating_records(records, "example.com", 300)
+            .into_iter()
+            .map(|(change, record)| {
+                assert_eq!(record.ttl, Some(300));
+                change
+            })
+            .collect();
+
+        assert_eq!(
+            changes,
+            vec![TtlChange {
+                name: "old.example.com.".to_owned(),
+                record_type: "A".to_owned(),
+                set_identifier: None,
+                current_ttl: 86_400,
+                new_ttl: 300,
+            }]
+        );
+    }
+}
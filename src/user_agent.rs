@@ -0,0 +1,50 @@
+use aws_smithy_runtime_api::{
+    box_error::BoxError,
+    client::{
+        interceptors::{context::BeforeTransmitInterceptorContextMut, Intercept},
+        runtime_components::RuntimeComponents,
+    },
+};
+use aws_smithy_types::config_bag::ConfigBag;
+
+const USER_AGENT_HEADER: &str = "user-agent";
+
+/// Appends a fixed suffix to the user agent of every request.
+///
+/// The SDK only allows setting an [`AppName`](aws_config::AppName), which is restricted
+/// to a few characters. This runs after signing, the user agent is not part of the
+/// signature.
+///
+/// A suffix that is not a valid header value, e.g. one containing a newline, fails
+/// the request instead of sending it with a mangled user agent.
+#[derive(Debug)]
+pub(crate) struct UserAgentSuffixInterceptor {
+    suffix: String,
+}
+
+impl UserAgentSuffixInterceptor {
+    pub(crate) const fn new(suffix: String) -> Self {
+        Self { suffix }
+    }
+}
+
+impl Intercept for UserAgentSuffixInterceptor {
+    fn name(&self) -> &'static str {
+        "UserAgentSuffixInterceptor"
+    }
+
+    fn modify_before_transmit(
+        &self,
+        context: &mut BeforeTransmitInterceptorContextMut<'_>,
+        _runtime_components: &RuntimeComponents,
+        _cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        let headers = context.request_mut().headers_mut();
+        let user_agent = match headers.get(USER_AGENT_HEADER) {
+            Some(user_agent) => format!("{user_agent} {}", self.suffix),
+            None => self.suffix.clone(),
+        };
+        let _previous = headers.try_insert(USER_AGENT_HEADER, user_agent)?;
+        Ok(())
+    }
+}
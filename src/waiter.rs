@@ -0,0 +1,239 @@
+//! Polling for state changes, e.g. of instances
+//!
+//! The poll intervals are set by the [`WaiterProfile`] of a
+//! [`RegionClient`](crate::RegionClient). Against a local emulator like LocalStack,
+//! state changes are instant, so polling in production intervals only slows down
+//! tests.
+
+use std::{
+    future::Future,
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::{Error, InstanceStateName};
+
+const LOCAL_ENDPOINT_HOSTS: [&str; 4] = ["localhost", "127.0.0.1", "[::1]", "localstack"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WaiterProfile {
+    /// Intervals as used by the waiters of the AWS SDK
+    Production,
+    /// Short intervals for local emulators
+    Fast,
+    /// The first poll happens immediately, then the delay doubles from `min_delay` up to
+    /// `max_delay`
+    Custom {
+        min_delay: Duration,
+        max_delay: Duration,
+    },
+}
+
+impl WaiterProfile {
+    /// [`Fast`](Self::Fast) if `endpoint_url` points to a local emulator,
+    /// [`Production`](Self::Production) otherwise.
+    pub fn for_endpoint(endpoint_url: Option<&str>) -> Self {
+        match endpoint_url {
+            Some(url) if LOCAL_ENDPOINT_HOSTS.iter().any(|host| url.contains(host)) => Self::Fast,
+            Some(_) | None => Self::Production,
+        }
+    }
+
+    const fn delays(self) -> (Duration, Duration) {
+        match self {
+            Self::Production => (Duration::from_secs(15), Duration::from_secs(120)),
+            Self::Fast => (Duration::from_millis(100), Duration::from_secs(1)),
+            Self::Custom {
+                min_delay,
+                max_delay,
+            } => (min_delay, max_delay),
+        }
+    }
+
+    fn delay(self, attempt: u32) -> Duration {
+        let (min_delay, max_delay) = self.delays();
+        min_delay
+            .checked_mul(2_u32.saturating_pow(attempt))
+            .unwrap_or(max_delay)
+            .min(max_delay)
+    }
+}
+
+/// How long to wait for operations to finish, if not given explicitly
+///
+/// With the `serde` feature, the durations are (de)serialized as seconds, and missing
+/// fields take their default.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WaitConfig {
+    #[cfg_attr(feature = "serde", serde(with = "seconds"))]
+    pub instance_start: Duration,
+    #[cfg_attr(feature = "serde", serde(with = "seconds"))]
+    pub instance_stop: Duration,
+    #[cfg_attr(feature = "serde", serde(with = "seconds"))]
+    pub instance_terminate: Duration,
+    #[cfg_attr(feature = "serde", serde(with = "seconds"))]
+    pub ssm_command: Duration,
+    /// For a patch run to finish, see `ssm::trigger_patch_now()` (feature `ssm`)
+    #[cfg_attr(feature = "serde", serde(with = "seconds"))]
+    pub patch_run: Duration,
+    #[cfg_attr(feature = "serde", serde(with = "seconds"))]
+    pub cloudfront_deploy: Duration,
+    /// For the address quota to free up, see [`allocate_eip_or_wait()`](crate::allocate_eip_or_wait())
+    #[cfg_attr(feature = "serde", serde(with = "seconds"))]
+    pub eip_quota: Duration,
+    /// For an AMI copy to become available, see [`copy_ami()`](crate::copy_ami())
+    #[cfg_attr(feature = "serde", serde(with = "seconds"))]
+    pub ami_copy: Duration,
+    /// For a volume modification to complete, see
+    /// [`Volume::wait_until_optimizing_complete()`](crate::Volume::wait_until_optimizing_complete())
+    #[cfg_attr(feature = "serde", serde(with = "seconds"))]
+    pub volume_modification: Duration,
+    /// For a single step of a teardown plan to finish, e.g. the deletion of a stack, see
+    /// [`execute_teardown()`](crate::teardown::execute_teardown())
+    #[cfg_attr(feature = "serde", serde(with = "seconds"))]
+    pub teardown_step: Duration,
+    /// For a reachability analysis to finish, see
+    /// [`check_reachability()`](crate::reachability::check_reachability())
+    #[cfg_attr(feature = "serde", serde(with = "seconds"))]
+    pub reachability_analysis: Duration,
+    /// For a DataSync task execution to finish, see
+    /// `datasync::wait_for_task_execution()` (feature `datasync`)
+    #[cfg_attr(feature = "serde", serde(with = "seconds"))]
+    pub datasync_execution: Duration,
+    /// For a Fargate task to stop, see `ecs::wait_for_task()` (feature `ecs`)
+    #[cfg_attr(feature = "serde", serde(with = "seconds"))]
+    pub ecs_task: Duration,
+    /// For instances to become healthy in an ELB target group during a rolling
+    /// operation, see `group::InstanceGroup` (feature `groups`)
+    #[cfg_attr(feature = "serde", serde(with = "seconds"))]
+    pub target_health: Duration,
+    /// For an EBS snapshot to complete, see
+    /// [`Snapshot::wait_until_completed()`](crate::snapshot::Snapshot::wait_until_completed())
+    #[cfg_attr(feature = "serde", serde(with = "seconds"))]
+    pub snapshot: Duration,
+    /// For a Windows instance to finish its first boot, see
+    /// `windows::wait_for_windows_ready()` (feature `windows`)
+    #[cfg_attr(feature = "serde", serde(with = "seconds"))]
+    pub windows_ready: Duration,
+}
+
+impl WaitConfig {
+    /// How long to wait for an instance to reach `state`
+    pub const fn instance_state(&self, state: &InstanceStateName) -> Duration {
+        match *state.inner() {
+            aws_sdk_ec2::types::InstanceStateName::Stopping
+            | aws_sdk_ec2::types::InstanceStateName::Stopped => self.instance_stop,
+            aws_sdk_ec2::types::InstanceStateName::ShuttingDown
+            | aws_sdk_ec2::types::InstanceStateName::Terminated => self.instance_terminate,
+            _ => self.instance_start,
+        }
+    }
+}
+
+impl Default for WaitConfig {
+    fn default() -> Self {
+        Self {
+            instance_start: Duration::from_secs(600),
+            instance_stop: Duration::from_secs(300),
+            instance_terminate: Duration::from_secs(300),
+            ssm_command: Duration::from_secs(600),
+            patch_run: Duration::from_secs(3600),
+            cloudfront_deploy: Duration::from_secs(1200),
+            eip_quota: Duration::from_secs(300),
+            ami_copy: Duration::from_secs(3600),
+            volume_modification: Duration::from_secs(21600),
+            teardown_step: Duration::from_secs(1800),
+            reachability_analysis: Duration::from_secs(300),
+            datasync_execution: Duration::from_secs(21600),
+            ecs_task: Duration::from_secs(3600),
+            target_health: Duration::from_secs(600),
+            snapshot: Duration::from_secs(7200),
+            windows_ready: Duration::from_secs(1800),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+mod seconds {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub(super) fn serialize<S: Serializer>(
+        duration: &Duration,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(duration.as_secs())
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Duration, D::Error> {
+        u64::deserialize(deserializer).map(Duration::from_secs)
+    }
+}
+
+/// Calls `poll` until it returns `Some`, for at most `max_wait`. Returns `None` if
+/// `max_wait` was exceeded.
+pub(crate) async fn poll<T, F, Fut>(
+    profile: WaiterProfile,
+    max_wait: Duration,
+    mut poll: F,
+) -> Result<Option<T>, Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<Option<T>, Error>>,
+{
+    let start = Instant::now();
+    let mut attempt = 0_u32;
+
+    loop {
+        if let Some(value) = poll().await? {
+            return Ok(Some(value));
+        }
+
+        let remaining = max_wait.saturating_sub(start.elapsed());
+        if remaining.is_zero() {
+            return Ok(None);
+        }
+
+        tokio::time::sleep(profile.delay(attempt).min(remaining)).await;
+        attempt = attempt.saturating_add(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delays_double_up_to_max() {
+        let profile = WaiterProfile::Custom {
+            min_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(5),
+        };
+
+        assert_eq!(profile.delay(0), Duration::from_secs(1));
+        assert_eq!(profile.delay(1), Duration::from_secs(2));
+        assert_eq!(profile.delay(2), Duration::from_secs(4));
+        assert_eq!(profile.delay(3), Duration::from_secs(5));
+        assert_eq!(profile.delay(100), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn detects_local_endpoints() {
+        assert_eq!(
+            WaiterProfile::for_endpoint(Some("http://localhost:4566")),
+            WaiterProfile::Fast
+        );
+        assert_eq!(
+            WaiterProfile::for_endpoint(Some("https://ec2.eu-central-1.amazonaws.com")),
+            WaiterProfile::Production
+        );
+        assert_eq!(WaiterProfile::for_endpoint(None), WaiterProfile::Production);
+    }
+}
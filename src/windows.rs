@@ -0,0 +1,146 @@
+//! Windows instances
+//!
+//! Windows instances take longer to boot than Linux instances, as EC2Launch generates
+//! the administrator password and prepares the system on first boot. Only then can
+//! users log in via RDP, see [`wait_for_windows_ready()`].
+//!
+//! Decrypting the administrator password needs the `windows-password` feature. It uses
+//! the `rsa` crate, which is affected by the Marvin timing side channel
+//! ([RUSTSEC-2023-0071](https://rustsec.org/advisories/RUSTSEC-2023-0071)) with no fixed
+//! release yet. An attacker who can measure many decryptions with the same key could
+//! recover it. Only decrypt on machines where no untrusted party observes the timing,
+//! e.g. not in a service that decrypts on behalf of remote callers.
+
+use std::time::Duration;
+
+#[cfg(feature = "windows-password")]
+use rsa::{
+    pkcs1::DecodeRsaPrivateKey as _, pkcs8::DecodePrivateKey as _, Pkcs1v15Encrypt, RsaPrivateKey,
+};
+
+use super::{waiter, Error, InstanceId, RegionClient};
+
+/// Printed to the console by EC2Launch once the instance is ready for logins
+const READY_MESSAGE: &str = "Windows is Ready to use";
+
+/// The encrypted password of `instance`, or `None` if it is not available yet
+pub(crate) async fn password_data(
+    client: &RegionClient,
+    instance: &InstanceId,
+) -> Result<Option<String>, Error> {
+    Ok(client
+        .main
+        .ec2
+        .get_password_data()
+        .instance_id(instance.as_str())
+        .send()
+        .await?
+        .password_data
+        .map(|data| data.trim().to_owned())
+        .filter(|data| !data.is_empty()))
+}
+
+/// Decrypts the password data with the PEM encoded private key of the key pair that
+/// the instance was launched with. Both PKCS#1 and PKCS#8 keys are accepted.
+#[cfg(feature = "windows-password")]
+pub(crate) fn decrypt_password(
+    instance: &InstanceId,
+    password_data: &str,
+    private_key: &str,
+) -> Result<String, Error> {
+    let failed = |message: String| Error::WindowsPasswordDecryptionFailed {
+        instance: instance.clone(),
+        message,
+    };
+
+    let key = RsaPrivateKey::from_pkcs1_pem(private_key)
+        .or_else(|_| RsaPrivateKey::from_pkcs8_pem(private_key))
+        .map_err(|e| failed(format!("invalid private key: {e}")))?;
+
+    let encrypted = aws_smithy_types::base64::decode(password_data)
+        .map_err(|e| failed(format!("invalid password data: {e}")))?;
+
+    let password = key
+        .decrypt(Pkcs1v15Encrypt, &encrypted)
+        .map_err(|e| failed(format!("{e}")))?;
+
+    String::from_utf8(password).map_err(|e| failed(format!("password is not utf-8: {e}")))
+}
+
+fn console_signals_ready(output: &str) -> bool {
+    output.contains(READY_MESSAGE)
+}
+
+async fn console_output(client: &RegionClient, instance: &InstanceId) -> Result<String, Error> {
+    let Some(output) = client
+        .main
+        .ec2
+        .get_console_output()
+        .instance_id(instance.as_str())
+        .send()
+        .await?
+        .output
+    else {
+        return Ok(String::new());
+    };
+
+    let output =
+        aws_smithy_types::base64::decode(output).map_err(|e| Error::InvalidResponseError {
+            message: format!("invalid console output: {e}"),
+        })?;
+
+    Ok(String::from_utf8_lossy(&output).into_owned())
+}
+
+/// Waits until `instance` has finished its first boot: either EC2Launch reported
+/// readiness on the console, or the administrator password is available.
+///
+/// If `max_wait` is `None`, the [`WaitConfig`](crate::waiter::WaitConfig) of `client`
+/// applies.
+pub async fn wait_for_windows_ready(
+    client: &RegionClient,
+    instance: &InstanceId,
+    max_wait: Option<Duration>,
+) -> Result<(), Error> {
+    let max_wait = max_wait.unwrap_or(client.wait_config.windows_ready);
+
+    waiter::poll(client.waiter_profile, max_wait, || async move {
+        if console_signals_ready(&console_output(client, instance).await?) {
+            return Ok(Some(()));
+        }
+        Ok(password_data(client, instance).await?.map(|_| ()))
+    })
+    .await?
+    .ok_or_else(|| Error::WindowsReadyExceededMaxWait {
+        max_wait,
+        instance: instance.clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ready_message_on_console() {
+        assert!(console_signals_ready(
+            "2024/01/01 10:00:00Z: Message: Windows is Ready to use\r\n"
+        ));
+        assert!(!console_signals_ready(
+            "2024/01/01 10:00:00Z: Message: Waiting for meta-data accessibility...\r\n"
+        ));
+    }
+
+    #[cfg(feature = "windows-password")]
+    #[test]
+    fn invalid_private_key() {
+        assert!(matches!(
+            decrypt_password(
+                &InstanceId("i-0123456789abcdef0".to_owned()),
+                "aGVsbG8=",
+                "not a key",
+            ),
+            Err(Error::WindowsPasswordDecryptionFailed { .. })
+        ));
+    }
+}
@@ -0,0 +1,374 @@
+//! Resumable multi-step workflows
+//!
+//! Long workflows like replacing an instance create several resources one after the
+//! other. If such a run crashes halfway, running it again from scratch creates
+//! everything twice. Instead, a [`Run`] persists the output of each finished step to a
+//! [`StateStore`], and a resumed run returns the recorded outputs instead of running
+//! finished steps again:
+//!
+//! ```rust,no_run
+//! # use aws_lib::{workflow::{FileStore, Run, RunId}, Error};
+//! # async fn f(resume: Option<RunId>) -> Result<(), Error> {
+//! let store = FileStore::new("/var/lib/deployer/runs".into());
+//!
+//! let mut run = match resume {
+//!     Some(ref run_id) => Run::resume(&store, run_id).await?,
+//!     None => Run::start(&store, "replace-web").await?,
+//! };
+//!
+//! let ami: String = run.step("build-ami", || async { Ok("ami-123".to_owned()) }).await?;
+//! let _instance: String = run
+//!     .step("launch", || async move { Ok(format!("i-from-{ami}")) })
+//!     .await?;
+//!
+//! run.complete().await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Steps are identified by their name, so step names have to be unique within a
+//! workflow and must not change between attempts.
+
+use std::{fs, future::Future, io, path::PathBuf};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use super::{Error, Timestamp};
+
+crate::string_newtype!(RunId);
+
+impl RunId {
+    pub const fn new(value: String) -> Self {
+        Self(value)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "status")]
+pub enum RunStatus {
+    Running,
+    /// A step failed, the run can be resumed from that step
+    Failed {
+        step: String,
+        message: String,
+    },
+    Completed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompletedStep {
+    pub name: String,
+    pub output: serde_json::Value,
+    pub finished: Timestamp,
+}
+
+/// Everything that is persisted about a run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunState {
+    pub run_id: RunId,
+    pub workflow: String,
+    pub status: RunStatus,
+    pub steps: Vec<CompletedStep>,
+    pub started: Timestamp,
+}
+
+impl RunState {
+    fn completed_step(&self, name: &str) -> Option<&CompletedStep> {
+        self.steps.iter().find(|step| step.name == name)
+    }
+}
+
+/// Where the state of runs is persisted, e.g. a local directory or a database table
+pub trait StateStore: Sync {
+    fn load(&self, run_id: &RunId) -> impl Future<Output = Result<Option<RunState>, Error>> + Send;
+
+    /// Replaces the stored state of the run
+    fn save(&self, state: &RunState) -> impl Future<Output = Result<(), Error>> + Send;
+}
+
+fn workflow_error(run_id: &RunId, message: impl Into<String>) -> Error {
+    Error::WorkflowError {
+        run_id: run_id.to_string(),
+        message: message.into(),
+    }
+}
+
+/// Stores each run as a JSON file in a local directory
+#[derive(Debug, Clone)]
+pub struct FileStore {
+    directory: PathBuf,
+}
+
+impl FileStore {
+    pub const fn new(directory: PathBuf) -> Self {
+        Self { directory }
+    }
+
+    /// Run IDs become file names, so they are restricted to characters that cannot
+    /// escape the directory, like `/` or a leading `.`
+    fn path(&self, run_id: &RunId) -> Result<PathBuf, Error> {
+        let valid = !run_id.as_str().is_empty()
+            && !run_id.as_str().starts_with('.')
+            && run_id
+                .as_str()
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+
+        if !valid {
+            return Err(workflow_error(
+                run_id,
+                "run id may only contain ascii letters, digits, '-', '_' and '.', and must not start with '.'",
+            ));
+        }
+
+        Ok(self.directory.join(format!("{run_id}.json")))
+    }
+
+    fn read(&self, run_id: &RunId) -> Result<Option<RunState>, Error> {
+        match fs::read_to_string(self.path(run_id)?) {
+            Ok(content) => serde_json::from_str(&content)
+                .map(Some)
+                .map_err(|e| workflow_error(run_id, format!("invalid state file: {e}"))),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(workflow_error(run_id, format!("cannot read state: {e}"))),
+        }
+    }
+
+    /// Writes to a temporary file first, so a crash never leaves a truncated state
+    /// file behind
+    fn write(&self, state: &RunState) -> Result<(), Error> {
+        let io_error =
+            |e: io::Error| workflow_error(&state.run_id, format!("cannot write state: {e}"));
+
+        fs::create_dir_all(&self.directory).map_err(io_error)?;
+
+        let content = serde_json::to_string_pretty(state)
+            .map_err(|e| workflow_error(&state.run_id, e.to_string()))?;
+        let path = self.path(&state.run_id)?;
+        let temporary = path.with_extension("json.tmp");
+
+        fs::write(&temporary, content).map_err(io_error)?;
+        fs::rename(&temporary, &path).map_err(io_error)
+    }
+}
+
+/// File access blocks, so it runs on the blocking thread pool of the runtime instead of
+/// stalling the executor
+impl StateStore for FileStore {
+    fn load(&self, run_id: &RunId) -> impl Future<Output = Result<Option<RunState>, Error>> + Send {
+        let store = self.clone();
+        let run_id = run_id.clone();
+        async move {
+            tokio::task::spawn_blocking({
+                let run_id = run_id.clone();
+                move || store.read(&run_id)
+            })
+            .await
+            .map_err(|e| workflow_error(&run_id, format!("cannot read state: {e}")))?
+        }
+    }
+
+    fn save(&self, state: &RunState) -> impl Future<Output = Result<(), Error>> + Send {
+        let store = self.clone();
+        let state = state.clone();
+        let run_id = state.run_id.clone();
+        async move {
+            tokio::task::spawn_blocking(move || store.write(&state))
+                .await
+                .map_err(|e| workflow_error(&run_id, format!("cannot write state: {e}")))?
+        }
+    }
+}
+
+/// `workflow` with everything that is not allowed in a [`RunId`] by [`FileStore`]
+/// replaced by `_`. The run state keeps the workflow name unchanged.
+fn run_id_prefix(workflow: &str) -> String {
+    workflow
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// A single execution of a workflow, see the [module documentation](self)
+#[derive(Debug)]
+pub struct Run<'s, S: StateStore> {
+    store: &'s S,
+    state: RunState,
+}
+
+impl<'s, S: StateStore> Run<'s, S> {
+    /// Starts a new run of `workflow` with a fresh [`RunId`]
+    pub async fn start(store: &'s S, workflow: &str) -> Result<Self, Error> {
+        let started = Timestamp::now();
+        let state = RunState {
+            run_id: RunId(format!(
+                "{}-{}-{:08x}",
+                run_id_prefix(workflow),
+                started.inner().format("%Y%m%dT%H%M%S"),
+                fastrand::u32(..)
+            )),
+            workflow: workflow.to_owned(),
+            status: RunStatus::Running,
+            steps: vec![],
+            started,
+        };
+
+        store.save(&state).await?;
+        Ok(Self { store, state })
+    }
+
+    /// Continues a run that crashed or failed. Completed runs cannot be resumed.
+    pub async fn resume(store: &'s S, run_id: &RunId) -> Result<Self, Error> {
+        let mut state = store
+            .load(run_id)
+            .await?
+            .ok_or_else(|| workflow_error(run_id, "run not found"))?;
+
+        if state.status == RunStatus::Completed {
+            return Err(workflow_error(run_id, "run is completed already"));
+        }
+
+        state.status = RunStatus::Running;
+        store.save(&state).await?;
+        Ok(Self { store, state })
+    }
+
+    pub const fn run_id(&self) -> &RunId {
+        &self.state.run_id
+    }
+
+    pub const fn state(&self) -> &RunState {
+        &self.state
+    }
+
+    /// Runs `step` and records its output. If the step completed in an earlier
+    /// attempt of this run, its recorded output is returned instead.
+    ///
+    /// If `step` fails, the failure is recorded and the run can be resumed later.
+    pub async fn step<T, F, Fut>(&mut self, name: &str, step: F) -> Result<T, Error>
+    where
+        T: Serialize + DeserializeOwned,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, Error>>,
+    {
+        if let Some(completed) = self.state.completed_step(name) {
+            return serde_json::from_value(completed.output.clone()).map_err(|e| {
+                workflow_error(
+                    &self.state.run_id,
+                    format!("recorded output of step \"{name}\" is invalid: {e}"),
+                )
+            });
+        }
+
+        match step().await {
+            Ok(output) => {
+                self.state.steps.push(CompletedStep {
+                    name: name.to_owned(),
+                    output: serde_json::to_value(&output)
+                        .map_err(|e| workflow_error(&self.state.run_id, e.to_string()))?,
+                    finished: Timestamp::now(),
+                });
+                self.store.save(&self.state).await?;
+                Ok(output)
+            }
+            Err(e) => {
+                self.state.status = RunStatus::Failed {
+                    step: name.to_owned(),
+                    message: e.to_string(),
+                };
+                self.store.save(&self.state).await?;
+                Err(e)
+            }
+        }
+    }
+
+    /// Marks the run as completed
+    pub async fn complete(mut self) -> Result<RunState, Error> {
+        self.state.status = RunStatus::Completed;
+        self.store.save(&self.state).await?;
+        Ok(self.state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn file_store_round_trip() {
+        let store = FileStore::new(
+            std::env::temp_dir().join(format!("aws-lib-workflow-{:08x}", fastrand::u32(..))),
+        );
+        let state = RunState {
+            run_id: RunId::new("replace-web-1".to_owned()),
+            workflow: "replace-web".to_owned(),
+            status: RunStatus::Failed {
+                step: "launch".to_owned(),
+                message: "no capacity".to_owned(),
+            },
+            steps: vec![CompletedStep {
+                name: "build-ami".to_owned(),
+                output: serde_json::json!("ami-123"),
+                finished: Timestamp::now(),
+            }],
+            started: Timestamp::now(),
+        };
+
+        assert!(store.load(&state.run_id).await.unwrap().is_none());
+        store.save(&state).await.unwrap();
+        let loaded = store.load(&state.run_id).await.unwrap().unwrap();
+
+        assert_eq!(loaded.status, state.status);
+        assert!(loaded.completed_step("build-ami").is_some());
+        assert!(loaded.completed_step("launch").is_none());
+
+        fs::remove_dir_all(&store.directory).unwrap();
+    }
+
+    #[test]
+    fn workflow_names_are_encoded_in_run_ids() {
+        let store = FileStore::new(PathBuf::from("/var/lib/runs"));
+
+        for workflow in ["replace-web", "deploy/web", "../etc", ".hidden", "café"] {
+            let run_id = RunId::new(format!(
+                "{}-20240101T000000-0000abcd",
+                run_id_prefix(workflow)
+            ));
+            assert_eq!(
+                store.path(&run_id).ok(),
+                Some(PathBuf::from(format!("/var/lib/runs/{run_id}.json"))),
+                "{workflow} rejected"
+            );
+        }
+        assert_eq!(run_id_prefix("deploy/web"), "deploy_web");
+    }
+
+    #[test]
+    fn run_ids_stay_in_directory() {
+        let store = FileStore::new(PathBuf::from("/var/lib/runs"));
+
+        assert_eq!(
+            store.path(&RunId::new("replace-web-1".to_owned())).unwrap(),
+            PathBuf::from("/var/lib/runs/replace-web-1.json")
+        );
+        for run_id in ["../etc/passwd", "a/b", ".hidden", ""] {
+            assert!(
+                matches!(
+                    store.path(&RunId::new(run_id.to_owned())),
+                    Err(Error::WorkflowError { .. })
+                ),
+                "{run_id} accepted"
+            );
+        }
+    }
+}